@@ -12,6 +12,7 @@ pub enum BankError {
     ZeroAmmount,
     InsufficientFunds,
     TransferToItself,
+    Overflow,
 }
 
 #[derive(Debug, Default, Clone, PartialEq)]
@@ -88,28 +89,36 @@ impl Bank {
         }
     }
 
-    fn update_account_balance_by_amount(
-        &mut self,
-        id: AccountID,
-        amount: i64,
-    ) -> Result<(), BankError> {
+    fn increase_account_balance(&mut self, id: AccountID, amount: u64) -> Result<(), BankError> {
         if amount == 0 {
             return Err(BankError::ZeroAmmount);
         }
 
         let account = self.accounts.get_mut(&id).ok_or(BankError::NotFound)?;
+        account.balance = account
+            .balance
+            .checked_add(amount)
+            .ok_or(BankError::Overflow)?;
 
-        let result_balance = account.balance as i64 + amount;
-        if result_balance < 0 {
-            return Err(BankError::InsufficientFunds);
+        Ok(())
+    }
+
+    fn decrease_account_balance(&mut self, id: AccountID, amount: u64) -> Result<(), BankError> {
+        if amount == 0 {
+            return Err(BankError::ZeroAmmount);
         }
 
-        account.balance = result_balance as u64;
+        let account = self.accounts.get_mut(&id).ok_or(BankError::NotFound)?;
+        account.balance = account
+            .balance
+            .checked_sub(amount)
+            .ok_or(BankError::InsufficientFunds)?;
+
         Ok(())
     }
 
     fn do_deposit(&mut self, id: AccountID, amount: u64) -> Result<(), BankError> {
-        self.update_account_balance_by_amount(id, amount as i64)?;
+        self.increase_account_balance(id, amount)?;
         Ok(())
     }
 
@@ -121,7 +130,7 @@ impl Bank {
     }
 
     fn do_withdraw(&mut self, id: AccountID, amount: u64) -> Result<(), BankError> {
-        self.update_account_balance_by_amount(id, -(amount as i64))?;
+        self.decrease_account_balance(id, amount)?;
         Ok(())
     }
 
@@ -142,8 +151,25 @@ impl Bank {
             return Err(BankError::TransferToItself);
         }
 
-        self.update_account_balance_by_amount(sender_id, -(amount as i64))?;
-        self.update_account_balance_by_amount(reciever_id, amount as i64)?;
+        if amount == 0 {
+            return Err(BankError::ZeroAmmount);
+        }
+
+        // validate both sides before touching any balance,
+        // so a failed transfer never leaves a half-applied debit
+        let sender_balance = self.get_balance(sender_id)?;
+        let reciever_balance = self.get_balance(reciever_id)?;
+
+        if sender_balance < amount {
+            return Err(BankError::InsufficientFunds);
+        }
+
+        if reciever_balance.checked_add(amount).is_none() {
+            return Err(BankError::Overflow);
+        }
+
+        self.decrease_account_balance(sender_id, amount)?;
+        self.increase_account_balance(reciever_id, amount)?;
 
         Ok(())
     }
@@ -322,6 +348,57 @@ mod tests {
         assert_eq!(bank.get_balance(reciever_id).unwrap(), 250);
     }
 
+    #[test]
+    fn failed_transfer_keeps_balances_works() {
+        let mut bank = Bank::new();
+        let sender = Account::new(100);
+        let reciever = Account::new(200);
+        let sender_id = sender.id;
+        let reciever_id = reciever.id;
+        let account_undefined_id = AccountID::new();
+
+        bank.register_account(sender).unwrap();
+        bank.register_account(reciever).unwrap();
+
+        let failed_transfers = vec![
+            (sender_id, reciever_id, 0, BankError::ZeroAmmount),
+            (sender_id, sender_id, 50, BankError::TransferToItself),
+            (sender_id, reciever_id, 1000, BankError::InsufficientFunds),
+            (sender_id, account_undefined_id, 50, BankError::NotFound),
+            (account_undefined_id, reciever_id, 50, BankError::NotFound),
+        ];
+
+        for (from_id, to_id, amount, err) in failed_transfers {
+            assert_eq!(bank.transfer(from_id, to_id, amount), Err(err));
+            assert_eq!(bank.get_balance(sender_id).unwrap(), 100);
+            assert_eq!(bank.get_balance(reciever_id).unwrap(), 200);
+            assert_eq!(bank.get_all_operations().count(), 2);
+        }
+    }
+
+    #[test]
+    fn overflow_works() {
+        let mut bank = Bank::new();
+        let rich = Account::new(u64::MAX);
+        let poor = Account::new(10);
+        let rich_id = rich.id;
+        let poor_id = poor.id;
+
+        bank.register_account(rich).unwrap();
+        bank.register_account(poor).unwrap();
+
+        assert_eq!(bank.deposit(rich_id, 1), Err(BankError::Overflow));
+        assert_eq!(bank.transfer(poor_id, rich_id, 5), Err(BankError::Overflow));
+        assert_eq!(
+            bank.withdraw(poor_id, u64::MAX),
+            Err(BankError::InsufficientFunds)
+        );
+
+        assert_eq!(bank.get_balance(rich_id).unwrap(), u64::MAX);
+        assert_eq!(bank.get_balance(poor_id).unwrap(), 10);
+        assert_eq!(bank.get_all_operations().count(), 2);
+    }
+
     #[test]
     fn get_all_operations_works() {
         let mut bank = Bank::new();
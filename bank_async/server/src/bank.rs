@@ -1,17 +1,67 @@
 pub mod account;
+pub mod clock;
+pub mod currency;
+pub mod fee;
+pub mod hold;
+pub mod idempotency;
+pub mod interest;
+pub mod limit;
 pub mod log;
+pub mod money;
+pub mod profile;
+pub mod schedule;
+pub mod snapshot;
+pub mod statement;
+pub mod verify;
 
 use account::*;
+use clock::*;
+use currency::*;
+use fee::*;
+use hold::*;
+use idempotency::*;
+use interest::*;
+use limit::*;
 use log::*;
-use std::collections::HashMap;
-
-#[derive(Debug, PartialEq)]
+use money::*;
+use profile::*;
+use schedule::*;
+use snapshot::*;
+use statement::*;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use verify::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum BankError {
     NotFound,
     AlreadyExists,
     ZeroAmount,
+    NegativeAmount,
     InsufficientFunds,
     TransferToItself,
+    Overflow,
+    NoExchangeRate,
+    InvalidExchangeRate,
+    AccountClosed,
+    AccountFrozen,
+    AccountNotFrozen,
+    BalanceNotEmpty,
+    InvalidOverdraftLimit,
+    OperationNotFound,
+    NotReversible,
+    AlreadyReversed,
+    ScheduleNotFound,
+    InvalidSchedule,
+    AliasTaken,
+    AliasNotFound,
+    LimitExceeded,
+    HoldNotFound,
+    CaptureExceedsHold,
+    HoldsPending,
+    HistoryUnavailable,
+    InvalidAccount,
+    KeyReused,
+    InvalidCounterparty,
 }
 
 impl std::fmt::Display for BankError {
@@ -20,8 +70,32 @@ impl std::fmt::Display for BankError {
             BankError::NotFound => write!(f, "Account not found"),
             BankError::AlreadyExists => write!(f, "Account already exists"),
             BankError::ZeroAmount => write!(f, "Zero amount"),
+            BankError::NegativeAmount => write!(f, "Negative amount"),
             BankError::InsufficientFunds => write!(f, "Insufficient funds"),
             BankError::TransferToItself => write!(f, "Transfer to itself"),
+            BankError::Overflow => write!(f, "Balance overflow"),
+            BankError::NoExchangeRate => write!(f, "No exchange rate"),
+            BankError::InvalidExchangeRate => write!(f, "Invalid exchange rate"),
+            BankError::AccountClosed => write!(f, "Account closed"),
+            BankError::AccountFrozen => write!(f, "Account frozen"),
+            BankError::AccountNotFrozen => write!(f, "Account not frozen"),
+            BankError::BalanceNotEmpty => write!(f, "Balance is not empty"),
+            BankError::InvalidOverdraftLimit => write!(f, "Invalid overdraft limit"),
+            BankError::OperationNotFound => write!(f, "Operation not found"),
+            BankError::NotReversible => write!(f, "Operation can't be reversed"),
+            BankError::AlreadyReversed => write!(f, "Operation already reversed"),
+            BankError::ScheduleNotFound => write!(f, "Schedule not found"),
+            BankError::InvalidSchedule => write!(f, "Invalid schedule"),
+            BankError::AliasTaken => write!(f, "Alias already taken"),
+            BankError::AliasNotFound => write!(f, "Alias not found"),
+            BankError::LimitExceeded => write!(f, "Limit exceeded"),
+            BankError::HoldNotFound => write!(f, "Hold not found"),
+            BankError::CaptureExceedsHold => write!(f, "Capture exceeds hold"),
+            BankError::HoldsPending => write!(f, "Account has pending holds"),
+            BankError::HistoryUnavailable => write!(f, "History unavailable"),
+            BankError::InvalidAccount => write!(f, "Invalid account"),
+            BankError::KeyReused => write!(f, "Idempotency key used for another operation"),
+            BankError::InvalidCounterparty => write!(f, "Counterparty is the listed account"),
         }
     }
 }
@@ -32,18 +106,72 @@ impl std::error::Error for BankError {}
 pub struct Bank {
     accounts: HashMap<AccountID, Account>,
     operations_log: OperationsLog,
+    exchange_rates: HashMap<(Currency, Currency), Rate>,
+    reversed_operations: HashSet<OperationID>,
+    idempotency_keys: HashMap<IdempotencyKey, (OperationID, KeyedRequest)>,
+    schedules: HashMap<ScheduleID, Schedule>,
+    fees: HashMap<FeeOperation, Fee>,
+    aliases: HashMap<Alias, AccountID>,
+    /// Bank-wide limits are kept under `None`.
+    limits: HashMap<Option<AccountID>, Limits>,
+    holds: HashMap<HoldID, Hold>,
+    /// Total of open holds of every account, kept in sync with `holds`.
+    held: HashMap<AccountID, Money>,
+    base_snapshot: Snapshot,
 }
 
 pub type Result<T> = std::result::Result<T, BankError>;
 
 impl Bank {
     pub fn restore<'a, I: Iterator<Item = &'a Operation>>(operations: I) -> Result<Bank> {
+        Self::restore_from_snapshot(Snapshot::default(), operations)
+    }
+
+    /// Restores bank from `snapshot` replaying only operations made after it.
+    pub fn restore_from_snapshot<'a, I: Iterator<Item = &'a Operation>>(
+        snapshot: Snapshot,
+        operations: I,
+    ) -> Result<Bank> {
         let mut bank = Self::default();
 
+        for account in snapshot.accounts.iter() {
+            bank.do_register_account(*account)?;
+        }
+        for (from, to, rate) in snapshot.exchange_rates.iter() {
+            bank.do_set_exchange_rate(*from, *to, *rate)?;
+        }
+        bank.reversed_operations
+            .extend(snapshot.reversed_operations.iter());
+        bank.idempotency_keys.extend(
+            snapshot
+                .idempotency_keys
+                .iter()
+                .map(|(key, operation_id, request)| (*key, (*operation_id, *request))),
+        );
+        bank.schedules.extend(
+            snapshot
+                .schedules
+                .iter()
+                .map(|schedule| (schedule.id, *schedule)),
+        );
+        bank.fees.extend(snapshot.fees.iter().copied());
+        bank.limits.extend(snapshot.limits.iter().copied());
+        for hold in snapshot.holds.iter() {
+            bank.add_hold(*hold)?;
+        }
+        for (id, timestamp, amount) in snapshot.outflows.iter() {
+            bank.operations_log.record_outflow(*id, *timestamp, *amount);
+        }
+        bank.base_snapshot = snapshot;
+
         for operation in operations {
             match operation.kind {
-                OperationKind::Register { id, balance } => {
-                    let mut account = Account::new(balance);
+                OperationKind::Register {
+                    id,
+                    balance,
+                    currency,
+                } => {
+                    let mut account = Account::with_currency(balance, currency);
                     account.id = id;
                     bank.do_register_account(account)?;
                 }
@@ -57,11 +185,101 @@ impl Bank {
                     sender_id,
                     receiver_id,
                     amount,
+                    rate,
+                } => {
+                    bank.do_transfer(sender_id, receiver_id, amount, rate)?;
+                }
+                OperationKind::SetExchangeRate { from, to, rate } => {
+                    bank.do_set_exchange_rate(from, to, rate)?;
+                }
+                OperationKind::SetOverdraftLimit { id, limit } => {
+                    bank.do_set_overdraft_limit(id, limit)?;
+                }
+                OperationKind::SetInterestRate { id, rate } => {
+                    bank.do_set_interest_rate(id, rate)?;
+                }
+                OperationKind::Interest { id, amount } => {
+                    bank.increase_account_balance(id, amount)?;
+                }
+                OperationKind::Reverse {
+                    operation_id,
+                    debit,
+                    credit,
+                } => {
+                    bank.do_reverse(operation_id, debit, credit)?;
+                }
+                OperationKind::Freeze { id } => bank.do_freeze(id)?,
+                OperationKind::Unfreeze { id } => bank.do_unfreeze(id)?,
+                OperationKind::Close {
+                    id, sweep_to, rate, ..
+                } => {
+                    bank.do_close(id, sweep_to, rate)?;
+                }
+                OperationKind::ScheduleTransfer {
+                    id,
+                    sender_id,
+                    receiver_id,
+                    amount,
+                    start,
+                    interval,
                 } => {
-                    bank.do_transfer(sender_id, receiver_id, amount)?;
+                    bank.do_schedule_transfer(Schedule {
+                        id,
+                        sender_id,
+                        receiver_id,
+                        amount,
+                        next_run: start,
+                        interval,
+                    })?;
                 }
+                OperationKind::CancelSchedule { id } => bank.do_cancel_schedule(id)?,
+                OperationKind::SkipSchedule { id } => bank.do_skip_schedule(id)?,
+                OperationKind::SetFee { operation, fee } => bank.do_set_fee(operation, fee),
+                OperationKind::UpdateProfile { id, field } => bank.do_update_profile(id, field)?,
+                OperationKind::SetLimit { id, kind, limit } => {
+                    bank.do_set_limit(id, kind, limit)?
+                }
+                OperationKind::Hold {
+                    id,
+                    account_id,
+                    amount,
+                } => bank.do_hold(Hold {
+                    id,
+                    account_id,
+                    amount,
+                })?,
+                OperationKind::Capture { id, amount, .. } => {
+                    bank.do_capture(id, amount)?;
+                }
+                OperationKind::Release { id, .. } => {
+                    bank.do_release(id)?;
+                }
+                OperationKind::InterbankSend {
+                    sender_id,
+                    amount,
+                    rate,
+                    ..
+                } => bank.do_send_to_bank(sender_id, amount, rate)?,
+                OperationKind::InterbankReceive {
+                    receiver_id,
+                    amount,
+                    ..
+                } => bank.increase_account_balance(receiver_id, amount)?,
+            }
+
+            if let Some((fee_account, fee)) = operation.fee {
+                let payer = Self::fee_payer(&operation.kind).ok_or(BankError::NotFound)?;
+                bank.do_charge_fee(payer, fee_account, fee)?;
+            }
+
+            if let Some(schedule) = operation.schedule {
+                bank.advance_schedule(schedule);
             }
 
+            if let Some(key) = operation.key {
+                let request = KeyedRequest::of(&operation.kind).ok_or(BankError::KeyReused)?;
+                bank.idempotency_keys.insert(key, (operation.id, request));
+            }
             bank.operations_log.log_operation(*operation);
         }
 
@@ -73,290 +291,2943 @@ impl Bank {
         if self.accounts.contains_key(&account_id) {
             return Err(BankError::AlreadyExists);
         }
+        if let Some(alias) = account.profile.alias {
+            if self.aliases.contains_key(&alias) {
+                return Err(BankError::AliasTaken);
+            }
+            self.aliases.insert(alias, account_id);
+        }
 
         self.accounts.insert(account_id, account);
         Ok(())
     }
 
     pub fn register_account(&mut self, account: Account) -> Result<OperationID> {
+        // only balance and currency are logged, the rest is set by its own operations
+        let registered = Account {
+            id: account.id,
+            ..Account::with_currency(account.balance, account.currency)
+        };
+        if account != registered {
+            return Err(BankError::InvalidAccount);
+        }
+        if account.balance.is_negative() {
+            return Err(BankError::InsufficientFunds);
+        }
+
         self.do_register_account(account)?;
 
         let operation_id = self.operations_log.log(OperationKind::Register {
             id: account.id,
             balance: account.balance,
+            currency: account.currency,
         });
 
         Ok(operation_id)
     }
 
+    /// Current state of accounts, enough to restore bank without replaying its log.
+    pub fn snapshot(&self) -> Snapshot {
+        let (last_operation_id, last_operation_time) =
+            match self.operations_log.get_all_operations().last() {
+                Some(operation) => (Some(operation.id), Some(operation.timestamp)),
+                None => (
+                    self.base_snapshot.last_operation_id,
+                    self.base_snapshot.last_operation_time,
+                ),
+            };
+
+        let mut exchange_rates: Vec<(Currency, Currency, Rate)> = self
+            .exchange_rates
+            .iter()
+            .map(|((from, to), rate)| (*from, *to, *rate))
+            .collect();
+        exchange_rates.sort_by_key(|(from, to, _)| (*from, *to));
+
+        let mut reversed_operations: Vec<OperationID> =
+            self.reversed_operations.iter().copied().collect();
+        reversed_operations.sort();
+
+        let mut idempotency_keys: Vec<(IdempotencyKey, OperationID, KeyedRequest)> = self
+            .idempotency_keys
+            .iter()
+            .map(|(key, (operation_id, request))| (*key, *operation_id, *request))
+            .collect();
+        idempotency_keys.sort_by_key(|(key, _, _)| *key);
+
+        let mut accounts: Vec<Account> = self.accounts.values().copied().collect();
+        accounts.sort_by_key(|account| account.id);
+
+        let mut schedules: Vec<Schedule> = self.schedules.values().copied().collect();
+        schedules.sort_by_key(|schedule| schedule.id);
+
+        let fees = self.get_fees();
+
+        let mut holds: Vec<Hold> = self.holds.values().copied().collect();
+        holds.sort_by_key(|hold| hold.id);
+
+        let mut limits: Vec<(Option<AccountID>, Limits)> = self
+            .limits
+            .iter()
+            .map(|(id, limits)| (*id, *limits))
+            .collect();
+        limits.sort_by_key(|(id, _)| *id);
+
+        Snapshot {
+            accounts,
+            exchange_rates,
+            reversed_operations,
+            idempotency_keys,
+            schedules,
+            fees,
+            limits,
+            outflows: self.operations_log.outflows().entries(),
+            holds,
+            last_operation_id,
+            last_operation_time,
+        }
+    }
+
+    /// Snapshot the bank was restored from, operations log contains only what happened after it.
+    pub fn base_snapshot(&self) -> &Snapshot {
+        &self.base_snapshot
+    }
+
+    /// Clock new operations are timestamped with, restored ones keep their time.
+    pub fn set_clock(&mut self, clock: SharedClock) {
+        self.operations_log.set_clock(clock);
+    }
+
     pub fn get_operation(&self, operation_id: OperationID) -> Option<&Operation> {
         self.operations_log.get(operation_id)
     }
 
-    pub fn get_balance(&self, id: AccountID) -> Result<u64> {
+    pub fn get_operation_by_key(&self, key: IdempotencyKey) -> Option<OperationID> {
+        self.idempotency_keys
+            .get(&key)
+            .map(|(operation_id, _)| *operation_id)
+    }
+
+    /// Operation made with `key` for `request`, `None` if the key is not used yet.
+    /// Key used for another request is `KeyReused`.
+    pub fn find_keyed(
+        &self,
+        key: IdempotencyKey,
+        request: KeyedRequest,
+    ) -> Result<Option<OperationID>> {
+        match self.idempotency_keys.get(&key) {
+            Some((operation_id, keyed)) if *keyed == request => Ok(Some(*operation_id)),
+            Some(_) => Err(BankError::KeyReused),
+            None => Ok(None),
+        }
+    }
+
+    pub fn apply_request(&mut self, request: KeyedRequest) -> Result<OperationID> {
+        match request {
+            KeyedRequest::Deposit { id, amount } => self.deposit(id, amount),
+            KeyedRequest::Withdraw { id, amount } => self.withdraw(id, amount),
+            KeyedRequest::Transfer {
+                sender_id,
+                receiver_id,
+                amount,
+            } => self.transfer(sender_id, receiver_id, amount),
+        }
+    }
+
+    /// Applies `request` only once for the same `key`, its operation gets the key attached.
+    /// Next calls with the key return the original operation without applying anything.
+    pub fn with_key(&mut self, key: IdempotencyKey, request: KeyedRequest) -> Result<OperationID> {
+        if let Some(operation_id) = self.find_keyed(key, request)? {
+            return Ok(operation_id);
+        }
+
+        let operation_id = self.apply_request(request)?;
+        self.operations_log.set_key(operation_id, key);
+        self.idempotency_keys.insert(key, (operation_id, request));
+
+        Ok(operation_id)
+    }
+
+    /// Rate to convert amounts in `from` currency to `to` currency.
+    pub fn get_exchange_rate(&self, from: Currency, to: Currency) -> Result<Rate> {
+        if from == to {
+            return Ok(Rate::ONE);
+        }
+
+        self.exchange_rates
+            .get(&(from, to))
+            .copied()
+            .ok_or(BankError::NoExchangeRate)
+    }
+
+    fn do_set_exchange_rate(&mut self, from: Currency, to: Currency, rate: Rate) -> Result<()> {
+        if from == to {
+            return Err(BankError::InvalidExchangeRate);
+        }
+
+        self.exchange_rates.insert((from, to), rate);
+        Ok(())
+    }
+
+    pub fn set_exchange_rate(
+        &mut self,
+        from: Currency,
+        to: Currency,
+        rate: Rate,
+    ) -> Result<OperationID> {
+        self.do_set_exchange_rate(from, to, rate)?;
+
+        let operation_id =
+            self.operations_log
+                .log(OperationKind::SetExchangeRate { from, to, rate });
+        Ok(operation_id)
+    }
+
+    /// Currency of the account, which has to be open to receive money from another bank.
+    pub fn get_receiving_currency(&self, id: AccountID) -> Result<Currency> {
+        self.get_open_account(id).map(|account| account.currency)
+    }
+
+    pub fn get_currency(&self, id: AccountID) -> Result<Currency> {
+        match self.accounts.get(&id) {
+            Some(account) => Ok(account.currency),
+            None => Err(BankError::NotFound),
+        }
+    }
+
+    pub fn get_balance(&self, id: AccountID) -> Result<Money> {
         match self.accounts.get(&id) {
             Some(account) => Ok(account.balance),
             None => Err(BankError::NotFound),
         }
     }
 
-    fn update_account_balance_by_amount(&mut self, id: AccountID, amount: i64) -> Result<()> {
-        if amount == 0 {
-            return Err(BankError::ZeroAmount);
+    // Balance the account had in the base snapshot, zero if it was opened later.
+    fn base_balance(&self, id: AccountID) -> Money {
+        self.base_snapshot
+            .accounts
+            .iter()
+            .find(|account| account.id == id)
+            .map_or(Money::ZERO, |account| account.balance)
+    }
+
+    // Balance of `id` after `posting` of its own.
+    fn post(balance: Money, posting: &Posting) -> Result<Money> {
+        match posting.direction {
+            Direction::Credit => balance.checked_add(posting.amount),
+            Direction::Debit => balance.checked_sub(posting.amount),
+        }
+        .ok_or(BankError::Overflow)
+    }
+
+    /// Balance of the account right after `cutoff`, replayed from its own operations only.
+    /// History compacted into the base snapshot is gone, cutoffs before the snapshot fail.
+    pub fn get_balance_at(&self, id: AccountID, cutoff: Cutoff) -> Result<Money> {
+        self.get_balance(id)?;
+
+        let mut balance = self.base_balance(id);
+        let base = &self.base_snapshot;
+        match cutoff {
+            Cutoff::Operation(operation_id) if base.last_operation_id == Some(operation_id) => {
+                return Ok(balance)
+            }
+            Cutoff::Time(time) if base.last_operation_time.is_some_and(|last| time < last) => {
+                return Err(BankError::HistoryUnavailable)
+            }
+            _ => {}
+        }
+
+        // an operation missing from the log may have been compacted into the snapshot
+        let not_found = match base.last_operation_id {
+            Some(_) => BankError::HistoryUnavailable,
+            None => BankError::OperationNotFound,
+        };
+        let operations = self
+            .operations_log
+            .get_account_operations_until(id, cutoff)
+            .ok_or(not_found)?;
+        for operation in operations {
+            for posting in postings(operation).ok_or(BankError::Overflow)? {
+                if posting.account_id == id {
+                    balance = Self::post(balance, &posting)?;
+                }
+            }
+        }
+
+        Ok(balance)
+    }
+
+    /// Postings of the account with running balance, starting from the base snapshot.
+    pub fn statement(&self, id: AccountID) -> Result<Vec<StatementEntry>> {
+        self.get_balance(id)?;
+
+        let mut balance = self.base_balance(id);
+        let mut entries = Vec::new();
+        for operation in self.get_account_operations(id) {
+            for posting in postings(operation).ok_or(BankError::Overflow)? {
+                if posting.account_id != id {
+                    continue;
+                }
+
+                balance = Self::post(balance, &posting)?;
+                entries.push(StatementEntry {
+                    operation_id: operation.id,
+                    timestamp: operation.timestamp,
+                    kind: posting.kind,
+                    direction: posting.direction,
+                    amount: posting.amount,
+                    counterparty: posting.counterparty,
+                    balance,
+                });
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// Replays the log from the base snapshot and compares accounts it gives with the live ones,
+    /// then checks that balances of every currency add up to the money the log moved.
+    pub fn verify(&self) -> Result<VerifyReport> {
+        let mut discrepancies = Vec::new();
+
+        match Self::restore_from_snapshot(self.base_snapshot.clone(), self.get_all_operations()) {
+            Ok(replayed) => {
+                let mut ids: Vec<AccountID> = self
+                    .accounts
+                    .keys()
+                    .chain(replayed.accounts.keys())
+                    .copied()
+                    .collect();
+                ids.sort();
+                ids.dedup();
+
+                for id in ids {
+                    let (replayed, live) = (replayed.accounts.get(&id), self.accounts.get(&id));
+                    if replayed != live {
+                        discrepancies.push(Discrepancy::Account {
+                            id,
+                            replayed: replayed.copied().map(Box::new),
+                            live: live.copied().map(Box::new),
+                        });
+                    }
+                }
+            }
+            Err(e) => discrepancies.push(Discrepancy::Replay(e)),
+        }
+
+        let mut totals: BTreeMap<Currency, Reconciliation> = BTreeMap::new();
+        let add = |total: &mut Money, amount: Money| -> Result<()> {
+            *total = total.checked_add(amount).ok_or(BankError::Overflow)?;
+            Ok(())
+        };
+
+        for account in self.base_snapshot.accounts.iter() {
+            let currency_totals = totals
+                .entry(account.currency)
+                .or_insert_with(|| Reconciliation::new(account.currency));
+            add(&mut currency_totals.opening, account.balance)?;
+        }
+        for operation in self.get_all_operations() {
+            for posting in postings(operation).ok_or(BankError::Overflow)? {
+                // an account missing from the live map is already reported above
+                let currency = match self.accounts.get(&posting.account_id) {
+                    Some(account) => account.currency,
+                    None => continue,
+                };
+                let currency_totals = totals
+                    .entry(currency)
+                    .or_insert_with(|| Reconciliation::new(currency));
+
+                match (posting.kind, posting.direction) {
+                    ("register", _) => add(&mut currency_totals.opening, posting.amount)?,
+                    (_, Direction::Credit) => add(&mut currency_totals.credited, posting.amount)?,
+                    (_, Direction::Debit) => add(&mut currency_totals.debited, posting.amount)?,
+                }
+            }
+        }
+        for account in self.accounts.values() {
+            let currency_totals = totals
+                .entry(account.currency)
+                .or_insert_with(|| Reconciliation::new(account.currency));
+            add(&mut currency_totals.balance, account.balance)?;
+        }
+
+        let totals: Vec<Reconciliation> = totals.into_values().collect();
+        discrepancies.extend(
+            totals
+                .iter()
+                .filter(|totals| totals.expected() != Some(totals.balance))
+                .map(|totals| Discrepancy::Total(*totals)),
+        );
+
+        Ok(VerifyReport {
+            totals,
+            discrepancies,
+        })
+    }
+
+    pub fn get_status(&self, id: AccountID) -> Result<AccountStatus> {
+        match self.accounts.get(&id) {
+            Some(account) => Ok(account.status),
+            None => Err(BankError::NotFound),
+        }
+    }
+
+    fn check_open(account: &Account) -> Result<()> {
+        match account.status {
+            AccountStatus::Open => Ok(()),
+            AccountStatus::Frozen => Err(BankError::AccountFrozen),
+            AccountStatus::Closed => Err(BankError::AccountClosed),
         }
+    }
+
+    fn get_open_account(&self, id: AccountID) -> Result<&Account> {
+        let account = self.accounts.get(&id).ok_or(BankError::NotFound)?;
+        Self::check_open(account)?;
+
+        Ok(account)
+    }
 
+    fn get_open_account_mut(&mut self, id: AccountID) -> Result<&mut Account> {
         let account = self.accounts.get_mut(&id).ok_or(BankError::NotFound)?;
+        Self::check_open(account)?;
+
+        Ok(account)
+    }
+
+    // Money is signed, but operations move positive amounts only, direction is up to the operation.
+    fn check_amount(amount: Money) -> Result<()> {
+        if amount.is_zero() {
+            return Err(BankError::ZeroAmount);
+        }
+        if amount.is_negative() {
+            return Err(BankError::NegativeAmount);
+        }
+
+        Ok(())
+    }
+
+    fn increase_account_balance(&mut self, id: AccountID, amount: Money) -> Result<()> {
+        Self::check_amount(amount)?;
+
+        let account = self.get_open_account_mut(id)?;
+        account.balance = account
+            .balance
+            .checked_add(amount)
+            .ok_or(BankError::Overflow)?;
+
+        Ok(())
+    }
+
+    fn held(&self, id: AccountID) -> Money {
+        self.held.get(&id).copied().unwrap_or(Money::ZERO)
+    }
+
+    // Balance after debiting `amount`, it may go below zero down to the overdraft limit.
+    // Funds on hold can't be spent, so they have to stay above the limit too.
+    fn debited_balance(account: &Account, held: Money, amount: Money) -> Result<Money> {
+        let balance = account
+            .balance
+            .checked_sub(amount)
+            .ok_or(BankError::InsufficientFunds)?;
+        let available = balance
+            .checked_sub(held)
+            .ok_or(BankError::InsufficientFunds)?;
+
+        match account.overdraft_limit.checked_neg() {
+            Some(min_balance) if available >= min_balance => Ok(balance),
+            _ => Err(BankError::InsufficientFunds),
+        }
+    }
+
+    fn decrease_account_balance(&mut self, id: AccountID, amount: Money) -> Result<()> {
+        Self::check_amount(amount)?;
+
+        let held = self.held(id);
+        let account = self.get_open_account_mut(id)?;
+        account.balance = Self::debited_balance(account, held, amount)?;
+
+        Ok(())
+    }
+
+    fn do_set_fee(&mut self, operation: FeeOperation, fee: Option<Fee>) {
+        match fee {
+            Some(fee) => self.fees.insert(operation, fee),
+            None => self.fees.remove(&operation),
+        };
+    }
+
+    /// Sets fee charged for every `operation` to the built-in fee account, `None` removes it.
+    pub fn set_fee(&mut self, operation: FeeOperation, fee: Option<Fee>) -> Result<OperationID> {
+        self.do_set_fee(operation, fee);
+
+        let operation_id = self
+            .operations_log
+            .log(OperationKind::SetFee { operation, fee });
+
+        Ok(operation_id)
+    }
+
+    pub fn get_fees(&self) -> Vec<(FeeOperation, Fee)> {
+        let mut fees: Vec<(FeeOperation, Fee)> = self
+            .fees
+            .iter()
+            .map(|(operation, fee)| (*operation, *fee))
+            .collect();
+        fees.sort_by_key(|(operation, _)| *operation);
+        fees
+    }
+
+    fn do_set_limit(
+        &mut self,
+        id: Option<AccountID>,
+        kind: LimitKind,
+        limit: Option<Money>,
+    ) -> Result<()> {
+        if let Some(id) = id {
+            if self.get_status(id)? == AccountStatus::Closed {
+                return Err(BankError::AccountClosed);
+            }
+        }
+
+        let limits = self.limits.get(&id).copied().unwrap_or_default();
+        match limits.updated(kind, limit) {
+            limits if limits.is_empty() => self.limits.remove(&id),
+            limits => self.limits.insert(id, limits),
+        };
+
+        Ok(())
+    }
+
+    /// Sets limit of the account or, if `id` is `None`, of every account without its own one.
+    pub fn set_limit(
+        &mut self,
+        id: Option<AccountID>,
+        kind: LimitKind,
+        limit: Option<Money>,
+    ) -> Result<OperationID> {
+        self.do_set_limit(id, kind, limit)?;
+
+        let operation_id = self
+            .operations_log
+            .log(OperationKind::SetLimit { id, kind, limit });
+
+        Ok(operation_id)
+    }
+
+    /// Limits in effect for the account, or bank-wide ones if `id` is `None`.
+    pub fn get_limits(&self, id: Option<AccountID>) -> Result<Limits> {
+        let bank_limits = self.limits.get(&None).copied().unwrap_or_default();
+        match id {
+            Some(id) => {
+                self.get_status(id)?;
+                let limits = self.limits.get(&Some(id)).copied().unwrap_or_default();
+                Ok(limits.or(bank_limits))
+            }
+            None => Ok(bank_limits),
+        }
+    }
+
+    /// Money that left the account by withdrawals and transfers within the last day.
+    pub fn get_outflow(&self, id: AccountID) -> Result<Money> {
+        self.get_status(id)?;
+        self.operations_log
+            .outflows()
+            .total(id, self.operations_log.now())
+            .ok_or(BankError::Overflow)
+    }
+
+    // Checked only when an operation is made, replay trusts the log.
+    // Open holds count against the daily limit, their captures are not checked again.
+    fn check_limits(&self, id: AccountID, amount: Money) -> Result<()> {
+        self.get_open_account(id)?;
+        let limits = self.get_limits(Some(id))?;
+
+        if limits.per_transaction.is_some_and(|limit| amount > limit) {
+            return Err(BankError::LimitExceeded);
+        }
+        if let Some(limit) = limits.daily {
+            let total = self
+                .get_outflow(id)?
+                .checked_add(self.held(id))
+                .and_then(|total| total.checked_add(amount))
+                .ok_or(BankError::LimitExceeded)?;
+            if total > limit {
+                return Err(BankError::LimitExceeded);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn fee_payer(kind: &OperationKind) -> Option<AccountID> {
+        match *kind {
+            OperationKind::Withdraw { id, .. } => Some(id),
+            OperationKind::Transfer { sender_id, .. }
+            | OperationKind::InterbankSend { sender_id, .. } => Some(sender_id),
+            _ => None,
+        }
+    }
+
+    // Fee of `operation` for `amount` together with the account it goes to.
+    // Checks that the payer can afford both the amount and the fee, so the fee
+    // is never charged for a failed operation and never fails after it.
+    fn prepare_fee(
+        &self,
+        operation: FeeOperation,
+        payer: AccountID,
+        amount: Money,
+    ) -> Result<Option<(AccountID, Money)>> {
+        let fee = match self.fees.get(&operation) {
+            Some(fee) => fee.amount(amount).ok_or(BankError::Overflow)?,
+            None => return Ok(None),
+        };
+        if fee.is_zero() {
+            return Ok(None);
+        }
+
+        let account = self.get_open_account(payer)?;
+        let total = amount.checked_add(fee).ok_or(BankError::Overflow)?;
+        Self::debited_balance(account, self.held(payer), total)?;
+
+        let fee_account = AccountID::fee_account(account.currency);
+        if let Some(fee_account) = self.accounts.get(&fee_account) {
+            Self::check_open(fee_account)?;
+            fee_account
+                .balance
+                .checked_add(fee)
+                .ok_or(BankError::Overflow)?;
+        }
+
+        Ok(Some((fee_account, fee)))
+    }
+
+    // Moves `fee` from `payer` to the fee account, which is opened on the first fee.
+    fn do_charge_fee(
+        &mut self,
+        payer: AccountID,
+        fee_account: AccountID,
+        fee: Money,
+    ) -> Result<()> {
+        if !self.accounts.contains_key(&fee_account) {
+            let mut account = Account::with_currency(Money::ZERO, self.get_currency(payer)?);
+            account.id = fee_account;
+            self.do_register_account(account)?;
+        }
+
+        self.decrease_account_balance(payer, fee)?;
+        self.increase_account_balance(fee_account, fee)
+    }
+
+    // Logs operation of `payer` that was just applied and charges its fee.
+    fn log_with_fee(
+        &mut self,
+        kind: OperationKind,
+        payer: AccountID,
+        fee: Option<(AccountID, Money)>,
+    ) -> Result<OperationID> {
+        if let Some((fee_account, fee)) = fee {
+            self.do_charge_fee(payer, fee_account, fee)?;
+        }
+
+        let operation_id = self.operations_log.log(kind);
+        if let Some((fee_account, fee)) = fee {
+            self.operations_log.set_fee(operation_id, fee_account, fee);
+        }
+
+        Ok(operation_id)
+    }
+
+    fn do_deposit(&mut self, id: AccountID, amount: Money) -> Result<()> {
+        self.increase_account_balance(id, amount)
+    }
+
+    pub fn deposit(&mut self, id: AccountID, amount: Money) -> Result<OperationID> {
+        self.do_deposit(id, amount)?;
+
+        let operation_id = self
+            .operations_log
+            .log(OperationKind::Deposit { id, amount });
+        Ok(operation_id)
+    }
+
+    fn do_withdraw(&mut self, id: AccountID, amount: Money) -> Result<()> {
+        self.decrease_account_balance(id, amount)
+    }
+
+    pub fn withdraw(&mut self, id: AccountID, amount: Money) -> Result<OperationID> {
+        self.check_limits(id, amount)?;
+        let fee = self.prepare_fee(FeeOperation::Withdraw, id, amount)?;
+        self.do_withdraw(id, amount)?;
+
+        self.log_with_fee(OperationKind::Withdraw { id, amount }, id, fee)
+    }
+
+    fn do_transfer(
+        &mut self,
+        sender_id: AccountID,
+        receiver_id: AccountID,
+        amount: Money,
+        rate: Rate,
+    ) -> Result<()> {
+        if sender_id == receiver_id {
+            return Err(BankError::TransferToItself);
+        }
+
+        Self::check_amount(amount)?;
+
+        // validate both sides before touching any balance,
+        // so a failed transfer never leaves a half-applied debit
+        let sender = self.get_open_account(sender_id)?;
+        let receiver_balance = self.get_open_account(receiver_id)?.balance;
+
+        Self::debited_balance(sender, self.held(sender_id), amount)?;
+
+        let received_amount = rate.convert(amount).ok_or(BankError::Overflow)?;
+        if received_amount.is_zero() {
+            return Err(BankError::ZeroAmount);
+        }
+
+        if receiver_balance.checked_add(received_amount).is_none() {
+            return Err(BankError::Overflow);
+        }
+
+        self.decrease_account_balance(sender_id, amount)?;
+        self.increase_account_balance(receiver_id, received_amount)?;
+
+        Ok(())
+    }
+
+    pub fn transfer(
+        &mut self,
+        sender_id: AccountID,
+        receiver_id: AccountID,
+        amount: Money,
+    ) -> Result<OperationID> {
+        // rate is looked up once and logged, so restore never depends on the current table
+        let rate = self.get_exchange_rate(
+            self.get_currency(sender_id)?,
+            self.get_currency(receiver_id)?,
+        )?;
+        self.check_limits(sender_id, amount)?;
+        let fee = self.prepare_fee(FeeOperation::Transfer, sender_id, amount)?;
+        self.do_transfer(sender_id, receiver_id, amount, rate)?;
+
+        self.log_with_fee(
+            OperationKind::Transfer {
+                sender_id,
+                receiver_id,
+                amount,
+                rate,
+            },
+            sender_id,
+            fee,
+        )
+    }
+
+    fn do_send_to_bank(&mut self, sender_id: AccountID, amount: Money, rate: Rate) -> Result<()> {
+        Self::check_amount(amount)?;
+        if rate.convert(amount).ok_or(BankError::Overflow)?.is_zero() {
+            return Err(BankError::ZeroAmount);
+        }
+
+        self.decrease_account_balance(sender_id, amount)
+    }
+
+    /// Debits the sending leg of a transfer to `receiver_id` of bank `bank_id`, whose
+    /// account is in `currency`. Returns the operation and the amount the receiver gets.
+    pub fn send_to_bank(
+        &mut self,
+        sender_id: AccountID,
+        bank_id: u64,
+        receiver_id: AccountID,
+        currency: Currency,
+        amount: Money,
+    ) -> Result<(OperationID, Money)> {
+        let rate = self.get_exchange_rate(self.get_currency(sender_id)?, currency)?;
+        self.check_limits(sender_id, amount)?;
+        let fee = self.prepare_fee(FeeOperation::Transfer, sender_id, amount)?;
+        let received_amount = rate.convert(amount).ok_or(BankError::Overflow)?;
+        self.do_send_to_bank(sender_id, amount, rate)?;
+
+        let operation_id = self.log_with_fee(
+            OperationKind::InterbankSend {
+                sender_id,
+                bank_id,
+                receiver_id,
+                amount,
+                rate,
+            },
+            sender_id,
+            fee,
+        )?;
+
+        Ok((operation_id, received_amount))
+    }
+
+    /// Credits the receiving leg of the transfer sent as `operation_id` by bank `bank_id`.
+    pub fn receive_from_bank(
+        &mut self,
+        operation_id: OperationID,
+        bank_id: u64,
+        sender_id: AccountID,
+        receiver_id: AccountID,
+        amount: Money,
+    ) -> Result<OperationID> {
+        self.increase_account_balance(receiver_id, amount)?;
+
+        let operation_id = self.operations_log.log(OperationKind::InterbankReceive {
+            operation_id,
+            bank_id,
+            sender_id,
+            receiver_id,
+            amount,
+        });
+
+        Ok(operation_id)
+    }
+
+    fn do_reverse(
+        &mut self,
+        operation_id: OperationID,
+        debit: Option<(AccountID, Money)>,
+        credit: Option<(AccountID, Money)>,
+    ) -> Result<()> {
+        if self.reversed_operations.contains(&operation_id) {
+            return Err(BankError::AlreadyReversed);
+        }
+
+        // validate both sides before touching any balance, like in do_transfer
+        if let Some((id, amount)) = debit {
+            Self::debited_balance(self.get_open_account(id)?, self.held(id), amount)?;
+        }
+        if let Some((id, amount)) = credit {
+            let balance = self.get_open_account(id)?.balance;
+            balance.checked_add(amount).ok_or(BankError::Overflow)?;
+        }
+
+        if let Some((id, amount)) = debit {
+            self.decrease_account_balance(id, amount)?;
+        }
+        if let Some((id, amount)) = credit {
+            self.increase_account_balance(id, amount)?;
+        }
+
+        self.reversed_operations.insert(operation_id);
+        Ok(())
+    }
+
+    /// Undoes deposit, withdraw or transfer by a compensating operation,
+    /// every operation can be reversed only once. Fees it charged are not refunded.
+    pub fn reverse(&mut self, operation_id: OperationID) -> Result<OperationID> {
+        let operation = self
+            .get_operation(operation_id)
+            .ok_or(BankError::OperationNotFound)?;
+
+        let (debit, credit) = match operation.kind {
+            OperationKind::Deposit { id, amount } => (Some((id, amount)), None),
+            OperationKind::Withdraw { id, amount } => (None, Some((id, amount))),
+            OperationKind::Transfer {
+                sender_id,
+                receiver_id,
+                amount,
+                rate,
+            } => {
+                // receiver gives back exactly what it got, sender gets the original amount
+                let received_amount = rate.convert(amount).ok_or(BankError::Overflow)?;
+                (
+                    Some((receiver_id, received_amount)),
+                    Some((sender_id, amount)),
+                )
+            }
+            _ => return Err(BankError::NotReversible),
+        };
+
+        self.do_reverse(operation_id, debit, credit)?;
+
+        let operation_id = self.operations_log.log(OperationKind::Reverse {
+            operation_id,
+            debit,
+            credit,
+        });
+
+        Ok(operation_id)
+    }
+
+    fn do_set_overdraft_limit(&mut self, id: AccountID, limit: Money) -> Result<()> {
+        let account = self.get_open_account_mut(id)?;
+        // already overdrawn account can't get a limit below its current debt
+        match limit.checked_neg() {
+            Some(min_balance) if !limit.is_negative() && account.balance >= min_balance => {
+                account.overdraft_limit = limit;
+                Ok(())
+            }
+            _ => Err(BankError::InvalidOverdraftLimit),
+        }
+    }
+
+    /// Allows balance of the account to go below zero down to `-limit`.
+    pub fn set_overdraft_limit(&mut self, id: AccountID, limit: Money) -> Result<OperationID> {
+        self.do_set_overdraft_limit(id, limit)?;
+
+        let operation_id = self
+            .operations_log
+            .log(OperationKind::SetOverdraftLimit { id, limit });
+
+        Ok(operation_id)
+    }
+
+    fn do_set_interest_rate(&mut self, id: AccountID, rate: Rate) -> Result<()> {
+        self.get_open_account_mut(id)?.kind = AccountKind::Savings { rate };
+
+        Ok(())
+    }
+
+    /// Turns the account into a savings one, or changes the rate of a savings account.
+    pub fn set_interest_rate(&mut self, id: AccountID, rate: Rate) -> Result<OperationID> {
+        self.do_set_interest_rate(id, rate)?;
+
+        let operation_id = self
+            .operations_log
+            .log(OperationKind::SetInterestRate { id, rate });
+
+        Ok(operation_id)
+    }
+
+    /// Credits interest for `days` to every open savings account with positive balance,
+    /// one `Interest` operation per account. Nothing is credited if any of them overflows.
+    pub fn accrue_interest(&mut self, days: u32) -> Result<Vec<OperationID>> {
+        let mut accounts: Vec<&Account> = self.accounts.values().collect();
+        accounts.sort_by_key(|account| account.id);
+
+        let mut accruals = Vec::new();
+        for account in accounts {
+            let rate = match account.kind {
+                AccountKind::Savings { rate } => rate,
+                AccountKind::Checking => continue,
+            };
+            if account.status != AccountStatus::Open || !account.balance.is_positive() {
+                continue;
+            }
+
+            let amount =
+                accrued_interest(account.balance, rate, days).ok_or(BankError::Overflow)?;
+            account
+                .balance
+                .checked_add(amount)
+                .ok_or(BankError::Overflow)?;
+            if !amount.is_zero() {
+                accruals.push((account.id, amount));
+            }
+        }
+
+        let mut operation_ids = Vec::new();
+        for (id, amount) in accruals {
+            self.increase_account_balance(id, amount)?;
+            operation_ids.push(
+                self.operations_log
+                    .log(OperationKind::Interest { id, amount }),
+            );
+        }
+
+        Ok(operation_ids)
+    }
+
+    fn do_hold(&mut self, hold: Hold) -> Result<()> {
+        Self::check_amount(hold.amount)?;
+        if self.holds.contains_key(&hold.id) {
+            return Err(BankError::AlreadyExists);
+        }
+
+        Self::debited_balance(
+            self.get_open_account(hold.account_id)?,
+            self.held(hold.account_id),
+            hold.amount,
+        )?;
+
+        self.add_hold(hold)
+    }
+
+    // Records the hold as is, account may have been frozen or lost its overdraft
+    // since the hold was placed, so a snapshot is restored without do_hold checks.
+    fn add_hold(&mut self, hold: Hold) -> Result<()> {
+        let held = self
+            .held(hold.account_id)
+            .checked_add(hold.amount)
+            .ok_or(BankError::Overflow)?;
+
+        self.held.insert(hold.account_id, held);
+        self.holds.insert(hold.id, hold);
+        Ok(())
+    }
+
+    /// Reserves `amount` of the account, it is checked against limits like a withdrawal.
+    pub fn hold(&mut self, account_id: AccountID, amount: Money) -> Result<(HoldID, OperationID)> {
+        self.check_limits(account_id, amount)?;
+
+        let id = HoldID::new();
+        self.do_hold(Hold {
+            id,
+            account_id,
+            amount,
+        })?;
+
+        let operation_id = self.operations_log.log(OperationKind::Hold {
+            id,
+            account_id,
+            amount,
+        });
+
+        Ok((id, operation_id))
+    }
+
+    // Removes the hold, its funds become available again.
+    fn do_release(&mut self, id: HoldID) -> Result<Hold> {
+        let hold = self.holds.remove(&id).ok_or(BankError::HoldNotFound)?;
+        match self.held(hold.account_id).checked_sub(hold.amount) {
+            Some(held) if !held.is_zero() => self.held.insert(hold.account_id, held),
+            _ => self.held.remove(&hold.account_id),
+        };
+
+        Ok(hold)
+    }
+
+    /// Drops the hold without moving any money, works for frozen accounts too.
+    pub fn release(&mut self, id: HoldID) -> Result<OperationID> {
+        let hold = self.do_release(id)?;
+
+        let operation_id = self.operations_log.log(OperationKind::Release {
+            id,
+            account_id: hold.account_id,
+            amount: hold.amount,
+        });
+
+        Ok(operation_id)
+    }
+
+    // Reserved funds are debited without checking the overdraft limit again.
+    fn do_capture(&mut self, id: HoldID, amount: Money) -> Result<Hold> {
+        let hold = *self.holds.get(&id).ok_or(BankError::HoldNotFound)?;
+        Self::check_amount(amount)?;
+        if amount > hold.amount {
+            return Err(BankError::CaptureExceedsHold);
+        }
+
+        let balance = self
+            .get_open_account(hold.account_id)?
+            .balance
+            .checked_sub(amount)
+            .ok_or(BankError::Overflow)?;
+
+        self.do_release(id)?;
+        self.get_open_account_mut(hold.account_id)?.balance = balance;
+        Ok(hold)
+    }
+
+    /// Debits the whole hold or its `amount` part, the rest is released.
+    pub fn capture(&mut self, id: HoldID, amount: Option<Money>) -> Result<OperationID> {
+        let amount = match amount {
+            Some(amount) => amount,
+            None => self.holds.get(&id).ok_or(BankError::HoldNotFound)?.amount,
+        };
+        let hold = self.do_capture(id, amount)?;
+
+        let operation_id = self.operations_log.log(OperationKind::Capture {
+            id,
+            account_id: hold.account_id,
+            amount,
+        });
+
+        Ok(operation_id)
+    }
+
+    /// Open holds of the account ordered by id.
+    pub fn get_holds(&self, account_id: AccountID) -> Result<Vec<Hold>> {
+        self.get_status(account_id)?;
+
+        let mut holds: Vec<Hold> = self
+            .holds
+            .values()
+            .filter(|hold| hold.account_id == account_id)
+            .copied()
+            .collect();
+        holds.sort_by_key(|hold| hold.id);
+        Ok(holds)
+    }
+
+    /// Balance without funds on hold, what the account can spend before going into overdraft.
+    pub fn get_available_balance(&self, id: AccountID) -> Result<Money> {
+        self.get_balance(id)?
+            .checked_sub(self.held(id))
+            .ok_or(BankError::Overflow)
+    }
+
+    // Unlike balance changes, profile of a frozen account can be updated.
+    fn do_update_profile(&mut self, id: AccountID, field: ProfileField) -> Result<()> {
+        let account = self.accounts.get(&id).ok_or(BankError::NotFound)?;
+        if account.status == AccountStatus::Closed {
+            return Err(BankError::AccountClosed);
+        }
+        let old_alias = account.profile.alias;
+
+        if let ProfileField::Alias(Some(alias)) = field {
+            match self.aliases.get(&alias) {
+                Some(owner) if *owner != id => return Err(BankError::AliasTaken),
+                _ => {}
+            }
+        }
+        if let ProfileField::Alias(alias) = field {
+            if let Some(old_alias) = old_alias {
+                self.aliases.remove(&old_alias);
+            }
+            if let Some(alias) = alias {
+                self.aliases.insert(alias, id);
+            }
+        }
+
+        let account = self.accounts.get_mut(&id).ok_or(BankError::NotFound)?;
+        account.profile = account.profile.updated(field);
+
+        Ok(())
+    }
+
+    /// Sets a single field of the holder profile, `None` in the field clears it.
+    pub fn update_profile(&mut self, id: AccountID, field: ProfileField) -> Result<OperationID> {
+        self.do_update_profile(id, field)?;
+
+        let operation_id = self
+            .operations_log
+            .log(OperationKind::UpdateProfile { id, field });
+
+        Ok(operation_id)
+    }
+
+    pub fn get_profile(&self, id: AccountID) -> Result<Profile> {
+        match self.accounts.get(&id) {
+            Some(account) => Ok(account.profile),
+            None => Err(BankError::NotFound),
+        }
+    }
+
+    /// Account with the alias, closed accounts keep their aliases.
+    pub fn find_alias(&self, alias: Alias) -> Result<AccountID> {
+        self.aliases
+            .get(&alias)
+            .copied()
+            .ok_or(BankError::AliasNotFound)
+    }
+
+    fn do_freeze(&mut self, id: AccountID) -> Result<()> {
+        self.get_open_account_mut(id)?.status = AccountStatus::Frozen;
+
+        Ok(())
+    }
+
+    /// Blocks all balance changes of the account until it is unfrozen.
+    pub fn freeze(&mut self, id: AccountID) -> Result<OperationID> {
+        self.do_freeze(id)?;
+
+        let operation_id = self.operations_log.log(OperationKind::Freeze { id });
+
+        Ok(operation_id)
+    }
+
+    fn do_unfreeze(&mut self, id: AccountID) -> Result<()> {
+        let account = self.accounts.get_mut(&id).ok_or(BankError::NotFound)?;
+        match account.status {
+            AccountStatus::Frozen => account.status = AccountStatus::Open,
+            AccountStatus::Open => return Err(BankError::AccountNotFrozen),
+            AccountStatus::Closed => return Err(BankError::AccountClosed),
+        }
+
+        Ok(())
+    }
+
+    pub fn unfreeze(&mut self, id: AccountID) -> Result<OperationID> {
+        self.do_unfreeze(id)?;
+
+        let operation_id = self.operations_log.log(OperationKind::Unfreeze { id });
+
+        Ok(operation_id)
+    }
+
+    // Returns amount that was swept from the closed account.
+    fn do_close(
+        &mut self,
+        id: AccountID,
+        sweep_to: Option<AccountID>,
+        rate: Rate,
+    ) -> Result<Money> {
+        let balance = self.get_open_account(id)?.balance;
+        if !self.held(id).is_zero() {
+            return Err(BankError::HoldsPending);
+        }
+        // debt has to be paid off before closing
+        if balance.is_negative() {
+            return Err(BankError::BalanceNotEmpty);
+        }
+
+        if let Some(sweep_to) = sweep_to {
+            if sweep_to == id {
+                return Err(BankError::TransferToItself);
+            }
+            self.get_open_account(sweep_to)?;
+        }
+
+        if !balance.is_zero() {
+            match sweep_to {
+                Some(sweep_to) => self.do_transfer(id, sweep_to, balance, rate)?,
+                None => return Err(BankError::BalanceNotEmpty),
+            }
+        }
+
+        self.get_open_account_mut(id)?.status = AccountStatus::Closed;
+        Ok(balance)
+    }
+
+    /// Closes account, non-empty balance has to be swept to `sweep_to` account.
+    pub fn close(&mut self, id: AccountID, sweep_to: Option<AccountID>) -> Result<OperationID> {
+        let rate = match sweep_to {
+            Some(sweep_to) => {
+                self.get_exchange_rate(self.get_currency(id)?, self.get_currency(sweep_to)?)?
+            }
+            None => Rate::ONE,
+        };
+        let amount = self.do_close(id, sweep_to, rate)?;
+
+        let operation_id = self.operations_log.log(OperationKind::Close {
+            id,
+            sweep_to,
+            amount,
+            rate,
+        });
+
+        Ok(operation_id)
+    }
+
+    fn do_schedule_transfer(&mut self, schedule: Schedule) -> Result<()> {
+        if schedule.sender_id == schedule.receiver_id {
+            return Err(BankError::TransferToItself);
+        }
+
+        Self::check_amount(schedule.amount)?;
+
+        if schedule.interval == Some(0) || self.schedules.contains_key(&schedule.id) {
+            return Err(BankError::InvalidSchedule);
+        }
+
+        self.get_open_account(schedule.sender_id)?;
+        self.get_open_account(schedule.receiver_id)?;
+
+        self.schedules.insert(schedule.id, schedule);
+        Ok(())
+    }
+
+    /// Registers transfer that becomes due at `start` and repeats every `interval` seconds.
+    pub fn schedule_transfer(
+        &mut self,
+        sender_id: AccountID,
+        receiver_id: AccountID,
+        amount: Money,
+        start: Timestamp,
+        interval: Option<u64>,
+    ) -> Result<(ScheduleID, OperationID)> {
+        let id = ScheduleID::new();
+        self.do_schedule_transfer(Schedule {
+            id,
+            sender_id,
+            receiver_id,
+            amount,
+            next_run: start,
+            interval,
+        })?;
+
+        let operation_id = self.operations_log.log(OperationKind::ScheduleTransfer {
+            id,
+            sender_id,
+            receiver_id,
+            amount,
+            start,
+            interval,
+        });
+
+        Ok((id, operation_id))
+    }
+
+    fn do_cancel_schedule(&mut self, id: ScheduleID) -> Result<()> {
+        self.schedules
+            .remove(&id)
+            .map(|_| ())
+            .ok_or(BankError::ScheduleNotFound)
+    }
+
+    pub fn cancel_schedule(&mut self, id: ScheduleID) -> Result<OperationID> {
+        self.do_cancel_schedule(id)?;
+
+        let operation_id = self
+            .operations_log
+            .log(OperationKind::CancelSchedule { id });
+
+        Ok(operation_id)
+    }
+
+    fn do_skip_schedule(&mut self, id: ScheduleID) -> Result<()> {
+        if !self.schedules.contains_key(&id) {
+            return Err(BankError::ScheduleNotFound);
+        }
+        self.advance_schedule(id);
+
+        Ok(())
+    }
+
+    /// Moves schedule `id` to its next run without executing the current one,
+    /// one-time schedule is removed.
+    pub fn skip_schedule(&mut self, id: ScheduleID) -> Result<OperationID> {
+        self.do_skip_schedule(id)?;
+
+        let operation_id = self.operations_log.log(OperationKind::SkipSchedule { id });
+
+        Ok(operation_id)
+    }
+
+    // Moves schedule to its next execution, one-time schedule is done after the first one.
+    fn advance_schedule(&mut self, id: ScheduleID) {
+        match self.schedules.get(&id).map(Schedule::advanced) {
+            Some(Some(schedule)) => {
+                self.schedules.insert(id, schedule);
+            }
+            Some(None) => {
+                self.schedules.remove(&id);
+            }
+            None => {}
+        }
+    }
+
+    /// Executes the next transfer of schedule `id` no matter if it is due yet.
+    /// Failed execution leaves the schedule as it was, `skip_schedule` moves it on.
+    pub fn run_schedule(&mut self, id: ScheduleID) -> Result<OperationID> {
+        let schedule = *self.schedules.get(&id).ok_or(BankError::ScheduleNotFound)?;
+
+        let operation_id =
+            self.transfer(schedule.sender_id, schedule.receiver_id, schedule.amount)?;
+        self.operations_log.set_schedule(operation_id, id);
+        self.advance_schedule(id);
+
+        Ok(operation_id)
+    }
+
+    /// Schedules ordered by their next execution time.
+    pub fn get_schedules(&self) -> Vec<Schedule> {
+        let mut schedules: Vec<Schedule> = self.schedules.values().copied().collect();
+        schedules.sort_by_key(|schedule| (schedule.next_run, schedule.id));
+        schedules
+    }
+
+    pub fn get_due_schedules(&self, now: Timestamp) -> Vec<ScheduleID> {
+        self.get_schedules()
+            .into_iter()
+            .filter(|schedule| schedule.is_due(now))
+            .map(|schedule| schedule.id)
+            .collect()
+    }
+
+    pub fn get_all_operations(&self) -> impl Iterator<Item = &Operation> {
+        self.operations_log.get_all_operations()
+    }
+
+    pub fn get_account_operations(
+        &self,
+        account_id: AccountID,
+    ) -> impl Iterator<Item = &Operation> {
+        self.operations_log.get_account_operations(account_id)
+    }
+
+    /// Page of operations matching `query`, only the account's ones if `account_id` is set.
+    pub fn query_operations(
+        &self,
+        account_id: Option<AccountID>,
+        query: OperationsQuery,
+    ) -> Result<impl Iterator<Item = &Operation>> {
+        if account_id.is_some() && account_id == query.counterparty {
+            return Err(BankError::InvalidCounterparty);
+        }
+
+        self.operations_log
+            .query(account_id, query)
+            .ok_or(BankError::OperationNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn register_account_works() {
+        let clock = Arc::new(ManualClock::new(1000));
+        let mut bank = Bank::default();
+        bank.set_clock(SharedClock::new(clock.clone()));
+        let account1 = Account::new(Money::from_minor(100));
+        let account2 = Account::new(Money::from_minor(200));
+
+        let account1_id = account1.id;
+        let account2_id = account2.id;
+
+        let operation1_id = bank.register_account(account1).unwrap();
+        clock.advance(60);
+        let operation2_id = bank.register_account(account2).unwrap();
+
+        assert_ne!(operation1_id, operation2_id);
+
+        assert_eq!(
+            bank.get_operation(operation1_id),
+            Some(&Operation {
+                id: operation1_id,
+                kind: OperationKind::Register {
+                    id: account1_id,
+                    balance: Money::from_minor(100),
+                    currency: Currency::default(),
+                },
+                key: None,
+                schedule: None,
+                fee: None,
+                timestamp: 1000,
+            })
+        );
+
+        assert_eq!(
+            bank.get_operation(operation2_id),
+            Some(&Operation {
+                id: operation2_id,
+                kind: OperationKind::Register {
+                    id: account2_id,
+                    balance: Money::from_minor(200),
+                    currency: Currency::default(),
+                },
+                key: None,
+                schedule: None,
+                fee: None,
+                timestamp: 1060,
+            })
+        );
+
+        let account3 = account1;
+        assert_eq!(
+            bank.register_account(account3),
+            Err(BankError::AlreadyExists)
+        );
+
+        // fields a register operation does not log are rejected
+        for account in [
+            Account {
+                status: AccountStatus::Frozen,
+                ..Account::new(Money::ZERO)
+            },
+            Account {
+                overdraft_limit: Money::from_minor(100),
+                ..Account::new(Money::ZERO)
+            },
+            Account {
+                kind: AccountKind::Savings {
+                    rate: "0.02".parse().unwrap(),
+                },
+                ..Account::new(Money::ZERO)
+            },
+            Account {
+                profile: Profile {
+                    alias: Some("jane".parse().unwrap()),
+                    ..Profile::default()
+                },
+                ..Account::new(Money::ZERO)
+            },
+        ] {
+            assert_eq!(
+                bank.register_account(account),
+                Err(BankError::InvalidAccount)
+            );
+            assert_eq!(bank.get_balance(account.id), Err(BankError::NotFound));
+        }
+    }
+
+    #[test]
+    fn restore_account_settings_works() {
+        let mut bank = Bank::default();
+        let account = Account::new(Money::from_minor(100));
+        let account_id = account.id;
+
+        bank.register_account(account).unwrap();
+        bank.update_profile(account_id, ProfileField::parse("name", "Jane Doe").unwrap())
+            .unwrap();
+        bank.update_profile(account_id, ProfileField::parse("alias", "jane").unwrap())
+            .unwrap();
+        bank.set_overdraft_limit(account_id, Money::from_minor(500))
+            .unwrap();
+        bank.set_interest_rate(account_id, "0.02".parse().unwrap())
+            .unwrap();
+        bank.freeze(account_id).unwrap();
+
+        let restored_bank = Bank::restore(bank.get_all_operations()).unwrap();
+        assert_eq!(restored_bank.accounts, bank.accounts);
+        assert_eq!(restored_bank, bank);
+    }
+
+    #[test]
+    fn get_balance_works() {
+        let mut bank = Bank::default();
+
+        let account1 = Account::new(Money::from_minor(100));
+        let account2 = Account::new(Money::from_minor(200));
+        let account1_id = account1.id;
+        let account2_id = account2.id;
+        let account_undifned_id = AccountID::new();
+
+        bank.register_account(account1).unwrap();
+        bank.register_account(account2).unwrap();
+
+        assert_eq!(bank.get_balance(account1_id), Ok(Money::from_minor(100)));
+        assert_eq!(bank.get_balance(account2_id), Ok(Money::from_minor(200)));
+        assert_eq!(
+            bank.get_balance(account_undifned_id),
+            Err(BankError::NotFound)
+        );
+    }
+
+    #[test]
+    fn deposit_works() {
+        let mut bank = Bank::default();
+        let account = Account::new(Money::from_minor(100));
+        let account_id = account.id;
+
+        bank.register_account(account).unwrap();
+
+        assert_eq!(
+            bank.deposit(account_id, Money::from_minor(0)),
+            Err(BankError::ZeroAmount)
+        );
+
+        let operation_id = bank.deposit(account_id, Money::from_minor(50)).unwrap();
+        assert_eq!(
+            bank.get_operation(operation_id).unwrap().kind,
+            OperationKind::Deposit {
+                id: account_id,
+                amount: Money::from_minor(50),
+            }
+        );
+        assert_eq!(
+            bank.get_balance(account_id).unwrap(),
+            Money::from_minor(150)
+        );
+
+        let account_undifned_id = AccountID::new();
+        assert_eq!(
+            bank.deposit(account_undifned_id, Money::from_minor(40)),
+            Err(BankError::NotFound)
+        );
+    }
+
+    #[test]
+    fn withdraw_works() {
+        let mut bank = Bank::default();
+        let account = Account::new(Money::from_minor(100));
+        let account_id = account.id;
+        bank.register_account(account).unwrap();
+
+        assert_eq!(
+            bank.withdraw(account_id, Money::from_minor(0)),
+            Err(BankError::ZeroAmount)
+        );
+        assert_eq!(
+            bank.withdraw(account_id, Money::from_minor(200)),
+            Err(BankError::InsufficientFunds)
+        );
+
+        let operation_id = bank.withdraw(account_id, Money::from_minor(50)).unwrap();
+        assert_eq!(
+            bank.get_operation(operation_id).unwrap().kind,
+            OperationKind::Withdraw {
+                id: account_id,
+                amount: Money::from_minor(50)
+            }
+        );
+        assert_eq!(bank.get_balance(account_id).unwrap(), Money::from_minor(50));
+
+        let account_undifned_id = AccountID::new();
+        assert_eq!(
+            bank.withdraw(account_undifned_id, Money::from_minor(10)),
+            Err(BankError::NotFound)
+        )
+    }
+
+    #[test]
+    fn transfer_works() {
+        let mut bank = Bank::default();
+        let sender = Account::new(Money::from_minor(100));
+        let receiver = Account::new(Money::from_minor(200));
+        let sender_id = sender.id;
+        let receiver_id = receiver.id;
+
+        bank.register_account(sender).unwrap();
+        bank.register_account(receiver).unwrap();
+
+        assert_eq!(
+            bank.transfer(sender_id, receiver_id, Money::from_minor(0)),
+            Err(BankError::ZeroAmount)
+        );
+        assert_eq!(
+            bank.transfer(sender_id, receiver_id, Money::from_minor(1000)),
+            Err(BankError::InsufficientFunds)
+        );
+        assert_eq!(
+            bank.transfer(sender_id, sender_id, Money::from_minor(50)),
+            Err(BankError::TransferToItself)
+        );
+
+        let operation_id = bank
+            .transfer(sender_id, receiver_id, Money::from_minor(50))
+            .unwrap();
+        assert_eq!(
+            bank.get_operation(operation_id).unwrap().kind,
+            OperationKind::Transfer {
+                sender_id,
+                receiver_id,
+                amount: Money::from_minor(50),
+                rate: Rate::ONE,
+            },
+        );
+
+        assert_eq!(bank.get_balance(sender_id).unwrap(), Money::from_minor(50));
+        assert_eq!(
+            bank.get_balance(receiver_id).unwrap(),
+            Money::from_minor(250)
+        );
+    }
+
+    #[test]
+    fn failed_transfer_keeps_balances_works() {
+        let mut bank = Bank::default();
+        let sender = Account::new(Money::from_minor(100));
+        let receiver = Account::new(Money::from_minor(200));
+        let sender_id = sender.id;
+        let receiver_id = receiver.id;
+        let account_undefined_id = AccountID::new();
+
+        bank.register_account(sender).unwrap();
+        bank.register_account(receiver).unwrap();
+
+        let failed_transfers = vec![
+            (
+                sender_id,
+                receiver_id,
+                Money::from_minor(0),
+                BankError::ZeroAmount,
+            ),
+            (
+                sender_id,
+                sender_id,
+                Money::from_minor(50),
+                BankError::TransferToItself,
+            ),
+            (
+                sender_id,
+                receiver_id,
+                Money::from_minor(1000),
+                BankError::InsufficientFunds,
+            ),
+            (
+                sender_id,
+                account_undefined_id,
+                Money::from_minor(50),
+                BankError::NotFound,
+            ),
+            (
+                account_undefined_id,
+                receiver_id,
+                Money::from_minor(50),
+                BankError::NotFound,
+            ),
+        ];
+
+        for (from_id, to_id, amount, err) in failed_transfers {
+            assert_eq!(bank.transfer(from_id, to_id, amount), Err(err));
+            assert_eq!(bank.get_balance(sender_id).unwrap(), Money::from_minor(100));
+            assert_eq!(
+                bank.get_balance(receiver_id).unwrap(),
+                Money::from_minor(200)
+            );
+            assert_eq!(bank.get_all_operations().count(), 2);
+        }
+    }
+
+    #[test]
+    fn transfer_between_currencies_works() {
+        let mut bank = Bank::default();
+        let usd = Currency::default();
+        let eur: Currency = "EUR".parse().unwrap();
+
+        let sender = Account::with_currency(Money::from_minor(1000), usd);
+        let receiver = Account::with_currency(Money::from_minor(0), eur);
+        let sender_id = sender.id;
+        let receiver_id = receiver.id;
+
+        bank.register_account(sender).unwrap();
+        bank.register_account(receiver).unwrap();
+
+        assert_eq!(
+            bank.transfer(sender_id, receiver_id, Money::from_minor(100)),
+            Err(BankError::NoExchangeRate)
+        );
+        assert_eq!(
+            bank.set_exchange_rate(usd, usd, Rate::ONE),
+            Err(BankError::InvalidExchangeRate)
+        );
+
+        let rate = "0.92".parse().unwrap();
+        bank.set_exchange_rate(usd, eur, rate).unwrap();
+        let operation_id = bank
+            .transfer(sender_id, receiver_id, Money::from_minor(100))
+            .unwrap();
+
+        assert_eq!(
+            bank.get_operation(operation_id).unwrap().kind,
+            OperationKind::Transfer {
+                sender_id,
+                receiver_id,
+                amount: Money::from_minor(100),
+                rate,
+            },
+        );
+        assert_eq!(bank.get_balance(sender_id).unwrap(), Money::from_minor(900));
+        assert_eq!(
+            bank.get_balance(receiver_id).unwrap(),
+            Money::from_minor(92)
+        );
+
+        // restore uses logged rate even if the table has changed since
+        bank.set_exchange_rate(usd, eur, "0.5".parse().unwrap())
+            .unwrap();
+        let restored_bank = Bank::restore(bank.get_all_operations()).unwrap();
+        assert_eq!(restored_bank, bank);
+        assert_eq!(
+            restored_bank.get_balance(receiver_id).unwrap(),
+            Money::from_minor(92)
+        );
+        assert_eq!(
+            restored_bank.get_exchange_rate(usd, eur),
+            Ok("0.5".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn reverse_works() {
+        let mut bank = Bank::default();
+        let usd = Currency::default();
+        let eur: Currency = "EUR".parse().unwrap();
+        let sender = Account::with_currency(Money::from_minor(1000), usd);
+        let receiver = Account::with_currency(Money::from_minor(0), eur);
+        let sender_id = sender.id;
+        let receiver_id = receiver.id;
+
+        let register_id = bank.register_account(sender).unwrap();
+        bank.register_account(receiver).unwrap();
+        bank.set_exchange_rate(usd, eur, "0.5".parse().unwrap())
+            .unwrap();
+
+        assert_eq!(
+            bank.reverse(OperationID::new()),
+            Err(BankError::OperationNotFound)
+        );
+        assert_eq!(bank.reverse(register_id), Err(BankError::NotReversible));
+
+        let deposit_id = bank.deposit(sender_id, Money::from_minor(100)).unwrap();
+        let transfer_id = bank
+            .transfer(sender_id, receiver_id, Money::from_minor(400))
+            .unwrap();
+        let withdraw_id = bank.withdraw(receiver_id, Money::from_minor(150)).unwrap();
+        let snapshot = bank.snapshot();
+        let operations_count = bank.get_all_operations().count();
+
+        // receiver has only 50 left of 200 it got
+        assert_eq!(bank.reverse(transfer_id), Err(BankError::InsufficientFunds));
+
+        let reverse_id = bank.reverse(withdraw_id).unwrap();
+        assert_eq!(
+            bank.get_operation(reverse_id).unwrap().kind,
+            OperationKind::Reverse {
+                operation_id: withdraw_id,
+                debit: None,
+                credit: Some((receiver_id, Money::from_minor(150))),
+            },
+        );
+        assert_eq!(bank.reverse(withdraw_id), Err(BankError::AlreadyReversed));
+        assert_eq!(bank.reverse(reverse_id), Err(BankError::NotReversible));
+
+        bank.reverse(transfer_id).unwrap();
+        bank.reverse(deposit_id).unwrap();
+        assert_eq!(bank.get_balance(sender_id), Ok(Money::from_minor(1000)));
+        assert_eq!(bank.get_balance(receiver_id), Ok(Money::ZERO));
+        assert_eq!(bank.get_account_operations(receiver_id).count(), 5);
+
+        let restored_bank = Bank::restore(bank.get_all_operations()).unwrap();
+        assert_eq!(restored_bank, bank);
+
+        // reversal replays even when the original operation is covered by a snapshot
+        let restored_bank =
+            Bank::restore_from_snapshot(snapshot, bank.get_all_operations().skip(operations_count))
+                .unwrap();
+        assert_eq!(restored_bank.snapshot(), bank.snapshot());
+        assert_eq!(restored_bank.snapshot().reversed_operations.len(), 3);
+    }
+
+    #[test]
+    fn with_key_works() {
+        let mut bank = Bank::default();
+        let account = Account::new(Money::from_minor(100));
+        let account_id = account.id;
+        bank.register_account(account).unwrap();
+
+        let key: IdempotencyKey = "retry-1".parse().unwrap();
+        assert_eq!(bank.get_operation_by_key(key), None);
+
+        let deposit = KeyedRequest::Deposit {
+            id: account_id,
+            amount: Money::from_minor(10),
+        };
+        let operation_id = bank.with_key(key, deposit).unwrap();
+        assert_eq!(bank.get_operation(operation_id).unwrap().key, Some(key));
+        assert_eq!(bank.with_key(key, deposit), Ok(operation_id));
+        assert_eq!(bank.get_balance(account_id), Ok(Money::from_minor(110)));
+
+        // the key can't be reused for another request
+        for request in [
+            KeyedRequest::Deposit {
+                id: account_id,
+                amount: Money::from_minor(20),
+            },
+            KeyedRequest::Withdraw {
+                id: account_id,
+                amount: Money::from_minor(10),
+            },
+        ] {
+            assert_eq!(bank.with_key(key, request), Err(BankError::KeyReused));
+        }
+        assert_eq!(bank.get_balance(account_id), Ok(Money::from_minor(110)));
+        assert_eq!(bank.get_all_operations().count(), 2);
+
+        let restored_bank = Bank::restore(bank.get_all_operations()).unwrap();
+        assert_eq!(restored_bank, bank);
+
+        let snapshot_bank =
+            Bank::restore_from_snapshot(bank.snapshot(), std::iter::empty()).unwrap();
+        assert_eq!(snapshot_bank.get_operation_by_key(key), Some(operation_id));
+        assert_eq!(
+            snapshot_bank.find_keyed(key, deposit),
+            Ok(Some(operation_id))
+        );
+        assert_eq!(
+            snapshot_bank.find_keyed(
+                key,
+                KeyedRequest::Withdraw {
+                    id: account_id,
+                    amount: Money::from_minor(10),
+                }
+            ),
+            Err(BankError::KeyReused)
+        );
+    }
+
+    #[test]
+    fn fees_works() {
+        let mut bank = Bank::default();
+
+        let sender = Account::new(Money::from_minor(10000));
+        let receiver = Account::new(Money::ZERO);
+        let (sender_id, receiver_id) = (sender.id, receiver.id);
+        bank.register_account(sender).unwrap();
+        bank.register_account(receiver).unwrap();
+        let fee_account_id = AccountID::fee_account(Currency::default());
+
+        bank.set_fee(FeeOperation::Withdraw, Some("flat:0.50".parse().unwrap()))
+            .unwrap();
+        bank.set_fee(
+            FeeOperation::Transfer,
+            Some("capped:0.01:0.30".parse().unwrap()),
+        )
+        .unwrap();
+        assert_eq!(bank.get_balance(fee_account_id), Err(BankError::NotFound));
+
+        let withdraw_id = bank.withdraw(sender_id, Money::from_minor(1000)).unwrap();
+        assert_eq!(
+            bank.get_operation(withdraw_id).unwrap().fee,
+            Some((fee_account_id, Money::from_minor(50)))
+        );
+
+        // 1% of 20.00 and then capped 1% of 50.00
+        bank.transfer(sender_id, receiver_id, Money::from_minor(2000))
+            .unwrap();
+        let transfer_id = bank
+            .transfer(sender_id, receiver_id, Money::from_minor(5000))
+            .unwrap();
+        assert_eq!(bank.get_balance(sender_id), Ok(Money::from_minor(1900)));
+        assert_eq!(bank.get_balance(receiver_id), Ok(Money::from_minor(7000)));
+        assert_eq!(bank.get_balance(fee_account_id), Ok(Money::from_minor(100)));
+        assert_eq!(bank.get_account_operations(fee_account_id).count(), 3);
+
+        // amount is affordable, but not together with the fee
+        assert_eq!(
+            bank.withdraw(sender_id, Money::from_minor(1900)),
+            Err(BankError::InsufficientFunds)
+        );
+        assert_eq!(bank.get_balance(sender_id), Ok(Money::from_minor(1900)));
+
+        bank.set_fee(FeeOperation::Withdraw, None).unwrap();
+        let second_withdraw_id = bank.withdraw(sender_id, Money::from_minor(1900)).unwrap();
+        assert_eq!(bank.get_operation(second_withdraw_id).unwrap().fee, None);
+
+        // fees are not refunded when the operation is reversed
+        let reverse_id = bank.reverse(transfer_id).unwrap();
+        assert_eq!(bank.get_operation(reverse_id).unwrap().fee, None);
+        bank.reverse(withdraw_id).unwrap();
+        assert_eq!(bank.get_balance(sender_id), Ok(Money::from_minor(6000)));
+        assert_eq!(bank.get_balance(receiver_id), Ok(Money::from_minor(2000)));
+        assert_eq!(bank.get_balance(fee_account_id), Ok(Money::from_minor(100)));
+
+        let restored_bank = Bank::restore(bank.get_all_operations()).unwrap();
+        assert_eq!(restored_bank, bank);
+        assert_eq!(restored_bank.snapshot(), bank.snapshot());
+    }
+
+    #[test]
+    fn holds_count_against_daily_limit_works() {
+        let mut bank = Bank::default();
+        let account = Account::new(Money::from_minor(1000));
+        let account_id = account.id;
+        bank.register_account(account).unwrap();
+        bank.set_limit(None, LimitKind::Daily, Some(Money::from_minor(100)))
+            .unwrap();
+
+        let (first, _) = bank.hold(account_id, Money::from_minor(100)).unwrap();
+        assert_eq!(
+            bank.hold(account_id, Money::from_minor(100)),
+            Err(BankError::LimitExceeded)
+        );
+        assert_eq!(
+            bank.withdraw(account_id, Money::from_minor(1)),
+            Err(BankError::LimitExceeded)
+        );
+
+        bank.capture(first, None).unwrap();
+        assert_eq!(bank.get_outflow(account_id), Ok(Money::from_minor(100)));
+        assert_eq!(
+            bank.hold(account_id, Money::from_minor(1)),
+            Err(BankError::LimitExceeded)
+        );
+    }
+
+    #[test]
+    fn holds_works() {
+        let mut bank = Bank::default();
+        let account = Account::new(Money::from_minor(1000));
+        let other = Account::new(Money::ZERO);
+        let (account_id, other_id) = (account.id, other.id);
+        bank.register_account(account).unwrap();
+        bank.register_account(other).unwrap();
+
+        let (hold_id, _) = bank.hold(account_id, Money::from_minor(600)).unwrap();
+        assert_eq!(bank.get_balance(account_id), Ok(Money::from_minor(1000)));
+        assert_eq!(
+            bank.get_available_balance(account_id),
+            Ok(Money::from_minor(400))
+        );
+        assert_eq!(
+            bank.hold(account_id, Money::from_minor(500)),
+            Err(BankError::InsufficientFunds)
+        );
+        assert_eq!(
+            bank.transfer(account_id, other_id, Money::from_minor(500)),
+            Err(BankError::InsufficientFunds)
+        );
+        assert_eq!(
+            bank.close(account_id, Some(other_id)),
+            Err(BankError::HoldsPending)
+        );
+
+        assert_eq!(
+            bank.capture(hold_id, Some(Money::from_minor(700))),
+            Err(BankError::CaptureExceedsHold)
+        );
+        bank.capture(hold_id, Some(Money::from_minor(450))).unwrap();
+        assert_eq!(bank.capture(hold_id, None), Err(BankError::HoldNotFound));
+        assert_eq!(bank.get_balance(account_id), Ok(Money::from_minor(550)));
+        assert_eq!(
+            bank.get_available_balance(account_id),
+            Ok(Money::from_minor(550))
+        );
+
+        let (hold_id, _) = bank.hold(account_id, Money::from_minor(300)).unwrap();
+        let (other_hold_id, _) = bank.hold(account_id, Money::from_minor(200)).unwrap();
+        bank.release(other_hold_id).unwrap();
+        assert_eq!(bank.release(other_hold_id), Err(BankError::HoldNotFound));
+        assert_eq!(bank.get_holds(account_id).unwrap().len(), 1);
+
+        let restored_bank = Bank::restore(bank.get_all_operations()).unwrap();
+        assert_eq!(restored_bank, bank);
+        let restored_bank =
+            Bank::restore_from_snapshot(bank.snapshot(), std::iter::empty()).unwrap();
+        assert_eq!(
+            restored_bank.get_available_balance(account_id),
+            Ok(Money::from_minor(250))
+        );
+
+        // capture is a debit, so a frozen account blocks it
+        bank.freeze(account_id).unwrap();
+        assert_eq!(bank.capture(hold_id, None), Err(BankError::AccountFrozen));
+        bank.unfreeze(account_id).unwrap();
+        bank.capture(hold_id, None).unwrap();
+        assert_eq!(bank.get_balance(account_id), Ok(Money::from_minor(250)));
+
+        let statement = bank.statement(account_id).unwrap();
+        assert_eq!(
+            statement
+                .iter()
+                .map(|entry| (entry.kind, entry.balance.minor_units()))
+                .collect::<Vec<_>>(),
+            vec![("register", 1000), ("capture", 550), ("capture", 250)]
+        );
+    }
+
+    #[test]
+    fn negative_amount_works() {
+        let mut bank = Bank::default();
+        let sender = Account::new(Money::from_minor(1000));
+        let receiver = Account::new(Money::from_minor(1000));
+        let (sender_id, receiver_id) = (sender.id, receiver.id);
+        bank.register_account(sender).unwrap();
+        bank.register_account(receiver).unwrap();
+        bank.set_fee(FeeOperation::Transfer, Some("flat:0.10".parse().unwrap()))
+            .unwrap();
+        let (hold_id, _) = bank.hold(sender_id, Money::from_minor(100)).unwrap();
+        let operations_count = bank.get_all_operations().count();
+
+        let negative = Money::from_minor(-500);
+        assert_eq!(
+            bank.deposit(sender_id, negative),
+            Err(BankError::NegativeAmount)
+        );
+        assert_eq!(
+            bank.withdraw(sender_id, negative),
+            Err(BankError::NegativeAmount)
+        );
+        assert_eq!(
+            bank.transfer(sender_id, receiver_id, negative),
+            Err(BankError::NegativeAmount)
+        );
+        assert_eq!(
+            bank.hold(sender_id, negative)
+                .map(|(_, operation_id)| operation_id),
+            Err(BankError::NegativeAmount)
+        );
+        assert_eq!(
+            bank.capture(hold_id, Some(Money::from_minor(-10))),
+            Err(BankError::NegativeAmount)
+        );
+        assert_eq!(
+            bank.schedule_transfer(sender_id, receiver_id, negative, 0, None)
+                .map(|(_, operation_id)| operation_id),
+            Err(BankError::NegativeAmount)
+        );
+        assert_eq!(
+            bank.send_to_bank(sender_id, 2, receiver_id, Currency::default(), negative)
+                .map(|(operation_id, _)| operation_id),
+            Err(BankError::NegativeAmount)
+        );
+
+        assert_eq!(bank.get_balance(sender_id), Ok(Money::from_minor(1000)));
+        assert_eq!(bank.get_balance(receiver_id), Ok(Money::from_minor(1000)));
+        assert_eq!(bank.get_holds(sender_id).unwrap().len(), 1);
+        assert!(bank.get_schedules().is_empty());
+        assert_eq!(bank.get_all_operations().count(), operations_count);
+
+        // a log with such an amount does not replay either
+        let operations = [Operation {
+            id: OperationID::new(),
+            kind: OperationKind::Deposit {
+                id: sender_id,
+                amount: negative,
+            },
+            key: None,
+            schedule: None,
+            fee: None,
+            timestamp: 0,
+        }];
+        let operations: Vec<&Operation> = bank.get_all_operations().chain(&operations).collect();
+        assert_eq!(
+            Bank::restore(operations.into_iter()),
+            Err(BankError::NegativeAmount)
+        );
+    }
+
+    #[test]
+    fn verify_works() {
+        let mut bank = Bank::default();
+        let usd = Currency::default();
+        let eur: Currency = "EUR".parse().unwrap();
+
+        let account = Account::with_currency(Money::from_minor(1000), usd);
+        let other = Account::with_currency(Money::ZERO, eur);
+        let (account_id, other_id) = (account.id, other.id);
+        bank.register_account(account).unwrap();
+        bank.register_account(other).unwrap();
+        bank.set_exchange_rate(usd, eur, "0.5".parse().unwrap())
+            .unwrap();
+        bank.set_fee(FeeOperation::Withdraw, Some("flat:1".parse().unwrap()))
+            .unwrap();
+        bank.deposit(account_id, Money::from_minor(500)).unwrap();
+        bank.withdraw(account_id, Money::from_minor(200)).unwrap();
+        bank.transfer(account_id, other_id, Money::from_minor(300))
+            .unwrap();
+
+        let report = bank.verify().unwrap();
+        assert!(report.is_consistent());
+        assert_eq!(
+            report
+                .totals
+                .iter()
+                .map(|totals| totals.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "EUR opening 0.00 credited 1.50 debited 0.00 balance 1.50",
+                "USD opening 10.00 credited 6.00 debited 6.00 balance 10.00",
+            ]
+        );
+
+        // snapshot balances are the opening ones
+        let restored = Bank::restore_from_snapshot(bank.snapshot(), std::iter::empty()).unwrap();
+        assert!(restored.verify().unwrap().is_consistent());
+
+        let account = bank.accounts[&account_id];
+        let other = bank.accounts[&other_id];
+        bank.accounts.get_mut(&account_id).unwrap().balance = Money::from_minor(500);
+        bank.accounts.remove(&other_id);
+
+        let report = bank.verify().unwrap();
+        assert_eq!(report.discrepancies.len(), 3);
+        assert!(report.discrepancies.contains(&Discrepancy::Account {
+            id: account_id,
+            replayed: Some(Box::new(account)),
+            live: Some(Box::new(Account {
+                balance: Money::from_minor(500),
+                ..account
+            })),
+        }));
+        assert!(report.discrepancies.contains(&Discrepancy::Account {
+            id: other_id,
+            replayed: Some(Box::new(other)),
+            live: None,
+        }));
+        // postings of the missing account are not counted, so EUR has no totals mismatch
+        assert_eq!(
+            report.discrepancies[2].to_string(),
+            "total USD: expected 10.00, live 6.00"
+        );
+    }
+
+    #[test]
+    fn interbank_transfer_works() {
+        let (mut sender_bank, mut receiver_bank) = (Bank::default(), Bank::default());
+        let usd = Currency::default();
+        let eur: Currency = "EUR".parse().unwrap();
+
+        let sender = Account::with_currency(Money::from_minor(1000), usd);
+        let receiver = Account::with_currency(Money::from_minor(0), eur);
+        let (sender_id, receiver_id) = (sender.id, receiver.id);
+        sender_bank.register_account(sender).unwrap();
+        receiver_bank.register_account(receiver).unwrap();
+
+        assert_eq!(
+            receiver_bank.get_receiving_currency(sender_id),
+            Err(BankError::NotFound)
+        );
+        let currency = receiver_bank.get_receiving_currency(receiver_id).unwrap();
+        assert_eq!(
+            sender_bank.send_to_bank(sender_id, 2, receiver_id, currency, Money::from_minor(100)),
+            Err(BankError::NoExchangeRate)
+        );
+
+        let rate = "0.92".parse().unwrap();
+        sender_bank.set_exchange_rate(usd, eur, rate).unwrap();
+        sender_bank
+            .set_limit(None, LimitKind::Daily, Some(Money::from_minor(150)))
+            .unwrap();
+        let (send_id, amount) = sender_bank
+            .send_to_bank(sender_id, 2, receiver_id, currency, Money::from_minor(100))
+            .unwrap();
+        assert_eq!(amount, Money::from_minor(92));
+        receiver_bank
+            .receive_from_bank(send_id, 1, sender_id, receiver_id, amount)
+            .unwrap();
+
+        assert_eq!(
+            sender_bank.get_balance(sender_id),
+            Ok(Money::from_minor(900))
+        );
+        assert_eq!(
+            receiver_bank.get_balance(receiver_id),
+            Ok(Money::from_minor(92))
+        );
+        assert_eq!(
+            sender_bank.send_to_bank(sender_id, 2, receiver_id, currency, Money::from_minor(100)),
+            Err(BankError::LimitExceeded)
+        );
+        assert_eq!(sender_bank.reverse(send_id), Err(BankError::NotReversible));
+
+        assert_eq!(
+            Bank::restore(sender_bank.get_all_operations()).unwrap(),
+            sender_bank
+        );
+        assert_eq!(
+            Bank::restore(receiver_bank.get_all_operations()).unwrap(),
+            receiver_bank
+        );
+    }
+
+    #[test]
+    fn limits_works() {
+        let clock = Arc::new(ManualClock::new(1000));
+        let mut bank = Bank::default();
+        bank.set_clock(SharedClock::new(clock.clone()));
+
+        let sender = Account::new(Money::from_minor(100000));
+        let receiver = Account::new(Money::ZERO);
+        let (sender_id, receiver_id) = (sender.id, receiver.id);
+        bank.register_account(sender).unwrap();
+        bank.register_account(receiver).unwrap();
+
+        bank.set_limit(
+            None,
+            LimitKind::PerTransaction,
+            Some(Money::from_minor(5000)),
+        )
+        .unwrap();
+        bank.set_limit(
+            Some(sender_id),
+            LimitKind::Daily,
+            Some(Money::from_minor(8000)),
+        )
+        .unwrap();
+        assert_eq!(
+            bank.get_limits(Some(sender_id)),
+            Ok(Limits {
+                per_transaction: Some(Money::from_minor(5000)),
+                daily: Some(Money::from_minor(8000)),
+            })
+        );
+
+        assert_eq!(
+            bank.withdraw(sender_id, Money::from_minor(5001)),
+            Err(BankError::LimitExceeded)
+        );
+        bank.withdraw(sender_id, Money::from_minor(5000)).unwrap();
+        clock.advance(OUTFLOW_WINDOW / 2);
+        bank.transfer(sender_id, receiver_id, Money::from_minor(3000))
+            .unwrap();
+        assert_eq!(bank.get_outflow(sender_id), Ok(Money::from_minor(8000)));
+        assert_eq!(
+            bank.transfer(sender_id, receiver_id, Money::from_minor(1)),
+            Err(BankError::LimitExceeded)
+        );
+        // failed attempts don't count
+        assert_eq!(bank.get_outflow(sender_id), Ok(Money::from_minor(8000)));
+
+        // the first withdrawal leaves the window
+        clock.advance(OUTFLOW_WINDOW / 2);
+        assert_eq!(bank.get_outflow(sender_id), Ok(Money::from_minor(3000)));
+        bank.withdraw(sender_id, Money::from_minor(5000)).unwrap();
+
+        let restored_bank = Bank::restore(bank.get_all_operations()).unwrap();
+        assert_eq!(restored_bank, bank);
+        let restored_bank =
+            Bank::restore_from_snapshot(bank.snapshot(), std::iter::empty()).unwrap();
+        assert_eq!(restored_bank.snapshot(), bank.snapshot());
+
+        bank.set_limit(Some(sender_id), LimitKind::Daily, None)
+            .unwrap();
+        bank.withdraw(sender_id, Money::from_minor(5000)).unwrap();
+        assert_eq!(bank.get_limits(None), bank.get_limits(Some(sender_id)));
+        assert_eq!(
+            bank.set_limit(Some(AccountID::new()), LimitKind::Daily, None),
+            Err(BankError::NotFound)
+        );
+    }
+
+    #[test]
+    fn get_balance_at_works() {
+        let clock = Arc::new(ManualClock::new(1000));
+        let mut bank = Bank::default();
+        bank.set_clock(SharedClock::new(clock.clone()));
 
-        let result_balance = account.balance as i64 + amount;
-        if result_balance < 0 {
-            return Err(BankError::InsufficientFunds);
+        let sender = Account::new(Money::from_minor(10000));
+        let receiver = Account::new(Money::ZERO);
+        let (sender_id, receiver_id) = (sender.id, receiver.id);
+        let register_id = bank.register_account(sender).unwrap();
+        bank.register_account(receiver).unwrap();
+        let fee_account_id = AccountID::fee_account(Currency::default());
+        bank.set_fee(FeeOperation::Withdraw, Some("flat:0.50".parse().unwrap()))
+            .unwrap();
+
+        clock.advance(60);
+        let withdraw_id = bank.withdraw(sender_id, Money::from_minor(1000)).unwrap();
+        clock.advance(60);
+        let transfer_id = bank
+            .transfer(sender_id, receiver_id, Money::from_minor(2000))
+            .unwrap();
+        clock.advance(60);
+        bank.reverse(transfer_id).unwrap();
+
+        let balance_at = |id, cutoff| bank.get_balance_at(id, cutoff).map(|b| b.minor_units());
+        assert_eq!(
+            balance_at(sender_id, Cutoff::Operation(register_id)),
+            Ok(10000)
+        );
+        assert_eq!(
+            balance_at(sender_id, Cutoff::Operation(withdraw_id)),
+            Ok(8950)
+        );
+        assert_eq!(
+            balance_at(fee_account_id, Cutoff::Operation(withdraw_id)),
+            Ok(50)
+        );
+        // cutoff may be an operation of another account
+        assert_eq!(
+            balance_at(receiver_id, Cutoff::Operation(withdraw_id)),
+            Ok(0)
+        );
+        assert_eq!(
+            balance_at(receiver_id, Cutoff::Operation(transfer_id)),
+            Ok(2000)
+        );
+        assert_eq!(balance_at(sender_id, Cutoff::Time(999)), Ok(0));
+        assert_eq!(balance_at(sender_id, Cutoff::Time(1119)), Ok(8950));
+        assert_eq!(balance_at(sender_id, Cutoff::Time(1120)), Ok(6950));
+        for id in [sender_id, receiver_id, fee_account_id] {
+            assert_eq!(
+                bank.get_balance_at(id, Cutoff::Time(Timestamp::MAX)),
+                bank.get_balance(id)
+            );
         }
 
-        account.balance = result_balance as u64;
-        Ok(())
-    }
+        assert_eq!(
+            balance_at(sender_id, Cutoff::Operation(OperationID::new())),
+            Err(BankError::OperationNotFound)
+        );
+        assert_eq!(
+            balance_at(AccountID::new(), Cutoff::Time(1000)),
+            Err(BankError::NotFound)
+        );
 
-    fn do_deposit(&mut self, id: AccountID, amount: u64) -> Result<()> {
-        self.update_account_balance_by_amount(id, amount as i64)
+        // history covered by the snapshot is not kept
+        let snapshot = bank.snapshot();
+        let last_operation_id = snapshot.last_operation_id.unwrap();
+        assert_eq!(snapshot.last_operation_time, Some(1180));
+        let mut bank = Bank::restore_from_snapshot(snapshot, std::iter::empty()).unwrap();
+        bank.set_clock(SharedClock::new(clock.clone()));
+        clock.advance(60);
+        let deposit_id = bank.deposit(sender_id, Money::from_minor(50)).unwrap();
+
+        let balance_at = |id, cutoff| bank.get_balance_at(id, cutoff).map(|b| b.minor_units());
+        assert_eq!(
+            balance_at(sender_id, Cutoff::Operation(last_operation_id)),
+            Ok(8950)
+        );
+        assert_eq!(balance_at(sender_id, Cutoff::Time(1180)), Ok(8950));
+        assert_eq!(
+            balance_at(sender_id, Cutoff::Operation(deposit_id)),
+            Ok(9000)
+        );
+        assert_eq!(balance_at(sender_id, Cutoff::Time(1240)), Ok(9000));
+        assert_eq!(
+            balance_at(sender_id, Cutoff::Operation(withdraw_id)),
+            Err(BankError::HistoryUnavailable)
+        );
+        assert_eq!(
+            balance_at(sender_id, Cutoff::Time(1179)),
+            Err(BankError::HistoryUnavailable)
+        );
+        assert_eq!(
+            balance_at(sender_id, Cutoff::Time(999)),
+            Err(BankError::HistoryUnavailable)
+        );
     }
 
-    pub fn deposit(&mut self, id: AccountID, amount: u64) -> Result<OperationID> {
-        self.do_deposit(id, amount)?;
+    #[test]
+    fn statement_works() {
+        let mut bank = Bank::default();
 
-        let operation_id = self
-            .operations_log
-            .log(OperationKind::Deposit { id, amount });
-        Ok(operation_id)
-    }
+        let sender = Account::new(Money::from_minor(10000));
+        let receiver = Account::new(Money::ZERO);
+        let (sender_id, receiver_id) = (sender.id, receiver.id);
+        bank.register_account(sender).unwrap();
+        bank.register_account(receiver).unwrap();
+        let fee_account_id = AccountID::fee_account(Currency::default());
+        bank.set_fee(FeeOperation::Withdraw, Some("flat:0.50".parse().unwrap()))
+            .unwrap();
+
+        bank.deposit(sender_id, Money::from_minor(1000)).unwrap();
+        let withdraw_id = bank.withdraw(sender_id, Money::from_minor(1000)).unwrap();
+        let transfer_id = bank
+            .transfer(sender_id, receiver_id, Money::from_minor(2000))
+            .unwrap();
+        bank.reverse(transfer_id).unwrap();
+
+        let lines = |id| {
+            bank.statement(id)
+                .unwrap()
+                .into_iter()
+                .map(|entry| {
+                    (
+                        entry.kind,
+                        entry.direction,
+                        entry.amount.minor_units(),
+                        entry.counterparty,
+                        entry.balance.minor_units(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            lines(sender_id),
+            vec![
+                ("register", Direction::Credit, 10000, None, 10000),
+                ("deposit", Direction::Credit, 1000, None, 11000),
+                ("withdraw", Direction::Debit, 1000, None, 10000),
+                ("fee", Direction::Debit, 50, Some(fee_account_id), 9950),
+                ("transfer", Direction::Debit, 2000, Some(receiver_id), 7950),
+                ("reverse", Direction::Credit, 2000, Some(receiver_id), 9950),
+            ]
+        );
+        assert_eq!(
+            lines(receiver_id),
+            vec![
+                ("register", Direction::Credit, 0, None, 0),
+                ("transfer", Direction::Credit, 2000, Some(sender_id), 2000),
+                ("reverse", Direction::Debit, 2000, Some(sender_id), 0),
+            ]
+        );
+        assert_eq!(
+            lines(fee_account_id),
+            vec![("fee", Direction::Credit, 50, Some(sender_id), 50)]
+        );
+        assert_eq!(
+            bank.statement(sender_id).unwrap()[3].operation_id,
+            withdraw_id
+        );
 
-    fn do_withdraw(&mut self, id: AccountID, amount: u64) -> Result<()> {
-        self.update_account_balance_by_amount(id, -(amount as i64))
+        // statement of a restored bank starts from its snapshot balance
+        let bank = Bank::restore_from_snapshot(bank.snapshot(), std::iter::empty()).unwrap();
+        assert_eq!(bank.statement(sender_id), Ok(Vec::new()));
+        assert_eq!(bank.statement(AccountID::new()), Err(BankError::NotFound));
     }
 
-    pub fn withdraw(&mut self, id: AccountID, amount: u64) -> Result<OperationID> {
-        self.do_withdraw(id, amount)?;
+    #[test]
+    fn query_operations_works() {
+        let mut bank = Bank::default();
 
-        let operation_id = self
-            .operations_log
-            .log(OperationKind::Withdraw { id, amount });
-        Ok(operation_id)
-    }
+        let accounts = [
+            Account::new(Money::from_minor(10000)),
+            Account::new(Money::ZERO),
+            Account::new(Money::ZERO),
+        ];
+        let [a_id, b_id, c_id] = accounts.map(|account| account.id);
+        for account in accounts {
+            bank.register_account(account).unwrap();
+        }
+        bank.deposit(a_id, Money::from_minor(5000)).unwrap();
+        let a_to_b = bank.transfer(a_id, b_id, Money::from_minor(2000)).unwrap();
+        let a_to_c = bank.transfer(a_id, c_id, Money::from_minor(3000)).unwrap();
+        bank.withdraw(b_id, Money::from_minor(500)).unwrap();
+        let b_to_c = bank.transfer(b_id, c_id, Money::from_minor(1000)).unwrap();
+
+        let query = |account_id, query| {
+            bank.query_operations(account_id, query)
+                .map(|operations| operations.map(|operation| operation.id).collect::<Vec<_>>())
+        };
+        let all: Vec<OperationID> = bank.get_all_operations().map(|op| op.id).collect();
+
+        assert_eq!(query(None, OperationsQuery::default()), Ok(all.clone()));
+        assert_eq!(
+            query(
+                None,
+                OperationsQuery {
+                    kind: Some("transfer"),
+                    ..OperationsQuery::default()
+                }
+            ),
+            Ok(vec![a_to_b, a_to_c, b_to_c])
+        );
+        assert_eq!(
+            query(
+                None,
+                OperationsQuery {
+                    kind: Some("transfer"),
+                    min_amount: Some(Money::from_minor(2000)),
+                    max_amount: Some(Money::from_minor(3000)),
+                    ..OperationsQuery::default()
+                }
+            ),
+            Ok(vec![a_to_b, a_to_c])
+        );
+        assert_eq!(
+            query(
+                Some(a_id),
+                OperationsQuery {
+                    counterparty: Some(c_id),
+                    ..OperationsQuery::default()
+                }
+            ),
+            Ok(vec![a_to_c])
+        );
+        // opening deposit of c has no counterparty
+        assert_eq!(
+            query(
+                None,
+                OperationsQuery {
+                    counterparty: Some(c_id),
+                    ..OperationsQuery::default()
+                }
+            ),
+            Ok(vec![a_to_c, b_to_c])
+        );
+        assert_eq!(
+            query(
+                None,
+                OperationsQuery {
+                    counterparty: Some(c_id),
+                    offset: 1,
+                    ..OperationsQuery::default()
+                }
+            ),
+            Ok(vec![b_to_c])
+        );
+        // a listing of c matches b only where b is on the other side
+        assert_eq!(
+            query(
+                Some(c_id),
+                OperationsQuery {
+                    counterparty: Some(b_id),
+                    ..OperationsQuery::default()
+                }
+            ),
+            Ok(vec![b_to_c])
+        );
+        assert_eq!(
+            query(
+                Some(a_id),
+                OperationsQuery {
+                    counterparty: Some(a_id),
+                    ..OperationsQuery::default()
+                }
+            ),
+            Err(BankError::InvalidCounterparty)
+        );
+        assert_eq!(
+            query(
+                Some(a_id),
+                OperationsQuery {
+                    after: Some(a_to_b),
+                    ..OperationsQuery::default()
+                }
+            ),
+            Ok(vec![a_to_c])
+        );
 
-    fn do_transfer(
-        &mut self,
-        sender_id: AccountID,
-        receiver_id: AccountID,
-        amount: u64,
-    ) -> Result<()> {
-        if sender_id == receiver_id {
-            return Err(BankError::TransferToItself);
+        // paging through the log by cursor gives every operation once
+        let mut pages = Vec::new();
+        let mut after = None;
+        loop {
+            let page = query(
+                None,
+                OperationsQuery {
+                    after,
+                    limit: Some(3),
+                    ..OperationsQuery::default()
+                },
+            )
+            .unwrap();
+            if page.is_empty() {
+                break;
+            }
+            after = page.last().copied();
+            pages.push(page);
         }
+        assert_eq!(pages.len(), 3);
+        assert_eq!(pages.concat(), all);
 
-        self.update_account_balance_by_amount(sender_id, -(amount as i64))?;
-        self.update_account_balance_by_amount(receiver_id, amount as i64)?;
+        assert_eq!(
+            query(
+                None,
+                OperationsQuery {
+                    after: Some(OperationID::new()),
+                    ..OperationsQuery::default()
+                }
+            ),
+            Err(BankError::OperationNotFound)
+        );
 
-        Ok(())
+        // fee is paid by the sender, the receiver is not a counterparty of the fee account
+        bank.set_fee(FeeOperation::Transfer, Some("flat:0.10".parse().unwrap()))
+            .unwrap();
+        let c_to_b = bank.transfer(c_id, b_id, Money::from_minor(100)).unwrap();
+        let fee_account_id = AccountID::fee_account(Currency::default());
+        let query = |account_id, counterparty| {
+            bank.query_operations(
+                account_id,
+                OperationsQuery {
+                    counterparty: Some(counterparty),
+                    ..OperationsQuery::default()
+                },
+            )
+            .map(|operations| operations.map(|operation| operation.id).collect::<Vec<_>>())
+        };
+        assert_eq!(query(Some(c_id), fee_account_id), Ok(vec![c_to_b]));
+        assert_eq!(query(Some(b_id), fee_account_id), Ok(vec![]));
+        assert_eq!(query(Some(fee_account_id), c_id), Ok(vec![c_to_b]));
+        assert_eq!(query(Some(fee_account_id), b_id), Ok(vec![]));
     }
 
-    pub fn transfer(
-        &mut self,
-        sender_id: AccountID,
-        receiver_id: AccountID,
-        amount: u64,
-    ) -> Result<OperationID> {
-        self.do_transfer(sender_id, receiver_id, amount)?;
-
-        let operation_id = self.operations_log.log(OperationKind::Transfer {
-            sender_id,
-            receiver_id,
-            amount,
-        });
+    #[test]
+    fn interest_works() {
+        let mut bank = Bank::default();
 
-        Ok(operation_id)
-    }
+        let savings = Account::new(Money::from_minor(100000));
+        let checking = Account::new(Money::from_minor(100000));
+        let empty = Account::new(Money::ZERO);
+        let (savings_id, checking_id, empty_id) = (savings.id, checking.id, empty.id);
+        bank.register_account(savings).unwrap();
+        bank.register_account(checking).unwrap();
+        bank.register_account(empty).unwrap();
+
+        let rate: Rate = "0.05".parse().unwrap();
+        bank.set_interest_rate(savings_id, rate).unwrap();
+        bank.set_interest_rate(empty_id, rate).unwrap();
+        assert_eq!(
+            bank.set_interest_rate(AccountID::new(), rate),
+            Err(BankError::NotFound)
+        );
 
-    pub fn get_all_operations(&self) -> impl Iterator<Item = &Operation> {
-        self.operations_log.get_all_operations()
-    }
+        // 1000.00 at 5% for 30 days is 4.1095... rounded to 4.11
+        let operation_ids = bank.accrue_interest(30).unwrap();
+        assert_eq!(operation_ids.len(), 1);
+        assert_eq!(
+            bank.get_operation(operation_ids[0]).unwrap().kind,
+            OperationKind::Interest {
+                id: savings_id,
+                amount: Money::from_minor(411),
+            }
+        );
+        assert_eq!(bank.get_balance(savings_id), Ok(Money::from_minor(100411)));
+        assert_eq!(bank.get_balance(checking_id), Ok(Money::from_minor(100000)));
+
+        bank.freeze(savings_id).unwrap();
+        assert!(bank.accrue_interest(30).unwrap().is_empty());
+        bank.unfreeze(savings_id).unwrap();
+
+        bank.set_interest_rate(savings_id, "10".parse().unwrap())
+            .unwrap();
+        bank.deposit(
+            savings_id,
+            Money::MAX.checked_sub(Money::from_minor(200411)).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(bank.accrue_interest(365), Err(BankError::Overflow));
 
-    pub fn get_account_operations(
-        &self,
-        account_id: AccountID,
-    ) -> impl Iterator<Item = &Operation> {
-        self.operations_log.get_account_operations(account_id)
+        let restored_bank = Bank::restore(bank.get_all_operations()).unwrap();
+        assert_eq!(restored_bank, bank);
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
 
     #[test]
-    fn register_account_works() {
+    fn overdraft_works() {
         let mut bank = Bank::default();
-        let account1 = Account::new(100);
-        let account2 = Account::new(200);
+        let account = Account::new(Money::from_minor(100));
+        let other = Account::new(Money::from_minor(50));
+        let account_id = account.id;
+        let other_id = other.id;
 
-        let account1_id = account1.id;
-        let account2_id = account2.id;
+        bank.register_account(account).unwrap();
+        bank.register_account(other).unwrap();
 
-        let operation1_id = bank.register_account(account1).unwrap();
-        let operation2_id = bank.register_account(account2).unwrap();
+        assert_eq!(
+            bank.withdraw(account_id, Money::from_minor(101)),
+            Err(BankError::InsufficientFunds)
+        );
+        assert_eq!(
+            bank.set_overdraft_limit(account_id, Money::from_minor(-1)),
+            Err(BankError::InvalidOverdraftLimit)
+        );
 
-        assert_ne!(operation1_id, operation2_id);
+        bank.set_overdraft_limit(account_id, Money::from_minor(500))
+            .unwrap();
+        bank.withdraw(account_id, Money::from_minor(300)).unwrap();
+        assert_eq!(bank.get_balance(account_id), Ok(Money::from_minor(-200)));
 
         assert_eq!(
-            bank.get_operation(operation1_id),
-            Some(&Operation {
-                id: operation1_id,
-                kind: OperationKind::Register {
-                    id: account1_id,
-                    balance: 100
-                }
-            })
+            bank.transfer(account_id, other_id, Money::from_minor(301)),
+            Err(BankError::InsufficientFunds)
         );
+        bank.transfer(account_id, other_id, Money::from_minor(300))
+            .unwrap();
+        assert_eq!(bank.get_balance(account_id), Ok(Money::from_minor(-500)));
+        assert_eq!(bank.get_balance(other_id), Ok(Money::from_minor(350)));
 
         assert_eq!(
-            bank.get_operation(operation2_id),
-            Some(&Operation {
-                id: operation2_id,
-                kind: OperationKind::Register {
-                    id: account2_id,
-                    balance: 200
-                }
-            })
+            bank.set_overdraft_limit(account_id, Money::from_minor(499)),
+            Err(BankError::InvalidOverdraftLimit)
+        );
+        assert_eq!(
+            bank.close(account_id, Some(other_id)),
+            Err(BankError::BalanceNotEmpty)
         );
 
-        let account3 = account1;
+        let restored_bank = Bank::restore(bank.get_all_operations()).unwrap();
+        assert_eq!(restored_bank, bank);
+
+        let snapshot_bank =
+            Bank::restore_from_snapshot(bank.snapshot(), std::iter::empty()).unwrap();
         assert_eq!(
-            bank.register_account(account3),
-            Err(BankError::AlreadyExists)
+            snapshot_bank.get_balance(account_id),
+            Ok(Money::from_minor(-500))
+        );
+
+        bank.deposit(account_id, Money::from_minor(600)).unwrap();
+        bank.set_overdraft_limit(account_id, Money::ZERO).unwrap();
+        assert_eq!(
+            bank.withdraw(account_id, Money::from_minor(101)),
+            Err(BankError::InsufficientFunds)
         );
     }
 
     #[test]
-    fn get_balance_works() {
+    fn freeze_works() {
         let mut bank = Bank::default();
+        let account = Account::new(Money::from_minor(100));
+        let other = Account::new(Money::from_minor(50));
+        let account_id = account.id;
+        let other_id = other.id;
 
-        let account1 = Account::new(100);
-        let account2 = Account::new(200);
-        let account1_id = account1.id;
-        let account2_id = account2.id;
-        let account_undifned_id = AccountID::new();
+        bank.register_account(account).unwrap();
+        bank.register_account(other).unwrap();
 
-        bank.register_account(account1).unwrap();
-        bank.register_account(account2).unwrap();
+        assert_eq!(bank.unfreeze(account_id), Err(BankError::AccountNotFrozen));
+        bank.freeze(account_id).unwrap();
+        assert_eq!(bank.freeze(account_id), Err(BankError::AccountFrozen));
+        assert_eq!(bank.get_status(account_id), Ok(AccountStatus::Frozen));
+        assert_eq!(bank.get_balance(account_id), Ok(Money::from_minor(100)));
 
-        assert_eq!(bank.get_balance(account1_id), Ok(100));
-        assert_eq!(bank.get_balance(account2_id), Ok(200));
+        let amount = Money::from_minor(10);
         assert_eq!(
-            bank.get_balance(account_undifned_id),
-            Err(BankError::NotFound)
+            bank.deposit(account_id, amount),
+            Err(BankError::AccountFrozen)
         );
+        assert_eq!(
+            bank.withdraw(account_id, amount),
+            Err(BankError::AccountFrozen)
+        );
+        assert_eq!(
+            bank.transfer(other_id, account_id, amount),
+            Err(BankError::AccountFrozen)
+        );
+        assert_eq!(
+            bank.transfer(account_id, other_id, amount),
+            Err(BankError::AccountFrozen)
+        );
+        assert_eq!(
+            bank.close(account_id, Some(other_id)),
+            Err(BankError::AccountFrozen)
+        );
+
+        let restored_bank = Bank::restore(bank.get_all_operations()).unwrap();
+        assert_eq!(restored_bank, bank);
+        assert_eq!(
+            restored_bank.get_status(account_id),
+            Ok(AccountStatus::Frozen)
+        );
+
+        bank.unfreeze(account_id).unwrap();
+        bank.deposit(account_id, amount).unwrap();
+        assert_eq!(bank.get_account_operations(account_id).count(), 4);
+        assert_eq!(bank.get_status(account_id), Ok(AccountStatus::Open));
     }
 
     #[test]
-    fn deposit_works() {
+    fn profile_works() {
         let mut bank = Bank::default();
-        let account = Account::new(100);
+        let account = Account::new(Money::from_minor(0));
+        let other = Account::new(Money::from_minor(0));
         let account_id = account.id;
+        let other_id = other.id;
+        let jane: Alias = "jane".parse().unwrap();
 
         bank.register_account(account).unwrap();
-
-        assert_eq!(bank.deposit(account_id, 0), Err(BankError::ZeroAmount));
-
-        let operation_id = bank.deposit(account_id, 50).unwrap();
+        bank.register_account(other).unwrap();
+        assert!(bank.get_profile(account_id).unwrap().is_empty());
+        assert_eq!(bank.find_alias(jane), Err(BankError::AliasNotFound));
+
+        bank.update_profile(account_id, ProfileField::parse("name", "Jane_Doe").unwrap())
+            .unwrap();
+        bank.update_profile(account_id, ProfileField::Alias(Some(jane)))
+            .unwrap();
+        // setting the same alias again is fine
+        bank.update_profile(account_id, ProfileField::Alias(Some(jane)))
+            .unwrap();
+        assert_eq!(bank.find_alias(jane), Ok(account_id));
         assert_eq!(
-            bank.get_operation(operation_id).unwrap().kind,
-            OperationKind::Deposit {
-                id: account_id,
-                amount: 50,
-            }
+            bank.update_profile(other_id, ProfileField::Alias(Some(jane))),
+            Err(BankError::AliasTaken)
         );
-        assert_eq!(bank.get_balance(account_id).unwrap(), 150);
 
-        let account_undifned_id = AccountID::new();
+        let profile = bank.get_profile(account_id).unwrap();
+        assert_eq!(profile.name, Some("Jane_Doe".parse().unwrap()));
+        assert_eq!(profile.alias, Some(jane));
+
+        let restored_bank = Bank::restore(bank.get_all_operations()).unwrap();
+        assert_eq!(restored_bank, bank);
+        let restored_bank =
+            Bank::restore_from_snapshot(bank.snapshot(), std::iter::empty()).unwrap();
+        assert_eq!(restored_bank.find_alias(jane), Ok(account_id));
+
+        // alias is released when it is changed
+        let doe: Alias = "doe".parse().unwrap();
+        bank.update_profile(account_id, ProfileField::Alias(Some(doe)))
+            .unwrap();
+        assert_eq!(bank.find_alias(jane), Err(BankError::AliasNotFound));
+        bank.update_profile(other_id, ProfileField::Alias(Some(jane)))
+            .unwrap();
+
+        // closed accounts keep their aliases
+        bank.close(account_id, None).unwrap();
+        assert_eq!(bank.find_alias(doe), Ok(account_id));
         assert_eq!(
-            bank.deposit(account_undifned_id, 40),
+            bank.update_profile(account_id, ProfileField::Alias(None)),
+            Err(BankError::AccountClosed)
+        );
+        assert_eq!(
+            bank.update_profile(AccountID::new(), ProfileField::Alias(None)),
             Err(BankError::NotFound)
         );
     }
 
     #[test]
-    fn withdraw_works() {
+    fn close_works() {
         let mut bank = Bank::default();
-        let account = Account::new(100);
+        let account = Account::new(Money::from_minor(100));
+        let other = Account::new(Money::from_minor(50));
+        let empty = Account::new(Money::ZERO);
         let account_id = account.id;
+        let other_id = other.id;
+        let empty_id = empty.id;
+
         bank.register_account(account).unwrap();
+        bank.register_account(other).unwrap();
+        bank.register_account(empty).unwrap();
 
-        assert_eq!(bank.withdraw(account_id, 0), Err(BankError::ZeroAmount));
         assert_eq!(
-            bank.withdraw(account_id, 200),
-            Err(BankError::InsufficientFunds)
+            bank.close(account_id, None),
+            Err(BankError::BalanceNotEmpty)
+        );
+        assert_eq!(
+            bank.close(account_id, Some(account_id)),
+            Err(BankError::TransferToItself)
         );
 
-        let operation_id = bank.withdraw(account_id, 50).unwrap();
+        bank.close(empty_id, None).unwrap();
+        assert_eq!(
+            bank.close(account_id, Some(empty_id)),
+            Err(BankError::AccountClosed)
+        );
+
+        let operation_id = bank.close(account_id, Some(other_id)).unwrap();
         assert_eq!(
             bank.get_operation(operation_id).unwrap().kind,
-            OperationKind::Withdraw {
+            OperationKind::Close {
                 id: account_id,
-                amount: 50
-            }
+                sweep_to: Some(other_id),
+                amount: Money::from_minor(100),
+                rate: Rate::ONE,
+            },
         );
-        assert_eq!(bank.get_balance(account_id).unwrap(), 50);
+        assert_eq!(bank.get_balance(account_id).unwrap(), Money::ZERO);
+        assert_eq!(bank.get_balance(other_id).unwrap(), Money::from_minor(150));
+        assert_eq!(bank.get_account_operations(other_id).count(), 2);
 
-        let account_undifned_id = AccountID::new();
+        let amount = Money::from_minor(10);
         assert_eq!(
-            bank.withdraw(account_undifned_id, 10),
-            Err(BankError::NotFound)
-        )
+            bank.deposit(account_id, amount),
+            Err(BankError::AccountClosed)
+        );
+        assert_eq!(
+            bank.withdraw(account_id, amount),
+            Err(BankError::AccountClosed)
+        );
+        assert_eq!(
+            bank.transfer(other_id, account_id, amount),
+            Err(BankError::AccountClosed)
+        );
+        assert_eq!(bank.close(account_id, None), Err(BankError::AccountClosed));
+
+        let restored_bank = Bank::restore(bank.get_all_operations()).unwrap();
+        assert_eq!(restored_bank, bank);
+
+        let mut snapshot_bank =
+            Bank::restore_from_snapshot(bank.snapshot(), std::iter::empty()).unwrap();
+        assert_eq!(
+            snapshot_bank.deposit(account_id, amount),
+            Err(BankError::AccountClosed)
+        );
     }
 
     #[test]
-    fn transfer_works() {
+    fn overflow_works() {
         let mut bank = Bank::default();
-        let sender = Account::new(100);
-        let receiver = Account::new(200);
-        let sender_id = sender.id;
-        let receiver_id = receiver.id;
+        let rich = Account::new(Money::MAX);
+        let poor = Account::new(Money::from_minor(10));
+        let rich_id = rich.id;
+        let poor_id = poor.id;
 
-        bank.register_account(sender).unwrap();
-        bank.register_account(receiver).unwrap();
+        bank.register_account(rich).unwrap();
+        bank.register_account(poor).unwrap();
 
         assert_eq!(
-            bank.transfer(sender_id, receiver_id, 0),
-            Err(BankError::ZeroAmount)
+            bank.deposit(rich_id, Money::from_minor(1)),
+            Err(BankError::Overflow)
         );
         assert_eq!(
-            bank.transfer(sender_id, receiver_id, 1000),
-            Err(BankError::InsufficientFunds)
+            bank.transfer(poor_id, rich_id, Money::from_minor(5)),
+            Err(BankError::Overflow)
         );
         assert_eq!(
-            bank.transfer(sender_id, sender_id, 50),
-            Err(BankError::TransferToItself)
+            bank.withdraw(poor_id, Money::MAX),
+            Err(BankError::InsufficientFunds)
         );
-
-        let operation_id = bank.transfer(sender_id, receiver_id, 50).unwrap();
         assert_eq!(
-            bank.get_operation(operation_id).unwrap().kind,
-            OperationKind::Transfer {
-                sender_id,
-                receiver_id,
-                amount: 50
-            },
+            bank.transfer(rich_id, poor_id, Money::MAX),
+            Err(BankError::Overflow)
         );
 
-        assert_eq!(bank.get_balance(sender_id).unwrap(), 50);
-        assert_eq!(bank.get_balance(receiver_id).unwrap(), 250);
+        assert_eq!(bank.get_balance(rich_id).unwrap(), Money::MAX);
+        assert_eq!(bank.get_balance(poor_id).unwrap(), Money::from_minor(10));
+        assert_eq!(bank.get_all_operations().count(), 2);
+
+        bank.withdraw(rich_id, Money::from_minor(i64::MAX - 1))
+            .unwrap();
+        assert_eq!(bank.get_balance(rich_id).unwrap(), Money::from_minor(1));
     }
 
     #[test]
     fn get_all_operations_works() {
         let mut bank = Bank::default();
 
-        let account1 = Account::new(100);
-        let account2 = Account::new(200);
-        let account3 = Account::new(300);
+        let account1 = Account::new(Money::from_minor(100));
+        let account2 = Account::new(Money::from_minor(200));
+        let account3 = Account::new(Money::from_minor(300));
 
         let account1_id = account1.id;
         let account2_id = account2.id;
@@ -366,9 +3237,10 @@ mod tests {
         bank.register_account(account2).unwrap();
         bank.register_account(account3).unwrap();
 
-        bank.deposit(account1_id, 50).unwrap();
-        bank.withdraw(account2_id, 50).unwrap();
-        bank.transfer(account3_id, account2_id, 10).unwrap();
+        bank.deposit(account1_id, Money::from_minor(50)).unwrap();
+        bank.withdraw(account2_id, Money::from_minor(50)).unwrap();
+        bank.transfer(account3_id, account2_id, Money::from_minor(10))
+            .unwrap();
 
         let operations = bank
             .get_all_operations()
@@ -378,28 +3250,32 @@ mod tests {
         let expected_operations = vec![
             OperationKind::Register {
                 id: account1_id,
-                balance: 100,
+                balance: Money::from_minor(100),
+                currency: Currency::default(),
             },
             OperationKind::Register {
                 id: account2_id,
-                balance: 200,
+                balance: Money::from_minor(200),
+                currency: Currency::default(),
             },
             OperationKind::Register {
                 id: account3_id,
-                balance: 300,
+                balance: Money::from_minor(300),
+                currency: Currency::default(),
             },
             OperationKind::Deposit {
                 id: account1_id,
-                amount: 50,
+                amount: Money::from_minor(50),
             },
             OperationKind::Withdraw {
                 id: account2_id,
-                amount: 50,
+                amount: Money::from_minor(50),
             },
             OperationKind::Transfer {
                 sender_id: account3_id,
                 receiver_id: account2_id,
-                amount: 10,
+                amount: Money::from_minor(10),
+                rate: Rate::ONE,
             },
         ];
 
@@ -410,9 +3286,9 @@ mod tests {
     fn get_account_operations_works() {
         let mut bank = Bank::default();
 
-        let account1 = Account::new(100);
-        let account2 = Account::new(200);
-        let account3 = Account::new(300);
+        let account1 = Account::new(Money::from_minor(100));
+        let account2 = Account::new(Money::from_minor(200));
+        let account3 = Account::new(Money::from_minor(300));
 
         let account1_id = account1.id;
         let account2_id = account2.id;
@@ -422,12 +3298,14 @@ mod tests {
         bank.register_account(account2).unwrap();
         bank.register_account(account3).unwrap();
 
-        bank.deposit(account1_id, 50).unwrap();
-        bank.withdraw(account2_id, 50).unwrap();
-        bank.transfer(account3_id, account2_id, 20).unwrap();
-        bank.deposit(account1_id, 150).unwrap();
-        bank.withdraw(account1_id, 10).unwrap();
-        bank.transfer(account1_id, account2_id, 10).unwrap();
+        bank.deposit(account1_id, Money::from_minor(50)).unwrap();
+        bank.withdraw(account2_id, Money::from_minor(50)).unwrap();
+        bank.transfer(account3_id, account2_id, Money::from_minor(20))
+            .unwrap();
+        bank.deposit(account1_id, Money::from_minor(150)).unwrap();
+        bank.withdraw(account1_id, Money::from_minor(10)).unwrap();
+        bank.transfer(account1_id, account2_id, Money::from_minor(10))
+            .unwrap();
 
         let account1_operations = bank
             .get_account_operations(account1_id)
@@ -437,24 +3315,26 @@ mod tests {
         let account1_expected_operations = vec![
             OperationKind::Register {
                 id: account1_id,
-                balance: 100,
+                balance: Money::from_minor(100),
+                currency: Currency::default(),
             },
             OperationKind::Deposit {
                 id: account1_id,
-                amount: 50,
+                amount: Money::from_minor(50),
             },
             OperationKind::Deposit {
                 id: account1_id,
-                amount: 150,
+                amount: Money::from_minor(150),
             },
             OperationKind::Withdraw {
                 id: account1_id,
-                amount: 10,
+                amount: Money::from_minor(10),
             },
             OperationKind::Transfer {
                 sender_id: account1_id,
                 receiver_id: account2_id,
-                amount: 10,
+                amount: Money::from_minor(10),
+                rate: Rate::ONE,
             },
         ];
 
@@ -468,21 +3348,24 @@ mod tests {
         let account2_expected_operations = vec![
             OperationKind::Register {
                 id: account2_id,
-                balance: 200,
+                balance: Money::from_minor(200),
+                currency: Currency::default(),
             },
             OperationKind::Withdraw {
                 id: account2_id,
-                amount: 50,
+                amount: Money::from_minor(50),
             },
             OperationKind::Transfer {
                 sender_id: account3_id,
                 receiver_id: account2_id,
-                amount: 20,
+                amount: Money::from_minor(20),
+                rate: Rate::ONE,
             },
             OperationKind::Transfer {
                 sender_id: account1_id,
                 receiver_id: account2_id,
-                amount: 10,
+                amount: Money::from_minor(10),
+                rate: Rate::ONE,
             },
         ];
 
@@ -496,12 +3379,14 @@ mod tests {
         let account3_expected_operations = vec![
             OperationKind::Register {
                 id: account3_id,
-                balance: 300,
+                balance: Money::from_minor(300),
+                currency: Currency::default(),
             },
             OperationKind::Transfer {
                 sender_id: account3_id,
                 receiver_id: account2_id,
-                amount: 20,
+                amount: Money::from_minor(20),
+                rate: Rate::ONE,
             },
         ];
 
@@ -512,9 +3397,9 @@ mod tests {
     fn restore_works() {
         let mut bank1 = Bank::default();
 
-        let account1 = Account::new(100);
-        let account2 = Account::new(200);
-        let account3 = Account::new(300);
+        let account1 = Account::new(Money::from_minor(100));
+        let account2 = Account::new(Money::from_minor(200));
+        let account3 = Account::new(Money::from_minor(300));
 
         let account1_id = account1.id;
         let account2_id = account2.id;
@@ -524,15 +3409,59 @@ mod tests {
         bank1.register_account(account2).unwrap();
         bank1.register_account(account3).unwrap();
 
-        bank1.deposit(account1_id, 50).unwrap();
-        bank1.withdraw(account2_id, 50).unwrap();
-        bank1.transfer(account3_id, account2_id, 20).unwrap();
-        bank1.deposit(account1_id, 150).unwrap();
-        bank1.withdraw(account1_id, 10).unwrap();
-        bank1.transfer(account1_id, account2_id, 10).unwrap();
+        bank1.deposit(account1_id, Money::from_minor(50)).unwrap();
+        bank1.withdraw(account2_id, Money::from_minor(50)).unwrap();
+        bank1
+            .transfer(account3_id, account2_id, Money::from_minor(20))
+            .unwrap();
+        bank1.deposit(account1_id, Money::from_minor(150)).unwrap();
+        bank1.withdraw(account1_id, Money::from_minor(10)).unwrap();
+        bank1
+            .transfer(account1_id, account2_id, Money::from_minor(10))
+            .unwrap();
 
         let bank2 = Bank::restore(bank1.get_all_operations()).unwrap();
 
         assert_eq!(bank1, bank2)
     }
+
+    #[test]
+    fn restore_from_snapshot_works() {
+        let mut bank1 = Bank::default();
+
+        let account1 = Account::new(Money::from_minor(100));
+        let account2 = Account::new(Money::from_minor(200));
+
+        let account1_id = account1.id;
+        let account2_id = account2.id;
+
+        bank1.register_account(account1).unwrap();
+        bank1.register_account(account2).unwrap();
+        let operation_id = bank1
+            .transfer(account1_id, account2_id, Money::from_minor(30))
+            .unwrap();
+
+        let snapshot = bank1.snapshot();
+        assert_eq!(snapshot.last_operation_id, Some(operation_id));
+        assert_eq!(snapshot.accounts.len(), 2);
+
+        let operations_count = bank1.get_all_operations().count();
+        bank1.deposit(account1_id, Money::from_minor(50)).unwrap();
+        bank1.withdraw(account2_id, Money::from_minor(20)).unwrap();
+
+        let bank2 = Bank::restore_from_snapshot(
+            snapshot.clone(),
+            bank1.get_all_operations().skip(operations_count),
+        )
+        .unwrap();
+
+        assert_eq!(bank2.base_snapshot(), &snapshot);
+        assert_eq!(bank2.get_all_operations().count(), 2);
+        assert_eq!(bank2.get_balance(account1_id), Ok(Money::from_minor(120)));
+        assert_eq!(bank2.get_balance(account2_id), Ok(Money::from_minor(210)));
+        assert_eq!(
+            bank2.snapshot().last_operation_id,
+            bank1.snapshot().last_operation_id
+        );
+    }
 }
@@ -1,7 +1,10 @@
+use crate::bank::currency::{Currency, Rate};
+use crate::bank::money::Money;
+use crate::bank::profile::Profile;
 use std::fmt;
 use uuid::Uuid;
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
 pub struct AccountID(Uuid);
 pub type Error = uuid::Error;
 
@@ -13,6 +16,14 @@ impl AccountID {
     pub fn parse_str(s: &str) -> Result<AccountID, Error> {
         Uuid::parse_str(s).map(AccountID)
     }
+
+    /// Built-in account collecting fees in `currency`,
+    /// its id is the currency code padded with zeros, e.g. `00000000-0000-0000-0000-000000555344`.
+    pub fn fee_account(currency: Currency) -> AccountID {
+        let mut bytes = [0; 16];
+        bytes[13..].copy_from_slice(&currency.code());
+        AccountID(Uuid::from_bytes(bytes))
+    }
 }
 
 impl fmt::Display for AccountID {
@@ -21,17 +32,94 @@ impl fmt::Display for AccountID {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AccountStatus {
+    #[default]
+    Open,
+    Frozen,
+    Closed,
+}
+
+impl fmt::Display for AccountStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AccountStatus::Open => write!(f, "open"),
+            AccountStatus::Frozen => write!(f, "frozen"),
+            AccountStatus::Closed => write!(f, "closed"),
+        }
+    }
+}
+
+impl std::str::FromStr for AccountStatus {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<AccountStatus, ()> {
+        match s {
+            "open" => Ok(AccountStatus::Open),
+            "frozen" => Ok(AccountStatus::Frozen),
+            "closed" => Ok(AccountStatus::Closed),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum AccountKind {
+    #[default]
+    Checking,
+    /// Earns interest at annual `rate`, e.g. 0.035 for 3.5%.
+    Savings { rate: Rate },
+}
+
+impl fmt::Display for AccountKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AccountKind::Checking => write!(f, "checking"),
+            AccountKind::Savings { rate } => write!(f, "savings:{}", rate),
+        }
+    }
+}
+
+impl std::str::FromStr for AccountKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<AccountKind, ()> {
+        match s.split_once(':') {
+            None if s == "checking" => Ok(AccountKind::Checking),
+            Some(("savings", rate)) => Ok(AccountKind::Savings {
+                rate: rate.parse().map_err(|_| ())?,
+            }),
+            _ => Err(()),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Account {
     pub id: AccountID,
-    pub balance: u64,
+    pub balance: Money,
+    pub currency: Currency,
+    pub status: AccountStatus,
+    /// How far below zero the balance may go.
+    pub overdraft_limit: Money,
+    pub kind: AccountKind,
+    pub profile: Profile,
 }
 
 impl Account {
-    pub fn new(balance: u64) -> Account {
+    pub fn new(balance: Money) -> Account {
+        Account::with_currency(balance, Currency::default())
+    }
+
+    pub fn with_currency(balance: Money, currency: Currency) -> Account {
         Account {
             id: AccountID::new(),
             balance,
+            currency,
+            status: AccountStatus::Open,
+            overdraft_limit: Money::ZERO,
+            kind: AccountKind::Checking,
+            profile: Profile::default(),
         }
     }
 }
@@ -42,9 +130,38 @@ mod tests {
 
     #[test]
     fn new_account_works() {
-        let account1 = Account::new(100);
-        let account2 = Account::new(200);
-        assert_eq!(account1.balance, 100);
-        assert_eq!(account2.balance, 200);
+        let account1 = Account::new(Money::from_minor(100));
+        let account2 = Account::new(Money::from_minor(200));
+        assert_eq!(account1.balance, Money::from_minor(100));
+        assert_eq!(account2.balance, Money::from_minor(200));
+        assert_eq!(account1.currency, Currency::default());
+    }
+
+    #[test]
+    fn parse_kind_works() {
+        let savings = AccountKind::Savings {
+            rate: "0.035".parse().unwrap(),
+        };
+        assert_eq!(savings.to_string(), "savings:0.035");
+        assert_eq!("savings:0.035".parse::<AccountKind>(), Ok(savings));
+        assert_eq!("checking".parse::<AccountKind>(), Ok(AccountKind::Checking));
+        assert_eq!("savings".parse::<AccountKind>(), Err(()));
+        assert_eq!("savings:0".parse::<AccountKind>(), Err(()));
+    }
+
+    #[test]
+    fn fee_account_works() {
+        let usd = AccountID::fee_account(Currency::default());
+        assert_eq!(usd.to_string(), "00000000-0000-0000-0000-000000555344");
+        assert_eq!(usd, AccountID::fee_account(Currency::default()));
+        assert_ne!(usd, AccountID::fee_account("EUR".parse().unwrap()));
+    }
+
+    #[test]
+    fn with_currency_works() {
+        let currency = "EUR".parse().unwrap();
+        let account = Account::with_currency(Money::from_minor(100), currency);
+        assert_eq!(account.balance, Money::from_minor(100));
+        assert_eq!(account.currency, currency);
     }
 }
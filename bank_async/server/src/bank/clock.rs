@@ -0,0 +1,220 @@
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Seconds since the Unix epoch.
+pub type Timestamp = u64;
+
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Timestamp;
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Timestamp {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs())
+    }
+}
+
+/// Clock that stands still until it is moved, makes tests deterministic.
+#[derive(Debug, Default)]
+pub struct ManualClock(AtomicU64);
+
+impl ManualClock {
+    pub fn new(now: Timestamp) -> ManualClock {
+        ManualClock(AtomicU64::new(now))
+    }
+
+    pub fn set(&self, now: Timestamp) {
+        self.0.store(now, Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, seconds: u64) {
+        self.0.fetch_add(seconds, Ordering::SeqCst);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Timestamp {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Handle to a clock shared by the repository and its banks, the system clock by default.
+///
+/// Clock is not a part of the state, so all handles are equal to each other.
+#[derive(Clone)]
+pub struct SharedClock(Arc<dyn Clock>);
+
+impl SharedClock {
+    pub fn new(clock: Arc<dyn Clock>) -> SharedClock {
+        SharedClock(clock)
+    }
+
+    pub fn now(&self) -> Timestamp {
+        self.0.now()
+    }
+}
+
+impl Default for SharedClock {
+    fn default() -> SharedClock {
+        SharedClock(Arc::new(SystemClock))
+    }
+}
+
+impl fmt::Debug for SharedClock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SharedClock({})", self.now())
+    }
+}
+
+impl PartialEq for SharedClock {
+    fn eq(&self, _: &SharedClock) -> bool {
+        true
+    }
+}
+
+/// Formats timestamp as UTC date and time, e.g. `2024-03-01T12:30:00Z`.
+pub fn format_timestamp(timestamp: Timestamp) -> String {
+    let days = timestamp / 86400;
+    let seconds = timestamp % 86400;
+    let (year, month, day) = civil_from_days(days as i64);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Parses `2024-03-01T12:30:00Z` or `2024-03-01`, the latter is the start of the day.
+pub fn parse_timestamp(s: &str) -> Option<Timestamp> {
+    let (date, time) = match s.split_once('T') {
+        Some((date, time)) => (date, Some(time.strip_suffix('Z')?)),
+        None => (s, None),
+    };
+
+    let number = |part: &str, len: usize| {
+        if part.len() == len && part.bytes().all(|b| b.is_ascii_digit()) {
+            part.parse::<u32>().ok()
+        } else {
+            None
+        }
+    };
+
+    let (year, month, day) = match date.split('-').collect::<Vec<_>>()[..] {
+        [year, month, day] => (number(year, 4)?, number(month, 2)?, number(day, 2)?),
+        _ => return None,
+    };
+    let (hours, minutes, seconds) = match time.map(|time| time.split(':').collect::<Vec<_>>()) {
+        Some(parts) => match parts[..] {
+            [hours, minutes, seconds] => {
+                (number(hours, 2)?, number(minutes, 2)?, number(seconds, 2)?)
+            }
+            _ => return None,
+        },
+        None => (0, 0, 0),
+    };
+
+    if year < 1970 || hours > 23 || minutes > 59 || seconds > 59 {
+        return None;
+    }
+
+    let days = days_from_civil(year as i64, month, day)?;
+    Some(days as u64 * 86400 + (hours * 3600 + minutes * 60 + seconds) as u64)
+}
+
+// Inverse of `civil_from_days`, `None` for a date that does not exist.
+fn days_from_civil(year: i64, month: u32, day: u32) -> Option<i64> {
+    if !(1..=12).contains(&month) || day < 1 {
+        return None;
+    }
+
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = if month > 2 { month - 3 } else { month + 9 } as i64;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+
+    // day past the end of the month lands in the next one
+    match civil_from_days(days) {
+        (_, date_month, _) if date_month == month => Some(days),
+        _ => None,
+    }
+}
+
+// Gregorian date of the day `days` after 1970-01-01, see
+// http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manual_clock_works() {
+        let clock = Arc::new(ManualClock::new(100));
+        let shared = SharedClock::new(clock.clone());
+        assert_eq!(shared.now(), 100);
+
+        clock.advance(20);
+        assert_eq!(shared.now(), 120);
+        clock.set(5);
+        assert_eq!(shared.now(), 5);
+    }
+
+    #[test]
+    fn format_timestamp_works() {
+        assert_eq!(format_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_timestamp(951782400), "2000-02-29T00:00:00Z");
+        assert_eq!(format_timestamp(1709296200), "2024-03-01T12:30:00Z");
+        assert_eq!(format_timestamp(4102444799), "2099-12-31T23:59:59Z");
+    }
+
+    #[test]
+    fn parse_timestamp_works() {
+        for timestamp in [0, 951782400, 1709296200, 4102444799] {
+            assert_eq!(
+                parse_timestamp(&format_timestamp(timestamp)),
+                Some(timestamp)
+            );
+        }
+        assert_eq!(parse_timestamp("2024-03-01"), Some(1709251200));
+
+        for invalid in [
+            "2024-02-30",
+            "2023-02-29",
+            "2024-13-01",
+            "2024-3-1",
+            "2024-03-01T24:00:00Z",
+            "2024-03-01T12:30:00",
+            "1969-12-31",
+            "yesterday",
+        ] {
+            assert_eq!(parse_timestamp(invalid), None, "{}", invalid);
+        }
+    }
+}
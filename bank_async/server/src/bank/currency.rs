@@ -0,0 +1,167 @@
+use crate::bank::money::Money;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Error {
+    InvalidCurrency,
+    InvalidRate,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidCurrency => write!(f, "currency code must be three latin letters"),
+            Error::InvalidRate => write!(f, "rate must be a positive decimal number"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// ISO 4217 like currency code, e.g. `USD`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Currency([u8; 3]);
+
+impl Currency {
+    pub const fn code(&self) -> [u8; 3] {
+        self.0
+    }
+}
+
+impl Default for Currency {
+    fn default() -> Currency {
+        Currency(*b"USD")
+    }
+}
+
+impl FromStr for Currency {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Currency, Error> {
+        let code: [u8; 3] = s
+            .as_bytes()
+            .try_into()
+            .map_err(|_| Error::InvalidCurrency)?;
+        if !code.iter().all(u8::is_ascii_alphabetic) {
+            return Err(Error::InvalidCurrency);
+        }
+
+        Ok(Currency(code.map(|c| c.to_ascii_uppercase())))
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // only ascii letters get inside, see from_str
+        write!(f, "{}", std::str::from_utf8(&self.0).unwrap())
+    }
+}
+
+/// Exchange rate as a fixed point number with `Rate::SCALE` fractional digits,
+/// so converting amounts never depends on floating point rounding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rate(u64);
+
+impl Rate {
+    pub const SCALE: u32 = 6;
+    pub const ONE: Rate = Rate(10u64.pow(Rate::SCALE));
+
+    /// Rate multiplied by 10^`Rate::SCALE`.
+    pub const fn units(&self) -> u64 {
+        self.0
+    }
+
+    /// Converts `amount` rounding down to minor units, `None` means that result is too large.
+    pub fn convert(&self, amount: Money) -> Option<Money> {
+        let converted = amount.minor_units() as i128 * self.0 as i128 / Rate::ONE.0 as i128;
+        converted.try_into().ok().map(Money::from_minor)
+    }
+}
+
+impl FromStr for Rate {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Rate, Error> {
+        let (int, frac) = s.split_once('.').unwrap_or((s, ""));
+        let is_digits = |part: &str| part.bytes().all(|c| c.is_ascii_digit());
+        if int.is_empty()
+            || !is_digits(int)
+            || !is_digits(frac)
+            || frac.len() > Rate::SCALE as usize
+        {
+            return Err(Error::InvalidRate);
+        }
+
+        let int: u64 = int.parse().map_err(|_| Error::InvalidRate)?;
+        let frac: u64 = format!("{:0<width$}", frac, width = Rate::SCALE as usize)
+            .parse()
+            .map_err(|_| Error::InvalidRate)?;
+
+        let rate = int
+            .checked_mul(Rate::ONE.0)
+            .and_then(|int| int.checked_add(frac))
+            .ok_or(Error::InvalidRate)?;
+
+        if rate == 0 {
+            return Err(Error::InvalidRate);
+        }
+
+        Ok(Rate(rate))
+    }
+}
+
+impl fmt::Display for Rate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let int = self.0 / Rate::ONE.0;
+        let frac = self.0 % Rate::ONE.0;
+        if frac == 0 {
+            return write!(f, "{}", int);
+        }
+
+        let frac = format!("{:0width$}", frac, width = Rate::SCALE as usize);
+        write!(f, "{}.{}", int, frac.trim_end_matches('0'))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_currency_works() {
+        assert_eq!("eur".parse::<Currency>().unwrap().to_string(), "EUR");
+        assert_eq!(Currency::default().to_string(), "USD");
+        assert_eq!("EURO".parse::<Currency>(), Err(Error::InvalidCurrency));
+        assert_eq!("E1R".parse::<Currency>(), Err(Error::InvalidCurrency));
+    }
+
+    #[test]
+    fn parse_rate_works() {
+        assert_eq!("1".parse::<Rate>().unwrap(), Rate::ONE);
+        assert_eq!("0.92".parse::<Rate>().unwrap().to_string(), "0.92");
+        assert_eq!(
+            "90.000001".parse::<Rate>().unwrap().to_string(),
+            "90.000001"
+        );
+        assert_eq!("0".parse::<Rate>(), Err(Error::InvalidRate));
+        assert_eq!(".5".parse::<Rate>(), Err(Error::InvalidRate));
+        assert_eq!("1.0000001".parse::<Rate>(), Err(Error::InvalidRate));
+        assert_eq!("-1".parse::<Rate>(), Err(Error::InvalidRate));
+    }
+
+    #[test]
+    fn convert_works() {
+        let rate: Rate = "0.925".parse().unwrap();
+        assert_eq!(
+            rate.convert(Money::from_minor(1000)),
+            Some(Money::from_minor(925))
+        );
+        assert_eq!(
+            rate.convert(Money::from_minor(3)),
+            Some(Money::from_minor(2))
+        );
+        assert_eq!(Rate::ONE.convert(Money::MAX), Some(Money::MAX));
+        assert_eq!("2".parse::<Rate>().unwrap().convert(Money::MAX), None);
+    }
+}
@@ -0,0 +1,156 @@
+use crate::bank::currency::Rate;
+use crate::bank::money::Money;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Error {
+    InvalidFee,
+    InvalidOperation,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidFee => write!(
+                f,
+                "fee must be none, flat:<amount>, percentage:<rate> or capped:<rate>:<cap>"
+            ),
+            Error::InvalidOperation => write!(f, "fee can be set for withdraw or transfer"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Operation kinds that can be charged a fee, the payer is the account money leaves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum FeeOperation {
+    Withdraw,
+    Transfer,
+}
+
+impl fmt::Display for FeeOperation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FeeOperation::Withdraw => write!(f, "withdraw"),
+            FeeOperation::Transfer => write!(f, "transfer"),
+        }
+    }
+}
+
+impl FromStr for FeeOperation {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<FeeOperation, Error> {
+        match s {
+            "withdraw" => Ok(FeeOperation::Withdraw),
+            "transfer" => Ok(FeeOperation::Transfer),
+            _ => Err(Error::InvalidOperation),
+        }
+    }
+}
+
+/// Fee in currency of the charged operation, percentages are fractions, e.g. 0.01 for 1%.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fee {
+    Flat(Money),
+    Percentage(Rate),
+    CappedPercentage { rate: Rate, cap: Money },
+}
+
+impl Fee {
+    /// Fee for operation of `amount` rounding down to minor units,
+    /// `None` means that it is too large.
+    pub fn amount(&self, amount: Money) -> Option<Money> {
+        match *self {
+            Fee::Flat(fee) => Some(fee),
+            Fee::Percentage(rate) => rate.convert(amount),
+            Fee::CappedPercentage { rate, cap } => rate.convert(amount).map(|fee| fee.min(cap)),
+        }
+    }
+}
+
+impl fmt::Display for Fee {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fee::Flat(fee) => write!(f, "flat:{}", fee),
+            Fee::Percentage(rate) => write!(f, "percentage:{}", rate),
+            Fee::CappedPercentage { rate, cap } => write!(f, "capped:{}:{}", rate, cap),
+        }
+    }
+}
+
+impl FromStr for Fee {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Fee, Error> {
+        let parts: Vec<&str> = s.split(':').collect();
+        let money = |value: &str| match value.parse::<Money>() {
+            Ok(money) if !money.is_negative() => Ok(money),
+            _ => Err(Error::InvalidFee),
+        };
+        let rate = |value: &str| value.parse::<Rate>().map_err(|_| Error::InvalidFee);
+
+        match parts[..] {
+            ["flat", fee] => Ok(Fee::Flat(money(fee)?)),
+            ["percentage", fee_rate] => Ok(Fee::Percentage(rate(fee_rate)?)),
+            ["capped", fee_rate, cap] => Ok(Fee::CappedPercentage {
+                rate: rate(fee_rate)?,
+                cap: money(cap)?,
+            }),
+            _ => Err(Error::InvalidFee),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_works() {
+        for fee in ["flat:0.50", "percentage:0.015", "capped:0.01:5.00"] {
+            assert_eq!(fee.parse::<Fee>().unwrap().to_string(), fee);
+        }
+
+        assert_eq!("flat:-1".parse::<Fee>(), Err(Error::InvalidFee));
+        assert_eq!("percentage:0".parse::<Fee>(), Err(Error::InvalidFee));
+        assert_eq!("capped:0.01".parse::<Fee>(), Err(Error::InvalidFee));
+        assert_eq!("free".parse::<Fee>(), Err(Error::InvalidFee));
+
+        assert_eq!(
+            "transfer".parse::<FeeOperation>(),
+            Ok(FeeOperation::Transfer)
+        );
+        assert_eq!(
+            "deposit".parse::<FeeOperation>(),
+            Err(Error::InvalidOperation)
+        );
+    }
+
+    #[test]
+    fn amount_works() {
+        let amount = Money::from_minor(100000);
+
+        assert_eq!(
+            "flat:0.50".parse::<Fee>().unwrap().amount(amount),
+            Some(Money::from_minor(50))
+        );
+        assert_eq!(
+            "percentage:0.015".parse::<Fee>().unwrap().amount(amount),
+            Some(Money::from_minor(1500))
+        );
+        assert_eq!(
+            "capped:0.015:5".parse::<Fee>().unwrap().amount(amount),
+            Some(Money::from_minor(500))
+        );
+        assert_eq!(
+            "capped:0.015:5"
+                .parse::<Fee>()
+                .unwrap()
+                .amount(Money::from_minor(1000)),
+            Some(Money::from_minor(15))
+        );
+    }
+}
@@ -0,0 +1,33 @@
+use crate::bank::account::AccountID;
+use crate::bank::money::Money;
+use std::fmt;
+use uuid::Uuid;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct HoldID(Uuid);
+pub type Error = uuid::Error;
+
+impl HoldID {
+    pub fn new() -> HoldID {
+        HoldID(Uuid::new_v4())
+    }
+
+    pub fn parse_str(s: &str) -> Result<HoldID, Error> {
+        Uuid::parse_str(s).map(HoldID)
+    }
+}
+
+impl fmt::Display for HoldID {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Funds reserved on an account until they are captured or released,
+/// they stay in the balance but can't be spent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hold {
+    pub id: HoldID,
+    pub account_id: AccountID,
+    pub amount: Money,
+}
@@ -0,0 +1,161 @@
+use crate::bank::account::AccountID;
+use crate::bank::log::OperationKind;
+use crate::bank::money::Money;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Error {
+    InvalidKey,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidKey => write!(
+                f,
+                "key must be 1 to {} latin letters, digits, '-' or '_'",
+                IdempotencyKey::MAX_LEN
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Client supplied token, operation sent again with the same key is applied only once.
+///
+/// Kept inline so `Operation` stays `Copy`, a UUID fits into it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct IdempotencyKey {
+    len: u8,
+    bytes: [u8; IdempotencyKey::MAX_LEN],
+}
+
+impl IdempotencyKey {
+    pub const MAX_LEN: usize = 36;
+}
+
+impl FromStr for IdempotencyKey {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<IdempotencyKey, Error> {
+        let is_valid = |c: &u8| c.is_ascii_alphanumeric() || *c == b'-' || *c == b'_';
+        if s.is_empty() || s.len() > IdempotencyKey::MAX_LEN || !s.as_bytes().iter().all(is_valid) {
+            return Err(Error::InvalidKey);
+        }
+
+        let mut bytes = [0; IdempotencyKey::MAX_LEN];
+        bytes[..s.len()].copy_from_slice(s.as_bytes());
+
+        Ok(IdempotencyKey {
+            len: s.len() as u8,
+            bytes,
+        })
+    }
+}
+
+impl fmt::Display for IdempotencyKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // only ascii gets inside, see from_str
+        let key = std::str::from_utf8(&self.bytes[..self.len as usize]).unwrap();
+        write!(f, "{}", key)
+    }
+}
+
+/// Operation applied under an idempotency key, the key can't be reused for another one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyedRequest {
+    Deposit {
+        id: AccountID,
+        amount: Money,
+    },
+    Withdraw {
+        id: AccountID,
+        amount: Money,
+    },
+    /// Rate is looked up when the transfer is applied, so it is not part of the request.
+    Transfer {
+        sender_id: AccountID,
+        receiver_id: AccountID,
+        amount: Money,
+    },
+}
+
+impl KeyedRequest {
+    /// Request the operation was made for, `None` for kinds that can't have a key.
+    pub fn of(kind: &OperationKind) -> Option<KeyedRequest> {
+        match *kind {
+            OperationKind::Deposit { id, amount } => Some(KeyedRequest::Deposit { id, amount }),
+            OperationKind::Withdraw { id, amount } => Some(KeyedRequest::Withdraw { id, amount }),
+            OperationKind::Transfer {
+                sender_id,
+                receiver_id,
+                amount,
+                ..
+            } => Some(KeyedRequest::Transfer {
+                sender_id,
+                receiver_id,
+                amount,
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_works() {
+        let key = "97c56a4e-0d75-4a82-b683-628b8c219fa3";
+        assert_eq!(key.parse::<IdempotencyKey>().unwrap().to_string(), key);
+        assert_eq!(
+            "pay_rent_1".parse::<IdempotencyKey>().unwrap().to_string(),
+            "pay_rent_1"
+        );
+
+        assert_eq!("".parse::<IdempotencyKey>(), Err(Error::InvalidKey));
+        assert_eq!("a b".parse::<IdempotencyKey>(), Err(Error::InvalidKey));
+        assert_eq!(
+            "x".repeat(IdempotencyKey::MAX_LEN + 1)
+                .parse::<IdempotencyKey>(),
+            Err(Error::InvalidKey)
+        );
+    }
+
+    #[test]
+    fn request_of_works() {
+        let (sender_id, receiver_id) = (AccountID::new(), AccountID::new());
+        let amount = Money::from_minor(100);
+
+        assert_eq!(
+            KeyedRequest::of(&OperationKind::Transfer {
+                sender_id,
+                receiver_id,
+                amount,
+                rate: "0.92".parse().unwrap(),
+            }),
+            Some(KeyedRequest::Transfer {
+                sender_id,
+                receiver_id,
+                amount,
+            })
+        );
+        assert_eq!(
+            KeyedRequest::of(&OperationKind::Deposit {
+                id: sender_id,
+                amount
+            }),
+            Some(KeyedRequest::Deposit {
+                id: sender_id,
+                amount
+            })
+        );
+        assert_eq!(
+            KeyedRequest::of(&OperationKind::Freeze { id: sender_id }),
+            None
+        );
+    }
+}
@@ -0,0 +1,75 @@
+use crate::bank::currency::Rate;
+use crate::bank::money::Money;
+use std::cmp::Ordering;
+
+/// Interest is accrued on the actual/365 basis.
+pub const DAYS_IN_YEAR: u32 = 365;
+
+/// Interest earned by `balance` in `days` at `annual_rate`, rounded half to even
+/// into minor units. `None` means that the result is too large.
+pub fn accrued_interest(balance: Money, annual_rate: Rate, days: u32) -> Option<Money> {
+    let numerator = (balance.minor_units() as i128)
+        .checked_mul(annual_rate.units() as i128)?
+        .checked_mul(days as i128)?;
+    let denominator = Rate::ONE.units() as i128 * DAYS_IN_YEAR as i128;
+
+    round_half_even(numerator, denominator)
+        .try_into()
+        .ok()
+        .map(Money::from_minor)
+}
+
+// Banker's rounding of `numerator / denominator`, `denominator` is positive.
+fn round_half_even(numerator: i128, denominator: i128) -> i128 {
+    let quotient = numerator.div_euclid(denominator);
+    let remainder = numerator.rem_euclid(denominator);
+
+    match (remainder * 2).cmp(&denominator) {
+        Ordering::Less => quotient,
+        Ordering::Greater => quotient + 1,
+        Ordering::Equal => quotient + quotient.rem_euclid(2),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_half_even_works() {
+        assert_eq!(round_half_even(5, 2), 2);
+        assert_eq!(round_half_even(7, 2), 4);
+        assert_eq!(round_half_even(-5, 2), -2);
+        assert_eq!(round_half_even(-7, 2), -4);
+        assert_eq!(round_half_even(26, 10), 3);
+        assert_eq!(round_half_even(24, 10), 2);
+        assert_eq!(round_half_even(-26, 10), -3);
+    }
+
+    #[test]
+    fn accrued_interest_works() {
+        let rate: Rate = "0.05".parse().unwrap();
+
+        // 1000.00 at 5% for a year
+        assert_eq!(
+            accrued_interest(Money::from_minor(100000), rate, DAYS_IN_YEAR),
+            Some(Money::from_minor(5000))
+        );
+        // 1000.00 at 5% for a day is 13.6986... cents
+        assert_eq!(
+            accrued_interest(Money::from_minor(100000), rate, 1),
+            Some(Money::from_minor(14))
+        );
+        // 36.50 at 5% for a day is exactly half a cent, rounded to even
+        assert_eq!(
+            accrued_interest(Money::from_minor(3650), rate, 1),
+            Some(Money::ZERO)
+        );
+        // 109.50 at 5% for a day is 1.5 cents
+        assert_eq!(
+            accrued_interest(Money::from_minor(10950), rate, 1),
+            Some(Money::from_minor(2))
+        );
+        assert_eq!(accrued_interest(Money::MAX, rate, u32::MAX), None);
+    }
+}
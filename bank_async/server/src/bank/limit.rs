@@ -0,0 +1,190 @@
+use crate::bank::account::AccountID;
+use crate::bank::clock::Timestamp;
+use crate::bank::money::Money;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::str::FromStr;
+
+/// Length of the rolling window the daily limit is checked against.
+pub const OUTFLOW_WINDOW: u64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Error {
+    InvalidKind,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidKind => write!(f, "limit must be per_transaction or daily"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum LimitKind {
+    /// Cap on a single withdrawal or transfer.
+    PerTransaction,
+    /// Cap on everything that left the account in the last `OUTFLOW_WINDOW` seconds.
+    Daily,
+}
+
+impl fmt::Display for LimitKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LimitKind::PerTransaction => write!(f, "per_transaction"),
+            LimitKind::Daily => write!(f, "daily"),
+        }
+    }
+}
+
+impl FromStr for LimitKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<LimitKind, Error> {
+        match s {
+            "per_transaction" => Ok(LimitKind::PerTransaction),
+            "daily" => Ok(LimitKind::Daily),
+            _ => Err(Error::InvalidKind),
+        }
+    }
+}
+
+/// Withdrawal and transfer limits in currency of the account money leaves, `None` is no limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Limits {
+    pub per_transaction: Option<Money>,
+    pub daily: Option<Money>,
+}
+
+impl Limits {
+    pub fn is_empty(&self) -> bool {
+        *self == Limits::default()
+    }
+
+    pub fn get(&self, kind: LimitKind) -> Option<Money> {
+        match kind {
+            LimitKind::PerTransaction => self.per_transaction,
+            LimitKind::Daily => self.daily,
+        }
+    }
+
+    pub fn updated(&self, kind: LimitKind, limit: Option<Money>) -> Limits {
+        match kind {
+            LimitKind::PerTransaction => Limits {
+                per_transaction: limit,
+                ..*self
+            },
+            LimitKind::Daily => Limits {
+                daily: limit,
+                ..*self
+            },
+        }
+    }
+
+    /// Limits set here, the missing ones are taken from `fallback`.
+    pub fn or(&self, fallback: Limits) -> Limits {
+        Limits {
+            per_transaction: self.per_transaction.or(fallback.per_transaction),
+            daily: self.daily.or(fallback.daily),
+        }
+    }
+}
+
+/// Money that left every account within the last `OUTFLOW_WINDOW` seconds of its latest outflow.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Outflows(HashMap<AccountID, VecDeque<(Timestamp, Money)>>);
+
+impl Outflows {
+    /// Adds outflow made at `timestamp` and drops the ones that are out of its window.
+    pub fn record(&mut self, id: AccountID, timestamp: Timestamp, amount: Money) {
+        let entries = self.0.entry(id).or_default();
+        while entries
+            .front()
+            .is_some_and(|(time, _)| time.saturating_add(OUTFLOW_WINDOW) <= timestamp)
+        {
+            entries.pop_front();
+        }
+        entries.push_back((timestamp, amount));
+    }
+
+    /// Outflow of the window ending at `now`, `None` if it is too large.
+    pub fn total(&self, id: AccountID, now: Timestamp) -> Option<Money> {
+        let entries = match self.0.get(&id) {
+            Some(entries) => entries,
+            None => return Some(Money::ZERO),
+        };
+
+        entries
+            .iter()
+            .filter(|(time, _)| time.saturating_add(OUTFLOW_WINDOW) > now)
+            .try_fold(Money::ZERO, |total, (_, amount)| total.checked_add(*amount))
+    }
+
+    /// All recorded outflows ordered by account and time.
+    pub fn entries(&self) -> Vec<(AccountID, Timestamp, Money)> {
+        let mut entries: Vec<(AccountID, Timestamp, Money)> = self
+            .0
+            .iter()
+            .flat_map(|(id, entries)| {
+                entries
+                    .iter()
+                    .map(move |(timestamp, amount)| (*id, *timestamp, *amount))
+            })
+            .collect();
+        entries.sort_by_key(|(id, timestamp, _)| (*id, *timestamp));
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_works() {
+        let account = Limits::default().updated(LimitKind::Daily, Some(Money::from_minor(500)));
+        let bank = Limits {
+            per_transaction: Some(Money::from_minor(100)),
+            daily: Some(Money::from_minor(1000)),
+        };
+
+        assert_eq!(
+            account.or(bank),
+            Limits {
+                per_transaction: Some(Money::from_minor(100)),
+                daily: Some(Money::from_minor(500)),
+            }
+        );
+        assert!(account.updated(LimitKind::Daily, None).is_empty());
+        assert_eq!("daily".parse::<LimitKind>().unwrap().to_string(), "daily");
+        assert_eq!("weekly".parse::<LimitKind>(), Err(Error::InvalidKind));
+    }
+
+    #[test]
+    fn outflows_works() {
+        let id = AccountID::new();
+        let mut outflows = Outflows::default();
+        assert_eq!(outflows.total(id, 0), Some(Money::ZERO));
+
+        outflows.record(id, 100, Money::from_minor(10));
+        outflows.record(id, 200, Money::from_minor(20));
+        assert_eq!(outflows.total(id, 200), Some(Money::from_minor(30)));
+        assert_eq!(
+            outflows.total(id, 100 + OUTFLOW_WINDOW),
+            Some(Money::from_minor(20))
+        );
+
+        // the first outflow is out of the window of the new one
+        outflows.record(id, 150 + OUTFLOW_WINDOW, Money::from_minor(5));
+        assert_eq!(
+            outflows.entries(),
+            vec![
+                (id, 200, Money::from_minor(20)),
+                (id, 150 + OUTFLOW_WINDOW, Money::from_minor(5))
+            ]
+        );
+    }
+}
@@ -1,3 +1,13 @@
+use crate::bank::clock::{format_timestamp, SharedClock, Timestamp};
+use crate::bank::currency::{Currency, Rate};
+use crate::bank::fee::{Fee, FeeOperation};
+use crate::bank::hold::HoldID;
+use crate::bank::idempotency::IdempotencyKey;
+use crate::bank::limit::{LimitKind, Outflows};
+use crate::bank::money::Money;
+use crate::bank::profile::ProfileField;
+use crate::bank::schedule::ScheduleID;
+use crate::bank::statement::postings;
 use crate::bank::AccountID;
 use std::collections::HashMap;
 use uuid::Uuid;
@@ -6,28 +16,244 @@ use uuid::Uuid;
 pub enum OperationKind {
     Register {
         id: AccountID,
-        balance: u64,
+        balance: Money,
+        currency: Currency,
     },
     Deposit {
         id: AccountID,
-        amount: u64,
+        amount: Money,
     },
     Withdraw {
         id: AccountID,
-        amount: u64,
+        amount: Money,
     },
+    /// `amount` is in sender's currency, receiver gets it converted at `rate`.
     Transfer {
         sender_id: AccountID,
         receiver_id: AccountID,
-        amount: u64,
+        amount: Money,
+        rate: Rate,
     },
+    SetExchangeRate {
+        from: Currency,
+        to: Currency,
+        rate: Rate,
+    },
+    SetOverdraftLimit {
+        id: AccountID,
+        limit: Money,
+    },
+    /// Makes the account a savings one with annual interest `rate`.
+    SetInterestRate {
+        id: AccountID,
+        rate: Rate,
+    },
+    /// Interest `amount` already rounded to minor units.
+    Interest {
+        id: AccountID,
+        amount: Money,
+    },
+    Freeze {
+        id: AccountID,
+    },
+    Unfreeze {
+        id: AccountID,
+    },
+    /// Compensates `operation_id`: takes money back from `debit` account
+    /// and returns it to `credit` one, either side may be missing.
+    Reverse {
+        operation_id: OperationID,
+        debit: Option<(AccountID, Money)>,
+        credit: Option<(AccountID, Money)>,
+    },
+    /// Remaining `amount` goes to `sweep_to` account converted at `rate`.
+    Close {
+        id: AccountID,
+        sweep_to: Option<AccountID>,
+        amount: Money,
+        rate: Rate,
+    },
+    /// Transfer to run at `start` and then every `interval` seconds, if any.
+    ScheduleTransfer {
+        id: ScheduleID,
+        sender_id: AccountID,
+        receiver_id: AccountID,
+        amount: Money,
+        start: Timestamp,
+        interval: Option<u64>,
+    },
+    CancelSchedule {
+        id: ScheduleID,
+    },
+    /// Run of the schedule that failed, it moves on to the next one or ends if it was the last.
+    SkipSchedule {
+        id: ScheduleID,
+    },
+    /// `None` makes the operation free again.
+    SetFee {
+        operation: FeeOperation,
+        fee: Option<Fee>,
+    },
+    UpdateProfile {
+        id: AccountID,
+        field: ProfileField,
+    },
+    /// Reserves `amount` of the account without debiting it.
+    Hold {
+        id: HoldID,
+        account_id: AccountID,
+        amount: Money,
+    },
+    /// Debits `amount` of the hold, the rest of it is released.
+    Capture {
+        id: HoldID,
+        account_id: AccountID,
+        amount: Money,
+    },
+    Release {
+        id: HoldID,
+        account_id: AccountID,
+        amount: Money,
+    },
+    /// Limit of the account, bank-wide one if `id` is `None`.
+    SetLimit {
+        id: Option<AccountID>,
+        kind: LimitKind,
+        limit: Option<Money>,
+    },
+    /// Debit leg of a transfer to `receiver_id` of bank `bank_id`,
+    /// the receiver gets `amount` converted at `rate`.
+    InterbankSend {
+        sender_id: AccountID,
+        bank_id: u64,
+        receiver_id: AccountID,
+        amount: Money,
+        rate: Rate,
+    },
+    /// Credit leg of the transfer logged as `operation_id` by bank `bank_id`,
+    /// `amount` is already converted.
+    InterbankReceive {
+        operation_id: OperationID,
+        bank_id: u64,
+        sender_id: AccountID,
+        receiver_id: AccountID,
+        amount: Money,
+    },
+}
+
+impl OperationKind {
+    /// Names of all kinds, the same ones the log is written with.
+    pub const NAMES: [&'static str; 23] = [
+        "register",
+        "deposit",
+        "withdraw",
+        "transfer",
+        "set_exchange_rate",
+        "set_overdraft_limit",
+        "set_interest_rate",
+        "interest",
+        "freeze",
+        "unfreeze",
+        "reverse",
+        "close",
+        "schedule_transfer",
+        "cancel_schedule",
+        "skip_schedule",
+        "set_fee",
+        "update_profile",
+        "set_limit",
+        "hold",
+        "capture",
+        "release",
+        "interbank_send",
+        "interbank_receive",
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            OperationKind::Register { .. } => "register",
+            OperationKind::Deposit { .. } => "deposit",
+            OperationKind::Withdraw { .. } => "withdraw",
+            OperationKind::Transfer { .. } => "transfer",
+            OperationKind::SetExchangeRate { .. } => "set_exchange_rate",
+            OperationKind::SetOverdraftLimit { .. } => "set_overdraft_limit",
+            OperationKind::SetInterestRate { .. } => "set_interest_rate",
+            OperationKind::Interest { .. } => "interest",
+            OperationKind::Freeze { .. } => "freeze",
+            OperationKind::Unfreeze { .. } => "unfreeze",
+            OperationKind::Reverse { .. } => "reverse",
+            OperationKind::Close { .. } => "close",
+            OperationKind::ScheduleTransfer { .. } => "schedule_transfer",
+            OperationKind::CancelSchedule { .. } => "cancel_schedule",
+            OperationKind::SkipSchedule { .. } => "skip_schedule",
+            OperationKind::SetFee { .. } => "set_fee",
+            OperationKind::UpdateProfile { .. } => "update_profile",
+            OperationKind::SetLimit { .. } => "set_limit",
+            OperationKind::Hold { .. } => "hold",
+            OperationKind::Capture { .. } => "capture",
+            OperationKind::Release { .. } => "release",
+            OperationKind::InterbankSend { .. } => "interbank_send",
+            OperationKind::InterbankReceive { .. } => "interbank_receive",
+        }
+    }
+
+    /// Money moved by the operation in currency of the account it leaves,
+    /// opening balance for registration.
+    pub fn amount(&self) -> Option<Money> {
+        match *self {
+            OperationKind::Register { balance, .. } => Some(balance),
+            OperationKind::Deposit { amount, .. }
+            | OperationKind::Withdraw { amount, .. }
+            | OperationKind::Transfer { amount, .. }
+            | OperationKind::Interest { amount, .. }
+            | OperationKind::ScheduleTransfer { amount, .. }
+            | OperationKind::Hold { amount, .. }
+            | OperationKind::Capture { amount, .. }
+            | OperationKind::Release { amount, .. }
+            | OperationKind::InterbankSend { amount, .. }
+            | OperationKind::InterbankReceive { amount, .. } => Some(amount),
+            OperationKind::Close {
+                sweep_to: Some(_),
+                amount,
+                ..
+            } => Some(amount),
+            OperationKind::Reverse { debit, credit, .. } => {
+                debit.or(credit).map(|(_, amount)| amount)
+            }
+            _ => None,
+        }
+    }
+
+    /// Account money leaves by a withdrawal, a transfer or a capture and the amount, fees aside.
+    /// Transfer to another bank is an outflow too.
+    pub fn outflow(&self) -> Option<(AccountID, Money)> {
+        match *self {
+            OperationKind::Withdraw { id, amount }
+            | OperationKind::Capture {
+                account_id: id,
+                amount,
+                ..
+            } => Some((id, amount)),
+            OperationKind::Transfer {
+                sender_id, amount, ..
+            }
+            | OperationKind::InterbankSend {
+                sender_id, amount, ..
+            } => Some((sender_id, amount)),
+            _ => None,
+        }
+    }
 }
 
 impl std::fmt::Display for OperationKind {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
-            OperationKind::Register { id, balance } => {
-                write!(f, "Register {} {}", id, balance)
+            OperationKind::Register {
+                id,
+                balance,
+                currency,
+            } => {
+                write!(f, "Register {} {} {}", id, balance, currency)
             }
             OperationKind::Deposit { id, amount } => {
                 write!(f, "Deposit {} {}", id, amount)
@@ -39,9 +265,130 @@ impl std::fmt::Display for OperationKind {
                 sender_id,
                 receiver_id,
                 amount,
+                rate,
+            } => {
+                write!(
+                    f,
+                    "Transfer {} {} {} {}",
+                    sender_id, receiver_id, amount, rate
+                )
+            }
+            OperationKind::SetExchangeRate { from, to, rate } => {
+                write!(f, "SetExchangeRate {} {} {}", from, to, rate)
+            }
+            OperationKind::SetOverdraftLimit { id, limit } => {
+                write!(f, "SetOverdraftLimit {} {}", id, limit)
+            }
+            OperationKind::SetInterestRate { id, rate } => {
+                write!(f, "SetInterestRate {} {}", id, rate)
+            }
+            OperationKind::Interest { id, amount } => write!(f, "Interest {} {}", id, amount),
+            OperationKind::Reverse {
+                operation_id,
+                debit,
+                credit,
+            } => {
+                write!(f, "Reverse {}", operation_id)?;
+                for (sign, side) in [("-", debit), ("+", credit)] {
+                    if let Some((id, amount)) = side {
+                        write!(f, " {}{} {}", sign, amount, id)?;
+                    }
+                }
+                Ok(())
+            }
+            OperationKind::Freeze { id } => write!(f, "Freeze {}", id),
+            OperationKind::Unfreeze { id } => write!(f, "Unfreeze {}", id),
+            OperationKind::Close {
+                id, sweep_to: None, ..
+            } => {
+                write!(f, "Close {}", id)
+            }
+            OperationKind::Close {
+                id,
+                sweep_to: Some(sweep_to),
+                amount,
+                rate,
+            } => {
+                write!(f, "Close {} {} {} {}", id, sweep_to, amount, rate)
+            }
+            OperationKind::ScheduleTransfer {
+                id,
+                sender_id,
+                receiver_id,
+                amount,
+                start,
+                interval,
             } => {
-                write!(f, "Transfer {} {} {}", sender_id, receiver_id, amount)
+                write!(
+                    f,
+                    "ScheduleTransfer {} {} {} {} {}",
+                    id, sender_id, receiver_id, amount, start
+                )?;
+                if let Some(interval) = interval {
+                    write!(f, " {}", interval)?;
+                }
+                Ok(())
             }
+            OperationKind::CancelSchedule { id } => write!(f, "CancelSchedule {}", id),
+            OperationKind::SkipSchedule { id } => write!(f, "SkipSchedule {}", id),
+            OperationKind::SetFee {
+                operation,
+                fee: Some(fee),
+            } => write!(f, "SetFee {} {}", operation, fee),
+            OperationKind::SetFee {
+                operation,
+                fee: None,
+            } => write!(f, "SetFee {} none", operation),
+            OperationKind::UpdateProfile { id, field } => {
+                write!(f, "UpdateProfile {} {}", id, field)
+            }
+            OperationKind::Hold {
+                id,
+                account_id,
+                amount,
+            } => write!(f, "Hold {} {} {}", id, account_id, amount),
+            OperationKind::Capture {
+                id,
+                account_id,
+                amount,
+            } => write!(f, "Capture {} {} {}", id, account_id, amount),
+            OperationKind::Release {
+                id,
+                account_id,
+                amount,
+            } => write!(f, "Release {} {} {}", id, account_id, amount),
+            OperationKind::SetLimit { id, kind, limit } => {
+                match id {
+                    Some(id) => write!(f, "SetLimit {} {}", id, kind)?,
+                    None => write!(f, "SetLimit bank {}", kind)?,
+                }
+                match limit {
+                    Some(limit) => write!(f, " {}", limit),
+                    None => write!(f, " none"),
+                }
+            }
+            OperationKind::InterbankSend {
+                sender_id,
+                bank_id,
+                receiver_id,
+                amount,
+                rate,
+            } => write!(
+                f,
+                "InterbankSend {} {}:{} {} {}",
+                sender_id, bank_id, receiver_id, amount, rate
+            ),
+            OperationKind::InterbankReceive {
+                operation_id,
+                bank_id,
+                sender_id,
+                receiver_id,
+                amount,
+            } => write!(
+                f,
+                "InterbankReceive {} {}:{} {} {}",
+                operation_id, bank_id, sender_id, receiver_id, amount
+            ),
         }
     }
 }
@@ -71,19 +418,140 @@ impl std::fmt::Display for OperationID {
 pub struct Operation {
     pub id: OperationID,
     pub kind: OperationKind,
+    pub key: Option<IdempotencyKey>,
+    /// Schedule this operation was executed by.
+    pub schedule: Option<ScheduleID>,
+    /// Fee account and the fee charged for this operation.
+    pub fee: Option<(AccountID, Money)>,
+    /// When the operation was made, kept as is on restore.
+    pub timestamp: Timestamp,
+}
+
+impl Operation {
+    /// Accounts the operation is listed for, the fee account included.
+    pub fn account_ids(&self) -> Vec<AccountID> {
+        let mut account_ids = match self.kind {
+            OperationKind::Register { id, .. }
+            | OperationKind::Deposit { id, .. }
+            | OperationKind::Withdraw { id, .. }
+            | OperationKind::SetOverdraftLimit { id, .. }
+            | OperationKind::SetInterestRate { id, .. }
+            | OperationKind::Interest { id, .. }
+            | OperationKind::Freeze { id }
+            | OperationKind::Unfreeze { id }
+            | OperationKind::UpdateProfile { id, .. }
+            | OperationKind::Hold { account_id: id, .. }
+            | OperationKind::Capture { account_id: id, .. }
+            | OperationKind::Release { account_id: id, .. }
+            | OperationKind::InterbankSend { sender_id: id, .. }
+            | OperationKind::InterbankReceive {
+                receiver_id: id, ..
+            } => vec![id],
+            OperationKind::Transfer {
+                sender_id,
+                receiver_id,
+                ..
+            }
+            | OperationKind::ScheduleTransfer {
+                sender_id,
+                receiver_id,
+                ..
+            } => vec![sender_id, receiver_id],
+            OperationKind::Reverse { debit, credit, .. } => debit
+                .iter()
+                .chain(credit.iter())
+                .map(|(id, _)| *id)
+                .collect(),
+            OperationKind::Close { id, sweep_to, .. } => {
+                std::iter::once(id).chain(sweep_to).collect()
+            }
+            OperationKind::SetLimit { id, .. } => id.into_iter().collect(),
+            OperationKind::SetExchangeRate { .. }
+            | OperationKind::CancelSchedule { .. }
+            | OperationKind::SkipSchedule { .. }
+            | OperationKind::SetFee { .. } => vec![],
+        };
+
+        if let Some((fee_account, _)) = self.fee {
+            account_ids.push(fee_account);
+        }
+
+        account_ids
+    }
 }
 
 impl std::fmt::Display for Operation {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "{}: ({})", self.id, self.kind)
+        write!(
+            f,
+            "{} {}: ({})",
+            format_timestamp(self.timestamp),
+            self.id,
+            self.kind
+        )?;
+        if let Some((_, fee)) = self.fee {
+            write!(f, " fee {}", fee)?;
+        }
+        if let Some(schedule) = self.schedule {
+            write!(f, " by schedule {}", schedule)?;
+        }
+        Ok(())
     }
 }
 
+/// Filters and page of operations listing, the default query lists everything.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct OperationsQuery {
+    /// One of `OperationKind::NAMES`.
+    pub kind: Option<&'static str>,
+    pub min_amount: Option<Money>,
+    pub max_amount: Option<Money>,
+    /// Account on the other side of a posting of the listed account, or of any posting.
+    pub counterparty: Option<AccountID>,
+    /// Cursor: listing starts right after this operation.
+    pub after: Option<OperationID>,
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+impl OperationsQuery {
+    /// Whether `operation` of the account `account_id`, or of any account,
+    /// passes the filters, paging is not taken into account.
+    pub fn matches(&self, account_id: Option<AccountID>, operation: &Operation) -> bool {
+        let amount = operation.kind.amount();
+
+        self.kind.is_none_or(|kind| operation.kind.name() == kind)
+            && self
+                .min_amount
+                .is_none_or(|min_amount| amount.is_some_and(|amount| amount >= min_amount))
+            && self
+                .max_amount
+                .is_none_or(|max_amount| amount.is_some_and(|amount| amount <= max_amount))
+            && self.counterparty.is_none_or(|counterparty| {
+                postings(operation).is_some_and(|postings| {
+                    postings.iter().any(|posting| {
+                        account_id.is_none_or(|account_id| posting.account_id == account_id)
+                            && posting.counterparty == Some(counterparty)
+                    })
+                })
+            })
+    }
+}
+
+/// Point of the log history, both bounds are inclusive.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Cutoff {
+    Operation(OperationID),
+    Time(Timestamp),
+}
+
 #[derive(Debug, Default, PartialEq, Clone)]
 pub struct OperationsLog {
     accounts_operations: HashMap<AccountID, Vec<OperationID>>,
     operations_by_id: HashMap<OperationID, usize>,
     operations: Vec<Operation>,
+    outflows: Outflows,
+    clock: SharedClock,
 }
 
 impl OperationsLog {
@@ -93,35 +561,47 @@ impl OperationsLog {
             .map(|idx| &self.operations[*idx])
     }
 
+    /// Clock new operations are timestamped with.
+    pub fn set_clock(&mut self, clock: SharedClock) {
+        self.clock = clock;
+    }
+
+    pub fn now(&self) -> Timestamp {
+        self.clock.now()
+    }
+
+    /// Withdrawals and transfers of the last day, kept up to date as operations are logged.
+    pub fn outflows(&self) -> &Outflows {
+        &self.outflows
+    }
+
+    /// Outflow made before the log starts, e.g. the one a snapshot remembers.
+    pub fn record_outflow(&mut self, id: AccountID, timestamp: Timestamp, amount: Money) {
+        self.outflows.record(id, timestamp, amount);
+    }
+
     fn log_for_account(&mut self, account_id: AccountID, operation_id: OperationID) {
-        self.accounts_operations
-            .entry(account_id)
-            .or_default()
-            .push(operation_id);
+        let operation_ids = self.accounts_operations.entry(account_id).or_default();
+        // the fee account may also be a side of the operation it is paid for
+        if operation_ids.last() != Some(&operation_id) {
+            operation_ids.push(operation_id);
+        }
     }
 
     pub fn log_operation(&mut self, operation: Operation) {
         let operation_id = operation.id;
-        let operation_kind = operation.kind;
+        let account_ids = operation.account_ids();
+
+        if let Some((id, amount)) = operation.kind.outflow() {
+            self.outflows.record(id, operation.timestamp, amount);
+        }
 
         let operation_idx = self.operations.len();
         self.operations_by_id.insert(operation_id, operation_idx);
         self.operations.push(operation);
 
-        match operation_kind {
-            OperationKind::Register { id, .. }
-            | OperationKind::Deposit { id, .. }
-            | OperationKind::Withdraw { id, .. } => {
-                self.log_for_account(id, operation_id);
-            }
-            OperationKind::Transfer {
-                sender_id,
-                receiver_id,
-                ..
-            } => {
-                self.log_for_account(sender_id, operation_id);
-                self.log_for_account(receiver_id, operation_id);
-            }
+        for account_id in account_ids {
+            self.log_for_account(account_id, operation_id);
         }
     }
 
@@ -130,6 +610,10 @@ impl OperationsLog {
         let operation = Operation {
             id: operation_id,
             kind: operation_kind,
+            key: None,
+            schedule: None,
+            fee: None,
+            timestamp: self.clock.now(),
         };
 
         self.log_operation(operation);
@@ -137,6 +621,25 @@ impl OperationsLog {
         operation_id
     }
 
+    pub fn set_key(&mut self, operation_id: OperationID, key: IdempotencyKey) {
+        if let Some(idx) = self.operations_by_id.get(&operation_id) {
+            self.operations[*idx].key = Some(key);
+        }
+    }
+
+    pub fn set_fee(&mut self, operation_id: OperationID, fee_account: AccountID, fee: Money) {
+        if let Some(idx) = self.operations_by_id.get(&operation_id) {
+            self.operations[*idx].fee = Some((fee_account, fee));
+            self.log_for_account(fee_account, operation_id);
+        }
+    }
+
+    pub fn set_schedule(&mut self, operation_id: OperationID, schedule: ScheduleID) {
+        if let Some(idx) = self.operations_by_id.get(&operation_id) {
+            self.operations[*idx].schedule = Some(schedule);
+        }
+    }
+
     pub fn get_all_operations(&self) -> impl Iterator<Item = &Operation> {
         self.operations.iter()
     }
@@ -150,4 +653,55 @@ impl OperationsLog {
             .map_or(Default::default(), |operation_ids| operation_ids.iter())
             .map(|operation_id| self.get(*operation_id).unwrap())
     }
+
+    /// Page of operations matching `query`, only the account's ones if `account_id` is set.
+    /// `None` if the cursor operation is unknown.
+    pub fn query(
+        &self,
+        account_id: Option<AccountID>,
+        query: OperationsQuery,
+    ) -> Option<impl Iterator<Item = &Operation>> {
+        let start = match query.after {
+            Some(operation_id) => self.operations_by_id.get(&operation_id)? + 1,
+            None => 0,
+        };
+
+        // the per account index is much shorter than the whole log
+        let operations: Box<dyn Iterator<Item = &Operation>> =
+            match account_id.or(query.counterparty) {
+                Some(account_id) => Box::new(
+                    self.get_account_operations(account_id)
+                        .skip_while(move |operation| self.operations_by_id[&operation.id] < start),
+                ),
+                None => Box::new(self.operations[start..].iter()),
+            };
+
+        Some(
+            operations
+                .filter(move |operation| query.matches(account_id, operation))
+                .skip(query.offset)
+                .take(query.limit.unwrap_or(usize::MAX)),
+        )
+    }
+
+    /// Operations of the account made up to `cutoff`, `None` if the cutoff operation is unknown.
+    pub fn get_account_operations_until(
+        &self,
+        account_id: AccountID,
+        cutoff: Cutoff,
+    ) -> Option<impl Iterator<Item = &Operation>> {
+        if let Cutoff::Operation(operation_id) = cutoff {
+            self.get(operation_id)?;
+        }
+
+        Some(
+            self.get_account_operations(account_id)
+                .take_while(move |operation| match cutoff {
+                    Cutoff::Operation(operation_id) => {
+                        self.operations_by_id[&operation.id] <= self.operations_by_id[&operation_id]
+                    }
+                    Cutoff::Time(time) => operation.timestamp <= time,
+                }),
+        )
+    }
 }
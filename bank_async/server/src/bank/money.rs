@@ -0,0 +1,153 @@
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Error {
+    InvalidFormat,
+    TooManyDecimals,
+    TooLarge,
+    Negative,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidFormat => write!(f, "amount must look like 12 or 12.34"),
+            Error::TooManyDecimals => {
+                write!(f, "amount can have at most {} decimals", Money::SCALE)
+            }
+            Error::TooLarge => write!(f, "amount is too large"),
+            Error::Negative => write!(f, "amount must not be negative"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Amount of money counted in minor units, e.g. cents.
+///
+/// Parsed from and formatted to decimal notation with `Money::SCALE` fractional digits,
+/// so `12.34` is 1234 minor units and `12` is 1200. It is signed to hold overdrawn
+/// balances, amounts of operations are never negative.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Money(i64);
+
+impl Money {
+    pub const SCALE: u32 = 2;
+    pub const ZERO: Money = Money(0);
+    pub const MAX: Money = Money(i64::MAX);
+
+    const MINOR_UNITS: i64 = 10i64.pow(Money::SCALE);
+
+    pub const fn from_minor(minor_units: i64) -> Money {
+        Money(minor_units)
+    }
+
+    pub const fn minor_units(&self) -> i64 {
+        self.0
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.0 < 0
+    }
+
+    pub fn is_positive(&self) -> bool {
+        self.0 > 0
+    }
+
+    pub fn checked_neg(self) -> Option<Money> {
+        self.0.checked_neg().map(Money)
+    }
+
+    pub fn checked_add(self, other: Money) -> Option<Money> {
+        self.0.checked_add(other.0).map(Money)
+    }
+
+    pub fn checked_sub(self, other: Money) -> Option<Money> {
+        self.0.checked_sub(other.0).map(Money)
+    }
+}
+
+impl FromStr for Money {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Money, Error> {
+        let (sign, s) = match s.strip_prefix('-') {
+            Some(s) => (-1, s),
+            None => (1, s),
+        };
+        let (units, frac) = s.split_once('.').unwrap_or((s, ""));
+        let is_digits = |part: &str| part.bytes().all(|c| c.is_ascii_digit());
+        if units.is_empty() || !is_digits(units) || !is_digits(frac) || s.ends_with('.') {
+            return Err(Error::InvalidFormat);
+        }
+
+        if frac.len() > Money::SCALE as usize {
+            return Err(Error::TooManyDecimals);
+        }
+
+        let units: i64 = units.parse().map_err(|_| Error::TooLarge)?;
+        let frac: i64 = format!("{:0<width$}", frac, width = Money::SCALE as usize)
+            .parse()
+            .map_err(|_| Error::InvalidFormat)?;
+
+        units
+            .checked_mul(Money::MINOR_UNITS)
+            .and_then(|units| units.checked_add(frac))
+            .map(|minor_units| Money(sign * minor_units))
+            .ok_or(Error::TooLarge)
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let minor_units = self.0.unsigned_abs();
+        let scale = Money::MINOR_UNITS as u64;
+        write!(
+            f,
+            "{}{}.{:0width$}",
+            if self.is_negative() { "-" } else { "" },
+            minor_units / scale,
+            minor_units % scale,
+            width = Money::SCALE as usize
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_works() {
+        assert_eq!("12.34".parse(), Ok(Money::from_minor(1234)));
+        assert_eq!("12.3".parse(), Ok(Money::from_minor(1230)));
+        assert_eq!("12".parse(), Ok(Money::from_minor(1200)));
+        assert_eq!("0.01".parse(), Ok(Money::from_minor(1)));
+
+        assert_eq!("12.".parse::<Money>(), Err(Error::InvalidFormat));
+        assert_eq!(".5".parse::<Money>(), Err(Error::InvalidFormat));
+        assert_eq!("-1.5".parse(), Ok(Money::from_minor(-150)));
+        assert_eq!("--1".parse::<Money>(), Err(Error::InvalidFormat));
+        assert_eq!("1e3".parse::<Money>(), Err(Error::InvalidFormat));
+        assert_eq!("12.345".parse::<Money>(), Err(Error::TooManyDecimals));
+        assert_eq!(
+            "92233720368547758.08".parse::<Money>(),
+            Err(Error::TooLarge)
+        );
+    }
+
+    #[test]
+    fn display_works() {
+        assert_eq!(Money::from_minor(1234).to_string(), "12.34");
+        assert_eq!(Money::from_minor(1200).to_string(), "12.00");
+        assert_eq!(Money::from_minor(5).to_string(), "0.05");
+        assert_eq!(Money::from_minor(-5).to_string(), "-0.05");
+        assert_eq!(Money::from_minor(-1234).to_string(), "-12.34");
+        assert_eq!(Money::MAX.to_string().parse(), Ok(Money::MAX));
+    }
+}
@@ -0,0 +1,289 @@
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Error {
+    InvalidName,
+    InvalidEmail,
+    InvalidAlias,
+    InvalidField,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidName => write!(
+                f,
+                "name must be 1 to {} bytes, words separated by single spaces",
+                HolderName::MAX_LEN
+            ),
+            Error::InvalidEmail => write!(
+                f,
+                "email must be 3 to {} bytes like user@example.com",
+                Email::MAX_LEN
+            ),
+            Error::InvalidAlias => write!(
+                f,
+                "alias must be 1 to {} lowercase latin letters, digits, '-' or '_' starting with a letter",
+                Alias::MAX_LEN
+            ),
+            Error::InvalidField => write!(f, "field must be name, email or alias"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+// Text kept inline, so `Account` and `Operation` stay `Copy`, like `IdempotencyKey`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct InlineStr<const N: usize> {
+    len: u8,
+    bytes: [u8; N],
+}
+
+impl<const N: usize> InlineStr<N> {
+    fn new(s: &str) -> Option<InlineStr<N>> {
+        if s.is_empty() || s.len() > N {
+            return None;
+        }
+
+        let mut bytes = [0; N];
+        bytes[..s.len()].copy_from_slice(s.as_bytes());
+
+        Some(InlineStr {
+            len: s.len() as u8,
+            bytes,
+        })
+    }
+
+    fn as_str(&self) -> &str {
+        // built from a whole &str, see new
+        std::str::from_utf8(&self.bytes[..self.len as usize]).unwrap()
+    }
+}
+
+// "-" stands for a missing value in the log and checkpoints, so it is never a valid one.
+fn is_plain(s: &str) -> bool {
+    s != "-" && !s.chars().any(|c| c.is_whitespace() || c.is_control())
+}
+
+/// Holder name, words are separated by single spaces, e.g. `Jane Doe`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HolderName(InlineStr<{ HolderName::MAX_LEN }>);
+
+impl HolderName {
+    pub const MAX_LEN: usize = 32;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Email(InlineStr<{ Email::MAX_LEN }>);
+
+impl Email {
+    pub const MAX_LEN: usize = 64;
+}
+
+/// Unique within a bank, accounts can be referred to as `@alias`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Alias(InlineStr<{ Alias::MAX_LEN }>);
+
+impl Alias {
+    pub const MAX_LEN: usize = 32;
+}
+
+impl FromStr for HolderName {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<HolderName, Error> {
+        // commands are split on spaces and joined back with single ones
+        let is_valid = s.split(' ').all(|word| {
+            !word.is_empty() && !word.chars().any(|c| c.is_whitespace() || c.is_control())
+        });
+
+        InlineStr::new(s)
+            .filter(|_| is_valid && s != "-")
+            .map(HolderName)
+            .ok_or(Error::InvalidName)
+    }
+}
+
+impl FromStr for Email {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Email, Error> {
+        let is_valid = match s.split_once('@') {
+            Some((user, domain)) => !user.is_empty() && !domain.is_empty() && !domain.contains('@'),
+            None => false,
+        };
+
+        InlineStr::new(s)
+            .filter(|_| is_valid && is_plain(s))
+            .map(Email)
+            .ok_or(Error::InvalidEmail)
+    }
+}
+
+impl FromStr for Alias {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Alias, Error> {
+        let is_valid =
+            |c: char| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_';
+        let starts_with_letter = s.starts_with(|c: char| c.is_ascii_lowercase());
+
+        InlineStr::new(s)
+            .filter(|_| starts_with_letter && s.chars().all(is_valid))
+            .map(Alias)
+            .ok_or(Error::InvalidAlias)
+    }
+}
+
+impl fmt::Display for HolderName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0.as_str())
+    }
+}
+
+impl fmt::Display for Email {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0.as_str())
+    }
+}
+
+impl fmt::Display for Alias {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0.as_str())
+    }
+}
+
+/// Optional information about the account holder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Profile {
+    pub name: Option<HolderName>,
+    pub email: Option<Email>,
+    pub alias: Option<Alias>,
+}
+
+impl Profile {
+    pub fn is_empty(&self) -> bool {
+        *self == Profile::default()
+    }
+
+    /// Profile with `field` set, `None` in the field clears it.
+    pub fn updated(&self, field: ProfileField) -> Profile {
+        match field {
+            ProfileField::Name(name) => Profile { name, ..*self },
+            ProfileField::Email(email) => Profile { email, ..*self },
+            ProfileField::Alias(alias) => Profile { alias, ..*self },
+        }
+    }
+}
+
+/// Single profile field with its new value, profiles are updated one field at a time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProfileField {
+    Name(Option<HolderName>),
+    Email(Option<Email>),
+    Alias(Option<Alias>),
+}
+
+impl ProfileField {
+    /// Parses field name and its value, `none` clears the field.
+    pub fn parse(field: &str, value: &str) -> Result<ProfileField, Error> {
+        let value = match value {
+            "none" => None,
+            value => Some(value),
+        };
+
+        match field {
+            "name" => value.map(str::parse).transpose().map(ProfileField::Name),
+            "email" => value.map(str::parse).transpose().map(ProfileField::Email),
+            "alias" => value.map(str::parse).transpose().map(ProfileField::Alias),
+            _ => Err(Error::InvalidField),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProfileField::Name(_) => "name",
+            ProfileField::Email(_) => "email",
+            ProfileField::Alias(_) => "alias",
+        }
+    }
+
+    pub fn value(&self) -> Option<String> {
+        match self {
+            ProfileField::Name(name) => name.map(|name| name.to_string()),
+            ProfileField::Email(email) => email.map(|email| email.to_string()),
+            ProfileField::Alias(alias) => alias.map(|alias| alias.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for ProfileField {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.value() {
+            Some(value) => write!(f, "{} {}", self.name(), value),
+            None => write!(f, "{} none", self.name()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_works() {
+        assert_eq!(
+            "Jane_Doe".parse::<HolderName>().unwrap().to_string(),
+            "Jane_Doe"
+        );
+        assert_eq!("Zoë".parse::<HolderName>().unwrap().to_string(), "Zoë");
+        assert_eq!(
+            "Jane Doe".parse::<HolderName>().unwrap().to_string(),
+            "Jane Doe"
+        );
+        assert_eq!(
+            "jane@example.com".parse::<Email>().unwrap().to_string(),
+            "jane@example.com"
+        );
+        assert_eq!("jane-d_2".parse::<Alias>().unwrap().to_string(), "jane-d_2");
+
+        assert_eq!("-".parse::<HolderName>(), Err(Error::InvalidName));
+        for name in [" Jane", "Jane ", "Jane  Doe", "Jane\tDoe"] {
+            assert_eq!(name.parse::<HolderName>(), Err(Error::InvalidName));
+        }
+        assert_eq!(
+            "x".repeat(33).parse::<HolderName>(),
+            Err(Error::InvalidName)
+        );
+        assert_eq!("jane".parse::<Email>(), Err(Error::InvalidEmail));
+        assert_eq!("@example.com".parse::<Email>(), Err(Error::InvalidEmail));
+        assert_eq!("a@b@c".parse::<Email>(), Err(Error::InvalidEmail));
+        assert_eq!("Jane".parse::<Alias>(), Err(Error::InvalidAlias));
+        assert_eq!("2jane".parse::<Alias>(), Err(Error::InvalidAlias));
+        assert_eq!("".parse::<Alias>(), Err(Error::InvalidAlias));
+    }
+
+    #[test]
+    fn update_works() {
+        let alias = ProfileField::parse("alias", "jane").unwrap();
+        assert_eq!(alias.to_string(), "alias jane");
+
+        let profile = Profile::default().updated(alias);
+        assert_eq!(profile.alias, Some("jane".parse().unwrap()));
+        assert!(!profile.is_empty());
+
+        let profile = profile.updated(ProfileField::parse("alias", "none").unwrap());
+        assert!(profile.is_empty());
+
+        assert_eq!(
+            ProfileField::parse("phone", "123"),
+            Err(Error::InvalidField)
+        );
+        assert_eq!(
+            ProfileField::parse("email", "jane"),
+            Err(Error::InvalidEmail)
+        );
+    }
+}
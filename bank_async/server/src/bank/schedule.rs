@@ -0,0 +1,81 @@
+use crate::bank::account::AccountID;
+use crate::bank::clock::Timestamp;
+use crate::bank::money::Money;
+use std::fmt;
+use uuid::Uuid;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct ScheduleID(Uuid);
+pub type Error = uuid::Error;
+
+impl ScheduleID {
+    pub fn new() -> ScheduleID {
+        ScheduleID(Uuid::new_v4())
+    }
+
+    pub fn parse_str(s: &str) -> Result<ScheduleID, Error> {
+        Uuid::parse_str(s).map(ScheduleID)
+    }
+}
+
+impl fmt::Display for ScheduleID {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Transfer that is due at `next_run` and then repeats every `interval` seconds,
+/// schedule without interval runs only once.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Schedule {
+    pub id: ScheduleID,
+    pub sender_id: AccountID,
+    pub receiver_id: AccountID,
+    pub amount: Money,
+    pub next_run: Timestamp,
+    pub interval: Option<u64>,
+}
+
+impl Schedule {
+    pub fn is_due(&self, now: Timestamp) -> bool {
+        self.next_run <= now
+    }
+
+    /// Schedule after one more execution, `None` if nothing is left to run.
+    pub fn advanced(&self) -> Option<Schedule> {
+        let interval = self.interval?;
+
+        Some(Schedule {
+            next_run: self.next_run.saturating_add(interval),
+            ..*self
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn advanced_works() {
+        let schedule = Schedule {
+            id: ScheduleID::new(),
+            sender_id: AccountID::new(),
+            receiver_id: AccountID::new(),
+            amount: Money::from_minor(100),
+            next_run: 1000,
+            interval: None,
+        };
+        assert!(!schedule.is_due(999));
+        assert!(schedule.is_due(1000));
+        assert_eq!(schedule.advanced(), None);
+
+        let schedule = Schedule {
+            interval: Some(60),
+            ..schedule
+        };
+        let next = schedule.advanced().unwrap();
+        assert_eq!(next.next_run, 1060);
+        assert!(!next.is_due(1000));
+    }
+}
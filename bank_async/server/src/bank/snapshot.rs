@@ -0,0 +1,29 @@
+use crate::bank::account::{Account, AccountID};
+use crate::bank::clock::Timestamp;
+use crate::bank::currency::{Currency, Rate};
+use crate::bank::fee::{Fee, FeeOperation};
+use crate::bank::hold::Hold;
+use crate::bank::idempotency::{IdempotencyKey, KeyedRequest};
+use crate::bank::limit::Limits;
+use crate::bank::log::OperationID;
+use crate::bank::money::Money;
+use crate::bank::schedule::Schedule;
+
+/// State of all bank accounts and exchange rates right after the operation `last_operation_id`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Snapshot {
+    pub accounts: Vec<Account>,
+    pub exchange_rates: Vec<(Currency, Currency, Rate)>,
+    pub reversed_operations: Vec<OperationID>,
+    pub idempotency_keys: Vec<(IdempotencyKey, OperationID, KeyedRequest)>,
+    pub schedules: Vec<Schedule>,
+    pub fees: Vec<(FeeOperation, Fee)>,
+    /// Bank-wide limits have no account.
+    pub limits: Vec<(Option<AccountID>, Limits)>,
+    /// Outflows still counted against daily limits.
+    pub outflows: Vec<(AccountID, Timestamp, Money)>,
+    pub holds: Vec<Hold>,
+    pub last_operation_id: Option<OperationID>,
+    /// Time of `last_operation_id`, balances before it are not known.
+    pub last_operation_time: Option<Timestamp>,
+}
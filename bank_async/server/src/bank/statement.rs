@@ -0,0 +1,342 @@
+use crate::bank::account::AccountID;
+use crate::bank::clock::{format_timestamp, Timestamp};
+use crate::bank::log::{Operation, OperationID, OperationKind};
+use crate::bank::money::Money;
+use crate::bank::Bank;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Error {
+    InvalidFormat,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidFormat => write!(f, "format must be csv or json"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Debit,
+    Credit,
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Direction::Debit => write!(f, "debit"),
+            Direction::Credit => write!(f, "credit"),
+        }
+    }
+}
+
+/// Single balance change an operation makes to an account.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Posting {
+    pub account_id: AccountID,
+    pub direction: Direction,
+    pub amount: Money,
+    pub counterparty: Option<AccountID>,
+    /// `fee` for the fee charged, kind name of the operation otherwise.
+    pub kind: &'static str,
+}
+
+/// Balance changes of `operation`, e.g. withdrawal with a fee debits the account twice.
+/// `None` if a converted amount is too large.
+pub fn postings(operation: &Operation) -> Option<Vec<Posting>> {
+    let kind = operation.kind.name();
+    let posting = |account_id, direction, amount, counterparty| Posting {
+        account_id,
+        direction,
+        amount,
+        counterparty,
+        kind,
+    };
+
+    let mut postings = match operation.kind {
+        OperationKind::Register { id, balance, .. } => {
+            vec![posting(id, Direction::Credit, balance, None)]
+        }
+        OperationKind::Deposit { id, amount } | OperationKind::Interest { id, amount } => {
+            vec![posting(id, Direction::Credit, amount, None)]
+        }
+        // the other side is an account of another bank
+        OperationKind::InterbankSend {
+            sender_id,
+            receiver_id,
+            amount,
+            ..
+        } => vec![posting(
+            sender_id,
+            Direction::Debit,
+            amount,
+            Some(receiver_id),
+        )],
+        OperationKind::InterbankReceive {
+            sender_id,
+            receiver_id,
+            amount,
+            ..
+        } => vec![posting(
+            receiver_id,
+            Direction::Credit,
+            amount,
+            Some(sender_id),
+        )],
+        OperationKind::Withdraw { id, amount }
+        | OperationKind::Capture {
+            account_id: id,
+            amount,
+            ..
+        } => vec![posting(id, Direction::Debit, amount, None)],
+        OperationKind::Transfer {
+            sender_id,
+            receiver_id,
+            amount,
+            rate,
+        }
+        | OperationKind::Close {
+            id: sender_id,
+            sweep_to: Some(receiver_id),
+            amount,
+            rate,
+        } => vec![
+            posting(sender_id, Direction::Debit, amount, Some(receiver_id)),
+            posting(
+                receiver_id,
+                Direction::Credit,
+                rate.convert(amount)?,
+                Some(sender_id),
+            ),
+        ],
+        OperationKind::Reverse { debit, credit, .. } => {
+            let mut postings = Vec::new();
+            if let Some((id, amount)) = debit {
+                postings.push(posting(
+                    id,
+                    Direction::Debit,
+                    amount,
+                    credit.map(|(id, _)| id),
+                ));
+            }
+            if let Some((id, amount)) = credit {
+                postings.push(posting(
+                    id,
+                    Direction::Credit,
+                    amount,
+                    debit.map(|(id, _)| id),
+                ));
+            }
+            postings
+        }
+        _ => Vec::new(),
+    };
+
+    if let Some((fee_account, fee)) = operation.fee {
+        let payer = Bank::fee_payer(&operation.kind)?;
+        postings.push(Posting {
+            kind: "fee",
+            ..posting(payer, Direction::Debit, fee, Some(fee_account))
+        });
+        postings.push(Posting {
+            kind: "fee",
+            ..posting(fee_account, Direction::Credit, fee, Some(payer))
+        });
+    }
+
+    Some(postings)
+}
+
+/// Statement line: posting of the account and its balance right after it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StatementEntry {
+    pub operation_id: OperationID,
+    pub timestamp: Timestamp,
+    pub kind: &'static str,
+    pub direction: Direction,
+    pub amount: Money,
+    pub counterparty: Option<AccountID>,
+    pub balance: Money,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StatementFormat {
+    #[default]
+    Csv,
+    Json,
+}
+
+impl FromStr for StatementFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<StatementFormat, Error> {
+        match s {
+            "csv" => Ok(StatementFormat::Csv),
+            "json" => Ok(StatementFormat::Json),
+            _ => Err(Error::InvalidFormat),
+        }
+    }
+}
+
+impl StatementFormat {
+    pub fn render(&self, entries: &[StatementEntry]) -> String {
+        match self {
+            StatementFormat::Csv => to_csv(entries),
+            StatementFormat::Json => to_json(entries),
+        }
+    }
+}
+
+const FIELDS: [&str; 7] = [
+    "operation_id",
+    "time",
+    "kind",
+    "direction",
+    "amount",
+    "counterparty",
+    "balance",
+];
+
+// All values are ids, names, times and amounts, so none of them needs quoting.
+fn values(entry: &StatementEntry) -> [String; 7] {
+    [
+        entry.operation_id.to_string(),
+        format_timestamp(entry.timestamp),
+        entry.kind.to_string(),
+        entry.direction.to_string(),
+        entry.amount.to_string(),
+        entry
+            .counterparty
+            .map_or(String::new(), |counterparty| counterparty.to_string()),
+        entry.balance.to_string(),
+    ]
+}
+
+/// Header line and a line per entry.
+pub fn to_csv(entries: &[StatementEntry]) -> String {
+    std::iter::once(FIELDS.join(","))
+        .chain(entries.iter().map(|entry| values(entry).join(",")))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Array of objects, one per line. Amounts are strings to keep them exact,
+/// missing counterparty is `null`.
+pub fn to_json(entries: &[StatementEntry]) -> String {
+    if entries.is_empty() {
+        return String::from("[]");
+    }
+
+    let objects: Vec<String> = entries
+        .iter()
+        .map(|entry| {
+            let fields: Vec<String> = FIELDS
+                .iter()
+                .zip(values(entry))
+                .map(|(field, value)| {
+                    if value.is_empty() {
+                        format!("\"{}\":null", field)
+                    } else {
+                        format!("\"{}\":\"{}\"", field, value)
+                    }
+                })
+                .collect();
+            format!("{{{}}}", fields.join(","))
+        })
+        .collect();
+
+    format!("[\n{}\n]", objects.join(",\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bank::currency::Rate;
+
+    #[test]
+    fn postings_works() {
+        let (sender_id, receiver_id) = (AccountID::new(), AccountID::new());
+        let fee_account = AccountID::fee_account(Default::default());
+        let operation = Operation {
+            id: OperationID::new(),
+            kind: OperationKind::Transfer {
+                sender_id,
+                receiver_id,
+                amount: Money::from_minor(1000),
+                rate: "0.5".parse::<Rate>().unwrap(),
+            },
+            key: None,
+            schedule: None,
+            fee: Some((fee_account, Money::from_minor(10))),
+            timestamp: 0,
+        };
+
+        let postings: Vec<_> = postings(&operation)
+            .unwrap()
+            .into_iter()
+            .map(|p| {
+                (
+                    p.account_id,
+                    p.direction,
+                    p.amount.minor_units(),
+                    p.counterparty,
+                    p.kind,
+                )
+            })
+            .collect();
+        assert_eq!(
+            postings,
+            vec![
+                (
+                    sender_id,
+                    Direction::Debit,
+                    1000,
+                    Some(receiver_id),
+                    "transfer"
+                ),
+                (
+                    receiver_id,
+                    Direction::Credit,
+                    500,
+                    Some(sender_id),
+                    "transfer"
+                ),
+                (sender_id, Direction::Debit, 10, Some(fee_account), "fee"),
+                (fee_account, Direction::Credit, 10, Some(sender_id), "fee"),
+            ]
+        );
+    }
+
+    #[test]
+    fn render_works() {
+        let entry = StatementEntry {
+            operation_id: OperationID::parse_str("0b9ee2f5-7d3c-4b61-9a5e-2f3c8d4e6a10").unwrap(),
+            timestamp: 1709296200,
+            kind: "deposit",
+            direction: Direction::Credit,
+            amount: Money::from_minor(1050),
+            counterparty: None,
+            balance: Money::from_minor(2050),
+        };
+
+        assert_eq!(
+            StatementFormat::Csv.render(&[entry]),
+            "operation_id,time,kind,direction,amount,counterparty,balance\n\
+             0b9ee2f5-7d3c-4b61-9a5e-2f3c8d4e6a10,2024-03-01T12:30:00Z,deposit,credit,10.50,,20.50"
+        );
+        assert_eq!(
+            StatementFormat::Json.render(&[entry]),
+            "[\n{\"operation_id\":\"0b9ee2f5-7d3c-4b61-9a5e-2f3c8d4e6a10\",\
+             \"time\":\"2024-03-01T12:30:00Z\",\"kind\":\"deposit\",\"direction\":\"credit\",\
+             \"amount\":\"10.50\",\"counterparty\":null,\"balance\":\"20.50\"}\n]"
+        );
+        assert_eq!(StatementFormat::Json.render(&[]), "[]");
+        assert_eq!("xml".parse::<StatementFormat>(), Err(Error::InvalidFormat));
+    }
+}
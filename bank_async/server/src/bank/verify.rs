@@ -0,0 +1,143 @@
+use crate::bank::account::{Account, AccountID};
+use crate::bank::currency::Currency;
+use crate::bank::money::Money;
+use crate::bank::BankError;
+use std::fmt;
+
+/// Money of one currency the log moved against what the accounts hold.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Reconciliation {
+    pub currency: Currency,
+    /// Balances of the base snapshot and of registered accounts.
+    pub opening: Money,
+    /// Deposits, interest and every other credit, incoming transfers included.
+    pub credited: Money,
+    /// Withdrawals, captures, fees and every other debit, outgoing transfers included.
+    pub debited: Money,
+    /// Sum of the live balances.
+    pub balance: Money,
+}
+
+impl Reconciliation {
+    pub fn new(currency: Currency) -> Reconciliation {
+        Reconciliation {
+            currency,
+            opening: Money::ZERO,
+            credited: Money::ZERO,
+            debited: Money::ZERO,
+            balance: Money::ZERO,
+        }
+    }
+
+    /// Balance the accounts should hold, `None` if it is too large.
+    pub fn expected(&self) -> Option<Money> {
+        self.opening
+            .checked_add(self.credited)?
+            .checked_sub(self.debited)
+    }
+}
+
+impl fmt::Display for Reconciliation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} opening {} credited {} debited {} balance {}",
+            self.currency, self.opening, self.credited, self.debited, self.balance
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Discrepancy {
+    /// The log can't be replayed, so accounts are not compared.
+    Replay(BankError),
+    /// Account differs from the one the log replays to, `None` if it is missing on that side.
+    Account {
+        id: AccountID,
+        replayed: Option<Box<Account>>,
+        live: Option<Box<Account>>,
+    },
+    /// Balances of the currency don't add up to the money the log moved.
+    Total(Reconciliation),
+}
+
+fn describe(account: Option<&Account>) -> String {
+    match account {
+        Some(account) => format!(
+            "{} {} {} overdraft {} {}",
+            account.balance,
+            account.currency,
+            account.status,
+            account.overdraft_limit,
+            account.kind
+        ),
+        None => String::from("missing"),
+    }
+}
+
+impl fmt::Display for Discrepancy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Discrepancy::Replay(e) => write!(f, "replay failed: {}", e),
+            Discrepancy::Account { id, replayed, live } => write!(
+                f,
+                "account {}: replayed {}, live {}",
+                id,
+                describe(replayed.as_deref()),
+                describe(live.as_deref())
+            ),
+            Discrepancy::Total(totals) => match totals.expected() {
+                Some(expected) => write!(
+                    f,
+                    "total {}: expected {}, live {}",
+                    totals.currency, expected, totals.balance
+                ),
+                None => write!(f, "total {}: expected overflows", totals.currency),
+            },
+        }
+    }
+}
+
+/// Outcome of `Bank::verify`, the bank is consistent if there are no discrepancies.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VerifyReport {
+    /// Ordered by currency.
+    pub totals: Vec<Reconciliation>,
+    pub discrepancies: Vec<Discrepancy>,
+}
+
+impl VerifyReport {
+    pub fn is_consistent(&self) -> bool {
+        self.discrepancies.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reconciliation_works() {
+        let totals = Reconciliation {
+            opening: Money::from_minor(1000),
+            credited: Money::from_minor(500),
+            debited: Money::from_minor(300),
+            balance: Money::from_minor(1100),
+            ..Reconciliation::new(Currency::default())
+        };
+
+        assert_eq!(totals.expected(), Some(Money::from_minor(1200)));
+        assert_eq!(
+            Discrepancy::Total(totals).to_string(),
+            "total USD: expected 12.00, live 11.00"
+        );
+        assert_eq!(
+            Reconciliation {
+                opening: Money::MAX,
+                ..totals
+            }
+            .expected(),
+            None
+        );
+    }
+}
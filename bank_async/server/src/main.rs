@@ -1,16 +1,14 @@
-use server::server::actor::repository_actor;
-use server::server::command::Command;
+use server::server::actor::{repository_actor, scheduler, Request};
 use server::server::handler::handle;
 use server::server::repository::Repository;
-use tokio::{
-    io::AsyncWriteExt,
-    net::TcpListener,
-    sync::{mpsc::unbounded_channel, oneshot::Sender},
-};
+use std::time::Duration;
+use tokio::{io::AsyncWriteExt, net::TcpListener, sync::mpsc::unbounded_channel};
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
 
 const ADDR: &str = "127.0.0.1:1337";
+const DATA_DIR: &str = "bank_data";
+const SCHEDULER_PERIOD: Duration = Duration::from_secs(1);
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -18,13 +16,22 @@ async fn main() -> Result<()> {
 
     println!("Listening on {}", listener.local_addr()?);
 
-    let (sender, mut receiver) = unbounded_channel::<(Command, Sender<String>)>();
+    let mut repository = Repository::open(DATA_DIR)?;
+
+    println!(
+        "Restored {} bank(s) from {}",
+        repository.banks.len(),
+        DATA_DIR
+    );
+
+    let (sender, mut receiver) = unbounded_channel::<Request>();
 
     tokio::spawn(async move {
-        let mut repository = Repository::default();
         repository_actor(&mut repository, &mut receiver).await;
     });
 
+    tokio::spawn(scheduler(sender.clone(), SCHEDULER_PERIOD));
+
     loop {
         let (mut stream, addr) = listener.accept().await?;
 
@@ -67,14 +74,17 @@ mod tests {
     use super::*;
     use regex::Regex;
     use server::bank::account::AccountID;
+    use server::bank::clock::{ManualClock, SharedClock};
     use server::bank::log::OperationID;
+    use server::bank::money;
     use server::server::command::ParseError;
     use std::str::from_utf8;
+    use std::sync::Arc;
 
     #[tokio::test]
     async fn unknown_command_works() {
         let mut terminal = Vec::new();
-        let (sender, _) = unbounded_channel::<(Command, Sender<String>)>();
+        let (sender, _) = unbounded_channel::<Request>();
 
         let reader = "test_command".as_bytes();
         let mut writer = Vec::new();
@@ -93,7 +103,7 @@ mod tests {
     async fn handle_empty_command_works() {
         let mut terminal = Vec::new();
 
-        let (sender, _) = unbounded_channel::<(Command, Sender<String>)>();
+        let (sender, _) = unbounded_channel::<Request>();
 
         let reader = "".as_bytes();
         let mut writer = Vec::new();
@@ -111,7 +121,7 @@ mod tests {
     #[tokio::test]
     async fn handle_quit_command_works() {
         let mut terminal = Vec::new();
-        let (sender, _) = unbounded_channel::<(Command, Sender<String>)>();
+        let (sender, _) = unbounded_channel::<Request>();
 
         let reader = "quit".as_bytes();
         let mut writer = Vec::new();
@@ -131,7 +141,7 @@ mod tests {
     #[tokio::test]
     async fn handle_new_bank_command() {
         let mut terminal = Vec::new();
-        let (sender, mut receiver) = unbounded_channel::<(Command, Sender<String>)>();
+        let (sender, mut receiver) = unbounded_channel::<Request>();
 
         let reader = "new_bank".as_bytes();
         let mut writer = Vec::new();
@@ -154,9 +164,9 @@ mod tests {
     #[tokio::test]
     async fn handle_which_bank_command() {
         let mut terminal = Vec::new();
-        let (sender, mut receiver) = unbounded_channel::<(Command, Sender<String>)>();
+        let (sender, mut receiver) = unbounded_channel::<Request>();
 
-        let input = vec!["which_bank", "new_bank", "which_bank"].join("\n");
+        let input = ["which_bank", "new_bank", "which_bank"].join("\n");
         let reader = input.as_bytes();
         let mut writer = Vec::new();
 
@@ -170,10 +180,10 @@ mod tests {
             .unwrap();
 
         assert_eq!(
-            vec![
+            [
                 "Bank: 1\nStatus: ok\nResult: 1\n\n",
                 "Bank: 1\nStatus: ok\nResult: 2\n\n",
-                "Bank: 2\nStatus: ok\nResult: 2\n\n",
+                "Bank: 2\nStatus: ok\nResult: 2\n\n"
             ]
             .join(""),
             from_utf8(writer.as_slice()).unwrap()
@@ -183,9 +193,9 @@ mod tests {
     #[tokio::test]
     async fn handle_change_bank_command() {
         let mut terminal = Vec::new();
-        let (sender, mut receiver) = unbounded_channel::<(Command, Sender<String>)>();
+        let (sender, mut receiver) = unbounded_channel::<Request>();
 
-        let input = vec![
+        let input = [
             "new_bank",
             "new_bank",
             "new_bank",
@@ -210,7 +220,7 @@ mod tests {
             .unwrap();
 
         assert_eq!(
-            vec![
+            [
                 "Bank: 0\nStatus: ok\nResult: 1\n\n",
                 "Bank: 1\nStatus: ok\nResult: 2\n\n",
                 "Bank: 2\nStatus: ok\nResult: 3\n\n",
@@ -219,7 +229,7 @@ mod tests {
                 "Bank: 3\nStatus: ok\nResult: 1\n\n",
                 "Bank: 1\nStatus: error\nType: bank\nError: invalid bank id\n\n",
                 "Bank: 1\nStatus: error\nType: bank\nError: invalid bank id\n\n",
-                "Bank: 1\nStatus: error\nType: bank\nError: invalid bank id\n\n",
+                "Bank: 1\nStatus: error\nType: bank\nError: invalid bank id\n\n"
             ]
             .join(""),
             from_utf8(writer.as_slice()).unwrap()
@@ -229,9 +239,9 @@ mod tests {
     #[tokio::test]
     async fn handle_register_account_works() {
         let mut terminal = Vec::new();
-        let (sender, mut receiver) = unbounded_channel::<(Command, Sender<String>)>();
+        let (sender, mut receiver) = unbounded_channel::<Request>();
 
-        let input = vec!["register_account", "register_account 100"].join("\n");
+        let input = ["register_account", "register_account 100"].join("\n");
         let reader = input.as_bytes();
         let mut writer = Vec::new();
 
@@ -246,7 +256,6 @@ mod tests {
 
         let result = from_utf8(writer.as_slice())
             .unwrap()
-            .to_owned()
             .split("\n\n")
             .map(|s| s.to_owned())
             .collect::<Vec<_>>();
@@ -279,7 +288,7 @@ mod tests {
 
         let mut terminal = Vec::new();
 
-        let (sender, mut receiver) = unbounded_channel::<(Command, Sender<String>)>();
+        let (sender, mut receiver) = unbounded_channel::<Request>();
 
         tokio::spawn(async move {
             let mut repository = Repository::default();
@@ -292,7 +301,6 @@ mod tests {
 
         let result = from_utf8(writer.as_slice())
             .unwrap()
-            .to_owned()
             .split("\n\n")
             .map(|s| s.to_owned())
             .collect::<Vec<_>>();
@@ -307,10 +315,13 @@ mod tests {
         assert!(OperationID::parse_str(operation_id).is_ok());
         assert!(AccountID::parse_str(account_id).is_ok());
 
-        let input = vec![
+        let input = [
             "get_balance".to_owned(),
             "get_balance test".to_owned(),
-            format!("get_balance {}", account_id.to_string()),
+            format!("get_balance {}", account_id),
+            format!("freeze_account {}", account_id),
+            format!("get_balance {}", account_id),
+            format!("deposit {} 1", account_id),
         ]
         .join("\n");
 
@@ -322,7 +333,6 @@ mod tests {
 
         let result = from_utf8(writer.as_slice())
             .unwrap()
-            .to_owned()
             .split("\n\n")
             .map(|s| s.to_owned())
             .collect::<Vec<_>>();
@@ -348,7 +358,15 @@ mod tests {
             result[2]
         );
 
-        assert_eq!(format!("Bank: 1\nStatus: ok\nResult: {}", 100), result[3]);
+        assert_eq!("Bank: 1\nStatus: ok\nResult: 100.00", result[3]);
+        assert!(Regex::new(r"Bank: 1\nOpID: [a-f0-9-]+\nStatus: ok")
+            .unwrap()
+            .is_match(&result[4]));
+        assert_eq!("Bank: 1\nStatus: ok\nResult: 100.00 (frozen)", result[5]);
+        assert_eq!(
+            "Bank: 1\nStatus: fail\nResult: Bank error: Account frozen",
+            result[6]
+        );
     }
 
     #[tokio::test]
@@ -358,7 +376,7 @@ mod tests {
 
         let mut terminal = Vec::new();
 
-        let (sender, mut receiver) = unbounded_channel::<(Command, Sender<String>)>();
+        let (sender, mut receiver) = unbounded_channel::<Request>();
 
         tokio::spawn(async move {
             let mut repository = Repository::default();
@@ -382,7 +400,7 @@ mod tests {
 
         let mut terminal = Vec::new();
 
-        let (sender, mut receiver) = unbounded_channel::<(Command, Sender<String>)>();
+        let (sender, mut receiver) = unbounded_channel::<Request>();
 
         tokio::spawn(async move {
             let mut repository = Repository::default();
@@ -395,7 +413,6 @@ mod tests {
 
         let result = from_utf8(writer.as_slice())
             .unwrap()
-            .to_owned()
             .split("\n\n")
             .map(|s| s.to_owned())
             .collect::<Vec<_>>();
@@ -410,11 +427,11 @@ mod tests {
         assert!(OperationID::parse_str(operation_id).is_ok());
         assert!(AccountID::parse_str(account_id).is_ok());
 
-        let input = vec![
+        let input = [
             "deposit".to_owned(),
             "deposit test 10".to_owned(),
-            format!("deposit {} test", account_id.to_string()),
-            format!("deposit {} 100", account_id.to_string()),
+            format!("deposit {} test", account_id),
+            format!("deposit {} 100", account_id),
         ]
         .join("\n");
 
@@ -426,7 +443,6 @@ mod tests {
 
         let result = from_utf8(writer.as_slice())
             .unwrap()
-            .to_owned()
             .split("\n\n")
             .map(|s| s.to_owned())
             .collect::<Vec<_>>();
@@ -455,10 +471,10 @@ mod tests {
         assert_eq!(
             format!(
                 "Command: deposit {} test\nStatus: error\nType: parse\nError: {}",
-                account_id.to_string(),
-                ParseError::InvalidArgumentUint {
+                account_id,
+                ParseError::InvalidArgumentMoney {
                     name: "amount".to_owned(),
-                    e: "test".parse::<u64>().unwrap_err(),
+                    e: money::Error::InvalidFormat,
                 }
             ),
             result[3]
@@ -481,7 +497,7 @@ mod tests {
         let mut writer = Vec::new();
         let mut terminal = Vec::new();
 
-        let (sender, mut receiver) = unbounded_channel::<(Command, Sender<String>)>();
+        let (sender, mut receiver) = unbounded_channel::<Request>();
 
         tokio::spawn(async move {
             let mut repository = Repository::default();
@@ -494,7 +510,6 @@ mod tests {
 
         let result = from_utf8(writer.as_slice())
             .unwrap()
-            .to_owned()
             .split("\n\n")
             .map(|s| s.to_owned())
             .collect::<Vec<_>>();
@@ -509,12 +524,12 @@ mod tests {
         assert!(OperationID::parse_str(operation_id).is_ok());
         assert!(AccountID::parse_str(account_id).is_ok());
 
-        let input = vec![
+        let input = [
             "withdraw".to_owned(),
             "withdraw test 10".to_owned(),
-            format!("withdraw {} test", account_id.to_string()),
-            format!("withdraw {} 100", account_id.to_string()),
-            format!("withdraw {} 100", account_id.to_string()),
+            format!("withdraw {} test", account_id),
+            format!("withdraw {} 100", account_id),
+            format!("withdraw {} 100", account_id),
         ]
         .join("\n");
 
@@ -526,7 +541,6 @@ mod tests {
 
         let result = from_utf8(writer.as_slice())
             .unwrap()
-            .to_owned()
             .split("\n\n")
             .map(|s| s.to_owned())
             .collect::<Vec<_>>();
@@ -555,10 +569,10 @@ mod tests {
         assert_eq!(
             format!(
                 "Command: withdraw {} test\nStatus: error\nType: parse\nError: {}",
-                account_id.to_string(),
-                ParseError::InvalidArgumentUint {
+                account_id,
+                ParseError::InvalidArgumentMoney {
                     name: "amount".to_owned(),
-                    e: "test".parse::<u64>().unwrap_err(),
+                    e: money::Error::InvalidFormat,
                 }
             ),
             result[3]
@@ -581,7 +595,7 @@ mod tests {
 
     #[tokio::test]
     async fn handle_transfer_works() {
-        let input = vec![
+        let input = [
             "register_account 100".to_owned(),
             "register_account 50".to_owned(),
         ]
@@ -592,7 +606,7 @@ mod tests {
         let mut writer = Vec::new();
         let mut terminal = Vec::new();
 
-        let (sender, mut receiver) = unbounded_channel::<(Command, Sender<String>)>();
+        let (sender, mut receiver) = unbounded_channel::<Request>();
 
         tokio::spawn(async move {
             let mut repository = Repository::default();
@@ -605,7 +619,6 @@ mod tests {
 
         let result = from_utf8(writer.as_slice())
             .unwrap()
-            .to_owned()
             .split("\n\n")
             .map(|s| s.to_owned())
             .collect::<Vec<_>>();
@@ -625,26 +638,14 @@ mod tests {
         assert!(OperationID::parse_str(operation2_id).is_ok());
         assert!(AccountID::parse_str(account2_id).is_ok());
 
-        let input = vec![
+        let input = [
             "transfer".to_owned(),
             "transfer test1 test2 50".to_owned(),
-            format!("transfer {} test2 50", account1_id.to_string()),
-            format!("transfer test1 {} 50", account2_id.to_string()),
-            format!(
-                "transfer {} {} test",
-                account1_id.to_string(),
-                account2_id.to_string()
-            ),
-            format!(
-                "transfer {} {} 50",
-                account1_id.to_string(),
-                account2_id.to_string()
-            ),
-            format!(
-                "transfer {} {} 500",
-                account2_id.to_string(),
-                account1_id.to_string()
-            ),
+            format!("transfer {} test2 50", account1_id),
+            format!("transfer test1 {} 50", account2_id),
+            format!("transfer {} {} test", account1_id, account2_id),
+            format!("transfer {} {} 50", account1_id, account2_id),
+            format!("transfer {} {} 500", account2_id, account1_id),
         ]
         .join("\n");
 
@@ -656,7 +657,6 @@ mod tests {
 
         let result = from_utf8(writer.as_slice())
             .unwrap()
-            .to_owned()
             .split("\n\n")
             .map(|s| s.to_owned())
             .collect::<Vec<_>>();
@@ -689,7 +689,7 @@ mod tests {
         assert_eq!(
             format!(
                 "Command: transfer {} test2 50\nStatus: error\nType: parse\nError: {}",
-                account1_id.to_string(),
+                account1_id,
                 ParseError::InvalidArgumentAccountID {
                     name: "receiver_account_id".to_owned(),
                     e: AccountID::parse_str("test2").unwrap_err()
@@ -701,7 +701,7 @@ mod tests {
         assert_eq!(
             format!(
                 "Command: transfer test1 {} 50\nStatus: error\nType: parse\nError: {}",
-                account2_id.to_string(),
+                account2_id,
                 ParseError::InvalidArgumentAccountID {
                     name: "sender_account_id".to_owned(),
                     e: AccountID::parse_str("test1").unwrap_err()
@@ -713,9 +713,9 @@ mod tests {
         assert_eq!(
             format!(
                 "Command: transfer {} {} test\nStatus: error\nType: parse\nError: invalid argument amount: {}",
-                account1_id.to_string(),
-                account2_id.to_string(),
-                "test".parse::<u64>().unwrap_err(),
+                account1_id,
+                account2_id,
+                money::Error::InvalidFormat,
             ),
             result[6]
         );
@@ -735,14 +735,508 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn handle_fees_works() {
+        let reader = "register_account 100".as_bytes();
+
+        let mut writer = Vec::new();
+        let mut terminal = Vec::new();
+
+        let (sender, mut receiver) = unbounded_channel::<Request>();
+
+        tokio::spawn(async move {
+            let mut repository = Repository::default();
+            repository_actor(&mut repository, &mut receiver).await;
+        });
+
+        handle(&sender, reader, &mut writer, &mut terminal)
+            .await
+            .unwrap();
+
+        let re =
+            Regex::new(r"Bank: 1\nOpID: [a-f0-9-]+\nStatus: ok\nResult: ([a-f0-9-]+)").unwrap();
+        let account = re.captures(from_utf8(writer.as_slice()).unwrap()).unwrap()[1].to_owned();
+
+        let input = [
+            "set_fee withdraw flat:1".to_owned(),
+            "set_fee transfer percentage:0.01".to_owned(),
+            "list_fees".to_owned(),
+            format!("withdraw {} 10", account),
+            format!("list_account_operations {}", account),
+            "fee_account".to_owned(),
+            "get_balance 00000000-0000-0000-0000-000000555344".to_owned(),
+        ]
+        .join("\n");
+
+        let mut writer = Vec::new();
+        handle(&sender, input.as_bytes(), &mut writer, &mut terminal)
+            .await
+            .unwrap();
+
+        let result = from_utf8(writer.as_slice())
+            .unwrap()
+            .split("\n\n")
+            .map(|s| s.to_owned())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            "Bank: 1\nStatus: ok\nResult:\nwithdraw flat:1.00\ntransfer percentage:0.01",
+            result[2]
+        );
+        assert!(result[4].ends_with(&format!("(Withdraw {} 10.00) fee 1.00", account)));
+        assert_eq!(
+            "Bank: 1\nStatus: ok\nResult: 00000000-0000-0000-0000-000000555344",
+            result[5]
+        );
+        assert_eq!("Bank: 1\nStatus: ok\nResult: 1.00", result[6]);
+    }
+
+    #[tokio::test]
+    async fn scheduler_works() {
+        let reader = "register_account 100\nregister_account 0".as_bytes();
+
+        let mut writer = Vec::new();
+        let mut terminal = Vec::new();
+
+        let (sender, mut receiver) = unbounded_channel::<Request>();
+
+        tokio::spawn(async move {
+            let mut repository = Repository::default();
+            repository_actor(&mut repository, &mut receiver).await;
+        });
+        tokio::spawn(scheduler(sender.clone(), Duration::from_millis(10)));
+
+        handle(&sender, reader, &mut writer, &mut terminal)
+            .await
+            .unwrap();
+
+        let re =
+            Regex::new(r"Bank: 1\nOpID: [a-f0-9-]+\nStatus: ok\nResult: ([a-f0-9-]+)").unwrap();
+        let accounts = re
+            .captures_iter(from_utf8(writer.as_slice()).unwrap())
+            .map(|captures| captures[1].to_owned())
+            .collect::<Vec<_>>();
+
+        let input = [
+            format!("schedule_transfer {} {} 30 0", accounts[0], accounts[1]),
+            format!(
+                "schedule_transfer {} {} 10 1h 30d",
+                accounts[0], accounts[1]
+            ),
+        ]
+        .join("\n");
+
+        let mut writer = Vec::new();
+        handle(&sender, input.as_bytes(), &mut writer, &mut terminal)
+            .await
+            .unwrap();
+
+        let schedules = re
+            .captures_iter(from_utf8(writer.as_slice()).unwrap())
+            .map(|captures| captures[1].to_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(schedules.len(), 2);
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let input = [
+            format!("get_balance {}", accounts[1]),
+            format!("list_account_operations {}", accounts[1]),
+            "list_schedules".to_owned(),
+            format!("cancel_schedule {}", schedules[1]),
+            format!("cancel_schedule {}", schedules[1]),
+            "list_schedules".to_owned(),
+        ]
+        .join("\n");
+
+        let mut writer = Vec::new();
+        handle(&sender, input.as_bytes(), &mut writer, &mut terminal)
+            .await
+            .unwrap();
+
+        let result = from_utf8(writer.as_slice())
+            .unwrap()
+            .split("\n\n")
+            .map(|s| s.to_owned())
+            .collect::<Vec<_>>();
+
+        // only the first schedule was due
+        assert_eq!("Bank: 1\nStatus: ok\nResult: 30.00", result[0]);
+        assert!(result[1].contains(&format!(") by schedule {}", schedules[0])));
+        assert!(Regex::new(&format!(
+            r"^Bank: 1\nStatus: ok\nResult:\n{}: {} -> {} 10.00 at \d+ every 30d$",
+            schedules[1], accounts[0], accounts[1]
+        ))
+        .unwrap()
+        .is_match(&result[2]));
+        assert!(result[3].starts_with("Bank: 1\nOpID: "));
+        assert_eq!(
+            "Bank: 1\nStatus: error\nType: bank\nError: Bank error: Schedule not found",
+            result[4]
+        );
+        assert_eq!("Bank: 1\nStatus: ok\nResult:\nno schedules yet", result[5]);
+    }
+
+    #[tokio::test]
+    async fn handle_batch_works() {
+        let reader = "register_account 100\nregister_account 50".as_bytes();
+
+        let mut writer = Vec::new();
+        let mut terminal = Vec::new();
+
+        let (sender, mut receiver) = unbounded_channel::<Request>();
+
+        tokio::spawn(async move {
+            let mut repository = Repository::default();
+            repository_actor(&mut repository, &mut receiver).await;
+        });
+
+        handle(&sender, reader, &mut writer, &mut terminal)
+            .await
+            .unwrap();
+
+        let re =
+            Regex::new(r"Bank: 1\nOpID: [a-f0-9-]+\nStatus: ok\nResult: ([a-f0-9-]+)").unwrap();
+        let accounts = re
+            .captures_iter(from_utf8(writer.as_slice()).unwrap())
+            .map(|captures| captures[1].to_owned())
+            .collect::<Vec<_>>();
+
+        let input = [
+            "begin".to_owned(),
+            format!("transfer {} {} 80", accounts[0], accounts[1]),
+            format!("transfer {} {} 80", accounts[0], accounts[1]),
+            "commit".to_owned(),
+            "begin".to_owned(),
+            format!("transfer {} {} 80", accounts[0], accounts[1]),
+            "new_bank".to_owned(),
+            "commit".to_owned(),
+            "begin".to_owned(),
+            format!("transfer {} {} 80", accounts[0], accounts[1]),
+            format!("deposit {} 10", accounts[1]),
+            "commit".to_owned(),
+            format!("get_balance {}", accounts[0]),
+            format!("get_balance {}", accounts[1]),
+        ]
+        .join("\n");
+
+        let mut writer = Vec::new();
+        handle(&sender, input.as_bytes(), &mut writer, &mut terminal)
+            .await
+            .unwrap();
+
+        let result = from_utf8(writer.as_slice())
+            .unwrap()
+            .split("\n\n")
+            .map(|s| s.to_owned())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            "Bank: 1\nStatus: error\nType: batch\nIndex: 2\nError: Bank error: Insufficient funds",
+            result[3]
+        );
+        assert_eq!(
+            "Bank: 1\nStatus: error\nType: batch\nIndex: 2\nError: Command can't be used in a batch",
+            result[7]
+        );
+        assert!(
+            Regex::new(r"^Bank: 1\nStatus: ok\nResult:\n[a-f0-9-]+\n[a-f0-9-]+$")
+                .unwrap()
+                .is_match(&result[11])
+        );
+        assert_eq!("Bank: 1\nStatus: ok\nResult: 20.00", result[12]);
+        assert_eq!("Bank: 1\nStatus: ok\nResult: 140.00", result[13]);
+    }
+
+    #[tokio::test]
+    async fn handle_alias_works() {
+        let reader = "register_account 100\nregister_account 50".as_bytes();
+
+        let mut writer = Vec::new();
+        let mut terminal = Vec::new();
+
+        let (sender, mut receiver) = unbounded_channel::<Request>();
+
+        tokio::spawn(async move {
+            let mut repository = Repository::default();
+            repository_actor(&mut repository, &mut receiver).await;
+        });
+
+        handle(&sender, reader, &mut writer, &mut terminal)
+            .await
+            .unwrap();
+
+        let re =
+            Regex::new(r"Bank: 1\nOpID: [a-f0-9-]+\nStatus: ok\nResult: ([a-f0-9-]+)").unwrap();
+        let accounts = re
+            .captures_iter(from_utf8(writer.as_slice()).unwrap())
+            .map(|captures| captures[1].to_owned())
+            .collect::<Vec<_>>();
+
+        let input = [
+            format!("update_profile {} alias jane", accounts[0]),
+            format!("update_profile {} name Jane Doe", accounts[0]),
+            "begin".to_owned(),
+            format!("update_profile {} alias joe", accounts[1]),
+            "transfer @jane @joe 30".to_owned(),
+            "commit".to_owned(),
+            "get_balance @joe".to_owned(),
+            "get_profile @jane".to_owned(),
+            format!("update_profile {} alias jane", accounts[1]),
+            "get_balance @nobody".to_owned(),
+        ]
+        .join("\n");
+
+        let mut writer = Vec::new();
+        handle(&sender, input.as_bytes(), &mut writer, &mut terminal)
+            .await
+            .unwrap();
+
+        let result = from_utf8(writer.as_slice())
+            .unwrap()
+            .split("\n\n")
+            .map(|s| s.to_owned())
+            .collect::<Vec<_>>();
+
+        // alias set earlier in the same batch is already visible
+        assert!(
+            Regex::new(r"^Bank: 1\nStatus: ok\nResult:\n[a-f0-9-]+\n[a-f0-9-]+$")
+                .unwrap()
+                .is_match(&result[5])
+        );
+        assert_eq!("Bank: 1\nStatus: ok\nResult: 80.00", result[6]);
+        assert_eq!(
+            "Bank: 1\nStatus: ok\nResult:\nname Jane Doe\nemail none\nalias jane",
+            result[7]
+        );
+        assert_eq!(
+            "Bank: 1\nStatus: error\nType: bank\nError: Bank error: Alias already taken",
+            result[8]
+        );
+        assert_eq!(
+            "Bank: 1\nStatus: error\nType: repository\nError: Bank error: Alias not found",
+            result[9]
+        );
+    }
+
+    #[tokio::test]
+    async fn handle_limits_works() {
+        let reader = "register_account 100".as_bytes();
+
+        let mut writer = Vec::new();
+        let mut terminal = Vec::new();
+
+        let (sender, mut receiver) = unbounded_channel::<Request>();
+
+        tokio::spawn(async move {
+            let mut repository = Repository::default();
+            repository_actor(&mut repository, &mut receiver).await;
+        });
+
+        handle(&sender, reader, &mut writer, &mut terminal)
+            .await
+            .unwrap();
+
+        let re =
+            Regex::new(r"Bank: 1\nOpID: [a-f0-9-]+\nStatus: ok\nResult: ([a-f0-9-]+)").unwrap();
+        let account = re.captures(from_utf8(writer.as_slice()).unwrap()).unwrap()[1].to_owned();
+
+        let input = [
+            "set_limit bank per_transaction 50".to_owned(),
+            format!("set_limit {} daily 70", account),
+            format!("withdraw {} 60", account),
+            format!("withdraw {} 40", account),
+            format!("withdraw {} 40", account),
+            format!("get_limits {}", account),
+        ]
+        .join("\n");
+
+        let mut writer = Vec::new();
+        handle(&sender, input.as_bytes(), &mut writer, &mut terminal)
+            .await
+            .unwrap();
+
+        let result = from_utf8(writer.as_slice())
+            .unwrap()
+            .split("\n\n")
+            .map(|s| s.to_owned())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            "Bank: 1\nStatus: error\nType: bank\nError: Bank error: Limit exceeded",
+            result[2]
+        );
+        assert!(result[3].ends_with("Status: ok"));
+        assert_eq!(
+            "Bank: 1\nStatus: error\nType: bank\nError: Bank error: Limit exceeded",
+            result[4]
+        );
+        assert_eq!(
+            "Bank: 1\nStatus: ok\nResult:\nper_transaction 50.00\ndaily 70.00\noutflow 40.00",
+            result[5]
+        );
+    }
+
+    #[tokio::test]
+    async fn handle_holds_works() {
+        let reader = "register_account 100".as_bytes();
+
+        let mut writer = Vec::new();
+        let mut terminal = Vec::new();
+
+        let (sender, mut receiver) = unbounded_channel::<Request>();
+
+        tokio::spawn(async move {
+            let mut repository = Repository::default();
+            repository_actor(&mut repository, &mut receiver).await;
+        });
+
+        handle(&sender, reader, &mut writer, &mut terminal)
+            .await
+            .unwrap();
+
+        let re =
+            Regex::new(r"Bank: 1\nOpID: [a-f0-9-]+\nStatus: ok\nResult: ([a-f0-9-]+)").unwrap();
+        let account = re.captures(from_utf8(writer.as_slice()).unwrap()).unwrap()[1].to_owned();
+
+        let input = format!("hold {} 60", account);
+        let mut writer = Vec::new();
+        handle(&sender, input.as_bytes(), &mut writer, &mut terminal)
+            .await
+            .unwrap();
+        let hold = re.captures(from_utf8(writer.as_slice()).unwrap()).unwrap()[1].to_owned();
+
+        let input = [
+            format!("withdraw {} 50", account),
+            format!("get_balance {}", account),
+            format!("capture {} 45", hold),
+            format!("get_balance {}", account),
+            format!("release {}", hold),
+        ]
+        .join("\n");
+
+        let mut writer = Vec::new();
+        handle(&sender, input.as_bytes(), &mut writer, &mut terminal)
+            .await
+            .unwrap();
+
+        let result = from_utf8(writer.as_slice())
+            .unwrap()
+            .split("\n\n")
+            .map(|s| s.to_owned())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            "Bank: 1\nStatus: error\nType: bank\nError: Bank error: Insufficient funds",
+            result[0]
+        );
+        assert_eq!(
+            "Bank: 1\nStatus: ok\nResult: 100.00\nAvailable: 40.00",
+            result[1]
+        );
+        assert!(result[2].ends_with("Status: ok"));
+        assert_eq!("Bank: 1\nStatus: ok\nResult: 55.00", result[3]);
+        assert_eq!(
+            "Bank: 1\nStatus: error\nType: bank\nError: Bank error: Hold not found",
+            result[4]
+        );
+    }
+
+    #[tokio::test]
+    async fn handle_interbank_transfer_works() {
+        let input = ["register_account 100", "new_bank", "register_account 0"].join("\n");
+
+        let mut writer = Vec::new();
+        let mut terminal = Vec::new();
+
+        let (sender, mut receiver) = unbounded_channel::<Request>();
+
+        tokio::spawn(async move {
+            let mut repository = Repository::default();
+            repository_actor(&mut repository, &mut receiver).await;
+        });
+
+        handle(&sender, input.as_bytes(), &mut writer, &mut terminal)
+            .await
+            .unwrap();
+
+        let re =
+            Regex::new(r"Bank: \d\nOpID: [a-f0-9-]+\nStatus: ok\nResult: ([a-f0-9-]+)").unwrap();
+        let accounts = re
+            .captures_iter(from_utf8(writer.as_slice()).unwrap())
+            .map(|captures| captures[1].to_owned())
+            .collect::<Vec<_>>();
+
+        let input = [
+            "change_bank 2".to_owned(),
+            format!("update_profile {} alias jane", accounts[1]),
+            "change_bank 1".to_owned(),
+            format!("interbank_transfer {} 2 @jane 30", accounts[0]),
+            format!("interbank_transfer {} 1 {} 30", accounts[0], accounts[0]),
+            format!("get_balance {}", accounts[0]),
+            "change_bank 2".to_owned(),
+            format!("get_balance {}", accounts[1]),
+        ]
+        .join("\n");
+
+        let mut writer = Vec::new();
+        handle(&sender, input.as_bytes(), &mut writer, &mut terminal)
+            .await
+            .unwrap();
+
+        let result = from_utf8(writer.as_slice())
+            .unwrap()
+            .split("\n\n")
+            .map(|s| s.to_owned())
+            .collect::<Vec<_>>();
+
+        assert!(re.is_match(&result[3]));
+        assert_eq!(
+            "Bank: 1\nStatus: error\nType: bank\nError: Receiver is in the same bank",
+            result[4]
+        );
+        assert_eq!("Bank: 1\nStatus: ok\nResult: 70.00", result[5]);
+        assert_eq!("Bank: 2\nStatus: ok\nResult: 30.00", result[7]);
+    }
+
+    #[tokio::test]
+    async fn handle_verify_works() {
+        let input = ["register_account 100", "register_account 20 EUR", "verify"].join("\n");
+        let reader = input.as_bytes();
+        let mut writer = Vec::new();
+        let mut terminal = Vec::new();
+
+        let (sender, mut receiver) = unbounded_channel::<Request>();
+
+        tokio::spawn(async move {
+            let mut repository = Repository::default();
+            repository_actor(&mut repository, &mut receiver).await;
+        });
+
+        handle(&sender, reader, &mut writer, &mut terminal)
+            .await
+            .unwrap();
+
+        let result = from_utf8(writer.as_slice())
+            .unwrap()
+            .split("\n\n")
+            .map(|s| s.to_owned())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            "Bank: 1\nStatus: ok\nResult:\nconsistent\nEUR opening 20.00 credited 0.00 debited 0.00 balance 20.00\nUSD opening 100.00 credited 0.00 debited 0.00 balance 100.00",
+            result[2]
+        );
+    }
+
     #[tokio::test]
     async fn handle_list_operations_empty_case_works() {
-        let input = vec!["get_all_operations"].join("\n");
+        let input = ["get_all_operations"].join("\n");
         let reader = input.as_bytes();
         let mut writer = Vec::new();
         let mut terminal = Vec::new();
 
-        let (sender, mut receiver) = unbounded_channel::<(Command, Sender<String>)>();
+        let (sender, mut receiver) = unbounded_channel::<Request>();
 
         tokio::spawn(async move {
             let mut repository = Repository::default();
@@ -761,16 +1255,98 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn handle_list_operations_timestamps_works() {
+        let input = ["register_account 100", "list_all_operations"].join("\n");
+        let reader = input.as_bytes();
+        let mut writer = Vec::new();
+        let mut terminal = Vec::new();
+
+        let (sender, mut receiver) = unbounded_channel::<Request>();
+
+        tokio::spawn(async move {
+            let mut repository = Repository::default();
+            repository.set_clock(SharedClock::new(Arc::new(ManualClock::new(1709296200))));
+            repository_actor(&mut repository, &mut receiver).await;
+        });
+
+        handle(&sender, reader, &mut writer, &mut terminal)
+            .await
+            .unwrap();
+
+        let result = from_utf8(writer.as_slice()).unwrap();
+
+        let re = Regex::new(concat!(
+            r"Bank: 1\nOpID: ([a-f0-9-]+)\nStatus: ok\nResult: ([a-f0-9-]+)\n\n",
+            r"Bank: 1\nStatus: ok\nResult:\n",
+            r"2024-03-01T12:30:00Z ([a-f0-9-]+): \(Register ([a-f0-9-]+) 100.00 USD\)\n\n$"
+        ))
+        .unwrap();
+        let (_, [operation_id, account_id, listed_operation_id, listed_account_id]) =
+            re.captures(result).unwrap().extract();
+
+        assert_eq!(operation_id, listed_operation_id);
+        assert_eq!(account_id, listed_account_id);
+    }
+
+    #[tokio::test]
+    async fn handle_list_operations_query_works() {
+        let input = [
+            "register_account 100",
+            "register_account 50",
+            "list_all_operations kind=register offset=1",
+            "list_all_operations min_amount=60 limit=1",
+            "list_all_operations kind=transfer",
+            "list_all_operations after=0b9ee2f5-7d3c-4b61-9a5e-2f3c8d4e6a10",
+        ]
+        .join("\n");
+        let reader = input.as_bytes();
+        let mut writer = Vec::new();
+        let mut terminal = Vec::new();
+
+        let (sender, mut receiver) = unbounded_channel::<Request>();
+
+        tokio::spawn(async move {
+            let mut repository = Repository::default();
+            repository_actor(&mut repository, &mut receiver).await;
+        });
+
+        handle(&sender, reader, &mut writer, &mut terminal)
+            .await
+            .unwrap();
+
+        let result = from_utf8(writer.as_slice())
+            .unwrap()
+            .split("\n\n")
+            .map(|s| s.to_owned())
+            .collect::<Vec<_>>();
+
+        let re =
+            Regex::new(r"^Bank: 1\nStatus: ok\nResult:\n[^\n]+ \(Register [a-f0-9-]+ 50.00 USD\)$")
+                .unwrap();
+        assert!(re.is_match(&result[2]));
+        let re = Regex::new(
+            r"^Bank: 1\nStatus: ok\nResult:\n[^\n]+ \(Register [a-f0-9-]+ 100.00 USD\)$",
+        )
+        .unwrap();
+        assert!(re.is_match(&result[3]));
+        assert_eq!("Bank: 1\nStatus: ok\nResult:\nno operations yet", result[4]);
+        assert_eq!(
+            "Bank: 1\nStatus: error\nType: repository\nError: Bank error: Operation not found",
+            result[5]
+        );
+    }
+
     #[tokio::test]
     async fn handle_list_operations_works() {
-        let input = vec!["register_account 100", "register_account 50"].join("\n");
+        let input = ["register_account 100", "register_account 50"].join("\n");
 
         let reader = input.as_bytes();
 
         let mut writer = Vec::new();
         let mut terminal = Vec::new();
 
-        let (sender, mut receiver) = unbounded_channel::<(Command, Sender<String>)>();
+        let (sender, mut receiver) = unbounded_channel::<Request>();
 
         tokio::spawn(async move {
             let mut repository = Repository::default();
@@ -783,7 +1359,6 @@ mod tests {
 
         let result = from_utf8(writer.as_slice())
             .unwrap()
-            .to_owned()
             .split("\n\n")
             .map(|s| s.to_owned())
             .collect::<Vec<_>>();
@@ -803,18 +1378,14 @@ mod tests {
         assert!(OperationID::parse_str(operation2_id).is_ok());
         assert!(AccountID::parse_str(account2_id).is_ok());
 
-        let input = vec![
-            format!("deposit {} 100", account1_id.to_string()),
-            format!("deposit {} 250", account2_id.to_string()),
-            format!(
-                "transfer {} {} 50",
-                account1_id.to_string(),
-                account2_id.to_string()
-            ),
-            format!("withdraw {} 50", account2_id.to_string()),
+        let input = [
+            format!("deposit {} 100", account1_id),
+            format!("deposit {} 250", account2_id),
+            format!("transfer {} {} 50", account1_id, account2_id),
+            format!("withdraw {} 50", account2_id),
             "list_account_operations".to_owned(),
             "list_account_operations test".to_owned(),
-            format!("list_account_operations {}", account1_id.to_string()),
+            format!("list_account_operations {}", account1_id),
             "list_all_operations".to_owned(),
         ]
         .join("\n");
@@ -827,7 +1398,6 @@ mod tests {
 
         let result = from_utf8(writer.as_slice())
             .unwrap()
-            .to_owned()
             .split("\n\n")
             .map(|s| s.to_owned())
             .collect::<Vec<_>>();
@@ -868,29 +1438,30 @@ mod tests {
 
         let (_, [operation1, operation2, operation3]) = re.captures(&result[8]).unwrap().extract();
 
-        let re = Regex::new(r"([a-f0-9-]+): \(Register ([a-f0-9-]+) 100\)").unwrap();
-        assert!(re.is_match(&operation1));
+        let re = Regex::new(r"([a-f0-9-]+): \(Register ([a-f0-9-]+) 100.00 USD\)").unwrap();
+        assert!(re.is_match(operation1));
 
-        let (_, [operation_id, account_id]) = re.captures(&operation1).unwrap().extract();
+        let (_, [operation_id, account_id]) = re.captures(operation1).unwrap().extract();
         assert!(OperationID::parse_str(operation_id).is_ok());
         assert!(AccountID::parse_str(account_id).is_ok());
 
         assert_eq!(account1_id, account_id);
 
-        let re = Regex::new(r"([a-f0-9-]+): \(Deposit ([a-f0-9-]+) 100\)").unwrap();
-        assert!(re.is_match(&operation2));
+        let re = Regex::new(r"([a-f0-9-]+): \(Deposit ([a-f0-9-]+) 100.00\)").unwrap();
+        assert!(re.is_match(operation2));
 
-        let (_, [operation_id, account_id]) = re.captures(&operation2).unwrap().extract();
+        let (_, [operation_id, account_id]) = re.captures(operation2).unwrap().extract();
         assert!(OperationID::parse_str(operation_id).is_ok());
         assert!(AccountID::parse_str(account_id).is_ok());
 
         assert_eq!(account1_id, account_id);
 
-        let re = Regex::new(r"([a-f0-9-]+): \(Transfer ([a-f0-9-]+) ([a-f0-9-]+) 50\)").unwrap();
-        assert!(re.is_match(&operation3));
+        let re =
+            Regex::new(r"([a-f0-9-]+): \(Transfer ([a-f0-9-]+) ([a-f0-9-]+) 50.00 1\)").unwrap();
+        assert!(re.is_match(operation3));
 
         let (_, [operation_id, sender_account_id, receiver_account_id]) =
-            re.captures(&operation3).unwrap().extract();
+            re.captures(operation3).unwrap().extract();
         assert!(OperationID::parse_str(operation_id).is_ok());
         assert!(AccountID::parse_str(sender_account_id).is_ok());
         assert!(AccountID::parse_str(receiver_account_id).is_ok());
@@ -906,47 +1477,48 @@ mod tests {
         let (_, [operation1, operation2, operation3, operation4, operation5, operation6]) =
             re.captures(&result[9]).unwrap().extract();
 
-        let re = Regex::new(r"([a-f0-9-]+): \(Register ([a-f0-9-]+) 100\)").unwrap();
-        assert!(re.is_match(&operation1));
+        let re = Regex::new(r"([a-f0-9-]+): \(Register ([a-f0-9-]+) 100.00 USD\)").unwrap();
+        assert!(re.is_match(operation1));
 
-        let (_, [operation_id, account_id]) = re.captures(&operation1).unwrap().extract();
+        let (_, [operation_id, account_id]) = re.captures(operation1).unwrap().extract();
         assert!(OperationID::parse_str(operation_id).is_ok());
         assert!(AccountID::parse_str(account_id).is_ok());
 
         assert_eq!(account1_id, account_id);
 
-        let re = Regex::new(r"([a-f0-9-]+): \(Register ([a-f0-9-]+) 50\)").unwrap();
-        assert!(re.is_match(&operation2));
+        let re = Regex::new(r"([a-f0-9-]+): \(Register ([a-f0-9-]+) 50.00 USD\)").unwrap();
+        assert!(re.is_match(operation2));
 
-        let (_, [operation_id, account_id]) = re.captures(&operation2).unwrap().extract();
+        let (_, [operation_id, account_id]) = re.captures(operation2).unwrap().extract();
         assert!(OperationID::parse_str(operation_id).is_ok());
         assert!(AccountID::parse_str(account_id).is_ok());
 
         assert_eq!(account2_id, account_id);
 
-        let re = Regex::new(r"([a-f0-9-]+): \(Deposit ([a-f0-9-]+) 100\)").unwrap();
-        assert!(re.is_match(&operation3));
+        let re = Regex::new(r"([a-f0-9-]+): \(Deposit ([a-f0-9-]+) 100.00\)").unwrap();
+        assert!(re.is_match(operation3));
 
-        let (_, [operation_id, account_id]) = re.captures(&operation3).unwrap().extract();
+        let (_, [operation_id, account_id]) = re.captures(operation3).unwrap().extract();
         assert!(OperationID::parse_str(operation_id).is_ok());
         assert!(AccountID::parse_str(account_id).is_ok());
 
         assert_eq!(account1_id, account_id);
 
-        let re = Regex::new(r"([a-f0-9-]+): \(Deposit ([a-f0-9-]+) 250\)").unwrap();
-        assert!(re.is_match(&operation4));
+        let re = Regex::new(r"([a-f0-9-]+): \(Deposit ([a-f0-9-]+) 250.00\)").unwrap();
+        assert!(re.is_match(operation4));
 
-        let (_, [operation_id, account_id]) = re.captures(&operation4).unwrap().extract();
+        let (_, [operation_id, account_id]) = re.captures(operation4).unwrap().extract();
         assert!(OperationID::parse_str(operation_id).is_ok());
         assert!(AccountID::parse_str(account_id).is_ok());
 
         assert_eq!(account2_id, account_id);
 
-        let re = Regex::new(r"([a-f0-9-]+): \(Transfer ([a-f0-9-]+) ([a-f0-9-]+) 50\)").unwrap();
-        assert!(re.is_match(&operation5));
+        let re =
+            Regex::new(r"([a-f0-9-]+): \(Transfer ([a-f0-9-]+) ([a-f0-9-]+) 50.00 1\)").unwrap();
+        assert!(re.is_match(operation5));
 
         let (_, [operation_id, sender_account_id, receiver_account_id]) =
-            re.captures(&operation5).unwrap().extract();
+            re.captures(operation5).unwrap().extract();
         assert!(OperationID::parse_str(operation_id).is_ok());
         assert!(AccountID::parse_str(sender_account_id).is_ok());
         assert!(AccountID::parse_str(receiver_account_id).is_ok());
@@ -954,10 +1526,10 @@ mod tests {
         assert_eq!(account1_id, sender_account_id);
         assert_eq!(account2_id, receiver_account_id);
 
-        let re = Regex::new(r"([a-f0-9-]+): \(Withdraw ([a-f0-9-]+) 50\)").unwrap();
-        assert!(re.is_match(&operation6));
+        let re = Regex::new(r"([a-f0-9-]+): \(Withdraw ([a-f0-9-]+) 50.00\)").unwrap();
+        assert!(re.is_match(operation6));
 
-        let (_, [operation_id, account_id]) = re.captures(&operation6).unwrap().extract();
+        let (_, [operation_id, account_id]) = re.captures(operation6).unwrap().extract();
         assert!(OperationID::parse_str(operation_id).is_ok());
         assert!(AccountID::parse_str(account_id).is_ok());
 
@@ -966,7 +1538,7 @@ mod tests {
 
     #[tokio::test]
     async fn handle_restore_bank_works() {
-        let input = vec![
+        let input = [
             "register_account 100".to_owned(),
             "register_account 50".to_owned(),
         ]
@@ -977,7 +1549,7 @@ mod tests {
         let mut writer = Vec::new();
         let mut terminal = Vec::new();
 
-        let (sender, mut receiver) = unbounded_channel::<(Command, Sender<String>)>();
+        let (sender, mut receiver) = unbounded_channel::<Request>();
 
         tokio::spawn(async move {
             let mut repository = Repository::default();
@@ -990,7 +1562,6 @@ mod tests {
 
         let result = from_utf8(writer.as_slice())
             .unwrap()
-            .to_owned()
             .split("\n\n")
             .map(|s| s.to_owned())
             .collect::<Vec<_>>();
@@ -1010,15 +1581,11 @@ mod tests {
         assert!(OperationID::parse_str(operation2_id).is_ok());
         assert!(AccountID::parse_str(account2_id).is_ok());
 
-        let input = vec![
-            format!("deposit {} 100", account1_id.to_string()),
-            format!("deposit {} 250", account2_id.to_string()),
-            format!(
-                "transfer {} {} 50",
-                account1_id.to_string(),
-                account2_id.to_string()
-            ),
-            format!("withdraw {} 50", account2_id.to_string()),
+        let input = [
+            format!("deposit {} 100", account1_id),
+            format!("deposit {} 250", account2_id),
+            format!("transfer {} {} 50", account1_id, account2_id),
+            format!("withdraw {} 50", account2_id),
             "restore_bank".to_owned(),
             "restore_bank test".to_owned(),
             "restore_bank 100".to_owned(),
@@ -1035,7 +1602,6 @@ mod tests {
 
         let result = from_utf8(writer.as_slice())
             .unwrap()
-            .to_owned()
             .split("\n\n")
             .map(|s| s.to_owned())
             .collect::<Vec<_>>();
@@ -1086,47 +1652,48 @@ mod tests {
         let (_, [operation1, operation2, operation3, operation4, operation5, operation6]) =
             re.captures(&result[10]).unwrap().extract();
 
-        let re = Regex::new(r"([a-f0-9-]+): \(Register ([a-f0-9-]+) 100\)").unwrap();
-        assert!(re.is_match(&operation1));
+        let re = Regex::new(r"([a-f0-9-]+): \(Register ([a-f0-9-]+) 100.00 USD\)").unwrap();
+        assert!(re.is_match(operation1));
 
-        let (_, [operation_id, account_id]) = re.captures(&operation1).unwrap().extract();
+        let (_, [operation_id, account_id]) = re.captures(operation1).unwrap().extract();
         assert!(OperationID::parse_str(operation_id).is_ok());
         assert!(AccountID::parse_str(account_id).is_ok());
 
         assert_eq!(account1_id, account_id);
 
-        let re = Regex::new(r"([a-f0-9-]+): \(Register ([a-f0-9-]+) 50\)").unwrap();
-        assert!(re.is_match(&operation2));
+        let re = Regex::new(r"([a-f0-9-]+): \(Register ([a-f0-9-]+) 50.00 USD\)").unwrap();
+        assert!(re.is_match(operation2));
 
-        let (_, [operation_id, account_id]) = re.captures(&operation2).unwrap().extract();
+        let (_, [operation_id, account_id]) = re.captures(operation2).unwrap().extract();
         assert!(OperationID::parse_str(operation_id).is_ok());
         assert!(AccountID::parse_str(account_id).is_ok());
 
         assert_eq!(account2_id, account_id);
 
-        let re = Regex::new(r"([a-f0-9-]+): \(Deposit ([a-f0-9-]+) 100\)").unwrap();
-        assert!(re.is_match(&operation3));
+        let re = Regex::new(r"([a-f0-9-]+): \(Deposit ([a-f0-9-]+) 100.00\)").unwrap();
+        assert!(re.is_match(operation3));
 
-        let (_, [operation_id, account_id]) = re.captures(&operation3).unwrap().extract();
+        let (_, [operation_id, account_id]) = re.captures(operation3).unwrap().extract();
         assert!(OperationID::parse_str(operation_id).is_ok());
         assert!(AccountID::parse_str(account_id).is_ok());
 
         assert_eq!(account1_id, account_id);
 
-        let re = Regex::new(r"([a-f0-9-]+): \(Deposit ([a-f0-9-]+) 250\)").unwrap();
-        assert!(re.is_match(&operation4));
+        let re = Regex::new(r"([a-f0-9-]+): \(Deposit ([a-f0-9-]+) 250.00\)").unwrap();
+        assert!(re.is_match(operation4));
 
-        let (_, [operation_id, account_id]) = re.captures(&operation4).unwrap().extract();
+        let (_, [operation_id, account_id]) = re.captures(operation4).unwrap().extract();
         assert!(OperationID::parse_str(operation_id).is_ok());
         assert!(AccountID::parse_str(account_id).is_ok());
 
         assert_eq!(account2_id, account_id);
 
-        let re = Regex::new(r"([a-f0-9-]+): \(Transfer ([a-f0-9-]+) ([a-f0-9-]+) 50\)").unwrap();
-        assert!(re.is_match(&operation5));
+        let re =
+            Regex::new(r"([a-f0-9-]+): \(Transfer ([a-f0-9-]+) ([a-f0-9-]+) 50.00 1\)").unwrap();
+        assert!(re.is_match(operation5));
 
         let (_, [operation_id, sender_account_id, receiver_account_id]) =
-            re.captures(&operation5).unwrap().extract();
+            re.captures(operation5).unwrap().extract();
         assert!(OperationID::parse_str(operation_id).is_ok());
         assert!(AccountID::parse_str(sender_account_id).is_ok());
         assert!(AccountID::parse_str(receiver_account_id).is_ok());
@@ -1134,10 +1701,10 @@ mod tests {
         assert_eq!(account1_id, sender_account_id);
         assert_eq!(account2_id, receiver_account_id);
 
-        let re = Regex::new(r"([a-f0-9-]+): \(Withdraw ([a-f0-9-]+) 50\)").unwrap();
-        assert!(re.is_match(&operation6));
+        let re = Regex::new(r"([a-f0-9-]+): \(Withdraw ([a-f0-9-]+) 50.00\)").unwrap();
+        assert!(re.is_match(operation6));
 
-        let (_, [operation_id, account_id]) = re.captures(&operation6).unwrap().extract();
+        let (_, [operation_id, account_id]) = re.captures(operation6).unwrap().extract();
         assert!(OperationID::parse_str(operation_id).is_ok());
         assert!(AccountID::parse_str(account_id).is_ok());
 
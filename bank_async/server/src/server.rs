@@ -1,4 +1,6 @@
 pub mod actor;
+pub mod checkpoint;
 pub mod command;
 pub mod handler;
 pub mod repository;
+pub mod wal;
@@ -1,31 +1,116 @@
-use crate::bank::account::AccountID;
-use crate::bank::log::Operation;
-use crate::server::command::Command;
-use crate::server::repository::{Repository, RepositoryError};
-use tokio::sync::{mpsc::UnboundedReceiver, oneshot::Sender};
+use crate::bank::account::{AccountID, AccountStatus};
+use crate::bank::currency::{Currency, Rate};
+use crate::bank::fee::{Fee, FeeOperation};
+use crate::bank::hold::HoldID;
+use crate::bank::idempotency::IdempotencyKey;
+use crate::bank::limit::{LimitKind, Limits};
+use crate::bank::log::{Cutoff, Operation, OperationID, OperationsQuery};
+use crate::bank::money::Money;
+use crate::bank::profile::{Profile, ProfileField};
+use crate::bank::schedule::{Schedule, ScheduleID};
+use crate::bank::statement::StatementFormat;
+use crate::server::command::{AccountRef, Command};
+use crate::server::repository::{Repository, RepositoryError, Session};
+use std::time::Duration;
+use tokio::sync::{
+    mpsc::{UnboundedReceiver, UnboundedSender},
+    oneshot::{self, Sender},
+};
+
+/// What a connection asks the repository for, commands of a batch are applied all or none.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Job {
+    Command(Command),
+    Batch(Vec<Command>),
+    /// Sent by the scheduler, executes schedules of all banks that are due by the repository clock.
+    RunSchedules,
+}
+
+/// Connection's session travels with every command and comes back updated with the response.
+pub type Response = (Session, String);
+pub type Request = (Job, Session, Sender<Response>);
 
 pub async fn repository_actor(
     repository: &mut Repository,
-    command_receiver: &mut UnboundedReceiver<(Command, Sender<String>)>,
+    command_receiver: &mut UnboundedReceiver<Request>,
 ) {
     loop {
-        if let Some((command, response_sender)) = command_receiver.recv().await {
-            let response = handle_command(repository, &command);
-            if let Err(err) = response_sender.send(response) {
+        if let Some((job, mut session, response_sender)) = command_receiver.recv().await {
+            let response = match job {
+                Job::Command(command) => handle_command(repository, &mut session, &command),
+                Job::Batch(commands) => handle_batch(repository, &mut session, &commands),
+                Job::RunSchedules => handle_run_schedules(repository),
+            };
+            if let Err((_, err)) = response_sender.send((session, response)) {
                 eprintln!("Error sending response: {}", err);
             }
         }
     }
 }
 
-fn handle_new_bank(repository: &mut Repository) -> String {
-    let bank_id = repository.new_bank();
-    format!("Bank: {}\nStatus: ok\nResult: {}\n\n", bank_id - 1, bank_id)
+/// Asks repository actor to run due schedules every `period` and prints their executions.
+/// Stops when the actor is gone.
+pub async fn scheduler(request_sender: UnboundedSender<Request>, period: Duration) {
+    let mut ticks = tokio::time::interval(period);
+
+    loop {
+        ticks.tick().await;
+
+        let (response_sender, response_receiver) = oneshot::channel();
+        let job = Job::RunSchedules;
+        if request_sender
+            .send((job, Session::default(), response_sender))
+            .is_err()
+        {
+            return;
+        }
+
+        match response_receiver.await {
+            Ok((_, report)) => print!("{}", report),
+            Err(_) => return,
+        }
+    }
+}
+
+// Shortest form of the duration, e.g. 30d instead of 2592000s.
+fn format_duration(seconds: u64) -> String {
+    let units = [
+        (7 * 24 * 60 * 60, "w"),
+        (24 * 60 * 60, "d"),
+        (60 * 60, "h"),
+        (60, "m"),
+    ];
+
+    units
+        .iter()
+        .find(|(unit, _)| seconds != 0 && seconds.is_multiple_of(*unit))
+        .map_or(format!("{}s", seconds), |(unit, suffix)| {
+            format!("{}{}", seconds / unit, suffix)
+        })
 }
 
-fn handle_change_bank(repository: &mut Repository, id: u64) -> String {
-    let current_bank_id = repository.current_bank_id();
-    match repository.change_bank(id) {
+fn handle_repository_error(
+    repository: &Repository,
+    session: &Session,
+    e: RepositoryError,
+) -> String {
+    format!(
+        "Bank: {}\nStatus: error\nType: repository\nError: {}\n\n",
+        repository.current_bank_id(session),
+        e,
+    )
+}
+
+fn handle_new_bank(repository: &mut Repository, session: &mut Session) -> String {
+    match repository.new_bank(session) {
+        Ok(bank_id) => format!("Bank: {}\nStatus: ok\nResult: {}\n\n", bank_id - 1, bank_id),
+        Err(e) => handle_repository_error(repository, session, e),
+    }
+}
+
+fn handle_change_bank(repository: &mut Repository, session: &mut Session, id: u64) -> String {
+    let current_bank_id = repository.current_bank_id(session);
+    match repository.change_bank(session, id) {
         Ok(_) => format!("Bank: {}\nStatus: ok\nResult: {}\n\n", current_bank_id, id),
         Err(_) => format!(
             "Bank: {}\nStatus: error\nType: bank\nError: invalid bank id\n\n",
@@ -34,26 +119,28 @@ fn handle_change_bank(repository: &mut Repository, id: u64) -> String {
     }
 }
 
-fn handle_which_bank(repository: &mut Repository) -> String {
-    if repository.current_bank_id() == 0 {
-        repository.new_bank();
+fn handle_which_bank(repository: &mut Repository, session: &mut Session) -> String {
+    if repository.current_bank_id(session) == 0 {
+        if let Err(e) = repository.new_bank(session) {
+            return handle_repository_error(repository, session, e);
+        }
     }
 
-    let current_bank = repository.current_bank_id();
+    let current_bank = repository.current_bank_id(session);
     format!(
         "Bank: {}\nStatus: ok\nResult: {}\n\n",
         current_bank, current_bank
     )
 }
 
-fn handle_restore_bank(repository: &mut Repository, id: u64) -> String {
-    let current_bank = repository.current_bank_id();
+fn handle_restore_bank(repository: &mut Repository, session: &mut Session, id: u64) -> String {
+    let current_bank = repository.current_bank_id(session);
 
-    match repository.restore_bank(id) {
+    match repository.restore_bank(session, id) {
         Ok(_) => format!(
             "Bank: {}\nStatus: ok\nResult: {}\n\n",
             current_bank,
-            repository.current_bank_id(),
+            repository.current_bank_id(session),
         ),
         Err(repository_err) => match repository_err {
             RepositoryError::InvalidBankId => format!(
@@ -64,16 +151,22 @@ fn handle_restore_bank(repository: &mut Repository, id: u64) -> String {
                 "Bank: {}\nStatus: error\nType: bank\nError: {}\n\n",
                 current_bank, e,
             ),
+            e => handle_repository_error(repository, session, e),
         },
     }
 }
 
-fn handle_register_account(repository: &mut Repository, balance: u64) -> String {
-    match repository.register_account(balance) {
+fn handle_register_account(
+    repository: &mut Repository,
+    session: &mut Session,
+    balance: Money,
+    currency: Currency,
+) -> String {
+    match repository.register_account(session, balance, currency) {
         Ok((account_id, opperation_id)) => {
             format!(
                 "Bank: {}\nOpID: {}\nStatus: ok\nResult: {}\n\n",
-                repository.current_bank_id(),
+                repository.current_bank_id(session),
                 opperation_id,
                 account_id
             )
@@ -81,64 +174,457 @@ fn handle_register_account(repository: &mut Repository, balance: u64) -> String
         Err(e) => {
             format!(
                 "Bank: {}\nStatus: error\nType: bank\nError: {}\n\n",
-                repository.current_bank_id(),
+                repository.current_bank_id(session),
+                e
+            )
+        }
+    }
+}
+
+fn handle_set_exchange_rate(
+    repository: &mut Repository,
+    session: &mut Session,
+    from: Currency,
+    to: Currency,
+    rate: Rate,
+) -> String {
+    match repository.set_exchange_rate(session, from, to, rate) {
+        Ok(opperation_id) => {
+            format!(
+                "Bank: {}\nOpID: {}\nStatus: ok\n\n",
+                repository.current_bank_id(session),
+                opperation_id,
+            )
+        }
+        Err(e) => {
+            format!(
+                "Bank: {}\nStatus: error\nType: bank\nError: {}\n\n",
+                repository.current_bank_id(session),
+                e
+            )
+        }
+    }
+}
+
+fn handle_set_fee(
+    repository: &mut Repository,
+    session: &mut Session,
+    operation: FeeOperation,
+    fee: Option<Fee>,
+) -> String {
+    match repository.set_fee(session, operation, fee) {
+        Ok(opperation_id) => {
+            format!(
+                "Bank: {}\nOpID: {}\nStatus: ok\n\n",
+                repository.current_bank_id(session),
+                opperation_id,
+            )
+        }
+        Err(e) => {
+            format!(
+                "Bank: {}\nStatus: error\nType: bank\nError: {}\n\n",
+                repository.current_bank_id(session),
+                e
+            )
+        }
+    }
+}
+
+fn handle_list_fees(repository: &mut Repository, session: &mut Session) -> String {
+    let fees = match repository.get_fees(session) {
+        Ok(fees) => fees,
+        Err(e) => return handle_repository_error(repository, session, e),
+    };
+
+    let fees: Vec<String> = fees
+        .iter()
+        .map(|(operation, fee)| format!("{} {}", operation, fee))
+        .collect();
+    format!(
+        "Bank: {}\nStatus: ok\nResult:\n{}\n\n",
+        repository.current_bank_id(session),
+        if fees.is_empty() {
+            String::from("no fees")
+        } else {
+            fees.join("\n")
+        },
+    )
+}
+
+fn handle_set_limit(
+    repository: &mut Repository,
+    session: &mut Session,
+    id: Option<AccountID>,
+    kind: LimitKind,
+    limit: Option<Money>,
+) -> String {
+    match repository.set_limit(session, id, kind, limit) {
+        Ok(opperation_id) => {
+            format!(
+                "Bank: {}\nOpID: {}\nStatus: ok\n\n",
+                repository.current_bank_id(session),
+                opperation_id,
+            )
+        }
+        Err(e) => {
+            format!(
+                "Bank: {}\nStatus: error\nType: bank\nError: {}\n\n",
+                repository.current_bank_id(session),
+                e
+            )
+        }
+    }
+}
+
+fn limits_as_string(limits: &Limits) -> String {
+    [LimitKind::PerTransaction, LimitKind::Daily]
+        .iter()
+        .map(|kind| match limits.get(*kind) {
+            Some(limit) => format!("{} {}", kind, limit),
+            None => format!("{} none", kind),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Account limits come with what it has already spent of the daily one.
+fn handle_get_limits(
+    repository: &mut Repository,
+    session: &mut Session,
+    id: Option<AccountID>,
+) -> String {
+    let result = repository.get_limits(session, id).and_then(|limits| {
+        let mut lines = limits_as_string(&limits);
+        if let Some(id) = id {
+            let outflow = repository.get_outflow(session, id)?;
+            lines.push_str(&format!("\noutflow {}", outflow));
+        }
+        Ok(lines)
+    });
+
+    match result {
+        Ok(lines) => format!(
+            "Bank: {}\nStatus: ok\nResult:\n{}\n\n",
+            repository.current_bank_id(session),
+            lines,
+        ),
+        Err(e) => handle_repository_error(repository, session, e),
+    }
+}
+
+// First line tells whether the bank is consistent, then totals of every currency
+// and the discrepancies found, one per line.
+fn handle_verify(repository: &mut Repository, session: &mut Session) -> String {
+    match repository.verify(session) {
+        Ok(report) => {
+            let mut lines = vec![match report.discrepancies.len() {
+                0 => String::from("consistent"),
+                count => format!("discrepancies {}", count),
+            }];
+            lines.extend(report.totals.iter().map(|totals| totals.to_string()));
+            lines.extend(
+                report
+                    .discrepancies
+                    .iter()
+                    .map(|discrepancy| discrepancy.to_string()),
+            );
+
+            format!(
+                "Bank: {}\nStatus: ok\nResult:\n{}\n\n",
+                repository.current_bank_id(session),
+                lines.join("\n"),
+            )
+        }
+        Err(e) => handle_repository_error(repository, session, e),
+    }
+}
+
+fn handle_get_fee_account(
+    repository: &mut Repository,
+    session: &mut Session,
+    currency: Currency,
+) -> String {
+    format!(
+        "Bank: {}\nStatus: ok\nResult: {}\n\n",
+        repository.current_bank_id(session),
+        AccountID::fee_account(currency),
+    )
+}
+
+fn handle_reverse(repository: &mut Repository, session: &mut Session, id: OperationID) -> String {
+    match repository.reverse(session, id) {
+        Ok(opperation_id) => {
+            format!(
+                "Bank: {}\nOpID: {}\nStatus: ok\n\n",
+                repository.current_bank_id(session),
+                opperation_id,
+            )
+        }
+        Err(e) => {
+            format!(
+                "Bank: {}\nStatus: error\nType: bank\nError: {}\n\n",
+                repository.current_bank_id(session),
+                e
+            )
+        }
+    }
+}
+
+fn handle_set_overdraft_limit(
+    repository: &mut Repository,
+    session: &mut Session,
+    id: AccountID,
+    limit: Money,
+) -> String {
+    match repository.set_overdraft_limit(session, id, limit) {
+        Ok(opperation_id) => {
+            format!(
+                "Bank: {}\nOpID: {}\nStatus: ok\n\n",
+                repository.current_bank_id(session),
+                opperation_id,
+            )
+        }
+        Err(e) => {
+            format!(
+                "Bank: {}\nStatus: error\nType: bank\nError: {}\n\n",
+                repository.current_bank_id(session),
+                e
+            )
+        }
+    }
+}
+
+fn handle_freeze_account(
+    repository: &mut Repository,
+    session: &mut Session,
+    id: AccountID,
+    freeze: bool,
+) -> String {
+    let result = if freeze {
+        repository.freeze_account(session, id)
+    } else {
+        repository.unfreeze_account(session, id)
+    };
+
+    match result {
+        Ok(opperation_id) => {
+            format!(
+                "Bank: {}\nOpID: {}\nStatus: ok\n\n",
+                repository.current_bank_id(session),
+                opperation_id,
+            )
+        }
+        Err(e) => {
+            format!(
+                "Bank: {}\nStatus: error\nType: bank\nError: {}\n\n",
+                repository.current_bank_id(session),
+                e
+            )
+        }
+    }
+}
+
+fn handle_close_account(
+    repository: &mut Repository,
+    session: &mut Session,
+    id: AccountID,
+    sweep_to: Option<AccountID>,
+) -> String {
+    match repository.close_account(session, id, sweep_to) {
+        Ok(opperation_id) => {
+            format!(
+                "Bank: {}\nOpID: {}\nStatus: ok\n\n",
+                repository.current_bank_id(session),
+                opperation_id,
+            )
+        }
+        Err(e) => {
+            format!(
+                "Bank: {}\nStatus: error\nType: bank\nError: {}\n\n",
+                repository.current_bank_id(session),
+                e
+            )
+        }
+    }
+}
+
+fn handle_update_profile(
+    repository: &mut Repository,
+    session: &mut Session,
+    id: AccountID,
+    field: ProfileField,
+) -> String {
+    match repository.update_profile(session, id, field) {
+        Ok(opperation_id) => {
+            format!(
+                "Bank: {}\nOpID: {}\nStatus: ok\n\n",
+                repository.current_bank_id(session),
+                opperation_id,
+            )
+        }
+        Err(e) => {
+            format!(
+                "Bank: {}\nStatus: error\nType: bank\nError: {}\n\n",
+                repository.current_bank_id(session),
+                e
+            )
+        }
+    }
+}
+
+// A line per field in the same form `update_profile` takes them.
+fn profile_as_string(profile: &Profile) -> String {
+    [
+        ProfileField::Name(profile.name),
+        ProfileField::Email(profile.email),
+        ProfileField::Alias(profile.alias),
+    ]
+    .iter()
+    .map(|field| field.to_string())
+    .collect::<Vec<_>>()
+    .join("\n")
+}
+
+fn handle_get_profile(repository: &mut Repository, session: &mut Session, id: AccountID) -> String {
+    match repository.get_profile(session, id) {
+        Ok(profile) => format!(
+            "Bank: {}\nStatus: ok\nResult:\n{}\n\n",
+            repository.current_bank_id(session),
+            profile_as_string(&profile),
+        ),
+        Err(e) => {
+            format!(
+                "Bank: {}\nStatus: fail\nResult: {}\n\n",
+                repository.current_bank_id(session),
                 e
             )
         }
     }
 }
 
-fn handle_get_balance(repository: &mut Repository, id: AccountID) -> String {
-    match repository.get_balance(id) {
+fn handle_get_balance(repository: &mut Repository, session: &mut Session, id: AccountID) -> String {
+    match repository.get_balance(session, id) {
+        Ok(balance) => {
+            // only unusual states are shown, so clients keep parsing plain balances
+            let status = match repository.get_status(session, id) {
+                Ok(AccountStatus::Open) | Err(_) => String::new(),
+                Ok(status) => format!(" ({})", status),
+            };
+            // result is the ledger balance, holds only lower the available one
+            let available = match repository.get_available_balance(session, id) {
+                Ok(available) if available != balance => format!("\nAvailable: {}", available),
+                _ => String::new(),
+            };
+            format!(
+                "Bank: {}\nStatus: ok\nResult: {}{}{}\n\n",
+                repository.current_bank_id(session),
+                balance,
+                status,
+                available
+            )
+        }
+        Err(e) => {
+            format!(
+                "Bank: {}\nStatus: fail\nResult: {}\n\n",
+                repository.current_bank_id(session),
+                e
+            )
+        }
+    }
+}
+
+fn handle_get_balance_at(
+    repository: &mut Repository,
+    session: &mut Session,
+    id: AccountID,
+    cutoff: Cutoff,
+) -> String {
+    match repository.get_balance_at(session, id, cutoff) {
         Ok(balance) => {
             format!(
                 "Bank: {}\nStatus: ok\nResult: {}\n\n",
-                repository.current_bank_id(),
+                repository.current_bank_id(session),
                 balance
             )
         }
         Err(e) => {
             format!(
                 "Bank: {}\nStatus: fail\nResult: {}\n\n",
-                repository.current_bank_id(),
+                repository.current_bank_id(session),
                 e
             )
         }
     }
 }
 
-fn handle_deposit(repository: &mut Repository, id: AccountID, amount: u64) -> String {
-    match repository.deposit(id, amount) {
+fn handle_statement(
+    repository: &mut Repository,
+    session: &mut Session,
+    id: AccountID,
+    format: StatementFormat,
+) -> String {
+    match repository.statement(session, id) {
+        Ok(entries) => {
+            format!(
+                "Bank: {}\nStatus: ok\nResult:\n{}\n\n",
+                repository.current_bank_id(session),
+                format.render(&entries)
+            )
+        }
+        Err(e) => {
+            format!(
+                "Bank: {}\nStatus: fail\nResult: {}\n\n",
+                repository.current_bank_id(session),
+                e
+            )
+        }
+    }
+}
+
+fn handle_deposit(
+    repository: &mut Repository,
+    session: &mut Session,
+    id: AccountID,
+    amount: Money,
+    key: Option<IdempotencyKey>,
+) -> String {
+    match repository.deposit(session, id, amount, key) {
         Ok(opperation_id) => {
             format!(
                 "Bank: {}\nOpID: {}\nStatus: ok\n\n",
-                repository.current_bank_id(),
+                repository.current_bank_id(session),
                 opperation_id,
             )
         }
         Err(e) => {
             format!(
                 "Bank: {}\nStatus: fail\nResult: {}\n\n",
-                repository.current_bank_id(),
+                repository.current_bank_id(session),
                 e
             )
         }
     }
 }
 
-fn handle_withdraw(repository: &mut Repository, id: AccountID, amount: u64) -> String {
-    match repository.withdraw(id, amount) {
+fn handle_withdraw(
+    repository: &mut Repository,
+    session: &mut Session,
+    id: AccountID,
+    amount: Money,
+    key: Option<IdempotencyKey>,
+) -> String {
+    match repository.withdraw(session, id, amount, key) {
         Ok(opperation_id) => {
             format!(
                 "Bank: {}\nOpID: {}\nStatus: ok\n\n",
-                repository.current_bank_id(),
+                repository.current_bank_id(session),
                 opperation_id,
             )
         }
         Err(e) => {
             format!(
                 "Bank: {}\nStatus: error\nType: bank\nError: {}\n\n",
-                repository.current_bank_id(),
+                repository.current_bank_id(session),
                 e
             )
         }
@@ -147,28 +633,291 @@ fn handle_withdraw(repository: &mut Repository, id: AccountID, amount: u64) -> S
 
 fn handle_transfer(
     repository: &mut Repository,
+    session: &mut Session,
     sender: AccountID,
     receiver: AccountID,
-    amount: u64,
+    amount: Money,
+    key: Option<IdempotencyKey>,
+) -> String {
+    match repository.transfer(session, sender, receiver, amount, key) {
+        Ok(opperation_id) => {
+            format!(
+                "Bank: {}\nOpID: {}\nStatus: ok\n\n",
+                repository.current_bank_id(session),
+                opperation_id,
+            )
+        }
+        Err(e) => {
+            format!(
+                "Bank: {}\nStatus: error\nType: bank\nError: {}\n\n",
+                repository.current_bank_id(session),
+                e
+            )
+        }
+    }
+}
+
+// Result is the operation of the receiving leg in bank `bank_id`.
+fn handle_interbank_transfer(
+    repository: &mut Repository,
+    session: &mut Session,
+    sender: AccountID,
+    bank_id: u64,
+    receiver: AccountRef,
+    amount: Money,
+) -> String {
+    let receiver = match receiver {
+        AccountRef::Id(id) => Ok(id),
+        AccountRef::Alias(alias) => {
+            let mut receiver_session = Session::default();
+            repository
+                .change_bank(&mut receiver_session, bank_id)
+                .and_then(|_| repository.find_alias(&mut receiver_session, alias))
+        }
+    };
+
+    match receiver.and_then(|receiver| {
+        repository.interbank_transfer(session, sender, bank_id, receiver, amount)
+    }) {
+        Ok((send_id, receive_id)) => {
+            format!(
+                "Bank: {}\nOpID: {}\nStatus: ok\nResult: {}\n\n",
+                repository.current_bank_id(session),
+                send_id,
+                receive_id
+            )
+        }
+        Err(e) => {
+            format!(
+                "Bank: {}\nStatus: error\nType: bank\nError: {}\n\n",
+                repository.current_bank_id(session),
+                e
+            )
+        }
+    }
+}
+
+fn handle_set_interest_rate(
+    repository: &mut Repository,
+    session: &mut Session,
+    id: AccountID,
+    rate: Rate,
 ) -> String {
-    match repository.transfer(sender, receiver, amount) {
+    match repository.set_interest_rate(session, id, rate) {
         Ok(opperation_id) => {
             format!(
                 "Bank: {}\nOpID: {}\nStatus: ok\n\n",
-                repository.current_bank_id(),
+                repository.current_bank_id(session),
                 opperation_id,
             )
         }
         Err(e) => {
             format!(
                 "Bank: {}\nStatus: error\nType: bank\nError: {}\n\n",
-                repository.current_bank_id(),
+                repository.current_bank_id(session),
                 e
             )
         }
     }
 }
 
+fn handle_accrue_interest(repository: &mut Repository, session: &mut Session, days: u32) -> String {
+    match repository.accrue_interest(session, days) {
+        Ok(operation_ids) if operation_ids.is_empty() => format!(
+            "Bank: {}\nStatus: ok\nResult:\nno interest accrued\n\n",
+            repository.current_bank_id(session),
+        ),
+        Ok(operation_ids) => format!(
+            "Bank: {}\nStatus: ok\nResult:\n{}\n\n",
+            repository.current_bank_id(session),
+            operation_ids
+                .iter()
+                .map(|operation_id| operation_id.to_string())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        ),
+        Err(e) => {
+            format!(
+                "Bank: {}\nStatus: error\nType: bank\nError: {}\n\n",
+                repository.current_bank_id(session),
+                e
+            )
+        }
+    }
+}
+
+fn handle_schedule_transfer(
+    repository: &mut Repository,
+    session: &mut Session,
+    sender: AccountID,
+    receiver: AccountID,
+    amount: Money,
+    delay: u64,
+    interval: Option<u64>,
+) -> String {
+    let start = repository.now().saturating_add(delay);
+    match repository.schedule_transfer(session, sender, receiver, amount, start, interval) {
+        Ok((schedule_id, opperation_id)) => {
+            format!(
+                "Bank: {}\nOpID: {}\nStatus: ok\nResult: {}\n\n",
+                repository.current_bank_id(session),
+                opperation_id,
+                schedule_id
+            )
+        }
+        Err(e) => {
+            format!(
+                "Bank: {}\nStatus: error\nType: bank\nError: {}\n\n",
+                repository.current_bank_id(session),
+                e
+            )
+        }
+    }
+}
+
+fn handle_hold(
+    repository: &mut Repository,
+    session: &mut Session,
+    id: AccountID,
+    amount: Money,
+) -> String {
+    match repository.hold(session, id, amount) {
+        Ok((hold_id, opperation_id)) => {
+            format!(
+                "Bank: {}\nOpID: {}\nStatus: ok\nResult: {}\n\n",
+                repository.current_bank_id(session),
+                opperation_id,
+                hold_id
+            )
+        }
+        Err(e) => {
+            format!(
+                "Bank: {}\nStatus: error\nType: bank\nError: {}\n\n",
+                repository.current_bank_id(session),
+                e
+            )
+        }
+    }
+}
+
+fn handle_capture(
+    repository: &mut Repository,
+    session: &mut Session,
+    id: HoldID,
+    amount: Option<Money>,
+) -> String {
+    match repository.capture(session, id, amount) {
+        Ok(opperation_id) => {
+            format!(
+                "Bank: {}\nOpID: {}\nStatus: ok\n\n",
+                repository.current_bank_id(session),
+                opperation_id,
+            )
+        }
+        Err(e) => {
+            format!(
+                "Bank: {}\nStatus: error\nType: bank\nError: {}\n\n",
+                repository.current_bank_id(session),
+                e
+            )
+        }
+    }
+}
+
+fn handle_release(repository: &mut Repository, session: &mut Session, id: HoldID) -> String {
+    match repository.release(session, id) {
+        Ok(opperation_id) => {
+            format!(
+                "Bank: {}\nOpID: {}\nStatus: ok\n\n",
+                repository.current_bank_id(session),
+                opperation_id,
+            )
+        }
+        Err(e) => {
+            format!(
+                "Bank: {}\nStatus: error\nType: bank\nError: {}\n\n",
+                repository.current_bank_id(session),
+                e
+            )
+        }
+    }
+}
+
+fn handle_cancel_schedule(
+    repository: &mut Repository,
+    session: &mut Session,
+    id: ScheduleID,
+) -> String {
+    match repository.cancel_schedule(session, id) {
+        Ok(opperation_id) => {
+            format!(
+                "Bank: {}\nOpID: {}\nStatus: ok\n\n",
+                repository.current_bank_id(session),
+                opperation_id,
+            )
+        }
+        Err(e) => {
+            format!(
+                "Bank: {}\nStatus: error\nType: bank\nError: {}\n\n",
+                repository.current_bank_id(session),
+                e
+            )
+        }
+    }
+}
+
+fn schedules_as_string(schedules: &[Schedule]) -> String {
+    if schedules.is_empty() {
+        return String::from("no schedules yet");
+    }
+
+    schedules
+        .iter()
+        .map(|schedule| {
+            let mut line = format!(
+                "{}: {} -> {} {} at {}",
+                schedule.id,
+                schedule.sender_id,
+                schedule.receiver_id,
+                schedule.amount,
+                schedule.next_run
+            );
+            if let Some(interval) = schedule.interval {
+                line.push_str(&format!(" every {}", format_duration(interval)));
+            }
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn handle_list_schedules(repository: &mut Repository, session: &mut Session) -> String {
+    match repository.get_schedules(session) {
+        Ok(schedules) => format!(
+            "Bank: {}\nStatus: ok\nResult:\n{}\n\n",
+            repository.current_bank_id(session),
+            schedules_as_string(&schedules),
+        ),
+        Err(e) => handle_repository_error(repository, session, e),
+    }
+}
+
+// Report for the server log, one line per execution.
+fn handle_run_schedules(repository: &mut Repository) -> String {
+    let now = repository.now();
+    repository
+        .run_schedules(now)
+        .into_iter()
+        .map(|(bank_id, schedule_id, result)| match result {
+            Ok(operation_id) => format!(
+                "Bank {}: schedule {} executed as {}\n",
+                bank_id, schedule_id, operation_id
+            ),
+            Err(e) => format!("Bank {}: schedule {} failed: {}\n", bank_id, schedule_id, e),
+        })
+        .collect()
+}
+
 fn operations_as_string<'a, I: Iterator<Item = &'a Operation>>(operations: I) -> String {
     let operations: Vec<String> = operations.map(|op| op.to_string()).collect();
     if operations.is_empty() {
@@ -177,45 +926,238 @@ fn operations_as_string<'a, I: Iterator<Item = &'a Operation>>(operations: I) ->
     operations.join("\n")
 }
 
-fn handle_list_account_operations(repository: &mut Repository, id: AccountID) -> String {
-    let operations = repository.get_account_operations(id);
-    format!(
-        "Bank: {}\nStatus: ok\nResult:\n{}\n\n",
-        repository.current_bank_id(),
-        operations_as_string(operations),
-    )
+fn handle_list_operations(
+    repository: &mut Repository,
+    session: &mut Session,
+    account_id: Option<AccountID>,
+    query: OperationsQuery,
+) -> String {
+    match repository.query_operations(session, account_id, query) {
+        Ok(operations) => format!(
+            "Bank: {}\nStatus: ok\nResult:\n{}\n\n",
+            repository.current_bank_id(session),
+            operations_as_string(operations.into_iter()),
+        ),
+        Err(e) => handle_repository_error(repository, session, e),
+    }
+}
+
+// Aliases are looked up in the current bank, so a batch sees aliases set by its earlier commands.
+fn resolve_command(
+    repository: &mut Repository,
+    session: &mut Session,
+    command: &Command,
+) -> std::result::Result<Command<AccountID>, RepositoryError> {
+    command.resolve(|account| match account {
+        AccountRef::Id(id) => Ok(id),
+        AccountRef::Alias(alias) => repository.find_alias(session, alias),
+    })
 }
 
-fn handle_list_all_operations(repository: &mut Repository) -> String {
-    let operations = repository.get_all_operations();
+// Only commands that change the current bank can be batched.
+fn apply_batch_command(
+    repository: &mut Repository,
+    session: &mut Session,
+    command: &Command,
+) -> std::result::Result<OperationID, RepositoryError> {
+    match resolve_command(repository, session, command)? {
+        Command::RegisterAccount { balance, currency } => repository
+            .register_account(session, balance, currency)
+            .map(|(_, operation_id)| operation_id),
+        Command::SetExchangeRate { from, to, rate } => {
+            repository.set_exchange_rate(session, from, to, rate)
+        }
+        Command::Reverse { id } => repository.reverse(session, id),
+        Command::SetFee { operation, fee } => repository.set_fee(session, operation, fee),
+        Command::SetOverdraftLimit { id, limit } => {
+            repository.set_overdraft_limit(session, id, limit)
+        }
+        Command::SetInterestRate { id, rate } => repository.set_interest_rate(session, id, rate),
+        Command::FreezeAccount { id } => repository.freeze_account(session, id),
+        Command::UnfreezeAccount { id } => repository.unfreeze_account(session, id),
+        Command::CloseAccount { id, sweep_to } => repository.close_account(session, id, sweep_to),
+        Command::Deposit { id, balance, key } => repository.deposit(session, id, balance, key),
+        Command::Withdraw { id, balance, key } => repository.withdraw(session, id, balance, key),
+        Command::Transfer {
+            sender,
+            receiver,
+            amount,
+            key,
+        } => repository.transfer(session, sender, receiver, amount, key),
+        Command::ScheduleTransfer {
+            sender,
+            receiver,
+            amount,
+            delay,
+            interval,
+        } => repository
+            .schedule_transfer(
+                session,
+                sender,
+                receiver,
+                amount,
+                repository.now().saturating_add(delay),
+                interval,
+            )
+            .map(|(_, operation_id)| operation_id),
+        Command::CancelSchedule { id } => repository.cancel_schedule(session, id),
+        Command::Hold { id, amount } => repository
+            .hold(session, id, amount)
+            .map(|(_, operation_id)| operation_id),
+        Command::Capture { id, amount } => repository.capture(session, id, amount),
+        Command::Release { id } => repository.release(session, id),
+        Command::UpdateProfile { id, field } => repository.update_profile(session, id, field),
+        Command::SetLimit { id, kind, limit } => repository.set_limit(session, id, kind, limit),
+        _ => Err(RepositoryError::NotBatchable),
+    }
+}
+
+fn handle_batch(
+    repository: &mut Repository,
+    session: &mut Session,
+    commands: &[Command],
+) -> String {
+    if let Err(e) = repository.begin_batch(session) {
+        return handle_repository_error(repository, session, e);
+    }
+
+    let mut operations = Vec::new();
+    for (idx, command) in commands.iter().enumerate() {
+        match apply_batch_command(repository, session, command) {
+            Ok(operation_id) => operations.push(operation_id.to_string()),
+            Err(e) => {
+                repository.rollback_batch();
+                return format!(
+                    "Bank: {}\nStatus: error\nType: batch\nIndex: {}\nError: {}\n\n",
+                    repository.current_bank_id(session),
+                    idx + 1,
+                    e
+                );
+            }
+        }
+    }
+
+    if let Err(e) = repository.commit_batch() {
+        return handle_repository_error(repository, session, e);
+    }
+
     format!(
         "Bank: {}\nStatus: ok\nResult:\n{}\n\n",
-        repository.current_bank_id(),
-        operations_as_string(operations),
+        repository.current_bank_id(session),
+        operations.join("\n"),
     )
 }
 
-fn handle_command(repository: &mut Repository, command: &Command) -> String {
-    match *command {
-        Command::NewBank => handle_new_bank(repository),
-        Command::ChangeBank { id } => handle_change_bank(repository, id),
-        Command::RestoreBank { id } => handle_restore_bank(repository, id),
-        Command::WhichBank => handle_which_bank(repository),
-        Command::RegisterAccount { balance } => handle_register_account(repository, balance),
-        Command::GetBalance { id } => handle_get_balance(repository, id),
-        Command::Deposit { id, balance } => handle_deposit(repository, id, balance),
-        Command::Withdraw { id, balance } => handle_withdraw(repository, id, balance),
+fn handle_command(repository: &mut Repository, session: &mut Session, command: &Command) -> String {
+    let command = match resolve_command(repository, session, command) {
+        Ok(command) => command,
+        Err(e) => return handle_repository_error(repository, session, e),
+    };
+
+    match command {
+        Command::NewBank => handle_new_bank(repository, session),
+        Command::ChangeBank { id } => handle_change_bank(repository, session, id),
+        Command::RestoreBank { id } => handle_restore_bank(repository, session, id),
+        Command::WhichBank => handle_which_bank(repository, session),
+        Command::RegisterAccount { balance, currency } => {
+            handle_register_account(repository, session, balance, currency)
+        }
+        Command::SetExchangeRate { from, to, rate } => {
+            handle_set_exchange_rate(repository, session, from, to, rate)
+        }
+        Command::Reverse { id } => handle_reverse(repository, session, id),
+        Command::SetFee { operation, fee } => handle_set_fee(repository, session, operation, fee),
+        Command::ListFees => handle_list_fees(repository, session),
+        Command::Verify => handle_verify(repository, session),
+        Command::SetLimit { id, kind, limit } => {
+            handle_set_limit(repository, session, id, kind, limit)
+        }
+        Command::GetLimits { id } => handle_get_limits(repository, session, id),
+        Command::GetFeeAccount { currency } => {
+            handle_get_fee_account(repository, session, currency)
+        }
+        Command::SetOverdraftLimit { id, limit } => {
+            handle_set_overdraft_limit(repository, session, id, limit)
+        }
+        Command::SetInterestRate { id, rate } => {
+            handle_set_interest_rate(repository, session, id, rate)
+        }
+        Command::AccrueInterest { days } => handle_accrue_interest(repository, session, days),
+        Command::FreezeAccount { id } => handle_freeze_account(repository, session, id, true),
+        Command::UnfreezeAccount { id } => handle_freeze_account(repository, session, id, false),
+        Command::CloseAccount { id, sweep_to } => {
+            handle_close_account(repository, session, id, sweep_to)
+        }
+        Command::GetBalance { id } => handle_get_balance(repository, session, id),
+        Command::GetBalanceAt { id, cutoff } => {
+            handle_get_balance_at(repository, session, id, cutoff)
+        }
+        Command::Statement { id, format } => handle_statement(repository, session, id, format),
+        Command::Deposit { id, balance, key } => {
+            handle_deposit(repository, session, id, balance, key)
+        }
+        Command::Withdraw { id, balance, key } => {
+            handle_withdraw(repository, session, id, balance, key)
+        }
         Command::Transfer {
             sender,
             receiver,
             amount,
-        } => handle_transfer(repository, sender, receiver, amount),
+            key,
+        } => handle_transfer(repository, session, sender, receiver, amount, key),
+        Command::InterbankTransfer {
+            sender,
+            bank_id,
+            receiver,
+            amount,
+        } => handle_interbank_transfer(repository, session, sender, bank_id, receiver, amount),
+        Command::ScheduleTransfer {
+            sender,
+            receiver,
+            amount,
+            delay,
+            interval,
+        } => handle_schedule_transfer(
+            repository, session, sender, receiver, amount, delay, interval,
+        ),
+        Command::Hold { id, amount } => handle_hold(repository, session, id, amount),
+        Command::Capture { id, amount } => handle_capture(repository, session, id, amount),
+        Command::Release { id } => handle_release(repository, session, id),
+        Command::ListSchedules => handle_list_schedules(repository, session),
+        Command::CancelSchedule { id } => handle_cancel_schedule(repository, session, id),
 
-        Command::ListAccountOperations { id } => handle_list_account_operations(repository, id),
-        Command::ListAllOperations => handle_list_all_operations(repository),
+        Command::ListAccountOperations {
+            id,
+            query,
+            counterparty,
+        } => handle_list_operations(
+            repository,
+            session,
+            Some(id),
+            OperationsQuery {
+                counterparty,
+                ..query
+            },
+        ),
+        Command::ListAllOperations {
+            query,
+            counterparty,
+        } => handle_list_operations(
+            repository,
+            session,
+            None,
+            OperationsQuery {
+                counterparty,
+                ..query
+            },
+        ),
+        Command::UpdateProfile { id, field } => {
+            handle_update_profile(repository, session, id, field)
+        }
+        Command::GetProfile { id } => handle_get_profile(repository, session, id),
         _ => format!(
             "Bank: {}\nStatus: error\nType: repository\nError: unknown command\n\n",
-            repository.current_bank_id(),
+            repository.current_bank_id(session),
         ),
     }
 }
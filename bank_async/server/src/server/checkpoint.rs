@@ -0,0 +1,471 @@
+use crate::bank::account::{Account, AccountID};
+use crate::bank::hold::{Hold, HoldID};
+use crate::bank::idempotency::KeyedRequest;
+use crate::bank::limit::Limits;
+use crate::bank::log::OperationID;
+use crate::bank::profile::{Profile, ProfileField};
+use crate::bank::schedule::{Schedule, ScheduleID};
+use crate::bank::snapshot::Snapshot;
+use crate::server::wal::{crc32, decode_text, encode_optional, encode_text, Result, WalError};
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+const CHECKPOINT_FILE: &str = "checkpoint";
+const CHECKPOINT_TMP_FILE: &str = "checkpoint.tmp";
+
+/// Snapshots of all repository banks, together with the first log segment
+/// that has to be replayed on top of them.
+///
+/// Stored as text, one item per line, the last line is the checksum of all
+/// previous ones:
+///
+/// ```text
+/// segment <first_segment>
+/// bank <last_operation_id last_operation_time|->
+/// account <account_id> <balance> <currency> <open|frozen|closed> <overdraft_limit> <checking|savings:rate>
+/// profile <name|-> <email|-> <alias|->, values escaped like in the log
+/// rate <from_currency> <to_currency> <rate>
+/// reversed <operation_id>
+/// key <idempotency_key> <operation_id> <deposit|withdraw> <account_id> <amount>
+/// key <idempotency_key> <operation_id> transfer <sender_id> <receiver_id> <amount>
+/// schedule <schedule_id> <sender_id> <receiver_id> <amount> <next_run> <interval|->
+/// fee <withdraw|transfer> <fee>
+/// limit <account_id|-> <per_transaction|-> <daily|->
+/// outflow <account_id> <timestamp> <amount>
+/// hold <hold_id> <account_id> <amount>
+/// crc32 <checksum>
+/// ```
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Checkpoint {
+    pub segment: u64,
+    pub banks: Vec<Snapshot>,
+}
+
+impl Checkpoint {
+    /// Loads checkpoint from `dir`, returns `None` if no checkpoint was saved yet.
+    pub fn load<P: AsRef<Path>>(dir: P) -> Result<Option<Checkpoint>> {
+        let path = dir.as_ref().join(CHECKPOINT_FILE);
+        let data = match std::fs::read_to_string(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        decode(&data).map(Some).ok_or(WalError::CorruptedCheckpoint)
+    }
+
+    /// Saves checkpoint to `dir`, the previous one is replaced atomically.
+    pub fn save<P: AsRef<Path>>(&self, dir: P) -> Result<()> {
+        let dir = dir.as_ref();
+        let tmp_path = dir.join(CHECKPOINT_TMP_FILE);
+
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(encode(self).as_bytes())?;
+        file.sync_all()?;
+
+        std::fs::rename(tmp_path, dir.join(CHECKPOINT_FILE))?;
+        File::open(dir)?.sync_all()?;
+
+        Ok(())
+    }
+}
+
+fn encode(checkpoint: &Checkpoint) -> String {
+    let mut data = format!("segment {}\n", checkpoint.segment);
+
+    for snapshot in checkpoint.banks.iter() {
+        match (snapshot.last_operation_id, snapshot.last_operation_time) {
+            (Some(operation_id), Some(time)) => {
+                data.push_str(&format!("bank {} {}\n", operation_id, time))
+            }
+            _ => data.push_str("bank -\n"),
+        }
+
+        for account in snapshot.accounts.iter() {
+            data.push_str(&format!(
+                "account {} {} {} {} {} {}\n",
+                account.id,
+                account.balance,
+                account.currency,
+                account.status,
+                account.overdraft_limit,
+                account.kind
+            ));
+            if !account.profile.is_empty() {
+                data.push_str(&format!(
+                    "profile {} {} {}\n",
+                    encode_optional(
+                        account
+                            .profile
+                            .name
+                            .map(|name| encode_text(&name.to_string()))
+                    ),
+                    encode_optional(
+                        account
+                            .profile
+                            .email
+                            .map(|email| encode_text(&email.to_string()))
+                    ),
+                    encode_optional(
+                        account
+                            .profile
+                            .alias
+                            .map(|alias| encode_text(&alias.to_string()))
+                    )
+                ));
+            }
+        }
+
+        for (from, to, rate) in snapshot.exchange_rates.iter() {
+            data.push_str(&format!("rate {} {} {}\n", from, to, rate));
+        }
+
+        for operation_id in snapshot.reversed_operations.iter() {
+            data.push_str(&format!("reversed {}\n", operation_id));
+        }
+
+        for (key, operation_id, request) in snapshot.idempotency_keys.iter() {
+            let request = match *request {
+                KeyedRequest::Deposit { id, amount } => format!("deposit {} {}", id, amount),
+                KeyedRequest::Withdraw { id, amount } => format!("withdraw {} {}", id, amount),
+                KeyedRequest::Transfer {
+                    sender_id,
+                    receiver_id,
+                    amount,
+                } => format!("transfer {} {} {}", sender_id, receiver_id, amount),
+            };
+            data.push_str(&format!("key {} {} {}\n", key, operation_id, request));
+        }
+
+        for (operation, fee) in snapshot.fees.iter() {
+            data.push_str(&format!("fee {} {}\n", operation, fee));
+        }
+
+        for (id, limits) in snapshot.limits.iter() {
+            data.push_str(&format!(
+                "limit {} {} {}\n",
+                encode_optional(*id),
+                encode_optional(limits.per_transaction),
+                encode_optional(limits.daily)
+            ));
+        }
+
+        for (id, timestamp, amount) in snapshot.outflows.iter() {
+            data.push_str(&format!("outflow {} {} {}\n", id, timestamp, amount));
+        }
+
+        for hold in snapshot.holds.iter() {
+            data.push_str(&format!(
+                "hold {} {} {}\n",
+                hold.id, hold.account_id, hold.amount
+            ));
+        }
+
+        for schedule in snapshot.schedules.iter() {
+            data.push_str(&format!(
+                "schedule {} {} {} {} {} {}\n",
+                schedule.id,
+                schedule.sender_id,
+                schedule.receiver_id,
+                schedule.amount,
+                schedule.next_run,
+                encode_optional(schedule.interval)
+            ));
+        }
+    }
+
+    let checksum = crc32(data.as_bytes());
+    data.push_str(&format!("crc32 {:08x}\n", checksum));
+    data
+}
+
+fn decode(data: &str) -> Option<Checkpoint> {
+    let body = data.strip_suffix('\n')?;
+    let (body, checksum_line) = match body.rsplit_once('\n') {
+        Some((body, checksum_line)) => (format!("{}\n", body), checksum_line),
+        None => return None,
+    };
+
+    let checksum = checksum_line.strip_prefix("crc32 ")?;
+    if u32::from_str_radix(checksum, 16).ok()? != crc32(body.as_bytes()) {
+        return None;
+    }
+
+    let mut lines = body.lines();
+    let segment = lines.next()?.strip_prefix("segment ")?.parse().ok()?;
+
+    let mut checkpoint = Checkpoint {
+        segment,
+        banks: Vec::new(),
+    };
+
+    for line in lines {
+        let parts: Vec<&str> = line.split(' ').collect();
+        match parts[..] {
+            ["bank", "-"] => checkpoint.banks.push(Snapshot::default()),
+            ["bank", operation_id, time] => checkpoint.banks.push(Snapshot {
+                last_operation_id: Some(OperationID::parse_str(operation_id).ok()?),
+                last_operation_time: Some(time.parse().ok()?),
+                ..Snapshot::default()
+            }),
+            ["account", id, balance, currency, status, overdraft_limit, kind] => {
+                checkpoint.banks.last_mut()?.accounts.push(Account {
+                    id: AccountID::parse_str(id).ok()?,
+                    balance: balance.parse().ok()?,
+                    currency: currency.parse().ok()?,
+                    status: status.parse().ok()?,
+                    overdraft_limit: overdraft_limit.parse().ok()?,
+                    kind: kind.parse().ok()?,
+                    profile: Profile::default(),
+                })
+            }
+            ["profile", name, email, alias] => {
+                let account = checkpoint.banks.last_mut()?.accounts.last_mut()?;
+                for (field, value) in [("name", name), ("email", email), ("alias", alias)] {
+                    if value != "-" {
+                        account.profile = account
+                            .profile
+                            .updated(ProfileField::parse(field, &decode_text(value)?).ok()?);
+                    }
+                }
+            }
+            ["reversed", operation_id] => checkpoint
+                .banks
+                .last_mut()?
+                .reversed_operations
+                .push(OperationID::parse_str(operation_id).ok()?),
+            ["key", key, operation_id, ref request @ ..] => {
+                let request = match *request {
+                    ["deposit", id, amount] => KeyedRequest::Deposit {
+                        id: AccountID::parse_str(id).ok()?,
+                        amount: amount.parse().ok()?,
+                    },
+                    ["withdraw", id, amount] => KeyedRequest::Withdraw {
+                        id: AccountID::parse_str(id).ok()?,
+                        amount: amount.parse().ok()?,
+                    },
+                    ["transfer", sender_id, receiver_id, amount] => KeyedRequest::Transfer {
+                        sender_id: AccountID::parse_str(sender_id).ok()?,
+                        receiver_id: AccountID::parse_str(receiver_id).ok()?,
+                        amount: amount.parse().ok()?,
+                    },
+                    _ => return None,
+                };
+                checkpoint.banks.last_mut()?.idempotency_keys.push((
+                    key.parse().ok()?,
+                    OperationID::parse_str(operation_id).ok()?,
+                    request,
+                ))
+            }
+            ["schedule", id, sender_id, receiver_id, amount, next_run, interval] => {
+                checkpoint.banks.last_mut()?.schedules.push(Schedule {
+                    id: ScheduleID::parse_str(id).ok()?,
+                    sender_id: AccountID::parse_str(sender_id).ok()?,
+                    receiver_id: AccountID::parse_str(receiver_id).ok()?,
+                    amount: amount.parse().ok()?,
+                    next_run: next_run.parse().ok()?,
+                    interval: match interval {
+                        "-" => None,
+                        interval => Some(interval.parse().ok()?),
+                    },
+                })
+            }
+            ["fee", operation, fee] => checkpoint
+                .banks
+                .last_mut()?
+                .fees
+                .push((operation.parse().ok()?, fee.parse().ok()?)),
+            ["limit", id, per_transaction, daily] => {
+                let optional_money = |value: &str| match value {
+                    "-" => Some(None),
+                    value => value.parse().ok().map(Some),
+                };
+                checkpoint.banks.last_mut()?.limits.push((
+                    match id {
+                        "-" => None,
+                        id => Some(AccountID::parse_str(id).ok()?),
+                    },
+                    Limits {
+                        per_transaction: optional_money(per_transaction)?,
+                        daily: optional_money(daily)?,
+                    },
+                ))
+            }
+            ["outflow", id, timestamp, amount] => checkpoint.banks.last_mut()?.outflows.push((
+                AccountID::parse_str(id).ok()?,
+                timestamp.parse().ok()?,
+                amount.parse().ok()?,
+            )),
+            ["hold", id, account_id, amount] => checkpoint.banks.last_mut()?.holds.push(Hold {
+                id: HoldID::parse_str(id).ok()?,
+                account_id: AccountID::parse_str(account_id).ok()?,
+                amount: amount.parse().ok()?,
+            }),
+            ["rate", from, to, rate] => checkpoint.banks.last_mut()?.exchange_rates.push((
+                from.parse().ok()?,
+                to.parse().ok()?,
+                rate.parse().ok()?,
+            )),
+            _ => return None,
+        }
+    }
+
+    Some(checkpoint)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bank::account::AccountKind;
+    use crate::bank::fee::FeeOperation;
+    use crate::bank::money::Money;
+
+    fn temp_dir() -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("bank-checkpoint-{}", OperationID::new()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn save_and_load_works() {
+        let dir = temp_dir();
+        assert!(Checkpoint::load(&dir).unwrap().is_none());
+
+        let checkpoint = Checkpoint {
+            segment: 3,
+            banks: vec![
+                Snapshot {
+                    accounts: vec![
+                        Account::new(Money::from_minor(100)),
+                        Account::with_currency(Money::from_minor(0), "EUR".parse().unwrap()),
+                        Account {
+                            kind: AccountKind::Savings {
+                                rate: "0.035".parse().unwrap(),
+                            },
+                            ..Account::new(Money::from_minor(5000))
+                        },
+                        Account {
+                            profile: Profile {
+                                name: Some("Jane Doe".parse().unwrap()),
+                                email: None,
+                                alias: Some("jane".parse().unwrap()),
+                            },
+                            ..Account::new(Money::from_minor(0))
+                        },
+                    ],
+                    exchange_rates: vec![(
+                        "USD".parse().unwrap(),
+                        "EUR".parse().unwrap(),
+                        "0.92".parse().unwrap(),
+                    )],
+                    reversed_operations: vec![OperationID::new()],
+                    idempotency_keys: vec![
+                        (
+                            "retry-1".parse().unwrap(),
+                            OperationID::new(),
+                            KeyedRequest::Deposit {
+                                id: AccountID::new(),
+                                amount: Money::from_minor(100),
+                            },
+                        ),
+                        (
+                            "retry-2".parse().unwrap(),
+                            OperationID::new(),
+                            KeyedRequest::Transfer {
+                                sender_id: AccountID::new(),
+                                receiver_id: AccountID::new(),
+                                amount: Money::from_minor(250),
+                            },
+                        ),
+                    ],
+                    fees: vec![
+                        (FeeOperation::Withdraw, "flat:0.50".parse().unwrap()),
+                        (FeeOperation::Transfer, "capped:0.01:5".parse().unwrap()),
+                    ],
+                    schedules: vec![
+                        Schedule {
+                            id: ScheduleID::new(),
+                            sender_id: AccountID::new(),
+                            receiver_id: AccountID::new(),
+                            amount: Money::from_minor(500),
+                            next_run: 1_700_000_000,
+                            interval: Some(86400),
+                        },
+                        Schedule {
+                            id: ScheduleID::new(),
+                            sender_id: AccountID::new(),
+                            receiver_id: AccountID::new(),
+                            amount: Money::from_minor(1),
+                            next_run: 0,
+                            interval: None,
+                        },
+                    ],
+                    limits: vec![
+                        (
+                            None,
+                            Limits {
+                                per_transaction: Some(Money::from_minor(100000)),
+                                daily: None,
+                            },
+                        ),
+                        (
+                            Some(AccountID::new()),
+                            Limits {
+                                per_transaction: None,
+                                daily: Some(Money::from_minor(50000)),
+                            },
+                        ),
+                    ],
+                    outflows: vec![(AccountID::new(), 1_700_000_000, Money::from_minor(2500))],
+                    holds: vec![Hold {
+                        id: HoldID::new(),
+                        account_id: AccountID::new(),
+                        amount: Money::from_minor(1250),
+                    }],
+                    last_operation_id: Some(OperationID::new()),
+                    last_operation_time: Some(1_700_000_060),
+                },
+                Snapshot::default(),
+            ],
+        };
+
+        checkpoint.save(&dir).unwrap();
+        assert_eq!(Checkpoint::load(&dir).unwrap(), Some(checkpoint.clone()));
+
+        let checkpoint = Checkpoint {
+            segment: 4,
+            banks: Vec::new(),
+        };
+
+        checkpoint.save(&dir).unwrap();
+        assert_eq!(Checkpoint::load(&dir).unwrap(), Some(checkpoint));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn load_detects_corrupted_checkpoint_works() {
+        let dir = temp_dir();
+
+        let checkpoint = Checkpoint {
+            segment: 3,
+            banks: vec![Snapshot {
+                accounts: vec![Account::new(Money::from_minor(100))],
+                ..Snapshot::default()
+            }],
+        };
+        checkpoint.save(&dir).unwrap();
+
+        let path = dir.join(CHECKPOINT_FILE);
+        let data = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, data.replacen(" 1.00 ", " 9.00 ", 1)).unwrap();
+
+        assert!(matches!(
+            Checkpoint::load(&dir),
+            Err(WalError::CorruptedCheckpoint)
+        ));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
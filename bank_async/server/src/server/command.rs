@@ -1,7 +1,31 @@
 use crate::bank::account::AccountID;
+use crate::bank::clock::parse_timestamp;
+use crate::bank::currency::{self, Currency, Rate};
+use crate::bank::fee::{self, Fee, FeeOperation};
+use crate::bank::hold::{self, HoldID};
+use crate::bank::idempotency::{self, IdempotencyKey};
+use crate::bank::limit::{self, LimitKind};
+use crate::bank::log::{Cutoff, OperationID, OperationKind, OperationsQuery};
+use crate::bank::money::{self, Money};
+use crate::bank::profile::{self, Alias, ProfileField};
+use crate::bank::schedule::ScheduleID;
+use crate::bank::statement::{self, StatementFormat};
 
+/// Account given by its id or, with `@` in front, by its alias in the current bank.
 #[derive(Debug, PartialEq, Copy, Clone)]
-pub enum Command {
+pub enum AccountRef {
+    Id(AccountID),
+    Alias(Alias),
+}
+
+impl From<AccountID> for AccountRef {
+    fn from(id: AccountID) -> AccountRef {
+        AccountRef::Id(id)
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Command<A = AccountRef> {
     NewBank,
     ChangeBank {
         id: u64,
@@ -11,32 +35,262 @@ pub enum Command {
     },
     WhichBank,
     RegisterAccount {
-        balance: u64,
+        balance: Money,
+        currency: Currency,
+    },
+    SetExchangeRate {
+        from: Currency,
+        to: Currency,
+        rate: Rate,
+    },
+    Reverse {
+        id: OperationID,
+    },
+    SetOverdraftLimit {
+        id: A,
+        limit: Money,
+    },
+    SetInterestRate {
+        id: A,
+        rate: Rate,
+    },
+    AccrueInterest {
+        days: u32,
+    },
+    FreezeAccount {
+        id: A,
+    },
+    UnfreezeAccount {
+        id: A,
+    },
+    CloseAccount {
+        id: A,
+        sweep_to: Option<A>,
     },
     GetBalance {
-        id: AccountID,
+        id: A,
+    },
+    GetBalanceAt {
+        id: A,
+        cutoff: Cutoff,
+    },
+    Statement {
+        id: A,
+        format: StatementFormat,
     },
     Deposit {
-        id: AccountID,
-        balance: u64,
+        id: A,
+        balance: Money,
+        key: Option<IdempotencyKey>,
     },
     Withdraw {
-        id: AccountID,
-        balance: u64,
+        id: A,
+        balance: Money,
+        key: Option<IdempotencyKey>,
     },
     Transfer {
-        sender: AccountID,
-        receiver: AccountID,
-        amount: u64,
+        sender: A,
+        receiver: A,
+        amount: Money,
+        key: Option<IdempotencyKey>,
     },
+    /// `receiver` belongs to bank `bank_id`, so its alias is never resolved in the current bank.
+    InterbankTransfer {
+        sender: A,
+        bank_id: u64,
+        receiver: AccountRef,
+        amount: Money,
+    },
+    /// First transfer runs `delay` seconds from now, then every `interval` seconds.
+    ScheduleTransfer {
+        sender: A,
+        receiver: A,
+        amount: Money,
+        delay: u64,
+        interval: Option<u64>,
+    },
+    Hold {
+        id: A,
+        amount: Money,
+    },
+    /// Whole hold is captured if `amount` is `None`.
+    Capture {
+        id: HoldID,
+        amount: Option<Money>,
+    },
+    Release {
+        id: HoldID,
+    },
+    ListSchedules,
+    CancelSchedule {
+        id: ScheduleID,
+    },
+    SetFee {
+        operation: FeeOperation,
+        fee: Option<Fee>,
+    },
+    ListFees,
+    /// Bank-wide limit if `id` is `None`.
+    SetLimit {
+        id: Option<A>,
+        kind: LimitKind,
+        limit: Option<Money>,
+    },
+    GetLimits {
+        id: Option<A>,
+    },
+    GetFeeAccount {
+        currency: Currency,
+    },
+    /// `query.counterparty` is set from `counterparty` once it is resolved.
     ListAccountOperations {
-        id: AccountID,
+        id: A,
+        query: OperationsQuery,
+        counterparty: Option<A>,
+    },
+    ListAllOperations {
+        query: OperationsQuery,
+        counterparty: Option<A>,
+    },
+    UpdateProfile {
+        id: A,
+        field: ProfileField,
     },
-    ListAllOperations,
+    GetProfile {
+        id: A,
+    },
+    /// Checks the current bank against its log.
+    Verify,
+    Begin,
+    Commit,
+    Rollback,
     Help,
     Quit,
 }
 
+impl<A> Command<A> {
+    /// Replaces every account reference with the result of `f`, stops at the first error.
+    pub fn resolve<B, E, F>(self, mut f: F) -> std::result::Result<Command<B>, E>
+    where
+        F: FnMut(A) -> std::result::Result<B, E>,
+    {
+        let command = match self {
+            Command::NewBank => Command::NewBank,
+            Command::ChangeBank { id } => Command::ChangeBank { id },
+            Command::RestoreBank { id } => Command::RestoreBank { id },
+            Command::WhichBank => Command::WhichBank,
+            Command::RegisterAccount { balance, currency } => {
+                Command::RegisterAccount { balance, currency }
+            }
+            Command::SetExchangeRate { from, to, rate } => {
+                Command::SetExchangeRate { from, to, rate }
+            }
+            Command::Reverse { id } => Command::Reverse { id },
+            Command::SetOverdraftLimit { id, limit } => {
+                Command::SetOverdraftLimit { id: f(id)?, limit }
+            }
+            Command::SetInterestRate { id, rate } => Command::SetInterestRate { id: f(id)?, rate },
+            Command::AccrueInterest { days } => Command::AccrueInterest { days },
+            Command::FreezeAccount { id } => Command::FreezeAccount { id: f(id)? },
+            Command::UnfreezeAccount { id } => Command::UnfreezeAccount { id: f(id)? },
+            Command::CloseAccount { id, sweep_to } => Command::CloseAccount {
+                id: f(id)?,
+                sweep_to: sweep_to.map(&mut f).transpose()?,
+            },
+            Command::GetBalance { id } => Command::GetBalance { id: f(id)? },
+            Command::GetBalanceAt { id, cutoff } => Command::GetBalanceAt { id: f(id)?, cutoff },
+            Command::Statement { id, format } => Command::Statement { id: f(id)?, format },
+            Command::Deposit { id, balance, key } => Command::Deposit {
+                id: f(id)?,
+                balance,
+                key,
+            },
+            Command::Withdraw { id, balance, key } => Command::Withdraw {
+                id: f(id)?,
+                balance,
+                key,
+            },
+            Command::Transfer {
+                sender,
+                receiver,
+                amount,
+                key,
+            } => Command::Transfer {
+                sender: f(sender)?,
+                receiver: f(receiver)?,
+                amount,
+                key,
+            },
+            Command::InterbankTransfer {
+                sender,
+                bank_id,
+                receiver,
+                amount,
+            } => Command::InterbankTransfer {
+                sender: f(sender)?,
+                bank_id,
+                receiver,
+                amount,
+            },
+            Command::ScheduleTransfer {
+                sender,
+                receiver,
+                amount,
+                delay,
+                interval,
+            } => Command::ScheduleTransfer {
+                sender: f(sender)?,
+                receiver: f(receiver)?,
+                amount,
+                delay,
+                interval,
+            },
+            Command::Hold { id, amount } => Command::Hold { id: f(id)?, amount },
+            Command::Capture { id, amount } => Command::Capture { id, amount },
+            Command::Release { id } => Command::Release { id },
+            Command::ListSchedules => Command::ListSchedules,
+            Command::CancelSchedule { id } => Command::CancelSchedule { id },
+            Command::SetFee { operation, fee } => Command::SetFee { operation, fee },
+            Command::ListFees => Command::ListFees,
+            Command::SetLimit { id, kind, limit } => Command::SetLimit {
+                id: id.map(&mut f).transpose()?,
+                kind,
+                limit,
+            },
+            Command::GetLimits { id } => Command::GetLimits {
+                id: id.map(&mut f).transpose()?,
+            },
+            Command::GetFeeAccount { currency } => Command::GetFeeAccount { currency },
+            Command::ListAccountOperations {
+                id,
+                query,
+                counterparty,
+            } => Command::ListAccountOperations {
+                id: f(id)?,
+                query,
+                counterparty: counterparty.map(&mut f).transpose()?,
+            },
+            Command::ListAllOperations {
+                query,
+                counterparty,
+            } => Command::ListAllOperations {
+                query,
+                counterparty: counterparty.map(&mut f).transpose()?,
+            },
+            Command::UpdateProfile { id, field } => Command::UpdateProfile { id: f(id)?, field },
+            Command::GetProfile { id } => Command::GetProfile { id: f(id)? },
+            Command::Verify => Command::Verify,
+            Command::Begin => Command::Begin,
+            Command::Commit => Command::Commit,
+            Command::Rollback => Command::Rollback,
+            Command::Help => Command::Help,
+            Command::Quit => Command::Quit,
+        };
+
+        Ok(command)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum ParseError {
     EmptyCommand,
@@ -47,10 +301,70 @@ pub enum ParseError {
         name: String,
         e: std::num::ParseIntError,
     },
+    InvalidArgumentMoney {
+        name: String,
+        e: money::Error,
+    },
     InvalidArgumentAccountID {
         name: String,
         e: crate::bank::account::Error,
     },
+    InvalidArgumentOperationID {
+        name: String,
+        e: crate::bank::log::Error,
+    },
+    InvalidArgumentCurrency {
+        name: String,
+        e: currency::Error,
+    },
+    InvalidArgumentKey {
+        name: String,
+        e: idempotency::Error,
+    },
+    InvalidArgumentRate {
+        name: String,
+        e: currency::Error,
+    },
+    InvalidArgumentDuration {
+        name: String,
+    },
+    InvalidArgumentFee {
+        name: String,
+        e: fee::Error,
+    },
+    InvalidArgumentScheduleID {
+        name: String,
+        e: crate::bank::schedule::Error,
+    },
+    InvalidArgumentHoldID {
+        name: String,
+        e: hold::Error,
+    },
+    InvalidArgumentCutoff {
+        name: String,
+    },
+    InvalidArgumentKind {
+        name: String,
+    },
+    InvalidArgumentFormat {
+        name: String,
+        e: statement::Error,
+    },
+    InvalidArgumentLimit {
+        name: String,
+        e: limit::Error,
+    },
+    InvalidArgumentAlias {
+        name: String,
+        e: profile::Error,
+    },
+    InvalidArgumentProfile {
+        name: String,
+        e: profile::Error,
+    },
+    InvalidOption {
+        option: String,
+    },
     UnknownCommand,
 }
 
@@ -64,9 +378,70 @@ impl std::fmt::Display for ParseError {
             ParseError::InvalidArgumentUint { name, e } => {
                 write!(f, "invalid argument {name}: {e}")
             }
+            ParseError::InvalidArgumentMoney { name, e } => {
+                write!(f, "invalid argument {name}: {e}")
+            }
             ParseError::InvalidArgumentAccountID { name, e } => {
                 write!(f, "invalid account {name}: {e}")
             }
+            ParseError::InvalidArgumentOperationID { name, e } => {
+                write!(f, "invalid operation {name}: {e}")
+            }
+            ParseError::InvalidArgumentCurrency { name, e } => {
+                write!(f, "invalid currency {name}: {e}")
+            }
+            ParseError::InvalidArgumentKey { name, e } => {
+                write!(f, "invalid argument {name}: {e}")
+            }
+            ParseError::InvalidArgumentRate { name, e } => {
+                write!(f, "invalid rate {name}: {e}")
+            }
+            ParseError::InvalidArgumentDuration { name } => {
+                write!(
+                    f,
+                    "invalid duration {name}: expected number with optional s, m, h, d or w suffix"
+                )
+            }
+            ParseError::InvalidArgumentFee { name, e } => {
+                write!(f, "invalid argument {name}: {e}")
+            }
+            ParseError::InvalidArgumentScheduleID { name, e } => {
+                write!(f, "invalid schedule {name}: {e}")
+            }
+            ParseError::InvalidArgumentHoldID { name, e } => {
+                write!(f, "invalid hold {name}: {e}")
+            }
+            ParseError::InvalidArgumentCutoff { name } => {
+                write!(
+                    f,
+                    "invalid cutoff {name}: expected operation id, date or time like 2024-03-01T12:30:00Z"
+                )
+            }
+            ParseError::InvalidArgumentKind { name } => {
+                write!(
+                    f,
+                    "invalid kind {name}: expected one of {}",
+                    OperationKind::NAMES.join(", ")
+                )
+            }
+            ParseError::InvalidArgumentFormat { name, e } => {
+                write!(f, "invalid argument {name}: {e}")
+            }
+            ParseError::InvalidArgumentLimit { name, e } => {
+                write!(f, "invalid argument {name}: {e}")
+            }
+            ParseError::InvalidArgumentAlias { name, e } => {
+                write!(f, "invalid account {name}: {e}")
+            }
+            ParseError::InvalidArgumentProfile { name, e } => {
+                write!(f, "invalid argument {name}: {e}")
+            }
+            ParseError::InvalidOption { option } => {
+                write!(
+                    f,
+                    "invalid option {option}: expected name=value, name is one of kind, min_amount, max_amount, counterparty, after, offset or limit"
+                )
+            }
             ParseError::UnknownCommand => {
                 write!(f, "unknown command")
             }
@@ -78,13 +453,212 @@ impl std::error::Error for ParseError {}
 
 pub type Result<T> = std::result::Result<T, ParseError>;
 
-pub fn parse_argument_account_id(name: &str, value: &str) -> Result<AccountID> {
-    AccountID::parse_str(value).map_err(|e| ParseError::InvalidArgumentAccountID {
+// Account id or `@alias`.
+pub fn parse_argument_account_id(name: &str, value: &str) -> Result<AccountRef> {
+    if let Some(alias) = value.strip_prefix('@') {
+        return alias.parse().map(AccountRef::Alias).map_err(|e| {
+            ParseError::InvalidArgumentAlias {
+                name: name.to_string(),
+                e,
+            }
+        });
+    }
+
+    AccountID::parse_str(value)
+        .map(AccountRef::Id)
+        .map_err(|e| ParseError::InvalidArgumentAccountID {
+            name: name.to_string(),
+            e,
+        })
+}
+
+pub fn parse_argument_operation_id(name: &str, value: &str) -> Result<OperationID> {
+    OperationID::parse_str(value).map_err(|e| ParseError::InvalidArgumentOperationID {
+        name: name.to_string(),
+        e,
+    })
+}
+
+pub fn parse_argument_schedule_id(name: &str, value: &str) -> Result<ScheduleID> {
+    ScheduleID::parse_str(value).map_err(|e| ParseError::InvalidArgumentScheduleID {
+        name: name.to_string(),
+        e,
+    })
+}
+
+pub fn parse_argument_hold_id(name: &str, value: &str) -> Result<HoldID> {
+    HoldID::parse_str(value).map_err(|e| ParseError::InvalidArgumentHoldID {
+        name: name.to_string(),
+        e,
+    })
+}
+
+// Duration in seconds, e.g. `90`, `15m` or `30d`.
+pub fn parse_argument_duration(name: &str, value: &str) -> Result<u64> {
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(idx) => value.split_at(idx),
+        None => (value, "s"),
+    };
+
+    let multiplier = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => 0,
+    };
+
+    number
+        .parse::<u64>()
+        .ok()
+        .filter(|_| multiplier != 0)
+        .and_then(|number| number.checked_mul(multiplier))
+        .ok_or(ParseError::InvalidArgumentDuration {
+            name: name.to_string(),
+        })
+}
+
+// Operation id or UTC time, see `parse_timestamp`.
+pub fn parse_argument_cutoff(name: &str, value: &str) -> Result<Cutoff> {
+    if let Ok(operation_id) = OperationID::parse_str(value) {
+        return Ok(Cutoff::Operation(operation_id));
+    }
+
+    parse_timestamp(value)
+        .map(Cutoff::Time)
+        .ok_or(ParseError::InvalidArgumentCutoff {
+            name: name.to_string(),
+        })
+}
+
+pub fn parse_argument_kind(name: &str, value: &str) -> Result<&'static str> {
+    OperationKind::NAMES
+        .into_iter()
+        .find(|kind| *kind == value)
+        .ok_or(ParseError::InvalidArgumentKind {
+            name: name.to_string(),
+        })
+}
+
+// Listing options in `name=value` form, e.g. `kind=transfer limit=20`.
+// Counterparty may be an alias, so it is returned apart from the query.
+pub fn parse_argument_query(options: &[&str]) -> Result<(OperationsQuery, Option<AccountRef>)> {
+    let mut query = OperationsQuery::default();
+    let mut counterparty = None;
+
+    for option in options {
+        let invalid_option = || ParseError::InvalidOption {
+            option: option.to_string(),
+        };
+        let (name, value) = option.split_once('=').ok_or_else(invalid_option)?;
+
+        match name {
+            "kind" => query.kind = Some(parse_argument_kind(name, value)?),
+            "min_amount" => query.min_amount = Some(parse_argument_money(name, value)?),
+            "max_amount" => query.max_amount = Some(parse_argument_money(name, value)?),
+            "counterparty" => counterparty = Some(parse_argument_account_id(name, value)?),
+            "after" => query.after = Some(parse_argument_operation_id(name, value)?),
+            "offset" => query.offset = parse_argument_uint(name, value)? as usize,
+            "limit" => query.limit = Some(parse_argument_uint(name, value)? as usize),
+            _ => return Err(invalid_option()),
+        }
+    }
+
+    Ok((query, counterparty))
+}
+
+// `bank` stands for all accounts of the current bank.
+pub fn parse_argument_limit_scope(name: &str, value: &str) -> Result<Option<AccountRef>> {
+    match value {
+        "bank" => Ok(None),
+        value => parse_argument_account_id(name, value).map(Some),
+    }
+}
+
+pub fn parse_argument_limit_kind(name: &str, value: &str) -> Result<LimitKind> {
+    value.parse().map_err(|e| ParseError::InvalidArgumentLimit {
+        name: name.to_string(),
+        e,
+    })
+}
+
+// `none` removes the limit.
+pub fn parse_argument_limit(name: &str, value: &str) -> Result<Option<Money>> {
+    match value {
+        "none" => Ok(None),
+        value => parse_argument_money(name, value).map(Some),
+    }
+}
+
+// Idempotency key is always the last optional argument.
+pub fn parse_argument_key(name: &str, value: Option<&str>) -> Result<Option<IdempotencyKey>> {
+    value
+        .map(|value| {
+            value.parse().map_err(|e| ParseError::InvalidArgumentKey {
+                name: name.to_string(),
+                e,
+            })
+        })
+        .transpose()
+}
+
+pub fn parse_argument_currency(name: &str, value: &str) -> Result<Currency> {
+    value
+        .parse()
+        .map_err(|e| ParseError::InvalidArgumentCurrency {
+            name: name.to_string(),
+            e,
+        })
+}
+
+pub fn parse_argument_rate(name: &str, value: &str) -> Result<Rate> {
+    value.parse().map_err(|e| ParseError::InvalidArgumentRate {
+        name: name.to_string(),
+        e,
+    })
+}
+
+pub fn parse_argument_fee_operation(name: &str, value: &str) -> Result<FeeOperation> {
+    value.parse().map_err(|e| ParseError::InvalidArgumentFee {
         name: name.to_string(),
         e,
     })
 }
 
+// `none` means that there is no fee.
+pub fn parse_argument_fee(name: &str, value: &str) -> Result<Option<Fee>> {
+    if value == "none" {
+        return Ok(None);
+    }
+
+    value
+        .parse()
+        .map(Some)
+        .map_err(|e| ParseError::InvalidArgumentFee {
+            name: name.to_string(),
+            e,
+        })
+}
+
+pub fn parse_argument_money(name: &str, value: &str) -> Result<Money> {
+    let money: Money = value
+        .parse()
+        .map_err(|e| ParseError::InvalidArgumentMoney {
+            name: name.to_string(),
+            e,
+        })?;
+
+    if money.is_negative() {
+        return Err(ParseError::InvalidArgumentMoney {
+            name: name.to_string(),
+            e: money::Error::Negative,
+        });
+    }
+
+    Ok(money)
+}
+
 pub fn parse_argument_uint(name: &str, value: &str) -> Result<u64> {
     value.parse().map_err(|e| ParseError::InvalidArgumentUint {
         name: name.to_string(),
@@ -106,7 +680,12 @@ pub fn parse_command(command: &str) -> Result<Command> {
     let command = parts[0];
 
     match command {
-        "get_balance" | "list_account_operations" | "get_account_operations" => {
+        "get_balance"
+        | "list_account_operations"
+        | "get_account_operations"
+        | "freeze_account"
+        | "unfreeze_account"
+        | "get_profile" => {
             if parts.len() < 2 {
                 return Err(ParseError::RequireArguments {
                     args: vec!["account_id".to_string()],
@@ -117,14 +696,83 @@ pub fn parse_command(command: &str) -> Result<Command> {
                 "get_balance" => Ok(Command::GetBalance {
                     id: parse_argument_account_id("account_id", parts[1])?,
                 }),
+                "freeze_account" => Ok(Command::FreezeAccount {
+                    id: parse_argument_account_id("account_id", parts[1])?,
+                }),
+                "unfreeze_account" => Ok(Command::UnfreezeAccount {
+                    id: parse_argument_account_id("account_id", parts[1])?,
+                }),
+                "get_profile" => Ok(Command::GetProfile {
+                    id: parse_argument_account_id("account_id", parts[1])?,
+                }),
                 "list_account_operations" | "get_account_operations" => {
+                    let (query, counterparty) = parse_argument_query(&parts[2..])?;
+
                     Ok(Command::ListAccountOperations {
                         id: parse_argument_account_id("account_id", parts[1])?,
+                        query,
+                        counterparty,
                     })
                 }
                 _ => unreachable!(),
             }
         }
+        "get_balance_at" => {
+            if parts.len() < 3 {
+                return Err(ParseError::RequireArguments {
+                    args: vec!["account_id".to_string(), "cutoff".to_string()],
+                });
+            }
+
+            Ok(Command::GetBalanceAt {
+                id: parse_argument_account_id("account_id", parts[1])?,
+                cutoff: parse_argument_cutoff("cutoff", parts[2])?,
+            })
+        }
+        "statement" => {
+            if parts.len() < 2 {
+                return Err(ParseError::RequireArguments {
+                    args: vec!["account_id".to_string()],
+                });
+            }
+
+            let format = match parts.get(2) {
+                Some(format) => format
+                    .parse()
+                    .map_err(|e| ParseError::InvalidArgumentFormat {
+                        name: "format".to_string(),
+                        e,
+                    })?,
+                None => StatementFormat::default(),
+            };
+
+            Ok(Command::Statement {
+                id: parse_argument_account_id("account_id", parts[1])?,
+                format,
+            })
+        }
+        "update_profile" => {
+            if parts.len() < 4 {
+                return Err(ParseError::RequireArguments {
+                    args: vec![
+                        "account_id".to_string(),
+                        "field".to_string(),
+                        "value".to_string(),
+                    ],
+                });
+            }
+
+            Ok(Command::UpdateProfile {
+                id: parse_argument_account_id("account_id", parts[1])?,
+                // name may span several parts
+                field: ProfileField::parse(parts[2], &parts[3..].join(" ")).map_err(|e| {
+                    ParseError::InvalidArgumentProfile {
+                        name: parts[2].to_string(),
+                        e,
+                    }
+                })?,
+            })
+        }
         "register_account" | "new_account" => {
             if parts.len() < 2 {
                 return Err(ParseError::RequireArguments {
@@ -132,115 +780,817 @@ pub fn parse_command(command: &str) -> Result<Command> {
                 });
             }
 
-            Ok(Command::RegisterAccount {
-                balance: parse_argument_uint("balance", parts[1])?,
-            })
-        }
-        "deposit" | "withdraw" => {
-            if parts.len() < 3 {
-                return Err(ParseError::RequireArguments {
-                    args: vec!["account_id".to_string(), "amount".to_string()],
-                });
+            let currency = match parts.get(2) {
+                Some(currency) => parse_argument_currency("currency", currency)?,
+                None => Currency::default(),
+            };
+
+            Ok(Command::RegisterAccount {
+                balance: parse_argument_money("balance", parts[1])?,
+                currency,
+            })
+        }
+        "set_exchange_rate" => {
+            if parts.len() < 4 {
+                return Err(ParseError::RequireArguments {
+                    args: vec![
+                        "from_currency".to_string(),
+                        "to_currency".to_string(),
+                        "rate".to_string(),
+                    ],
+                });
+            }
+
+            Ok(Command::SetExchangeRate {
+                from: parse_argument_currency("from_currency", parts[1])?,
+                to: parse_argument_currency("to_currency", parts[2])?,
+                rate: parse_argument_rate("rate", parts[3])?,
+            })
+        }
+        "reverse" => {
+            if parts.len() < 2 {
+                return Err(ParseError::RequireArguments {
+                    args: vec!["operation_id".to_string()],
+                });
+            }
+
+            Ok(Command::Reverse {
+                id: parse_argument_operation_id("operation_id", parts[1])?,
+            })
+        }
+        "set_overdraft_limit" => {
+            if parts.len() < 3 {
+                return Err(ParseError::RequireArguments {
+                    args: vec!["account_id".to_string(), "limit".to_string()],
+                });
+            }
+
+            Ok(Command::SetOverdraftLimit {
+                id: parse_argument_account_id("account_id", parts[1])?,
+                limit: parse_argument_money("limit", parts[2])?,
+            })
+        }
+        "set_interest_rate" => {
+            if parts.len() < 3 {
+                return Err(ParseError::RequireArguments {
+                    args: vec!["account_id".to_string(), "annual_rate".to_string()],
+                });
+            }
+
+            Ok(Command::SetInterestRate {
+                id: parse_argument_account_id("account_id", parts[1])?,
+                rate: parse_argument_rate("annual_rate", parts[2])?,
+            })
+        }
+        "accrue_interest" => {
+            if parts.len() < 2 {
+                return Err(ParseError::RequireArguments {
+                    args: vec!["days".to_string()],
+                });
+            }
+
+            Ok(Command::AccrueInterest {
+                days: parts[1]
+                    .parse()
+                    .map_err(|e| ParseError::InvalidArgumentUint {
+                        name: "days".to_string(),
+                        e,
+                    })?,
+            })
+        }
+        "close_account" => {
+            if parts.len() < 2 {
+                return Err(ParseError::RequireArguments {
+                    args: vec!["account_id".to_string()],
+                });
+            }
+
+            let sweep_to = match parts.get(2) {
+                Some(sweep_to) => Some(parse_argument_account_id("sweep_to_account_id", sweep_to)?),
+                None => None,
+            };
+
+            Ok(Command::CloseAccount {
+                id: parse_argument_account_id("account_id", parts[1])?,
+                sweep_to,
+            })
+        }
+        "deposit" | "withdraw" => {
+            if parts.len() < 3 {
+                return Err(ParseError::RequireArguments {
+                    args: vec!["account_id".to_string(), "amount".to_string()],
+                });
+            }
+
+            let id = parse_argument_account_id("account_id", parts[1])?;
+            let balance = parse_argument_money("amount", parts[2])?;
+            let key = parse_argument_key("idempotency_key", parts.get(3).copied())?;
+
+            match command {
+                "deposit" => Ok(Command::Deposit { id, balance, key }),
+                "withdraw" => Ok(Command::Withdraw { id, balance, key }),
+                _ => unreachable!(),
+            }
+        }
+        "transfer" => {
+            if parts.len() < 4 {
+                return Err(ParseError::RequireArguments {
+                    args: vec![
+                        "sender_account_id".to_string(),
+                        "receiver_account_id".to_string(),
+                        "amount".to_string(),
+                    ],
+                });
+            }
+
+            Ok(Command::Transfer {
+                sender: parse_argument_account_id("sender_account_id", parts[1])?,
+                receiver: parse_argument_account_id("receiver_account_id", parts[2])?,
+                amount: parse_argument_money("amount", parts[3])?,
+                key: parse_argument_key("idempotency_key", parts.get(4).copied())?,
+            })
+        }
+        "interbank_transfer" => {
+            if parts.len() < 5 {
+                return Err(ParseError::RequireArguments {
+                    args: vec![
+                        "sender_account_id".to_string(),
+                        "bank_id".to_string(),
+                        "receiver_account_id".to_string(),
+                        "amount".to_string(),
+                    ],
+                });
+            }
+
+            Ok(Command::InterbankTransfer {
+                sender: parse_argument_account_id("sender_account_id", parts[1])?,
+                bank_id: parse_argument_uint("bank_id", parts[2])?,
+                receiver: parse_argument_account_id("receiver_account_id", parts[3])?,
+                amount: parse_argument_money("amount", parts[4])?,
+            })
+        }
+        "schedule_transfer" => {
+            if parts.len() < 5 {
+                return Err(ParseError::RequireArguments {
+                    args: vec![
+                        "sender_account_id".to_string(),
+                        "receiver_account_id".to_string(),
+                        "amount".to_string(),
+                        "delay".to_string(),
+                    ],
+                });
+            }
+
+            let interval = match parts.get(5) {
+                Some(interval) => Some(parse_argument_duration("interval", interval)?),
+                None => None,
+            };
+
+            Ok(Command::ScheduleTransfer {
+                sender: parse_argument_account_id("sender_account_id", parts[1])?,
+                receiver: parse_argument_account_id("receiver_account_id", parts[2])?,
+                amount: parse_argument_money("amount", parts[3])?,
+                delay: parse_argument_duration("delay", parts[4])?,
+                interval,
+            })
+        }
+        "hold" => {
+            if parts.len() < 3 {
+                return Err(ParseError::RequireArguments {
+                    args: vec!["account_id".to_string(), "amount".to_string()],
+                });
+            }
+
+            Ok(Command::Hold {
+                id: parse_argument_account_id("account_id", parts[1])?,
+                amount: parse_argument_money("amount", parts[2])?,
+            })
+        }
+        "capture" | "release" => {
+            if parts.len() < 2 {
+                return Err(ParseError::RequireArguments {
+                    args: vec!["hold_id".to_string()],
+                });
+            }
+
+            let id = parse_argument_hold_id("hold_id", parts[1])?;
+
+            match command {
+                "capture" => Ok(Command::Capture {
+                    id,
+                    amount: match parts.get(2) {
+                        Some(amount) => Some(parse_argument_money("amount", amount)?),
+                        None => None,
+                    },
+                }),
+                "release" => Ok(Command::Release { id }),
+                _ => unreachable!(),
+            }
+        }
+        "cancel_schedule" => {
+            if parts.len() < 2 {
+                return Err(ParseError::RequireArguments {
+                    args: vec!["schedule_id".to_string()],
+                });
+            }
+
+            Ok(Command::CancelSchedule {
+                id: parse_argument_schedule_id("schedule_id", parts[1])?,
+            })
+        }
+        "set_fee" => {
+            if parts.len() < 3 {
+                return Err(ParseError::RequireArguments {
+                    args: vec!["operation".to_string(), "fee".to_string()],
+                });
+            }
+
+            Ok(Command::SetFee {
+                operation: parse_argument_fee_operation("operation", parts[1])?,
+                fee: parse_argument_fee("fee", parts[2])?,
+            })
+        }
+        "set_limit" => {
+            if parts.len() < 4 {
+                return Err(ParseError::RequireArguments {
+                    args: vec![
+                        "account_id".to_string(),
+                        "kind".to_string(),
+                        "limit".to_string(),
+                    ],
+                });
+            }
+
+            Ok(Command::SetLimit {
+                id: parse_argument_limit_scope("account_id", parts[1])?,
+                kind: parse_argument_limit_kind("kind", parts[2])?,
+                limit: parse_argument_limit("limit", parts[3])?,
+            })
+        }
+        "get_limits" => {
+            let id = match parts.get(1) {
+                Some(id) => parse_argument_limit_scope("account_id", id)?,
+                None => None,
+            };
+
+            Ok(Command::GetLimits { id })
+        }
+        "fee_account" => {
+            let currency = match parts.get(1) {
+                Some(currency) => parse_argument_currency("currency", currency)?,
+                None => Currency::default(),
+            };
+
+            Ok(Command::GetFeeAccount { currency })
+        }
+        "change_bank" | "restore_bank" => {
+            if parts.len() < 2 {
+                return Err(ParseError::RequireArguments {
+                    args: vec!["bank_id".to_string()],
+                });
+            }
+
+            let id = parse_argument_uint("bank_id", parts[1])?;
+
+            match command {
+                "change_bank" => Ok(Command::ChangeBank { id }),
+                "restore_bank" => Ok(Command::RestoreBank { id }),
+                _ => unreachable!(),
+            }
+        }
+        "new_bank" => Ok(Command::NewBank),
+        "which_bank" => Ok(Command::WhichBank),
+        "list_all_operations" | "get_all_operations" => {
+            let (query, counterparty) = parse_argument_query(&parts[1..])?;
+
+            Ok(Command::ListAllOperations {
+                query,
+                counterparty,
+            })
+        }
+        "list_schedules" => Ok(Command::ListSchedules),
+        "list_fees" => Ok(Command::ListFees),
+        "verify" => Ok(Command::Verify),
+        "begin" => Ok(Command::Begin),
+        "commit" => Ok(Command::Commit),
+        "rollback" => Ok(Command::Rollback),
+        "quit" => Ok(Command::Quit),
+        "help" => Ok(Command::Help),
+        _ => Err(ParseError::UnknownCommand),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_command_register_works() {
+        assert_eq!(
+            parse_command("register_account").unwrap_err(),
+            ParseError::RequireArguments {
+                args: vec!["balance".to_string()]
+            },
+        );
+
+        assert_eq!(
+            parse_command("register_account test").unwrap_err(),
+            ParseError::InvalidArgumentMoney {
+                name: "balance".to_string(),
+                e: money::Error::InvalidFormat,
+            },
+        );
+
+        assert_eq!(
+            parse_command("register_account 100").unwrap(),
+            Command::RegisterAccount {
+                balance: Money::from_minor(10000),
+                currency: Currency::default()
+            },
+        );
+
+        assert_eq!(
+            parse_command("register_account 12.5 eur").unwrap(),
+            Command::RegisterAccount {
+                balance: Money::from_minor(1250),
+                currency: "EUR".parse().unwrap()
+            },
+        );
+
+        assert_eq!(
+            parse_command("register_account 100 euro").unwrap_err(),
+            ParseError::InvalidArgumentCurrency {
+                name: "currency".to_string(),
+                e: currency::Error::InvalidCurrency
+            },
+        );
+    }
+
+    #[test]
+    fn parse_command_set_exchange_rate_works() {
+        assert_eq!(
+            parse_command("set_exchange_rate USD EUR").unwrap_err(),
+            ParseError::RequireArguments {
+                args: vec![
+                    "from_currency".to_string(),
+                    "to_currency".to_string(),
+                    "rate".to_string()
+                ]
+            },
+        );
+
+        assert_eq!(
+            parse_command("set_exchange_rate USD EUR -1").unwrap_err(),
+            ParseError::InvalidArgumentRate {
+                name: "rate".to_string(),
+                e: currency::Error::InvalidRate
+            },
+        );
+
+        assert_eq!(
+            parse_command("set_exchange_rate USD EUR 0.92").unwrap(),
+            Command::SetExchangeRate {
+                from: Currency::default(),
+                to: "EUR".parse().unwrap(),
+                rate: "0.92".parse().unwrap()
+            },
+        );
+    }
+
+    #[test]
+    fn parse_command_get_balance_works() {
+        assert_eq!(
+            parse_command("get_balance").unwrap_err(),
+            ParseError::RequireArguments {
+                args: vec!["account_id".to_string()]
+            },
+        );
+
+        assert_eq!(
+            parse_command("get_balance test").unwrap_err(),
+            ParseError::InvalidArgumentAccountID {
+                name: "account_id".to_string(),
+                e: AccountID::parse_str("test").unwrap_err()
+            },
+        );
+
+        assert_eq!(
+            parse_command("get_balance 97c56a4e-0d75-4a82-b683-628b8c219fa3").unwrap(),
+            Command::GetBalance {
+                id: AccountID::parse_str("97c56a4e-0d75-4a82-b683-628b8c219fa3")
+                    .unwrap()
+                    .into()
+            }
+        );
+    }
+
+    #[test]
+    fn parse_command_profile_works() {
+        let id = AccountID::new();
+
+        assert_eq!(
+            parse_command(&format!("update_profile {} alias", id)).unwrap_err(),
+            ParseError::RequireArguments {
+                args: vec![
+                    "account_id".to_string(),
+                    "field".to_string(),
+                    "value".to_string()
+                ]
+            },
+        );
+
+        assert_eq!(
+            parse_command(&format!("update_profile {} alias jane", id)).unwrap(),
+            Command::UpdateProfile {
+                id: id.into(),
+                field: ProfileField::Alias(Some("jane".parse().unwrap())),
+            }
+        );
+
+        assert_eq!(
+            parse_command("update_profile @jane email none").unwrap(),
+            Command::UpdateProfile {
+                id: AccountRef::Alias("jane".parse().unwrap()),
+                field: ProfileField::Email(None),
+            }
+        );
+
+        assert_eq!(
+            parse_command("update_profile @jane name Jane  Doe").unwrap(),
+            Command::UpdateProfile {
+                id: AccountRef::Alias("jane".parse().unwrap()),
+                field: ProfileField::Name(Some("Jane Doe".parse().unwrap())),
+            }
+        );
+
+        assert_eq!(
+            parse_command("update_profile @jane alias jane doe").unwrap_err(),
+            ParseError::InvalidArgumentProfile {
+                name: "alias".to_string(),
+                e: profile::Error::InvalidAlias,
+            }
+        );
+
+        assert_eq!(
+            parse_command(&format!("update_profile {} phone 123", id)).unwrap_err(),
+            ParseError::InvalidArgumentProfile {
+                name: "phone".to_string(),
+                e: profile::Error::InvalidField,
+            }
+        );
+
+        assert_eq!(
+            parse_command("get_profile @jane").unwrap(),
+            Command::GetProfile {
+                id: AccountRef::Alias("jane".parse().unwrap()),
+            }
+        );
+
+        assert_eq!(
+            parse_command("get_balance @Jane").unwrap_err(),
+            ParseError::InvalidArgumentAlias {
+                name: "account_id".to_string(),
+                e: profile::Error::InvalidAlias,
+            }
+        );
+    }
+
+    #[test]
+    fn parse_command_limits_works() {
+        let id = AccountID::new();
+
+        assert_eq!(
+            parse_command("set_limit bank daily").unwrap_err(),
+            ParseError::RequireArguments {
+                args: vec![
+                    "account_id".to_string(),
+                    "kind".to_string(),
+                    "limit".to_string()
+                ]
+            },
+        );
+
+        assert_eq!(
+            parse_command("set_limit bank per_transaction 100").unwrap(),
+            Command::SetLimit {
+                id: None,
+                kind: LimitKind::PerTransaction,
+                limit: Some(Money::from_minor(10000)),
+            }
+        );
+
+        assert_eq!(
+            parse_command(&format!("set_limit {} daily none", id)).unwrap(),
+            Command::SetLimit {
+                id: Some(id.into()),
+                kind: LimitKind::Daily,
+                limit: None,
+            }
+        );
+
+        assert_eq!(
+            parse_command("set_limit bank weekly 100").unwrap_err(),
+            ParseError::InvalidArgumentLimit {
+                name: "kind".to_string(),
+                e: limit::Error::InvalidKind,
+            }
+        );
+
+        assert_eq!(
+            parse_command("get_limits").unwrap(),
+            Command::GetLimits { id: None }
+        );
+        assert_eq!(
+            parse_command("get_limits @jane").unwrap(),
+            Command::GetLimits {
+                id: Some(AccountRef::Alias("jane".parse().unwrap()))
+            }
+        );
+    }
+
+    #[test]
+    fn parse_command_interbank_transfer_works() {
+        let sender = AccountID::new();
+        let receiver = AccountID::new();
+
+        assert_eq!(
+            parse_command(&format!("interbank_transfer {} 2 {}", sender, receiver)).unwrap_err(),
+            ParseError::RequireArguments {
+                args: vec![
+                    "sender_account_id".to_string(),
+                    "bank_id".to_string(),
+                    "receiver_account_id".to_string(),
+                    "amount".to_string(),
+                ]
+            },
+        );
+
+        assert_eq!(
+            parse_command(&format!("interbank_transfer {} 2 @jane 10", sender)).unwrap(),
+            Command::InterbankTransfer {
+                sender: sender.into(),
+                bank_id: 2,
+                receiver: AccountRef::Alias("jane".parse().unwrap()),
+                amount: Money::from_minor(1000),
+            }
+        );
+
+        assert!(matches!(
+            parse_command(&format!(
+                "interbank_transfer {} two {} 10",
+                sender, receiver
+            ))
+            .unwrap_err(),
+            ParseError::InvalidArgumentUint { .. }
+        ));
+    }
+
+    #[test]
+    fn parse_command_holds_works() {
+        let id = HoldID::new();
+
+        assert_eq!(
+            parse_command("hold @jane").unwrap_err(),
+            ParseError::RequireArguments {
+                args: vec!["account_id".to_string(), "amount".to_string()]
+            },
+        );
+
+        assert_eq!(
+            parse_command("hold @jane 25").unwrap(),
+            Command::Hold {
+                id: AccountRef::Alias("jane".parse().unwrap()),
+                amount: Money::from_minor(2500),
+            }
+        );
+
+        assert_eq!(
+            parse_command(&format!("capture {}", id)).unwrap(),
+            Command::Capture { id, amount: None }
+        );
+
+        assert_eq!(
+            parse_command(&format!("capture {} 10.5", id)).unwrap(),
+            Command::Capture {
+                id,
+                amount: Some(Money::from_minor(1050)),
+            }
+        );
+
+        assert_eq!(
+            parse_command(&format!("release {}", id)).unwrap(),
+            Command::Release { id }
+        );
+
+        assert_eq!(
+            parse_command("release test").unwrap_err(),
+            ParseError::InvalidArgumentHoldID {
+                name: "hold_id".to_string(),
+                e: HoldID::parse_str("test").unwrap_err(),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_command_get_balance_at_works() {
+        let account_id = "97c56a4e-0d75-4a82-b683-628b8c219fa3";
+        let operation_id = "0b9ee2f5-7d3c-4b61-9a5e-2f3c8d4e6a10";
+
+        assert_eq!(
+            parse_command(&format!("get_balance_at {}", account_id)).unwrap_err(),
+            ParseError::RequireArguments {
+                args: vec!["account_id".to_string(), "cutoff".to_string()]
+            },
+        );
+
+        assert_eq!(
+            parse_command(&format!("get_balance_at {} yesterday", account_id)).unwrap_err(),
+            ParseError::InvalidArgumentCutoff {
+                name: "cutoff".to_string()
+            },
+        );
+
+        assert_eq!(
+            parse_command(&format!("get_balance_at {} {}", account_id, operation_id)).unwrap(),
+            Command::GetBalanceAt {
+                id: AccountID::parse_str(account_id).unwrap().into(),
+                cutoff: Cutoff::Operation(OperationID::parse_str(operation_id).unwrap()),
+            }
+        );
+
+        assert_eq!(
+            parse_command(&format!(
+                "get_balance_at {} 2024-03-01T12:30:00Z",
+                account_id
+            ))
+            .unwrap(),
+            Command::GetBalanceAt {
+                id: AccountID::parse_str(account_id).unwrap().into(),
+                cutoff: Cutoff::Time(1709296200),
             }
+        );
+    }
 
-            let id = parse_argument_account_id("account_id", parts[1])?;
-            let balance = parse_argument_uint("amount", parts[2])?;
+    #[test]
+    fn parse_command_statement_works() {
+        let account_id = "97c56a4e-0d75-4a82-b683-628b8c219fa3";
 
-            match command {
-                "deposit" => Ok(Command::Deposit { id, balance }),
-                "withdraw" => Ok(Command::Withdraw { id, balance }),
-                _ => unreachable!(),
+        assert_eq!(
+            parse_command("statement").unwrap_err(),
+            ParseError::RequireArguments {
+                args: vec!["account_id".to_string()]
+            },
+        );
+
+        assert_eq!(
+            parse_command(&format!("statement {}", account_id)).unwrap(),
+            Command::Statement {
+                id: AccountID::parse_str(account_id).unwrap().into(),
+                format: StatementFormat::Csv,
             }
-        }
-        "transfer" => {
-            if parts.len() < 4 {
-                return Err(ParseError::RequireArguments {
-                    args: vec![
-                        "sender_account_id".to_string(),
-                        "receiver_account_id".to_string(),
-                        "amount".to_string(),
-                    ],
-                });
+        );
+
+        assert_eq!(
+            parse_command(&format!("statement {} json", account_id)).unwrap(),
+            Command::Statement {
+                id: AccountID::parse_str(account_id).unwrap().into(),
+                format: StatementFormat::Json,
             }
+        );
 
-            Ok(Command::Transfer {
-                sender: parse_argument_account_id("sender_account_id", parts[1])?,
-                receiver: parse_argument_account_id("receiver_account_id", parts[2])?,
-                amount: parse_argument_uint("amount", parts[3])?,
-            })
-        }
-        "change_bank" | "restore_bank" => {
-            if parts.len() < 2 {
-                return Err(ParseError::RequireArguments {
-                    args: vec!["bank_id".to_string()],
-                });
+        assert_eq!(
+            parse_command(&format!("statement {} xml", account_id)).unwrap_err(),
+            ParseError::InvalidArgumentFormat {
+                name: "format".to_string(),
+                e: statement::Error::InvalidFormat,
             }
+        );
+    }
 
-            let id = parse_argument_uint("bank_id", parts[1])?;
+    #[test]
+    fn parse_command_reverse_works() {
+        assert_eq!(
+            parse_command("reverse").unwrap_err(),
+            ParseError::RequireArguments {
+                args: vec!["operation_id".to_string()]
+            },
+        );
 
-            match command {
-                "change_bank" => Ok(Command::ChangeBank { id }),
-                "restore_bank" => Ok(Command::RestoreBank { id }),
-                _ => unreachable!(),
+        assert_eq!(
+            parse_command("reverse test").unwrap_err(),
+            ParseError::InvalidArgumentOperationID {
+                name: "operation_id".to_string(),
+                e: OperationID::parse_str("test").unwrap_err()
+            },
+        );
+
+        assert_eq!(
+            parse_command("reverse 97c56a4e-0d75-4a82-b683-628b8c219fa3").unwrap(),
+            Command::Reverse {
+                id: OperationID::parse_str("97c56a4e-0d75-4a82-b683-628b8c219fa3").unwrap()
             }
-        }
-        "new_bank" => Ok(Command::NewBank),
-        "which_bank" => Ok(Command::WhichBank),
-        "list_all_operations" | "get_all_operations" => Ok(Command::ListAllOperations),
-        "quit" => Ok(Command::Quit),
-        "help" => Ok(Command::Help),
-        _ => Err(ParseError::UnknownCommand),
+        );
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
 
     #[test]
-    fn parse_command_register_works() {
+    fn parse_command_set_overdraft_limit_works() {
         assert_eq!(
-            parse_command("register_account").unwrap_err(),
+            parse_command("set_overdraft_limit 97c56a4e-0d75-4a82-b683-628b8c219fa3").unwrap_err(),
             ParseError::RequireArguments {
-                args: vec!["balance".to_string()]
+                args: vec!["account_id".to_string(), "limit".to_string()]
             },
         );
 
         assert_eq!(
-            parse_command("register_account test").unwrap_err(),
-            ParseError::InvalidArgumentUint {
-                name: "balance".to_string(),
-                e: "test".parse::<u64>().unwrap_err()
+            parse_command("set_overdraft_limit 97c56a4e-0d75-4a82-b683-628b8c219fa3 -10")
+                .unwrap_err(),
+            ParseError::InvalidArgumentMoney {
+                name: "limit".to_string(),
+                e: money::Error::Negative
             },
         );
 
         assert_eq!(
-            parse_command("register_account 100").unwrap(),
-            Command::RegisterAccount { balance: 100 },
+            parse_command("set_overdraft_limit 97c56a4e-0d75-4a82-b683-628b8c219fa3 250").unwrap(),
+            Command::SetOverdraftLimit {
+                id: AccountID::parse_str("97c56a4e-0d75-4a82-b683-628b8c219fa3")
+                    .unwrap()
+                    .into(),
+                limit: Money::from_minor(25000)
+            }
         );
     }
 
     #[test]
-    fn parse_command_get_balance_works() {
+    fn parse_command_freeze_account_works() {
         assert_eq!(
-            parse_command("get_balance").unwrap_err(),
+            parse_command("freeze_account").unwrap_err(),
             ParseError::RequireArguments {
                 args: vec!["account_id".to_string()]
             },
         );
 
         assert_eq!(
-            parse_command("get_balance test").unwrap_err(),
+            parse_command("freeze_account 97c56a4e-0d75-4a82-b683-628b8c219fa3").unwrap(),
+            Command::FreezeAccount {
+                id: AccountID::parse_str("97c56a4e-0d75-4a82-b683-628b8c219fa3")
+                    .unwrap()
+                    .into()
+            }
+        );
+
+        assert_eq!(
+            parse_command("unfreeze_account 97c56a4e-0d75-4a82-b683-628b8c219fa3").unwrap(),
+            Command::UnfreezeAccount {
+                id: AccountID::parse_str("97c56a4e-0d75-4a82-b683-628b8c219fa3")
+                    .unwrap()
+                    .into()
+            }
+        );
+    }
+
+    #[test]
+    fn parse_command_close_account_works() {
+        assert_eq!(
+            parse_command("close_account").unwrap_err(),
+            ParseError::RequireArguments {
+                args: vec!["account_id".to_string()]
+            },
+        );
+
+        assert_eq!(
+            parse_command("close_account 97c56a4e-0d75-4a82-b683-628b8c219fa3").unwrap(),
+            Command::CloseAccount {
+                id: AccountID::parse_str("97c56a4e-0d75-4a82-b683-628b8c219fa3")
+                    .unwrap()
+                    .into(),
+                sweep_to: None
+            }
+        );
+
+        assert_eq!(
+            parse_command("close_account 97c56a4e-0d75-4a82-b683-628b8c219fa3 test").unwrap_err(),
             ParseError::InvalidArgumentAccountID {
-                name: "account_id".to_string(),
+                name: "sweep_to_account_id".to_string(),
                 e: AccountID::parse_str("test").unwrap_err()
             },
         );
 
         assert_eq!(
-            parse_command("get_balance 97c56a4e-0d75-4a82-b683-628b8c219fa3").unwrap(),
-            Command::GetBalance {
-                id: AccountID::parse_str("97c56a4e-0d75-4a82-b683-628b8c219fa3").unwrap()
+            parse_command(
+                "close_account 97c56a4e-0d75-4a82-b683-628b8c219fa3 \
+                 4f1f6b5c-7d2e-4c47-9a53-1d3c1a0b2e11"
+            )
+            .unwrap(),
+            Command::CloseAccount {
+                id: AccountID::parse_str("97c56a4e-0d75-4a82-b683-628b8c219fa3")
+                    .unwrap()
+                    .into(),
+                sweep_to: Some(
+                    AccountID::parse_str("4f1f6b5c-7d2e-4c47-9a53-1d3c1a0b2e11")
+                        .unwrap()
+                        .into()
+                )
             }
         );
     }
@@ -264,17 +1614,59 @@ mod tests {
 
         assert_eq!(
             parse_command("deposit 97c56a4e-0d75-4a82-b683-628b8c219fa3 test").unwrap_err(),
-            ParseError::InvalidArgumentUint {
+            ParseError::InvalidArgumentMoney {
                 name: "amount".to_string(),
-                e: "test".parse::<u64>().unwrap_err(),
+                e: money::Error::InvalidFormat,
             }
         );
 
         assert_eq!(
             parse_command("deposit 97c56a4e-0d75-4a82-b683-628b8c219fa3 150").unwrap(),
             Command::Deposit {
-                id: AccountID::parse_str("97c56a4e-0d75-4a82-b683-628b8c219fa3").unwrap(),
-                balance: 150
+                id: AccountID::parse_str("97c56a4e-0d75-4a82-b683-628b8c219fa3")
+                    .unwrap()
+                    .into(),
+                balance: Money::from_minor(15000),
+                key: None
+            }
+        );
+
+        assert_eq!(
+            parse_command("deposit 97c56a4e-0d75-4a82-b683-628b8c219fa3 12.34").unwrap(),
+            Command::Deposit {
+                id: AccountID::parse_str("97c56a4e-0d75-4a82-b683-628b8c219fa3")
+                    .unwrap()
+                    .into(),
+                balance: Money::from_minor(1234),
+                key: None
+            }
+        );
+
+        assert_eq!(
+            parse_command("deposit 97c56a4e-0d75-4a82-b683-628b8c219fa3 12.34 retry-1").unwrap(),
+            Command::Deposit {
+                id: AccountID::parse_str("97c56a4e-0d75-4a82-b683-628b8c219fa3")
+                    .unwrap()
+                    .into(),
+                balance: Money::from_minor(1234),
+                key: Some("retry-1".parse().unwrap())
+            }
+        );
+
+        assert_eq!(
+            parse_command("deposit 97c56a4e-0d75-4a82-b683-628b8c219fa3 12.34 retry.1")
+                .unwrap_err(),
+            ParseError::InvalidArgumentKey {
+                name: "idempotency_key".to_string(),
+                e: idempotency::Error::InvalidKey,
+            }
+        );
+
+        assert_eq!(
+            parse_command("deposit 97c56a4e-0d75-4a82-b683-628b8c219fa3 12.345").unwrap_err(),
+            ParseError::InvalidArgumentMoney {
+                name: "amount".to_string(),
+                e: money::Error::TooManyDecimals,
             }
         );
     }
@@ -298,17 +1690,20 @@ mod tests {
 
         assert_eq!(
             parse_command("withdraw 97c56a4e-0d75-4a82-b683-628b8c219fa3 test").unwrap_err(),
-            ParseError::InvalidArgumentUint {
+            ParseError::InvalidArgumentMoney {
                 name: "amount".to_string(),
-                e: "test".parse::<u64>().unwrap_err(),
+                e: money::Error::InvalidFormat,
             }
         );
 
         assert_eq!(
             parse_command("withdraw 97c56a4e-0d75-4a82-b683-628b8c219fa3 150").unwrap(),
             Command::Withdraw {
-                id: AccountID::parse_str("97c56a4e-0d75-4a82-b683-628b8c219fa3").unwrap(),
-                balance: 150
+                id: AccountID::parse_str("97c56a4e-0d75-4a82-b683-628b8c219fa3")
+                    .unwrap()
+                    .into(),
+                balance: Money::from_minor(15000),
+                key: None
             }
         );
     }
@@ -344,18 +1739,29 @@ mod tests {
 
         assert_eq!(
             parse_command("transfer 97c56a4e-0d75-4a82-b683-628b8c219fa3 12c56a4e-0d75-5a82-b683-728d8c219fa3 test").unwrap_err(),
-            ParseError::InvalidArgumentUint{
+            ParseError::InvalidArgumentMoney {
                 name: "amount".to_string(),
-                e: "test".parse::<u64>().unwrap_err(),
+                e: money::Error::InvalidFormat,
             }
         );
 
         assert_eq!(
             parse_command("transfer 97c56a4e-0d75-4a82-b683-628b8c219fa3 12c56a4e-0d75-5a82-b683-728d8c219fa3 1000").unwrap(),
             Command::Transfer {
-                sender: AccountID::parse_str("97c56a4e-0d75-4a82-b683-628b8c219fa3").unwrap(),
-                receiver: AccountID::parse_str("12c56a4e-0d75-5a82-b683-728d8c219fa3").unwrap(),
-                amount: 1000
+                sender: AccountID::parse_str("97c56a4e-0d75-4a82-b683-628b8c219fa3").unwrap().into(),
+                receiver: AccountID::parse_str("12c56a4e-0d75-5a82-b683-728d8c219fa3").unwrap().into(),
+                amount: Money::from_minor(100000),
+                key: None
+            }
+        );
+
+        assert_eq!(
+            parse_command("transfer 97c56a4e-0d75-4a82-b683-628b8c219fa3 12c56a4e-0d75-5a82-b683-728d8c219fa3 1000 rent-2024-05").unwrap(),
+            Command::Transfer {
+                sender: AccountID::parse_str("97c56a4e-0d75-4a82-b683-628b8c219fa3").unwrap().into(),
+                receiver: AccountID::parse_str("12c56a4e-0d75-5a82-b683-728d8c219fa3").unwrap().into(),
+                amount: Money::from_minor(100000),
+                key: Some("rent-2024-05".parse().unwrap())
             }
         );
     }
@@ -380,7 +1786,39 @@ mod tests {
         assert_eq!(
             parse_command("list_account_operations 97c56a4e-0d75-4a82-b683-628b8c219fa3").unwrap(),
             Command::ListAccountOperations {
-                id: AccountID::parse_str("97c56a4e-0d75-4a82-b683-628b8c219fa3").unwrap()
+                id: AccountID::parse_str("97c56a4e-0d75-4a82-b683-628b8c219fa3")
+                    .unwrap()
+                    .into(),
+                query: OperationsQuery::default(),
+                counterparty: None,
+            }
+        );
+
+        assert_eq!(
+            parse_command(
+                "list_account_operations 97c56a4e-0d75-4a82-b683-628b8c219fa3 counterparty=0b9ee2f5-7d3c-4b61-9a5e-2f3c8d4e6a10 limit=5"
+            )
+            .unwrap(),
+            Command::ListAccountOperations {
+                id: AccountID::parse_str("97c56a4e-0d75-4a82-b683-628b8c219fa3").unwrap().into(),
+                query: OperationsQuery {
+                    limit: Some(5),
+                    ..OperationsQuery::default()
+                },
+                counterparty: Some(
+                    AccountID::parse_str("0b9ee2f5-7d3c-4b61-9a5e-2f3c8d4e6a10")
+                        .unwrap()
+                        .into()
+                ),
+            }
+        );
+
+        assert_eq!(
+            parse_command("list_account_operations @jane counterparty=@joe").unwrap(),
+            Command::ListAccountOperations {
+                id: AccountRef::Alias("jane".parse().unwrap()),
+                query: OperationsQuery::default(),
+                counterparty: Some(AccountRef::Alias("joe".parse().unwrap())),
             }
         );
     }
@@ -389,7 +1827,50 @@ mod tests {
     fn parse_command_list_all_operations_works() {
         assert_eq!(
             parse_command("list_all_operations").unwrap(),
-            Command::ListAllOperations
+            Command::ListAllOperations {
+                query: OperationsQuery::default(),
+                counterparty: None,
+            }
+        );
+
+        assert_eq!(
+            parse_command(
+                "list_all_operations kind=transfer min_amount=10 max_amount=99.99 after=0b9ee2f5-7d3c-4b61-9a5e-2f3c8d4e6a10 offset=20 limit=10"
+            )
+            .unwrap(),
+            Command::ListAllOperations {
+                query: OperationsQuery {
+                    kind: Some("transfer"),
+                    min_amount: Some(Money::from_minor(1000)),
+                    max_amount: Some(Money::from_minor(9999)),
+                    counterparty: None,
+                    after: Some(
+                        OperationID::parse_str("0b9ee2f5-7d3c-4b61-9a5e-2f3c8d4e6a10").unwrap()
+                    ),
+                    offset: 20,
+                    limit: Some(10),
+                },
+                counterparty: None,
+            }
+        );
+
+        assert_eq!(
+            parse_command("list_all_operations kind=loan").unwrap_err(),
+            ParseError::InvalidArgumentKind {
+                name: "kind".to_string()
+            }
+        );
+        assert_eq!(
+            parse_command("list_all_operations limit").unwrap_err(),
+            ParseError::InvalidOption {
+                option: "limit".to_string()
+            }
+        );
+        assert_eq!(
+            parse_command("list_all_operations page=2").unwrap_err(),
+            ParseError::InvalidOption {
+                option: "page=2".to_string()
+            }
         );
     }
 
@@ -449,6 +1930,175 @@ mod tests {
         assert_eq!(parse_command("which_bank").unwrap(), Command::WhichBank);
     }
 
+    #[test]
+    fn parse_command_fees_works() {
+        assert_eq!(
+            parse_command("set_fee transfer capped:0.01:5").unwrap(),
+            Command::SetFee {
+                operation: FeeOperation::Transfer,
+                fee: Some(Fee::CappedPercentage {
+                    rate: "0.01".parse().unwrap(),
+                    cap: Money::from_minor(500),
+                }),
+            },
+        );
+        assert_eq!(
+            parse_command("set_fee withdraw none").unwrap(),
+            Command::SetFee {
+                operation: FeeOperation::Withdraw,
+                fee: None,
+            },
+        );
+        assert_eq!(
+            parse_command("set_fee deposit flat:1").unwrap_err(),
+            ParseError::InvalidArgumentFee {
+                name: "operation".to_string(),
+                e: fee::Error::InvalidOperation,
+            },
+        );
+        assert_eq!(
+            parse_command("set_fee withdraw 1").unwrap_err(),
+            ParseError::InvalidArgumentFee {
+                name: "fee".to_string(),
+                e: fee::Error::InvalidFee,
+            },
+        );
+
+        assert_eq!(parse_command("list_fees").unwrap(), Command::ListFees);
+        assert_eq!(
+            parse_command("fee_account eur").unwrap(),
+            Command::GetFeeAccount {
+                currency: "EUR".parse().unwrap()
+            },
+        );
+    }
+
+    #[test]
+    fn parse_command_interest_works() {
+        let id = AccountID::new();
+
+        assert_eq!(
+            parse_command(&format!("set_interest_rate {} 0.035", id)).unwrap(),
+            Command::SetInterestRate {
+                id: id.into(),
+                rate: "0.035".parse().unwrap(),
+            },
+        );
+        assert_eq!(
+            parse_command(&format!("set_interest_rate {}", id)).unwrap_err(),
+            ParseError::RequireArguments {
+                args: vec!["account_id".to_string(), "annual_rate".to_string()]
+            },
+        );
+
+        assert_eq!(
+            parse_command("accrue_interest 30").unwrap(),
+            Command::AccrueInterest { days: 30 },
+        );
+        assert!(matches!(
+            parse_command("accrue_interest -1").unwrap_err(),
+            ParseError::InvalidArgumentUint { .. }
+        ));
+    }
+
+    #[test]
+    fn parse_command_schedule_transfer_works() {
+        let sender = AccountID::new();
+        let receiver = AccountID::new();
+
+        assert_eq!(
+            parse_command(&format!("schedule_transfer {} {} 10", sender, receiver)).unwrap_err(),
+            ParseError::RequireArguments {
+                args: vec![
+                    "sender_account_id".to_string(),
+                    "receiver_account_id".to_string(),
+                    "amount".to_string(),
+                    "delay".to_string(),
+                ]
+            },
+        );
+
+        assert_eq!(
+            parse_command(&format!("schedule_transfer {} {} 10 1h", sender, receiver)).unwrap(),
+            Command::ScheduleTransfer {
+                sender: sender.into(),
+                receiver: receiver.into(),
+                amount: Money::from_minor(1000),
+                delay: 3600,
+                interval: None,
+            },
+        );
+
+        assert_eq!(
+            parse_command(&format!(
+                "schedule_transfer {} {} 10 0 30d",
+                sender, receiver
+            ))
+            .unwrap(),
+            Command::ScheduleTransfer {
+                sender: sender.into(),
+                receiver: receiver.into(),
+                amount: Money::from_minor(1000),
+                delay: 0,
+                interval: Some(30 * 24 * 60 * 60),
+            },
+        );
+
+        assert_eq!(
+            parse_command(&format!(
+                "schedule_transfer {} {} 10 1.5h",
+                sender, receiver
+            ))
+            .unwrap_err(),
+            ParseError::InvalidArgumentDuration {
+                name: "delay".to_string()
+            },
+        );
+
+        assert_eq!(
+            parse_command(&format!(
+                "schedule_transfer {} {} 10 1h 2y",
+                sender, receiver
+            ))
+            .unwrap_err(),
+            ParseError::InvalidArgumentDuration {
+                name: "interval".to_string()
+            },
+        );
+    }
+
+    #[test]
+    fn parse_command_schedules_works() {
+        let id = ScheduleID::new();
+
+        assert_eq!(
+            parse_command("list_schedules").unwrap(),
+            Command::ListSchedules
+        );
+        assert_eq!(
+            parse_command(&format!("cancel_schedule {}", id)).unwrap(),
+            Command::CancelSchedule { id },
+        );
+        assert_eq!(
+            parse_command("cancel_schedule").unwrap_err(),
+            ParseError::RequireArguments {
+                args: vec!["schedule_id".to_string()]
+            },
+        );
+    }
+
+    #[test]
+    fn parse_command_verify_works() {
+        assert_eq!(parse_command("verify").unwrap(), Command::Verify);
+    }
+
+    #[test]
+    fn parse_command_batch_works() {
+        assert_eq!(parse_command("begin").unwrap(), Command::Begin);
+        assert_eq!(parse_command("commit").unwrap(), Command::Commit);
+        assert_eq!(parse_command("rollback").unwrap(), Command::Rollback);
+    }
+
     #[test]
     fn parse_command_quit_works() {
         assert_eq!(parse_command("quit").unwrap(), Command::Quit);
@@ -1,10 +1,12 @@
 use crate::bank::Bank;
+use crate::server::actor::{Job, Request, Response};
 use crate::server::command::{parse_command, Command, ParseError};
+use crate::server::repository::Session;
 use std::io::Write;
 use tokio::sync::mpsc::UnboundedSender;
 use tokio::{
     io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader},
-    sync::oneshot::{channel, Sender},
+    sync::oneshot::channel,
 };
 
 #[derive(Default, Clone, Debug)]
@@ -27,38 +29,118 @@ async fn handle_help<W: AsyncWriteExt + Unpin>(writer: &mut W) -> Result<()> {
   change_bank <bank_id>
   restore_bank <bank_id>
   which_bank
-  register_account <balance>
-  new_account <balance> - alias for register_account
+  register_account <balance> [currency]
+  new_account <balance> [currency] - alias for register_account
+  set_exchange_rate <from_currency> <to_currency> <rate>
+  reverse <operation_id> - undo deposit, withdraw or transfer, fees are not refunded
+  set_fee <withdraw|transfer> <none|flat:amount|percentage:rate|capped:rate:cap>
+  list_fees
+  fee_account [currency] - id of the account collecting fees
+  set_limit <account_id|bank> <per_transaction|daily> <amount|none> - cap withdrawals and transfers
+  get_limits [account_id] - limits in effect and the outflow of the last 24 hours
+  set_overdraft_limit <account_id> <limit>
+  set_interest_rate <account_id> <annual_rate> - make it a savings account
+  accrue_interest <days> - credit interest to all savings accounts
+  freeze_account <account_id>
+  unfreeze_account <account_id>
+  close_account <account_id> [sweep_to_account_id]
   get_balance <account_id>
-  deposit <account_id> <amount>
-  withdraw <account_id> <amount>
-  transfer <sender_account_id> <receiver_account_id> <amount>
-  list_account_operations <account_id>
+  get_balance_at <account_id> <operation_id|time> - balance right after the operation or at the time
+  statement <account_id> [csv|json] - postings with running balance
+  update_profile <account_id> <name|email|alias> <value|none>
+  get_profile <account_id>
+  deposit <account_id> <amount> [idempotency_key]
+  withdraw <account_id> <amount> [idempotency_key]
+  transfer <sender_account_id> <receiver_account_id> <amount> [idempotency_key]
+  interbank_transfer <sender_account_id> <bank_id> <receiver_account_id> <amount> - to another bank, returns its OpID
+  hold <account_id> <amount> - reserve funds, returns hold id
+  capture <hold_id> [amount] - debit the hold or its part, the rest is released
+  release <hold_id>
+  schedule_transfer <sender_account_id> <receiver_account_id> <amount> <delay> [interval]
+  list_schedules
+  cancel_schedule <schedule_id>
+  list_account_operations <account_id> [option=value...]
   get_account_operations <account_id> - alias for list_account_operations
-  list_all_operations
+  list_all_operations [option=value...]
   get_all_operations - alias for list_all_operations
+  verify - replay the log and reconcile balances of every currency, lists discrepancies
+  begin - start a batch, next commands are queued
+  commit - apply queued commands all together or none of them
+  rollback - drop queued commands
   quit
 
+Account id can also be given as @alias, e.g. get_balance @jane
+Amounts are decimal with at most 2 fraction digits, e.g. 12.34
+Operation repeated with the same idempotency key returns the original OpID, the key fails for any other operation
+Daily limit counts withdrawals and transfers of the last 24 hours and open holds, account limits override bank ones
+Balances before the last checkpoint are unavailable to get_balance_at
+Annual rate is a fraction, e.g. 0.035 for 3.5%, interest is rounded half to even
+Listing options: kind, min_amount, max_amount, counterparty, after (operation id), offset, limit
+Time is UTC, e.g. 2024-03-01T12:30:00Z, or a date meaning its start, e.g. 2024-03-01
+Delay and interval are seconds or have a unit suffix: 90s, 15m, 12h, 30d, 1w
+
 ";
     writer.write_all(help).await?;
 
     Ok(())
 }
 
+async fn handle_job<W: AsyncWriteExt + Unpin>(
+    sender: &UnboundedSender<Request>,
+    session: &mut Session,
+    job: Job,
+    writer: &mut W,
+) -> Result<()> {
+    let (response_sender, response_receiver) = channel::<Response>();
+    sender.send((job, *session, response_sender))?;
+    let (updated_session, response) = response_receiver.await?;
+    *session = updated_session;
+    writer.write_all(response.as_bytes()).await?;
+
+    Ok(())
+}
+
+async fn handle_batch_error<W: AsyncWriteExt + Unpin>(error: &str, writer: &mut W) -> Result<()> {
+    writer
+        .write_all(format!("Status: error\nType: batch\nError: {}\n\n", error).as_bytes())
+        .await?;
+
+    Ok(())
+}
+
 async fn handle_command<W: AsyncWriteExt + Unpin>(
-    sender: &UnboundedSender<(Command, Sender<String>)>,
+    sender: &UnboundedSender<Request>,
+    session: &mut Session,
+    batch: &mut Option<Vec<Command>>,
     command: &Command,
     writer: &mut W,
 ) -> Result<()> {
-    match *command {
-        Command::Quit => handle_quit(writer).await?,
-        Command::Help => handle_help(writer).await?,
-        _ => {
-            let (response_sender, response_receiver) = channel::<String>();
-            sender.send((*command, response_sender))?;
-            let response = response_receiver.await?;
-            writer.write_all(response.as_bytes()).await?;
+    match (*command, batch.as_mut()) {
+        (Command::Quit, _) => handle_quit(writer).await?,
+        (Command::Help, _) => handle_help(writer).await?,
+        (Command::Begin, Some(_)) => handle_batch_error("batch already started", writer).await?,
+        (Command::Begin, None) => {
+            *batch = Some(Vec::new());
+            writer.write_all(b"Status: ok\n\n").await?;
+        }
+        (Command::Commit | Command::Rollback, None) => {
+            handle_batch_error("no batch started", writer).await?
         }
+        (Command::Commit, Some(_)) => {
+            let commands = batch.take().unwrap_or_default();
+            handle_job(sender, session, Job::Batch(commands), writer).await?;
+        }
+        (Command::Rollback, Some(_)) => {
+            *batch = None;
+            writer.write_all(b"Status: ok\n\n").await?;
+        }
+        (command, Some(commands)) => {
+            commands.push(command);
+            writer
+                .write_all(format!("Status: queued\nIndex: {}\n\n", commands.len()).as_bytes())
+                .await?;
+        }
+        (command, None) => handle_job(sender, session, Job::Command(command), writer).await?,
     };
 
     Ok(())
@@ -84,7 +166,7 @@ async fn handle_parse_error<W: AsyncWriteExt + Unpin>(
 }
 
 pub async fn handle<Reader, Writer, Terminal>(
-    sender: &UnboundedSender<(Command, Sender<String>)>,
+    sender: &UnboundedSender<Request>,
     reader: Reader,
     writer: &mut Writer,
     terminal: &mut Terminal,
@@ -95,6 +177,8 @@ where
     Terminal: Write,
 {
     let mut reader = BufReader::new(reader);
+    let mut session = Session::default();
+    let mut batch = None;
 
     loop {
         let mut line = String::new();
@@ -105,7 +189,7 @@ where
             }
             Ok(_) => match parse_command(&line) {
                 Ok(command) => {
-                    handle_command(sender, &command, writer).await?;
+                    handle_command(sender, &mut session, &mut batch, &command, writer).await?;
                     if command == Command::Quit {
                         terminal.write_all("Client quited\n".as_bytes())?;
                         break;
@@ -134,7 +218,7 @@ mod tests {
     async fn unknown_command_works() {
         let mut terminal = Vec::new();
 
-        let (sender, _) = unbounded_channel::<(Command, Sender<String>)>();
+        let (sender, _) = unbounded_channel::<Request>();
 
         let reader = "test_command".as_bytes();
         let mut writer = Vec::new();
@@ -152,7 +236,7 @@ mod tests {
     #[tokio::test]
     async fn handle_empty_command_works() {
         let mut terminal = Vec::new();
-        let (sender, _) = unbounded_channel::<(Command, Sender<String>)>();
+        let (sender, _) = unbounded_channel::<Request>();
 
         let reader = "".as_bytes();
         let mut writer = Vec::new();
@@ -170,7 +254,7 @@ mod tests {
     #[tokio::test]
     async fn handle_quit_command_works() {
         let mut terminal = Vec::new();
-        let (sender, _) = unbounded_channel::<(Command, Sender<String>)>();
+        let (sender, _) = unbounded_channel::<Request>();
 
         let reader = "quit".as_bytes();
         let mut writer = Vec::new();
@@ -187,19 +271,58 @@ mod tests {
         assert_eq!("Bye bye\n\n", from_utf8(writer.as_slice()).unwrap());
     }
 
+    #[tokio::test]
+    async fn handle_batch_works() {
+        let mut terminal = Vec::new();
+        let (sender, mut receiver) = unbounded_channel::<Request>();
+
+        let reader =
+            "commit\nbegin\nbegin\nnew_bank\nwhich_bank\nrollback\nbegin\nnew_bank\ncommit"
+                .as_bytes();
+        let mut writer = Vec::new();
+
+        tokio::spawn(async move {
+            let (job, session, response_sender) = receiver.recv().await.unwrap();
+            assert_eq!(job, Job::Batch(vec![Command::NewBank]));
+            response_sender
+                .send((session, "Response from command actor\n\n".to_owned()))
+                .unwrap();
+        });
+
+        handle(&sender, reader, &mut writer, &mut terminal)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            [
+                "Status: error\nType: batch\nError: no batch started\n\n",
+                "Status: ok\n\n",
+                "Status: error\nType: batch\nError: batch already started\n\n",
+                "Status: queued\nIndex: 1\n\n",
+                "Status: queued\nIndex: 2\n\n",
+                "Status: ok\n\n",
+                "Status: ok\n\n",
+                "Status: queued\nIndex: 1\n\n",
+                "Response from command actor\n\n",
+            ]
+            .concat(),
+            from_utf8(writer.as_slice()).unwrap()
+        );
+    }
+
     #[tokio::test]
     async fn handle_any_other_legal_command_works() {
         let mut terminal = Vec::new();
-        let (sender, mut receiver) = unbounded_channel::<(Command, Sender<String>)>();
+        let (sender, mut receiver) = unbounded_channel::<Request>();
 
         let reader = "new_bank".as_bytes();
         let mut writer = Vec::new();
 
         tokio::spawn(async move {
-            let (command, response_sender) = receiver.recv().await.unwrap();
-            assert_eq!(command, Command::NewBank);
+            let (job, session, response_sender) = receiver.recv().await.unwrap();
+            assert_eq!(job, Job::Command(Command::NewBank));
             response_sender
-                .send("Response from command actor\n\n".to_owned())
+                .send((session, "Response from command actor\n\n".to_owned()))
                 .unwrap();
         });
 
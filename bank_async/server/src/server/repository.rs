@@ -1,11 +1,38 @@
-use crate::bank::account::{Account, AccountID};
-use crate::bank::log::{Operation, OperationID};
+use crate::bank::account::{Account, AccountID, AccountStatus};
+use crate::bank::clock::{SharedClock, Timestamp};
+use crate::bank::currency::{Currency, Rate};
+use crate::bank::fee::{Fee, FeeOperation};
+use crate::bank::hold::HoldID;
+use crate::bank::idempotency::{IdempotencyKey, KeyedRequest};
+use crate::bank::limit::{LimitKind, Limits};
+use crate::bank::log::{Cutoff, Operation, OperationID, OperationsQuery};
+use crate::bank::money::Money;
+use crate::bank::profile::{Alias, Profile, ProfileField};
+use crate::bank::schedule::{Schedule, ScheduleID};
+use crate::bank::snapshot::Snapshot;
+use crate::bank::statement::StatementEntry;
+use crate::bank::verify::VerifyReport;
 use crate::bank::{Bank, BankError};
+use crate::server::checkpoint::Checkpoint;
+use crate::server::wal::{Record, Wal, WalError};
+use std::path::Path;
 
-#[derive(Debug, PartialEq)]
+/// Number of log records after which repository takes a new checkpoint.
+pub const SNAPSHOT_INTERVAL: usize = 1000;
+
+#[derive(Debug)]
 pub enum RepositoryError {
     InvalidBankId,
     BankError(BankError),
+    StorageError(WalError),
+    NotBatchable,
+    SameBank,
+    /// Scheduled run failed with `error` and skipping it could not be stored,
+    /// so the schedule stays due.
+    SkipNotStored {
+        error: BankError,
+        storage: WalError,
+    },
 }
 
 impl std::fmt::Display for RepositoryError {
@@ -13,6 +40,14 @@ impl std::fmt::Display for RepositoryError {
         match self {
             RepositoryError::InvalidBankId => write!(f, "Invalid bank id"),
             RepositoryError::BankError(e) => write!(f, "Bank error: {}", e),
+            RepositoryError::StorageError(e) => write!(f, "Storage error: {}", e),
+            RepositoryError::NotBatchable => write!(f, "Command can't be used in a batch"),
+            RepositoryError::SameBank => write!(f, "Receiver is in the same bank"),
+            RepositoryError::SkipNotStored { error, storage } => write!(
+                f,
+                "Bank error: {}, run is not skipped due to storage error: {}",
+                error, storage
+            ),
         }
     }
 }
@@ -21,134 +56,812 @@ impl std::error::Error for RepositoryError {}
 
 pub type Result<T> = std::result::Result<T, RepositoryError>;
 
-#[derive(Default, Clone)]
+/// Per-connection state: every client works with its own current bank.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Session {
+    pub current_bank: usize,
+}
+
+// Operations of an open batch are applied to the bank right away,
+// but reach the log only when the whole batch is committed.
+struct Batch {
+    bank: usize,
+    original: Bank,
+    records: Vec<Record>,
+}
+
+#[derive(Default)]
 pub struct Repository {
     pub banks: Vec<Bank>,
-    pub current_bank: usize,
+    pub snapshot_interval: usize,
+    wal: Option<Wal>,
+    records_since_snapshot: usize,
+    batch: Option<Batch>,
+    clock: SharedClock,
 }
 
 impl Repository {
-    pub fn current_bank_id(&self) -> usize {
-        if self.banks.is_empty() {
-            0
-        } else {
-            self.current_bank + 1
+    /// Opens repository stored in `dir`: banks are loaded from the latest
+    /// checkpoint and only the log written after it is replayed.
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<Repository> {
+        let checkpoint = Checkpoint::load(&dir)
+            .map_err(RepositoryError::StorageError)?
+            .unwrap_or_default();
+
+        let (wal, records) =
+            Wal::open(&dir, checkpoint.segment).map_err(RepositoryError::StorageError)?;
+
+        let mut banks_state: Vec<(Snapshot, Vec<Operation>)> = checkpoint
+            .banks
+            .into_iter()
+            .map(|snapshot| (snapshot, Vec::new()))
+            .collect();
+
+        let records_since_snapshot = records.len();
+        for record in records {
+            match record {
+                Record::NewBank => banks_state.push((Snapshot::default(), Vec::new())),
+                Record::RestoreBank { id } => {
+                    let state = Self::bank_index(banks_state.len(), id)
+                        .map(|idx| banks_state[idx].clone())?;
+                    banks_state.push(state);
+                }
+                Record::Operation { bank_id, operation } => {
+                    let idx = Self::bank_index(banks_state.len(), bank_id)?;
+                    banks_state[idx].1.push(operation);
+                }
+                // markers are consumed by the log itself
+                Record::Batch { .. } => {}
+            }
         }
+
+        let banks = banks_state
+            .into_iter()
+            .map(|(snapshot, operations)| Bank::restore_from_snapshot(snapshot, operations.iter()))
+            .collect::<std::result::Result<Vec<Bank>, BankError>>()
+            .map_err(RepositoryError::BankError)?;
+
+        Ok(Repository {
+            banks,
+            snapshot_interval: SNAPSHOT_INTERVAL,
+            wal: Some(wal),
+            records_since_snapshot,
+            batch: None,
+            clock: SharedClock::default(),
+        })
     }
 
-    pub fn new_bank(&mut self) -> usize {
-        self.banks.push(Bank::default());
-        self.current_bank = self.banks.len() - 1;
-        self.current_bank + 1
+    /// Replaces clock of the repository and all its banks.
+    pub fn set_clock(&mut self, clock: SharedClock) {
+        for bank in self.banks.iter_mut() {
+            bank.set_clock(clock.clone());
+        }
+        self.clock = clock;
     }
 
-    pub fn change_bank(&mut self, id: u64) -> Result<()> {
-        if id < 1 || id > self.banks.len() as u64 {
+    pub fn now(&self) -> Timestamp {
+        self.clock.now()
+    }
+
+    /// Saves snapshots of all banks and drops log segments they cover.
+    pub fn snapshot(&mut self) -> Result<()> {
+        let wal = match self.wal.as_mut() {
+            Some(wal) => wal,
+            None => return Ok(()),
+        };
+
+        // new records go to the fresh segment, so everything before it
+        // is covered by the checkpoint once it is saved
+        let segment = wal.rotate().map_err(RepositoryError::StorageError)?;
+
+        let checkpoint = Checkpoint {
+            segment,
+            banks: self.banks.iter().map(Bank::snapshot).collect(),
+        };
+
+        checkpoint
+            .save(wal.dir())
+            .map_err(RepositoryError::StorageError)?;
+        wal.compact(segment)
+            .map_err(RepositoryError::StorageError)?;
+
+        self.records_since_snapshot = 0;
+        Ok(())
+    }
+
+    fn maybe_snapshot(&mut self) {
+        if self.batch.is_some()
+            || self.snapshot_interval == 0
+            || self.records_since_snapshot < self.snapshot_interval
+        {
+            return;
+        }
+
+        // log already holds everything, failed snapshot only delays compaction
+        if let Err(e) = self.snapshot() {
+            eprintln!("Error taking snapshot: {}", e);
+        }
+    }
+
+    fn bank_index(banks_count: usize, id: u64) -> Result<usize> {
+        if id < 1 || id > banks_count as u64 {
             return Err(RepositoryError::InvalidBankId);
         }
 
-        let new_current_bank = (id - 1) as usize;
-        self.current_bank = new_current_bank;
+        Ok((id - 1) as usize)
+    }
+
+    fn append(&mut self, record: Record) -> Result<()> {
+        if let Some(batch) = self.batch.as_mut() {
+            batch.records.push(record);
+            return Ok(());
+        }
 
+        match self.wal.as_mut() {
+            Some(wal) => wal.append(&record).map_err(RepositoryError::StorageError)?,
+            None => return Ok(()),
+        }
+
+        self.records_since_snapshot += 1;
         Ok(())
     }
 
-    pub fn restore_bank(&mut self, id: u64) -> Result<()> {
-        if id < 1 || id > self.banks.len() as u64 {
-            return Err(RepositoryError::InvalidBankId);
+    fn operation_record(&self, bank: usize, operation_id: OperationID) -> Record {
+        Record::Operation {
+            bank_id: bank as u64 + 1,
+            operation: *self.banks[bank].get_operation(operation_id).unwrap(),
         }
+    }
+
+    // Rebuilds the bank without its latest operation.
+    fn drop_last_operation(&mut self, bank: usize) -> Result<()> {
+        let src_bank = &self.banks[bank];
+        let count = src_bank.get_all_operations().count();
+        let mut new_bank = Bank::restore_from_snapshot(
+            src_bank.base_snapshot().clone(),
+            src_bank.get_all_operations().take(count - 1),
+        )
+        .map_err(RepositoryError::BankError)?;
+        new_bank.set_clock(self.clock.clone());
+        self.banks[bank] = new_bank;
 
-        let src_bank = &self.banks[(id - 1) as usize];
+        Ok(())
+    }
+
+    // Persists operation just applied to the current bank.
+    // If it could not be written, it is dropped from memory too,
+    // so the client never sees a change that would be lost on restart.
+    fn commit(&mut self, current_bank: usize, operation_id: OperationID) -> Result<OperationID> {
+        let record = self.operation_record(current_bank, operation_id);
+
+        if let Err(e) = self.append(record) {
+            self.drop_last_operation(current_bank)?;
+            return Err(e);
+        }
+
+        self.maybe_snapshot();
+        Ok(operation_id)
+    }
+
+    /// Starts a batch on the session's bank: following operations are kept
+    /// only if `commit_batch` succeeds, `rollback_batch` drops all of them.
+    pub fn begin_batch(&mut self, session: &mut Session) -> Result<()> {
+        let current_bank = self.session_bank(session)?;
+        self.batch = Some(Batch {
+            bank: current_bank,
+            original: self.banks[current_bank].clone(),
+            records: Vec::new(),
+        });
+
+        Ok(())
+    }
 
-        match Bank::restore(src_bank.get_all_operations()) {
-            Ok(new_bank) => {
-                self.banks.push(new_bank);
-                self.current_bank = self.banks.len() - 1;
-                Ok(())
+    pub fn commit_batch(&mut self) -> Result<()> {
+        let batch = match self.batch.take() {
+            Some(batch) => batch,
+            None => return Ok(()),
+        };
+
+        if let Some(wal) = self.wal.as_mut() {
+            if let Err(e) = wal.append_batch(&batch.records) {
+                self.banks[batch.bank] = batch.original;
+                return Err(RepositoryError::StorageError(e));
             }
-            Err(e) => Err(RepositoryError::BankError(e)),
+            self.records_since_snapshot += batch.records.len();
         }
+
+        self.maybe_snapshot();
+        Ok(())
     }
 
-    pub fn register_account(&mut self, balance: u64) -> Result<(AccountID, OperationID)> {
-        if self.banks.is_empty() {
-            self.new_bank();
+    pub fn rollback_batch(&mut self) {
+        if let Some(batch) = self.batch.take() {
+            self.banks[batch.bank] = batch.original;
         }
+    }
 
-        let current_bank = self.current_bank;
+    // Applies `request` to the session's bank and commits it.
+    // A key that was already used for the request returns its operation and writes nothing.
+    fn apply_with_key(
+        &mut self,
+        session: &mut Session,
+        key: Option<IdempotencyKey>,
+        request: KeyedRequest,
+    ) -> Result<OperationID> {
+        let current_bank = self.session_bank(session)?;
         let bank = &mut self.banks[current_bank];
-        let account = Account::new(balance);
 
-        match bank.register_account(account) {
-            Ok(operation_id) => Ok((account.id, operation_id)),
-            Err(e) => Err(RepositoryError::BankError(e)),
+        let operation_id = match key {
+            Some(key) => {
+                if let Some(operation_id) = bank
+                    .find_keyed(key, request)
+                    .map_err(RepositoryError::BankError)?
+                {
+                    return Ok(operation_id);
+                }
+                bank.with_key(key, request)
+            }
+            None => bank.apply_request(request),
         }
+        .map_err(RepositoryError::BankError)?;
+
+        self.commit(current_bank, operation_id)
     }
 
-    pub fn get_balance(&mut self, id: AccountID) -> Result<u64> {
+    // Returns index of the session's bank, the very first bank is created on demand.
+    fn session_bank(&mut self, session: &mut Session) -> Result<usize> {
         if self.banks.is_empty() {
-            self.new_bank();
+            self.new_bank(session)?;
         }
 
-        let bank = &self.banks[self.current_bank];
-        bank.get_balance(id).map_err(RepositoryError::BankError)
+        Ok(session.current_bank)
     }
 
-    pub fn deposit(&mut self, id: AccountID, amount: u64) -> Result<OperationID> {
+    pub fn current_bank_id(&self, session: &Session) -> usize {
         if self.banks.is_empty() {
-            self.new_bank();
+            0
+        } else {
+            session.current_bank + 1
         }
+    }
+
+    pub fn new_bank(&mut self, session: &mut Session) -> Result<usize> {
+        self.append(Record::NewBank)?;
 
-        let bank = &mut self.banks[self.current_bank];
-        bank.deposit(id, amount).map_err(RepositoryError::BankError)
+        let mut bank = Bank::default();
+        bank.set_clock(self.clock.clone());
+        self.banks.push(bank);
+        session.current_bank = self.banks.len() - 1;
+
+        self.maybe_snapshot();
+        Ok(session.current_bank + 1)
     }
 
-    pub fn withdraw(&mut self, id: AccountID, amount: u64) -> Result<OperationID> {
-        if self.banks.is_empty() {
-            self.new_bank();
+    pub fn change_bank(&self, session: &mut Session, id: u64) -> Result<()> {
+        session.current_bank = Self::bank_index(self.banks.len(), id)?;
+
+        Ok(())
+    }
+
+    pub fn restore_bank(&mut self, session: &mut Session, id: u64) -> Result<()> {
+        let src_bank = &self.banks[Self::bank_index(self.banks.len(), id)?];
+
+        let mut new_bank = Bank::restore_from_snapshot(
+            src_bank.base_snapshot().clone(),
+            src_bank.get_all_operations(),
+        )
+        .map_err(RepositoryError::BankError)?;
+        new_bank.set_clock(self.clock.clone());
+
+        self.append(Record::RestoreBank { id })?;
+
+        self.banks.push(new_bank);
+        session.current_bank = self.banks.len() - 1;
+
+        self.maybe_snapshot();
+        Ok(())
+    }
+
+    pub fn register_account(
+        &mut self,
+        session: &mut Session,
+        balance: Money,
+        currency: Currency,
+    ) -> Result<(AccountID, OperationID)> {
+        let current_bank = self.session_bank(session)?;
+        let bank = &mut self.banks[current_bank];
+        let account = Account::with_currency(balance, currency);
+
+        let operation_id = bank
+            .register_account(account)
+            .map_err(RepositoryError::BankError)?;
+
+        self.commit(current_bank, operation_id)
+            .map(|operation_id| (account.id, operation_id))
+    }
+
+    pub fn set_exchange_rate(
+        &mut self,
+        session: &mut Session,
+        from: Currency,
+        to: Currency,
+        rate: Rate,
+    ) -> Result<OperationID> {
+        let current_bank = self.session_bank(session)?;
+        let bank = &mut self.banks[current_bank];
+        let operation_id = bank
+            .set_exchange_rate(from, to, rate)
+            .map_err(RepositoryError::BankError)?;
+
+        self.commit(current_bank, operation_id)
+    }
+
+    pub fn set_fee(
+        &mut self,
+        session: &mut Session,
+        operation: FeeOperation,
+        fee: Option<Fee>,
+    ) -> Result<OperationID> {
+        let current_bank = self.session_bank(session)?;
+        let bank = &mut self.banks[current_bank];
+        let operation_id = bank
+            .set_fee(operation, fee)
+            .map_err(RepositoryError::BankError)?;
+
+        self.commit(current_bank, operation_id)
+    }
+
+    pub fn get_fees(&mut self, session: &mut Session) -> Result<Vec<(FeeOperation, Fee)>> {
+        let current_bank = self.session_bank(session)?;
+        Ok(self.banks[current_bank].get_fees())
+    }
+
+    pub fn set_limit(
+        &mut self,
+        session: &mut Session,
+        id: Option<AccountID>,
+        kind: LimitKind,
+        limit: Option<Money>,
+    ) -> Result<OperationID> {
+        let current_bank = self.session_bank(session)?;
+        let bank = &mut self.banks[current_bank];
+        let operation_id = bank
+            .set_limit(id, kind, limit)
+            .map_err(RepositoryError::BankError)?;
+
+        self.commit(current_bank, operation_id)
+    }
+
+    pub fn get_limits(&mut self, session: &mut Session, id: Option<AccountID>) -> Result<Limits> {
+        let current_bank = self.session_bank(session)?;
+        let bank = &self.banks[current_bank];
+        bank.get_limits(id).map_err(RepositoryError::BankError)
+    }
+
+    pub fn get_outflow(&mut self, session: &mut Session, id: AccountID) -> Result<Money> {
+        let current_bank = self.session_bank(session)?;
+        let bank = &self.banks[current_bank];
+        bank.get_outflow(id).map_err(RepositoryError::BankError)
+    }
+
+    pub fn reverse(&mut self, session: &mut Session, id: OperationID) -> Result<OperationID> {
+        let current_bank = self.session_bank(session)?;
+        let bank = &mut self.banks[current_bank];
+        let operation_id = bank.reverse(id).map_err(RepositoryError::BankError)?;
+
+        self.commit(current_bank, operation_id)
+    }
+
+    pub fn set_overdraft_limit(
+        &mut self,
+        session: &mut Session,
+        id: AccountID,
+        limit: Money,
+    ) -> Result<OperationID> {
+        let current_bank = self.session_bank(session)?;
+        let bank = &mut self.banks[current_bank];
+        let operation_id = bank
+            .set_overdraft_limit(id, limit)
+            .map_err(RepositoryError::BankError)?;
+
+        self.commit(current_bank, operation_id)
+    }
+
+    pub fn set_interest_rate(
+        &mut self,
+        session: &mut Session,
+        id: AccountID,
+        rate: Rate,
+    ) -> Result<OperationID> {
+        let current_bank = self.session_bank(session)?;
+        let bank = &mut self.banks[current_bank];
+        let operation_id = bank
+            .set_interest_rate(id, rate)
+            .map_err(RepositoryError::BankError)?;
+
+        self.commit(current_bank, operation_id)
+    }
+
+    /// Accrues interest of the session's bank for `days`,
+    /// operations of all accounts are written as a single batch.
+    pub fn accrue_interest(
+        &mut self,
+        session: &mut Session,
+        days: u32,
+    ) -> Result<Vec<OperationID>> {
+        let current_bank = self.session_bank(session)?;
+        self.begin_batch(session)?;
+
+        let operation_ids = match self.banks[current_bank].accrue_interest(days) {
+            Ok(operation_ids) => operation_ids,
+            Err(e) => {
+                self.rollback_batch();
+                return Err(RepositoryError::BankError(e));
+            }
+        };
+        for operation_id in operation_ids.iter() {
+            self.commit(current_bank, *operation_id)?;
         }
 
-        let bank = &mut self.banks[self.current_bank];
-        bank.withdraw(id, amount)
+        self.commit_batch()?;
+        Ok(operation_ids)
+    }
+
+    pub fn freeze_account(&mut self, session: &mut Session, id: AccountID) -> Result<OperationID> {
+        let current_bank = self.session_bank(session)?;
+        let bank = &mut self.banks[current_bank];
+        let operation_id = bank.freeze(id).map_err(RepositoryError::BankError)?;
+
+        self.commit(current_bank, operation_id)
+    }
+
+    pub fn unfreeze_account(
+        &mut self,
+        session: &mut Session,
+        id: AccountID,
+    ) -> Result<OperationID> {
+        let current_bank = self.session_bank(session)?;
+        let bank = &mut self.banks[current_bank];
+        let operation_id = bank.unfreeze(id).map_err(RepositoryError::BankError)?;
+
+        self.commit(current_bank, operation_id)
+    }
+
+    pub fn close_account(
+        &mut self,
+        session: &mut Session,
+        id: AccountID,
+        sweep_to: Option<AccountID>,
+    ) -> Result<OperationID> {
+        let current_bank = self.session_bank(session)?;
+        let bank = &mut self.banks[current_bank];
+        let operation_id = bank
+            .close(id, sweep_to)
+            .map_err(RepositoryError::BankError)?;
+
+        self.commit(current_bank, operation_id)
+    }
+
+    pub fn update_profile(
+        &mut self,
+        session: &mut Session,
+        id: AccountID,
+        field: ProfileField,
+    ) -> Result<OperationID> {
+        let current_bank = self.session_bank(session)?;
+        let bank = &mut self.banks[current_bank];
+        let operation_id = bank
+            .update_profile(id, field)
+            .map_err(RepositoryError::BankError)?;
+
+        self.commit(current_bank, operation_id)
+    }
+
+    pub fn get_profile(&mut self, session: &mut Session, id: AccountID) -> Result<Profile> {
+        let current_bank = self.session_bank(session)?;
+        let bank = &self.banks[current_bank];
+        bank.get_profile(id).map_err(RepositoryError::BankError)
+    }
+
+    pub fn find_alias(&mut self, session: &mut Session, alias: Alias) -> Result<AccountID> {
+        let current_bank = self.session_bank(session)?;
+        let bank = &self.banks[current_bank];
+        bank.find_alias(alias).map_err(RepositoryError::BankError)
+    }
+
+    pub fn get_balance(&mut self, session: &mut Session, id: AccountID) -> Result<Money> {
+        let current_bank = self.session_bank(session)?;
+        let bank = &self.banks[current_bank];
+        bank.get_balance(id).map_err(RepositoryError::BankError)
+    }
+
+    pub fn get_balance_at(
+        &mut self,
+        session: &mut Session,
+        id: AccountID,
+        cutoff: Cutoff,
+    ) -> Result<Money> {
+        let current_bank = self.session_bank(session)?;
+        let bank = &self.banks[current_bank];
+        bank.get_balance_at(id, cutoff)
             .map_err(RepositoryError::BankError)
     }
 
+    pub fn statement(
+        &mut self,
+        session: &mut Session,
+        id: AccountID,
+    ) -> Result<Vec<StatementEntry>> {
+        let current_bank = self.session_bank(session)?;
+        let bank = &self.banks[current_bank];
+        bank.statement(id).map_err(RepositoryError::BankError)
+    }
+
+    pub fn verify(&mut self, session: &mut Session) -> Result<VerifyReport> {
+        let current_bank = self.session_bank(session)?;
+        let bank = &self.banks[current_bank];
+        bank.verify().map_err(RepositoryError::BankError)
+    }
+
+    pub fn get_status(&mut self, session: &mut Session, id: AccountID) -> Result<AccountStatus> {
+        let current_bank = self.session_bank(session)?;
+        let bank = &self.banks[current_bank];
+        bank.get_status(id).map_err(RepositoryError::BankError)
+    }
+
+    pub fn deposit(
+        &mut self,
+        session: &mut Session,
+        id: AccountID,
+        amount: Money,
+        key: Option<IdempotencyKey>,
+    ) -> Result<OperationID> {
+        self.apply_with_key(session, key, KeyedRequest::Deposit { id, amount })
+    }
+
+    pub fn withdraw(
+        &mut self,
+        session: &mut Session,
+        id: AccountID,
+        amount: Money,
+        key: Option<IdempotencyKey>,
+    ) -> Result<OperationID> {
+        self.apply_with_key(session, key, KeyedRequest::Withdraw { id, amount })
+    }
+
     pub fn transfer(
         &mut self,
+        session: &mut Session,
         sender_id: AccountID,
         receiver_id: AccountID,
-        amount: u64,
+        amount: Money,
+        key: Option<IdempotencyKey>,
     ) -> Result<OperationID> {
-        if self.banks.is_empty() {
-            self.new_bank();
+        self.apply_with_key(
+            session,
+            key,
+            KeyedRequest::Transfer {
+                sender_id,
+                receiver_id,
+                amount,
+            },
+        )
+    }
+
+    /// Moves `amount` from the session's bank to `receiver_id` of bank `bank_id`,
+    /// returns operations of the sending and the receiving leg. Both legs are
+    /// written as a single log batch, so a restart never finds only one of them.
+    pub fn interbank_transfer(
+        &mut self,
+        session: &mut Session,
+        sender_id: AccountID,
+        bank_id: u64,
+        receiver_id: AccountID,
+        amount: Money,
+    ) -> Result<(OperationID, OperationID)> {
+        // a batch rolls back only its own bank
+        if self.batch.is_some() {
+            return Err(RepositoryError::NotBatchable);
+        }
+
+        let current_bank = self.session_bank(session)?;
+        let receiver_bank = Self::bank_index(self.banks.len(), bank_id)?;
+        if receiver_bank == current_bank {
+            return Err(RepositoryError::SameBank);
         }
 
-        let bank = &mut self.banks[self.current_bank];
-        bank.transfer(sender_id, receiver_id, amount)
+        let currency = self.banks[receiver_bank]
+            .get_receiving_currency(receiver_id)
+            .map_err(RepositoryError::BankError)?;
+        let (send_id, received_amount) = self.banks[current_bank]
+            .send_to_bank(sender_id, bank_id, receiver_id, currency, amount)
+            .map_err(RepositoryError::BankError)?;
+
+        let receive_id = match self.banks[receiver_bank].receive_from_bank(
+            send_id,
+            current_bank as u64 + 1,
+            sender_id,
+            receiver_id,
+            received_amount,
+        ) {
+            Ok(receive_id) => receive_id,
+            Err(e) => {
+                self.drop_last_operation(current_bank)?;
+                return Err(RepositoryError::BankError(e));
+            }
+        };
+
+        let records = [
+            self.operation_record(current_bank, send_id),
+            self.operation_record(receiver_bank, receive_id),
+        ];
+        if let Some(wal) = self.wal.as_mut() {
+            if let Err(e) = wal.append_batch(&records) {
+                self.drop_last_operation(current_bank)?;
+                self.drop_last_operation(receiver_bank)?;
+                return Err(RepositoryError::StorageError(e));
+            }
+            self.records_since_snapshot += records.len();
+        }
+
+        self.maybe_snapshot();
+        Ok((send_id, receive_id))
+    }
+
+    pub fn schedule_transfer(
+        &mut self,
+        session: &mut Session,
+        sender_id: AccountID,
+        receiver_id: AccountID,
+        amount: Money,
+        start: Timestamp,
+        interval: Option<u64>,
+    ) -> Result<(ScheduleID, OperationID)> {
+        let current_bank = self.session_bank(session)?;
+        let bank = &mut self.banks[current_bank];
+        let (schedule_id, operation_id) = bank
+            .schedule_transfer(sender_id, receiver_id, amount, start, interval)
+            .map_err(RepositoryError::BankError)?;
+
+        self.commit(current_bank, operation_id)
+            .map(|operation_id| (schedule_id, operation_id))
+    }
+
+    pub fn hold(
+        &mut self,
+        session: &mut Session,
+        id: AccountID,
+        amount: Money,
+    ) -> Result<(HoldID, OperationID)> {
+        let current_bank = self.session_bank(session)?;
+        let bank = &mut self.banks[current_bank];
+        let (hold_id, operation_id) = bank.hold(id, amount).map_err(RepositoryError::BankError)?;
+
+        self.commit(current_bank, operation_id)
+            .map(|operation_id| (hold_id, operation_id))
+    }
+
+    pub fn capture(
+        &mut self,
+        session: &mut Session,
+        id: HoldID,
+        amount: Option<Money>,
+    ) -> Result<OperationID> {
+        let current_bank = self.session_bank(session)?;
+        let bank = &mut self.banks[current_bank];
+        let operation_id = bank
+            .capture(id, amount)
+            .map_err(RepositoryError::BankError)?;
+
+        self.commit(current_bank, operation_id)
+    }
+
+    pub fn release(&mut self, session: &mut Session, id: HoldID) -> Result<OperationID> {
+        let current_bank = self.session_bank(session)?;
+        let bank = &mut self.banks[current_bank];
+        let operation_id = bank.release(id).map_err(RepositoryError::BankError)?;
+
+        self.commit(current_bank, operation_id)
+    }
+
+    pub fn get_available_balance(&mut self, session: &mut Session, id: AccountID) -> Result<Money> {
+        let current_bank = self.session_bank(session)?;
+        let bank = &self.banks[current_bank];
+        bank.get_available_balance(id)
             .map_err(RepositoryError::BankError)
     }
 
-    pub fn get_account_operations(&self, id: AccountID) -> impl Iterator<Item = &Operation> {
+    pub fn cancel_schedule(
+        &mut self,
+        session: &mut Session,
+        id: ScheduleID,
+    ) -> Result<OperationID> {
+        let current_bank = self.session_bank(session)?;
+        let bank = &mut self.banks[current_bank];
+        let operation_id = bank
+            .cancel_schedule(id)
+            .map_err(RepositoryError::BankError)?;
+
+        self.commit(current_bank, operation_id)
+    }
+
+    pub fn get_schedules(&mut self, session: &mut Session) -> Result<Vec<Schedule>> {
+        let current_bank = self.session_bank(session)?;
+        Ok(self.banks[current_bank].get_schedules())
+    }
+
+    /// Executes schedules of all banks that are due at `now`, returns bank id
+    /// and outcome for every execution. Failed runs are skipped, recurring schedules
+    /// are retried at their next run and one-time ones are removed.
+    pub fn run_schedules(
+        &mut self,
+        now: Timestamp,
+    ) -> Vec<(usize, ScheduleID, Result<OperationID>)> {
+        let mut executions = Vec::new();
+
+        for current_bank in 0..self.banks.len() {
+            for schedule_id in self.banks[current_bank].get_due_schedules(now) {
+                let result = match self.banks[current_bank].run_schedule(schedule_id) {
+                    Ok(operation_id) => self.commit(current_bank, operation_id),
+                    // failed run is skipped, so it is not retried on every tick
+                    Err(e) => match self.banks[current_bank].skip_schedule(schedule_id) {
+                        Ok(operation_id) => match self.commit(current_bank, operation_id) {
+                            Err(RepositoryError::StorageError(storage)) => {
+                                Err(RepositoryError::SkipNotStored { error: e, storage })
+                            }
+                            _ => Err(RepositoryError::BankError(e)),
+                        },
+                        Err(_) => Err(RepositoryError::BankError(e)),
+                    },
+                };
+                executions.push((current_bank + 1, schedule_id, result));
+            }
+        }
+
+        executions
+    }
+
+    pub fn get_account_operations(
+        &self,
+        session: &Session,
+        id: AccountID,
+    ) -> impl Iterator<Item = &Operation> {
         let result: Vec<&Operation> = if self.banks.is_empty() {
             Vec::new()
         } else {
-            let bank = &self.banks[self.current_bank];
+            let bank = &self.banks[session.current_bank];
             bank.get_account_operations(id).collect()
         };
 
         result.into_iter()
     }
 
-    pub fn get_all_operations(&self) -> impl Iterator<Item = &Operation> {
+    pub fn get_all_operations(&self, session: &Session) -> impl Iterator<Item = &Operation> {
         let result: Vec<&Operation> = if self.banks.is_empty() {
             Vec::new()
         } else {
-            let bank = &self.banks[self.current_bank];
+            let bank = &self.banks[session.current_bank];
             bank.get_all_operations().collect()
         };
 
         result.into_iter()
     }
+
+    pub fn query_operations(
+        &self,
+        session: &Session,
+        account_id: Option<AccountID>,
+        query: OperationsQuery,
+    ) -> Result<Vec<&Operation>> {
+        if self.banks.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let bank = &self.banks[session.current_bank];
+        bank.query_operations(account_id, query)
+            .map(|operations| operations.collect())
+            .map_err(RepositoryError::BankError)
+    }
 }
 
 #[cfg(test)]
@@ -156,129 +869,287 @@ mod tests {
     use crate::bank::log::OperationKind;
 
     use super::*;
+    use crate::bank::clock::ManualClock;
+    use crate::bank::money::Money;
+    use std::sync::Arc;
 
     #[test]
     fn new_bank_works() {
-        let bank_id = Repository::default().new_bank();
+        let bank_id = Repository::default()
+            .new_bank(&mut Session::default())
+            .unwrap();
         assert_eq!(bank_id, 1);
     }
 
     #[test]
     fn current_bank_id_works() {
         let mut repository = Repository::default();
-        assert_eq!(repository.current_bank_id(), 0);
+        let mut session = Session::default();
+        assert_eq!(repository.current_bank_id(&session), 0);
 
-        repository.new_bank();
-        assert_eq!(repository.current_bank_id(), 1);
+        repository.new_bank(&mut session).unwrap();
+        assert_eq!(repository.current_bank_id(&session), 1);
     }
 
     #[test]
     fn change_bank_works() {
         let mut repository = Repository::default();
-        repository.new_bank();
-        repository.new_bank();
-        repository.new_bank();
+        let mut session = Session::default();
+        repository.new_bank(&mut session).unwrap();
+        repository.new_bank(&mut session).unwrap();
+        repository.new_bank(&mut session).unwrap();
 
-        assert_eq!(repository.current_bank_id(), 3);
+        assert_eq!(repository.current_bank_id(&session), 3);
 
-        assert!(repository.change_bank(1).is_ok());
-        assert_eq!(repository.current_bank_id(), 1);
-        assert!(repository.change_bank(2).is_ok());
-        assert_eq!(repository.current_bank_id(), 2);
-        assert!(repository.change_bank(3).is_ok());
-        assert_eq!(repository.current_bank_id(), 3);
+        assert!(repository.change_bank(&mut session, 1).is_ok());
+        assert_eq!(repository.current_bank_id(&session), 1);
+        assert!(repository.change_bank(&mut session, 2).is_ok());
+        assert_eq!(repository.current_bank_id(&session), 2);
+        assert!(repository.change_bank(&mut session, 3).is_ok());
+        assert_eq!(repository.current_bank_id(&session), 3);
 
-        assert_eq!(
-            Err(RepositoryError::InvalidBankId),
-            repository.change_bank(0)
-        );
-        assert_eq!(
-            Err(RepositoryError::InvalidBankId),
-            repository.change_bank(4)
-        );
-        assert_eq!(
-            Err(RepositoryError::InvalidBankId),
-            repository.change_bank(100)
-        );
+        assert!(matches!(
+            repository.change_bank(&mut session, 0),
+            Err(RepositoryError::InvalidBankId)
+        ));
+        assert!(matches!(
+            repository.change_bank(&mut session, 4),
+            Err(RepositoryError::InvalidBankId)
+        ));
+        assert!(matches!(
+            repository.change_bank(&mut session, 100),
+            Err(RepositoryError::InvalidBankId)
+        ));
 
-        assert_eq!(repository.current_bank_id(), 3);
+        assert_eq!(repository.current_bank_id(&session), 3);
     }
 
     #[test]
     fn register_account_works() {
         let mut repository = Repository::default();
-        assert!(repository.register_account(100).is_ok());
-        assert!(repository.register_account(0).is_ok());
+        let mut session = Session::default();
+        assert!(repository
+            .register_account(&mut session, Money::from_minor(100), Currency::default())
+            .is_ok());
+        assert!(repository
+            .register_account(&mut session, Money::from_minor(0), Currency::default())
+            .is_ok());
     }
 
     #[test]
     fn get_balance_works() {
         let mut repository = Repository::default();
-        let (account_id, _) = repository.register_account(100).unwrap();
-        assert_eq!(100, repository.get_balance(account_id).unwrap());
+        let mut session = Session::default();
+        let (account_id, _) = repository
+            .register_account(&mut session, Money::from_minor(100), Currency::default())
+            .unwrap();
+        assert_eq!(
+            Money::from_minor(100),
+            repository.get_balance(&mut session, account_id).unwrap()
+        );
 
-        let fake_account = Account::new(10);
-        assert!(repository.get_balance(fake_account.id).is_err());
+        let fake_account = Account::new(Money::from_minor(10));
+        assert!(repository
+            .get_balance(&mut session, fake_account.id)
+            .is_err());
     }
 
     #[test]
     fn deposit_works() {
         let mut repository = Repository::default();
-        let (account_id, _) = repository.register_account(100).unwrap();
-        assert!(repository.deposit(account_id, 10).is_ok());
-        assert_eq!(110, repository.get_balance(account_id).unwrap());
+        let mut session = Session::default();
+        let (account_id, _) = repository
+            .register_account(&mut session, Money::from_minor(100), Currency::default())
+            .unwrap();
+        assert!(repository
+            .deposit(&mut session, account_id, Money::from_minor(10), None)
+            .is_ok());
+        assert_eq!(
+            Money::from_minor(110),
+            repository.get_balance(&mut session, account_id).unwrap()
+        );
     }
 
     #[test]
     fn withdraw_works() {
         let mut repository = Repository::default();
-        let (account_id, _) = repository.register_account(100).unwrap();
-        assert!(repository.withdraw(account_id, 10).is_ok());
-        assert_eq!(90, repository.get_balance(account_id).unwrap());
+        let mut session = Session::default();
+        let (account_id, _) = repository
+            .register_account(&mut session, Money::from_minor(100), Currency::default())
+            .unwrap();
+        assert!(repository
+            .withdraw(&mut session, account_id, Money::from_minor(10), None)
+            .is_ok());
+        assert_eq!(
+            Money::from_minor(90),
+            repository.get_balance(&mut session, account_id).unwrap()
+        );
     }
 
     #[test]
     fn transfer_works() {
         let mut repository = Repository::default();
-        let (sender_id, _) = repository.register_account(100).unwrap();
-        let (receiver_id, _) = repository.register_account(100).unwrap();
-        assert!(repository.transfer(sender_id, receiver_id, 10).is_ok());
-        assert_eq!(90, repository.get_balance(sender_id).unwrap());
-        assert_eq!(110, repository.get_balance(receiver_id).unwrap());
+        let mut session = Session::default();
+        let (sender_id, _) = repository
+            .register_account(&mut session, Money::from_minor(100), Currency::default())
+            .unwrap();
+        let (receiver_id, _) = repository
+            .register_account(&mut session, Money::from_minor(100), Currency::default())
+            .unwrap();
+        assert!(repository
+            .transfer(
+                &mut session,
+                sender_id,
+                receiver_id,
+                Money::from_minor(10),
+                None
+            )
+            .is_ok());
+        assert_eq!(
+            Money::from_minor(90),
+            repository.get_balance(&mut session, sender_id).unwrap()
+        );
+        assert_eq!(
+            Money::from_minor(110),
+            repository.get_balance(&mut session, receiver_id).unwrap()
+        );
+    }
+
+    #[test]
+    fn interbank_transfer_works() {
+        let path = std::env::temp_dir().join(format!("bank-repository-{}", OperationID::new()));
+
+        let mut repository = Repository::open(&path).unwrap();
+        let mut session = Session::default();
+        let (sender_id, _) = repository
+            .register_account(&mut session, Money::from_minor(100), Currency::default())
+            .unwrap();
+        repository.new_bank(&mut session).unwrap();
+        let (receiver_id, _) = repository
+            .register_account(&mut session, Money::from_minor(100), Currency::default())
+            .unwrap();
+        repository.change_bank(&mut session, 1).unwrap();
+
+        assert!(matches!(
+            repository.interbank_transfer(
+                &mut session,
+                sender_id,
+                1,
+                receiver_id,
+                Money::from_minor(10)
+            ),
+            Err(RepositoryError::SameBank)
+        ));
+        assert!(matches!(
+            repository.interbank_transfer(
+                &mut session,
+                sender_id,
+                2,
+                sender_id,
+                Money::from_minor(10)
+            ),
+            Err(RepositoryError::BankError(BankError::NotFound))
+        ));
+        assert!(matches!(
+            repository.interbank_transfer(
+                &mut session,
+                sender_id,
+                2,
+                receiver_id,
+                Money::from_minor(500)
+            ),
+            Err(RepositoryError::BankError(BankError::InsufficientFunds))
+        ));
+
+        let (send_id, receive_id) = repository
+            .interbank_transfer(
+                &mut session,
+                sender_id,
+                2,
+                receiver_id,
+                Money::from_minor(30),
+            )
+            .unwrap();
+        assert_eq!(
+            repository.banks[1].get_operation(receive_id).unwrap().kind,
+            OperationKind::InterbankReceive {
+                operation_id: send_id,
+                bank_id: 1,
+                sender_id,
+                receiver_id,
+                amount: Money::from_minor(30),
+            }
+        );
+
+        let expected_banks = repository.banks.clone();
+        drop(repository);
+
+        let mut repository = Repository::open(&path).unwrap();
+        assert_eq!(repository.banks, expected_banks);
+        assert_eq!(
+            repository.get_balance(&mut session, sender_id).unwrap(),
+            Money::from_minor(70)
+        );
+        repository.change_bank(&mut session, 2).unwrap();
+        assert_eq!(
+            repository.get_balance(&mut session, receiver_id).unwrap(),
+            Money::from_minor(130)
+        );
+
+        std::fs::remove_dir_all(path).unwrap();
     }
 
     #[test]
     fn get_account_operations_works() {
         let mut repository = Repository::default();
-        let (account1_id, _) = repository.register_account(100).unwrap();
-        repository.deposit(account1_id, 10).unwrap();
-        repository.withdraw(account1_id, 10).unwrap();
+        let mut session = Session::default();
+        let (account1_id, _) = repository
+            .register_account(&mut session, Money::from_minor(100), Currency::default())
+            .unwrap();
+        repository
+            .deposit(&mut session, account1_id, Money::from_minor(10), None)
+            .unwrap();
+        repository
+            .withdraw(&mut session, account1_id, Money::from_minor(10), None)
+            .unwrap();
 
-        let (account2_id, _) = repository.register_account(50).unwrap();
-        repository.transfer(account1_id, account2_id, 10).unwrap();
+        let (account2_id, _) = repository
+            .register_account(&mut session, Money::from_minor(50), Currency::default())
+            .unwrap();
+        repository
+            .transfer(
+                &mut session,
+                account1_id,
+                account2_id,
+                Money::from_minor(10),
+                None,
+            )
+            .unwrap();
 
         let operations: Vec<OperationKind> = repository
-            .get_account_operations(account1_id)
+            .get_account_operations(&session, account1_id)
             .map(|op| op.kind)
             .collect();
 
         let expected: Vec<OperationKind> = vec![
             OperationKind::Register {
                 id: account1_id,
-                balance: 100,
+                balance: Money::from_minor(100),
+                currency: Currency::default(),
             },
             OperationKind::Deposit {
                 id: account1_id,
-                amount: 10,
+                amount: Money::from_minor(10),
             },
             OperationKind::Withdraw {
                 id: account1_id,
-                amount: 10,
+                amount: Money::from_minor(10),
             },
             OperationKind::Transfer {
                 sender_id: account1_id,
                 receiver_id: account2_id,
-                amount: 10,
+                amount: Money::from_minor(10),
+                rate: Rate::ONE,
             },
         ];
         assert_eq!(operations, expected);
@@ -287,37 +1158,59 @@ mod tests {
     #[test]
     fn get_all_operations_works() {
         let mut repository = Repository::default();
-        let (account1_id, _) = repository.register_account(100).unwrap();
-        repository.deposit(account1_id, 10).unwrap();
-        repository.withdraw(account1_id, 10).unwrap();
+        let mut session = Session::default();
+        let (account1_id, _) = repository
+            .register_account(&mut session, Money::from_minor(100), Currency::default())
+            .unwrap();
+        repository
+            .deposit(&mut session, account1_id, Money::from_minor(10), None)
+            .unwrap();
+        repository
+            .withdraw(&mut session, account1_id, Money::from_minor(10), None)
+            .unwrap();
 
-        let (account2_id, _) = repository.register_account(50).unwrap();
-        repository.transfer(account1_id, account2_id, 10).unwrap();
+        let (account2_id, _) = repository
+            .register_account(&mut session, Money::from_minor(50), Currency::default())
+            .unwrap();
+        repository
+            .transfer(
+                &mut session,
+                account1_id,
+                account2_id,
+                Money::from_minor(10),
+                None,
+            )
+            .unwrap();
 
-        let operations: Vec<OperationKind> =
-            repository.get_all_operations().map(|op| op.kind).collect();
+        let operations: Vec<OperationKind> = repository
+            .get_all_operations(&session)
+            .map(|op| op.kind)
+            .collect();
 
         let expected: Vec<OperationKind> = vec![
             OperationKind::Register {
                 id: account1_id,
-                balance: 100,
+                balance: Money::from_minor(100),
+                currency: Currency::default(),
             },
             OperationKind::Deposit {
                 id: account1_id,
-                amount: 10,
+                amount: Money::from_minor(10),
             },
             OperationKind::Withdraw {
                 id: account1_id,
-                amount: 10,
+                amount: Money::from_minor(10),
             },
             OperationKind::Register {
                 id: account2_id,
-                balance: 50,
+                balance: Money::from_minor(50),
+                currency: Currency::default(),
             },
             OperationKind::Transfer {
                 sender_id: account1_id,
                 receiver_id: account2_id,
-                amount: 10,
+                amount: Money::from_minor(10),
+                rate: Rate::ONE,
             },
         ];
         assert_eq!(operations, expected);
@@ -326,34 +1219,614 @@ mod tests {
     #[test]
     fn restore_bank_works() {
         let mut repository = Repository::default();
-        let (account1_id, _) = repository.register_account(100).unwrap();
-        let (account2_id, _) = repository.register_account(50).unwrap();
+        let mut session = Session::default();
+        let (account1_id, _) = repository
+            .register_account(&mut session, Money::from_minor(100), Currency::default())
+            .unwrap();
+        let (account2_id, _) = repository
+            .register_account(&mut session, Money::from_minor(50), Currency::default())
+            .unwrap();
 
-        repository.deposit(account1_id, 100).unwrap();
-        repository.deposit(account2_id, 250).unwrap();
-        repository.transfer(account1_id, account2_id, 50).unwrap();
-        repository.withdraw(account2_id, 50).unwrap();
+        repository
+            .deposit(&mut session, account1_id, Money::from_minor(100), None)
+            .unwrap();
+        repository
+            .deposit(&mut session, account2_id, Money::from_minor(250), None)
+            .unwrap();
+        repository
+            .transfer(
+                &mut session,
+                account1_id,
+                account2_id,
+                Money::from_minor(50),
+                None,
+            )
+            .unwrap();
+        repository
+            .withdraw(&mut session, account2_id, Money::from_minor(50), None)
+            .unwrap();
 
-        repository.new_bank();
-        repository.register_account(150).unwrap();
-        repository.register_account(10).unwrap();
+        repository.new_bank(&mut session).unwrap();
+        repository
+            .register_account(&mut session, Money::from_minor(150), Currency::default())
+            .unwrap();
+        repository
+            .register_account(&mut session, Money::from_minor(10), Currency::default())
+            .unwrap();
 
-        repository.restore_bank(1).unwrap();
+        repository.restore_bank(&mut session, 1).unwrap();
 
         let restored_bank_operations = repository
-            .get_all_operations()
+            .get_all_operations(&session)
             .map(|op| op.to_string())
             .collect::<Vec<_>>();
 
-        assert_eq!(3, repository.current_bank_id());
+        assert_eq!(3, repository.current_bank_id(&session));
 
-        repository.change_bank(1).unwrap();
+        repository.change_bank(&mut session, 1).unwrap();
 
         let original_bank_operations = repository
-            .get_all_operations()
+            .get_all_operations(&session)
             .map(|op| op.to_string())
             .collect::<Vec<_>>();
 
         assert_eq!(original_bank_operations, restored_bank_operations);
     }
+
+    #[test]
+    fn set_clock_works() {
+        let clock = Arc::new(ManualClock::new(100));
+        let mut repository = Repository::default();
+        repository.set_clock(SharedClock::new(clock.clone()));
+        let mut session = Session::default();
+
+        let (account_id, _) = repository
+            .register_account(&mut session, Money::from_minor(100), Currency::default())
+            .unwrap();
+        clock.advance(60);
+        repository
+            .deposit(&mut session, account_id, Money::from_minor(10), None)
+            .unwrap();
+
+        clock.set(500);
+        repository.restore_bank(&mut session, 1).unwrap();
+        repository
+            .withdraw(&mut session, account_id, Money::from_minor(10), None)
+            .unwrap();
+        repository.new_bank(&mut session).unwrap();
+        repository
+            .register_account(&mut session, Money::from_minor(100), Currency::default())
+            .unwrap();
+
+        let timestamps = |repository: &Repository, id| {
+            let mut session = Session::default();
+            repository.change_bank(&mut session, id).unwrap();
+            repository
+                .get_all_operations(&session)
+                .map(|operation| operation.timestamp)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(timestamps(&repository, 1), vec![100, 160]);
+        assert_eq!(timestamps(&repository, 2), vec![100, 160, 500]);
+        assert_eq!(timestamps(&repository, 3), vec![500]);
+        assert_eq!(repository.now(), 500);
+    }
+
+    #[test]
+    fn idempotency_key_works() {
+        let mut repository = Repository::default();
+        let mut session = Session::default();
+        let (account_id, _) = repository
+            .register_account(&mut session, Money::from_minor(100), Currency::default())
+            .unwrap();
+
+        let key = "retry-1".parse().unwrap();
+        let operation_id = repository
+            .deposit(&mut session, account_id, Money::from_minor(10), Some(key))
+            .unwrap();
+        assert_eq!(
+            repository
+                .deposit(&mut session, account_id, Money::from_minor(10), Some(key))
+                .unwrap(),
+            operation_id
+        );
+        // key can't be reused for another operation or other arguments
+        assert!(matches!(
+            repository.withdraw(&mut session, account_id, Money::from_minor(50), Some(key)),
+            Err(RepositoryError::BankError(BankError::KeyReused))
+        ));
+        assert!(matches!(
+            repository.deposit(&mut session, account_id, Money::from_minor(20), Some(key)),
+            Err(RepositoryError::BankError(BankError::KeyReused))
+        ));
+        assert_eq!(
+            repository.get_balance(&mut session, account_id).unwrap(),
+            Money::from_minor(110)
+        );
+
+        // failed operation does not take the key
+        let key = "retry-2".parse().unwrap();
+        assert!(repository
+            .withdraw(&mut session, account_id, Money::from_minor(500), Some(key))
+            .is_err());
+        assert!(repository
+            .withdraw(&mut session, account_id, Money::from_minor(50), Some(key))
+            .is_ok());
+        assert_eq!(
+            repository.get_balance(&mut session, account_id).unwrap(),
+            Money::from_minor(60)
+        );
+    }
+
+    #[test]
+    fn accrue_interest_works() {
+        let path = std::env::temp_dir().join(format!("bank-repository-{}", OperationID::new()));
+
+        let mut repository = Repository::open(&path).unwrap();
+        let mut session = Session::default();
+        let mut savings = Vec::new();
+        for _ in 0..2 {
+            let (account_id, _) = repository
+                .register_account(&mut session, Money::from_minor(100000), Currency::default())
+                .unwrap();
+            repository
+                .set_interest_rate(&mut session, account_id, "0.05".parse().unwrap())
+                .unwrap();
+            savings.push(account_id);
+        }
+
+        assert_eq!(
+            repository.accrue_interest(&mut session, 365).unwrap().len(),
+            2
+        );
+
+        let expected_banks = repository.banks.clone();
+        drop(repository);
+
+        let mut repository = Repository::open(&path).unwrap();
+        assert_eq!(repository.banks, expected_banks);
+        for account_id in savings {
+            assert_eq!(
+                repository.get_balance(&mut session, account_id).unwrap(),
+                Money::from_minor(105000)
+            );
+        }
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn run_schedules_works() {
+        let path = std::env::temp_dir().join(format!("bank-repository-{}", OperationID::new()));
+
+        let mut repository = Repository::open(&path).unwrap();
+        let mut session = Session::default();
+        let (sender_id, _) = repository
+            .register_account(&mut session, Money::from_minor(250), Currency::default())
+            .unwrap();
+        let (receiver_id, _) = repository
+            .register_account(&mut session, Money::ZERO, Currency::default())
+            .unwrap();
+
+        let (rent_id, _) = repository
+            .schedule_transfer(
+                &mut session,
+                sender_id,
+                receiver_id,
+                Money::from_minor(100),
+                1000,
+                Some(100),
+            )
+            .unwrap();
+        let (once_id, _) = repository
+            .schedule_transfer(
+                &mut session,
+                sender_id,
+                receiver_id,
+                Money::from_minor(10),
+                5000,
+                None,
+            )
+            .unwrap();
+
+        assert!(repository.run_schedules(999).is_empty());
+
+        let executions = repository.run_schedules(1000);
+        assert_eq!(executions.len(), 1);
+        let (bank_id, schedule_id, result) = &executions[0];
+        assert_eq!((*bank_id, *schedule_id), (1, rent_id));
+        let operation_id = *result.as_ref().unwrap();
+        assert_eq!(
+            repository
+                .get_all_operations(&session)
+                .find(|operation| operation.id == operation_id)
+                .unwrap()
+                .schedule,
+            Some(rent_id)
+        );
+
+        // rent of the second period, the third one is not covered
+        assert_eq!(repository.run_schedules(1100).len(), 1);
+        assert!(repository.run_schedules(1200)[0].2.is_err());
+        assert_eq!(
+            repository.get_balance(&mut session, receiver_id).unwrap(),
+            Money::from_minor(200)
+        );
+
+        // failed run is skipped instead of being retried on every tick
+        assert!(repository.run_schedules(1250).is_empty());
+        let schedules = repository.get_schedules(&mut session).unwrap();
+        assert_eq!(schedules[0].id, rent_id);
+        assert_eq!(schedules[0].next_run, 1300);
+        assert!(repository.run_schedules(1300)[0].2.is_err());
+
+        repository.cancel_schedule(&mut session, rent_id).unwrap();
+        assert!(repository.cancel_schedule(&mut session, rent_id).is_err());
+
+        let expected_banks = repository.banks.clone();
+        drop(repository);
+
+        let mut repository = Repository::open(&path).unwrap();
+        assert_eq!(repository.banks, expected_banks);
+        let schedules = repository.get_schedules(&mut session).unwrap();
+        assert_eq!(schedules.len(), 1);
+        assert_eq!(schedules[0].id, once_id);
+
+        assert_eq!(repository.run_schedules(5000).len(), 1);
+        assert!(repository.get_schedules(&mut session).unwrap().is_empty());
+        assert!(repository.run_schedules(10000).is_empty());
+
+        // one-time schedule ends with its failed run
+        repository
+            .schedule_transfer(
+                &mut session,
+                sender_id,
+                receiver_id,
+                Money::from_minor(1000),
+                20000,
+                None,
+            )
+            .unwrap();
+        assert!(repository.run_schedules(20000)[0].2.is_err());
+        assert!(repository.get_schedules(&mut session).unwrap().is_empty());
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn batch_works() {
+        let path = std::env::temp_dir().join(format!("bank-repository-{}", OperationID::new()));
+
+        let mut repository = Repository::open(&path).unwrap();
+        let mut session = Session::default();
+        let (account_id, _) = repository
+            .register_account(&mut session, Money::from_minor(100), Currency::default())
+            .unwrap();
+
+        repository.begin_batch(&mut session).unwrap();
+        repository
+            .deposit(&mut session, account_id, Money::from_minor(50), None)
+            .unwrap();
+        assert_eq!(
+            repository.get_balance(&mut session, account_id).unwrap(),
+            Money::from_minor(150)
+        );
+        repository.rollback_batch();
+        assert_eq!(
+            repository.get_balance(&mut session, account_id).unwrap(),
+            Money::from_minor(100)
+        );
+
+        repository.begin_batch(&mut session).unwrap();
+        repository
+            .deposit(&mut session, account_id, Money::from_minor(50), None)
+            .unwrap();
+        repository
+            .withdraw(&mut session, account_id, Money::from_minor(20), None)
+            .unwrap();
+        repository.commit_batch().unwrap();
+
+        let expected_banks = repository.banks.clone();
+        drop(repository);
+
+        let mut repository = Repository::open(&path).unwrap();
+        assert_eq!(repository.banks, expected_banks);
+        assert_eq!(
+            repository.get_balance(&mut session, account_id).unwrap(),
+            Money::from_minor(130)
+        );
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn sessions_are_independent_works() {
+        let mut repository = Repository::default();
+        let mut first_session = Session::default();
+        let mut second_session = Session::default();
+
+        let (account_id, _) = repository
+            .register_account(
+                &mut first_session,
+                Money::from_minor(100),
+                Currency::default(),
+            )
+            .unwrap();
+        assert_eq!(repository.new_bank(&mut second_session).unwrap(), 2);
+
+        assert_eq!(repository.current_bank_id(&first_session), 1);
+        assert_eq!(repository.current_bank_id(&second_session), 2);
+
+        repository
+            .deposit(&mut first_session, account_id, Money::from_minor(50), None)
+            .unwrap();
+        assert!(repository
+            .deposit(&mut second_session, account_id, Money::from_minor(50), None)
+            .is_err());
+
+        repository.change_bank(&mut second_session, 1).unwrap();
+        assert_eq!(
+            repository
+                .get_balance(&mut second_session, account_id)
+                .unwrap(),
+            Money::from_minor(150)
+        );
+        assert_eq!(repository.current_bank_id(&first_session), 1);
+    }
+
+    #[test]
+    fn open_restores_repository_works() {
+        let path = std::env::temp_dir().join(format!("bank-repository-{}", OperationID::new()));
+
+        let mut repository = Repository::open(&path).unwrap();
+        let mut session = Session::default();
+        let (account1_id, _) = repository
+            .register_account(&mut session, Money::from_minor(100), Currency::default())
+            .unwrap();
+        let (account2_id, _) = repository
+            .register_account(&mut session, Money::from_minor(50), Currency::default())
+            .unwrap();
+        let key = "retry-1".parse().unwrap();
+        let deposit_id = repository
+            .deposit(&mut session, account1_id, Money::from_minor(100), Some(key))
+            .unwrap();
+        repository
+            .transfer(
+                &mut session,
+                account1_id,
+                account2_id,
+                Money::from_minor(50),
+                None,
+            )
+            .unwrap();
+        repository
+            .withdraw(&mut session, account2_id, Money::from_minor(50), None)
+            .unwrap();
+        assert!(repository
+            .withdraw(&mut session, account2_id, Money::from_minor(500), None)
+            .is_err());
+
+        repository.restore_bank(&mut session, 1).unwrap();
+        repository
+            .deposit(&mut session, account1_id, Money::from_minor(10), None)
+            .unwrap();
+        repository.new_bank(&mut session).unwrap();
+
+        let expected_banks = repository.banks.clone();
+        drop(repository);
+
+        let mut repository = Repository::open(&path).unwrap();
+        let mut session = Session::default();
+        assert_eq!(repository.banks, expected_banks);
+        assert_eq!(repository.current_bank_id(&session), 1);
+
+        repository.change_bank(&mut session, 1).unwrap();
+        assert_eq!(
+            repository
+                .deposit(&mut session, account1_id, Money::from_minor(100), Some(key))
+                .unwrap(),
+            deposit_id
+        );
+        assert_eq!(
+            repository.get_balance(&mut session, account1_id).unwrap(),
+            Money::from_minor(150)
+        );
+        assert_eq!(
+            repository.get_balance(&mut session, account2_id).unwrap(),
+            Money::from_minor(50)
+        );
+
+        repository.change_bank(&mut session, 2).unwrap();
+        assert_eq!(
+            repository.get_balance(&mut session, account1_id).unwrap(),
+            Money::from_minor(160)
+        );
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn open_restores_name_with_spaces_works() {
+        let dir = std::env::temp_dir().join(format!("bank-repository-{}", OperationID::new()));
+        let name = ProfileField::parse("name", "Jane Doe").unwrap();
+
+        let mut repository = Repository::open(&dir).unwrap();
+        let mut session = Session::default();
+        let (account_id, _) = repository
+            .register_account(&mut session, Money::from_minor(100), Currency::default())
+            .unwrap();
+        repository
+            .update_profile(&mut session, account_id, name)
+            .unwrap();
+        let expected_profile = Profile::default().updated(name);
+        drop(repository);
+
+        // replayed from the log
+        let mut repository = Repository::open(&dir).unwrap();
+        assert_eq!(
+            repository.get_profile(&mut session, account_id).unwrap(),
+            expected_profile
+        );
+        repository.snapshot().unwrap();
+        drop(repository);
+
+        // restored from the checkpoint
+        let mut repository = Repository::open(&dir).unwrap();
+        assert_eq!(
+            repository.get_profile(&mut session, account_id).unwrap(),
+            expected_profile
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn open_restores_hold_of_frozen_account_works() {
+        let dir = std::env::temp_dir().join(format!("bank-repository-{}", OperationID::new()));
+
+        let mut repository = Repository::open(&dir).unwrap();
+        let mut session = Session::default();
+        let (account_id, _) = repository
+            .register_account(&mut session, Money::from_minor(100), Currency::default())
+            .unwrap();
+        let (hold_id, _) = repository
+            .hold(&mut session, account_id, Money::from_minor(60))
+            .unwrap();
+        repository.freeze_account(&mut session, account_id).unwrap();
+        repository.snapshot().unwrap();
+        let expected_banks = repository.banks.clone();
+        drop(repository);
+
+        let mut repository = Repository::open(&dir).unwrap();
+        assert_eq!(repository.banks[0].snapshot(), expected_banks[0].snapshot());
+
+        repository
+            .unfreeze_account(&mut session, account_id)
+            .unwrap();
+        repository.release(&mut session, hold_id).unwrap();
+        assert_eq!(
+            repository
+                .get_available_balance(&mut session, account_id)
+                .unwrap(),
+            Money::from_minor(100)
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn open_restores_hold_after_overdraft_change_works() {
+        let dir = std::env::temp_dir().join(format!("bank-repository-{}", OperationID::new()));
+
+        let mut repository = Repository::open(&dir).unwrap();
+        let mut session = Session::default();
+        let (account_id, _) = repository
+            .register_account(&mut session, Money::from_minor(100), Currency::default())
+            .unwrap();
+        repository
+            .set_overdraft_limit(&mut session, account_id, Money::from_minor(100))
+            .unwrap();
+        repository
+            .hold(&mut session, account_id, Money::from_minor(150))
+            .unwrap();
+        repository
+            .set_overdraft_limit(&mut session, account_id, Money::ZERO)
+            .unwrap();
+        repository.snapshot().unwrap();
+        let expected_banks = repository.banks.clone();
+        drop(repository);
+
+        let mut repository = Repository::open(&dir).unwrap();
+        assert_eq!(repository.banks[0].snapshot(), expected_banks[0].snapshot());
+        assert_eq!(
+            repository
+                .get_available_balance(&mut session, account_id)
+                .unwrap(),
+            Money::from_minor(-50)
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn snapshot_compacts_log_works() {
+        let dir = std::env::temp_dir().join(format!("bank-repository-{}", OperationID::new()));
+
+        let mut repository = Repository::open(&dir).unwrap();
+        let mut session = Session::default();
+        repository.snapshot_interval = 4;
+
+        let (account1_id, _) = repository
+            .register_account(&mut session, Money::from_minor(100), Currency::default())
+            .unwrap();
+        let (account2_id, _) = repository
+            .register_account(&mut session, Money::from_minor(50), Currency::default())
+            .unwrap();
+        for _ in 0..10 {
+            repository
+                .transfer(
+                    &mut session,
+                    account1_id,
+                    account2_id,
+                    Money::from_minor(5),
+                    None,
+                )
+                .unwrap();
+        }
+        repository.restore_bank(&mut session, 1).unwrap();
+        repository
+            .deposit(&mut session, account1_id, Money::from_minor(10), None)
+            .unwrap();
+
+        let segments = std::fs::read_dir(&dir)
+            .unwrap()
+            .filter(|entry| {
+                let name = entry.as_ref().unwrap().file_name();
+                name.to_str().unwrap().starts_with("wal.")
+            })
+            .count();
+        assert_eq!(segments, 1);
+
+        // the last snapshot was taken after the 12th record, before bank 2 appeared
+        let checkpoint = Checkpoint::load(&dir).unwrap().unwrap();
+        assert_eq!(checkpoint.banks.len(), 1);
+        let last_operation_id = checkpoint.banks[0].last_operation_id.unwrap();
+        assert!(repository.banks[0]
+            .get_operation(last_operation_id)
+            .is_some());
+        drop(repository);
+
+        let mut repository = Repository::open(&dir).unwrap();
+        let mut session = Session::default();
+        assert!(repository.banks[0].get_all_operations().count() < 12);
+
+        repository.change_bank(&mut session, 1).unwrap();
+        assert_eq!(
+            repository.get_balance(&mut session, account1_id).unwrap(),
+            Money::from_minor(50)
+        );
+        assert_eq!(
+            repository.get_balance(&mut session, account2_id).unwrap(),
+            Money::from_minor(100)
+        );
+
+        repository.change_bank(&mut session, 2).unwrap();
+        assert_eq!(
+            repository.get_balance(&mut session, account1_id).unwrap(),
+            Money::from_minor(60)
+        );
+        assert_eq!(
+            repository.get_balance(&mut session, account2_id).unwrap(),
+            Money::from_minor(100)
+        );
+
+        // bank restored from the snapshot still could be copied
+        repository.restore_bank(&mut session, 2).unwrap();
+        assert_eq!(
+            repository.get_balance(&mut session, account1_id).unwrap(),
+            Money::from_minor(60)
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
 }
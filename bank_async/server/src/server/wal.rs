@@ -0,0 +1,1341 @@
+use crate::bank::account::AccountID;
+use crate::bank::currency::Rate;
+use crate::bank::hold::HoldID;
+use crate::bank::log::{Operation, OperationID, OperationKind};
+use crate::bank::money::Money;
+use crate::bank::profile::ProfileField;
+use crate::bank::schedule::ScheduleID;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Record {
+    NewBank,
+    RestoreBank {
+        id: u64,
+    },
+    Operation {
+        bank_id: u64,
+        operation: Operation,
+    },
+    /// Marks that the next `size` records were written at once,
+    /// they are replayed all together or not at all.
+    Batch {
+        size: usize,
+    },
+}
+
+#[derive(Debug)]
+pub enum WalError {
+    Io(std::io::Error),
+    Corrupted {
+        segment: u64,
+        line: usize,
+    },
+    CorruptedCheckpoint,
+    /// Failed write could not be cut off the log, nothing more is appended to it.
+    Broken,
+}
+
+impl std::fmt::Display for WalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            WalError::Io(e) => write!(f, "io error: {}", e),
+            WalError::Corrupted { segment, line } => {
+                write!(
+                    f,
+                    "corrupted record in segment {} at line {}",
+                    segment, line
+                )
+            }
+            WalError::CorruptedCheckpoint => write!(f, "corrupted checkpoint"),
+            WalError::Broken => write!(f, "log is broken by a failed write"),
+        }
+    }
+}
+
+impl std::error::Error for WalError {}
+
+impl From<std::io::Error> for WalError {
+    fn from(e: std::io::Error) -> WalError {
+        WalError::Io(e)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, WalError>;
+
+/// Append-only log of repository changes split into numbered segments
+/// (`wal.1`, `wal.2`, ...) inside of a directory.
+///
+/// Every record is stored on its own line as `<crc32> <payload>`, so a record
+/// that was only partially written before a crash (torn tail) is detected on
+/// open and cut off, together with the rest of its batch.
+#[derive(Debug)]
+pub struct Wal {
+    dir: PathBuf,
+    segment: u64,
+    file: File,
+    /// Length of the segment up to its last fully written record.
+    len: u64,
+    broken: bool,
+}
+
+impl Wal {
+    /// Opens (or creates) log in `dir` and returns records of all segments
+    /// starting from `first_segment`, older segments are removed.
+    pub fn open<P: AsRef<Path>>(dir: P, first_segment: u64) -> Result<(Wal, Vec<Record>)> {
+        let dir = dir.as_ref().to_path_buf();
+        std::fs::create_dir_all(&dir)?;
+
+        let mut segments = Vec::new();
+        for segment in list_segments(&dir)? {
+            if segment < first_segment {
+                std::fs::remove_file(segment_path(&dir, segment))?;
+            } else {
+                segments.push(segment);
+            }
+        }
+
+        let last_segment = segments.last().copied().unwrap_or(first_segment);
+
+        let mut records = Vec::new();
+        let mut last_segment_len = 0;
+        for segment in segments.iter().copied() {
+            let data = std::fs::read(segment_path(&dir, segment))?;
+            let (segment_records, valid_len) =
+                decode(&data).map_err(|line| WalError::Corrupted { segment, line })?;
+
+            // only the newest segment is written to, so only it could have torn tail
+            if valid_len < data.len() && segment != last_segment {
+                return Err(WalError::Corrupted {
+                    segment,
+                    line: segment_records.len() + 1,
+                });
+            }
+
+            records.extend(segment_records);
+            last_segment_len = valid_len;
+        }
+
+        let file = open_segment(&dir, last_segment)?;
+        if file.metadata()?.len() > last_segment_len as u64 {
+            file.set_len(last_segment_len as u64)?;
+            file.sync_all()?;
+        }
+
+        let wal = Wal {
+            dir,
+            segment: last_segment,
+            file,
+            len: last_segment_len as u64,
+            broken: false,
+        };
+
+        Ok((wal, records))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Number of the segment new records are appended to.
+    pub fn segment(&self) -> u64 {
+        self.segment
+    }
+
+    /// Starts new segment, all following records are appended to it.
+    pub fn rotate(&mut self) -> Result<u64> {
+        let segment = self.segment + 1;
+        self.file = open_segment(&self.dir, segment)?;
+        self.len = self.file.metadata()?.len();
+        self.segment = segment;
+
+        Ok(segment)
+    }
+
+    /// Removes all segments older than `first_segment`.
+    pub fn compact(&mut self, first_segment: u64) -> Result<()> {
+        for segment in list_segments(&self.dir)? {
+            if segment < first_segment {
+                std::fs::remove_file(segment_path(&self.dir, segment))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Appends record and waits until it reaches the disk.
+    pub fn append(&mut self, record: &Record) -> Result<()> {
+        let payload = encode_record(record);
+        let line = format!("{:08x} {}\n", crc32(payload.as_bytes()), payload);
+
+        self.write(line.as_bytes())
+    }
+
+    /// Appends records as one batch, after a crash either all of them are read back or none.
+    pub fn append_batch(&mut self, records: &[Record]) -> Result<()> {
+        if records.is_empty() {
+            return Ok(());
+        }
+
+        let mut data = String::new();
+        let batch = Record::Batch {
+            size: records.len(),
+        };
+        for record in std::iter::once(&batch).chain(records.iter()) {
+            let payload = encode_record(record);
+            data.push_str(&format!("{:08x} {}\n", crc32(payload.as_bytes()), payload));
+        }
+
+        self.write(data.as_bytes())
+    }
+
+    // Writes `data` and waits until it reaches the disk. Failed write is cut off,
+    // so the next record doesn't follow a partial line and the failed one isn't
+    // read back after a restart, though its caller got an error.
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        if self.broken {
+            return Err(WalError::Broken);
+        }
+
+        if let Err(e) = self
+            .file
+            .write_all(data)
+            .and_then(|()| self.file.sync_data())
+        {
+            if self.truncate().is_err() {
+                self.broken = true;
+            }
+            return Err(WalError::Io(e));
+        }
+
+        self.len += data.len() as u64;
+        Ok(())
+    }
+
+    // Cuts the segment back to its last fully written record, through a new handle
+    // as the old one may be unusable after a failed write.
+    fn truncate(&mut self) -> Result<()> {
+        let file = open_segment(&self.dir, self.segment)?;
+        file.set_len(self.len)?;
+        file.sync_all()?;
+        self.file = file;
+
+        Ok(())
+    }
+}
+
+fn segment_path(dir: &Path, segment: u64) -> PathBuf {
+    dir.join(format!("wal.{}", segment))
+}
+
+fn list_segments(dir: &Path) -> Result<Vec<u64>> {
+    let mut segments = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let name = entry?.file_name();
+        let segment = name
+            .to_str()
+            .and_then(|name| name.strip_prefix("wal."))
+            .and_then(|segment| segment.parse().ok());
+
+        if let Some(segment) = segment {
+            segments.push(segment);
+        }
+    }
+
+    segments.sort();
+    Ok(segments)
+}
+
+fn open_segment(dir: &Path, segment: u64) -> Result<File> {
+    let file = OpenOptions::new()
+        .read(true)
+        .append(true)
+        .create(true)
+        .open(segment_path(dir, segment))?;
+
+    // make sure the new segment itself survives a crash, not only its content
+    File::open(dir)?.sync_all()?;
+
+    Ok(file)
+}
+
+// Returns decoded records and length of the valid prefix of data,
+// or number of the broken line if it is not the last one.
+// Batch markers are consumed here, unfinished batch is not a part of the valid prefix.
+fn decode(data: &[u8]) -> std::result::Result<(Vec<Record>, usize), usize> {
+    let mut records = Vec::new();
+    let mut valid_len = 0;
+
+    let mut batch = Vec::new();
+    let mut batch_len = 0;
+    let mut batch_remaining = 0;
+
+    let lines: Vec<&[u8]> = data.split_inclusive(|b| *b == b'\n').collect();
+    for (idx, line) in lines.iter().enumerate() {
+        let is_last = idx == lines.len() - 1;
+
+        match line.strip_suffix(b"\n").and_then(decode_line) {
+            Some(Record::Batch { .. }) if batch_remaining > 0 => return Err(idx + 1),
+            Some(Record::Batch { size }) => {
+                batch_remaining = size;
+                batch_len = line.len();
+            }
+            Some(record) if batch_remaining > 0 => {
+                batch.push(record);
+                batch_len += line.len();
+                batch_remaining -= 1;
+
+                if batch_remaining == 0 {
+                    records.append(&mut batch);
+                    valid_len += batch_len;
+                }
+            }
+            Some(record) => {
+                records.push(record);
+                valid_len += line.len();
+            }
+            // only the last record could be torn by a crash in the middle of write,
+            // broken record before it means that log is damaged
+            None if is_last => break,
+            None => return Err(idx + 1),
+        }
+    }
+
+    Ok((records, valid_len))
+}
+
+fn decode_line(line: &[u8]) -> Option<Record> {
+    let line = std::str::from_utf8(line).ok()?;
+    let (checksum, payload) = line.split_once(' ')?;
+
+    if u32::from_str_radix(checksum, 16).ok()? != crc32(payload.as_bytes()) {
+        return None;
+    }
+
+    decode_record(payload)
+}
+
+fn encode_record(record: &Record) -> String {
+    match record {
+        Record::NewBank => String::from("new_bank"),
+        Record::RestoreBank { id } => format!("restore_bank {}", id),
+        Record::Batch { size } => format!("batch {}", size),
+        Record::Operation { bank_id, operation } => {
+            let kind = match operation.kind {
+                OperationKind::Register {
+                    id,
+                    balance,
+                    currency,
+                } => format!("register {} {} {}", id, balance, currency),
+                OperationKind::Deposit { id, amount } => format!("deposit {} {}", id, amount),
+                OperationKind::Withdraw { id, amount } => format!("withdraw {} {}", id, amount),
+                OperationKind::Transfer {
+                    sender_id,
+                    receiver_id,
+                    amount,
+                    rate,
+                } => format!("transfer {} {} {} {}", sender_id, receiver_id, amount, rate),
+                OperationKind::SetExchangeRate { from, to, rate } => {
+                    format!("set_exchange_rate {} {} {}", from, to, rate)
+                }
+                OperationKind::SetOverdraftLimit { id, limit } => {
+                    format!("set_overdraft_limit {} {}", id, limit)
+                }
+                OperationKind::SetInterestRate { id, rate } => {
+                    format!("set_interest_rate {} {}", id, rate)
+                }
+                OperationKind::Interest { id, amount } => format!("interest {} {}", id, amount),
+                OperationKind::Reverse {
+                    operation_id,
+                    debit,
+                    credit,
+                } => format!(
+                    "reverse {} {} {}",
+                    operation_id,
+                    encode_side(debit),
+                    encode_side(credit)
+                ),
+                OperationKind::Freeze { id } => format!("freeze {}", id),
+                OperationKind::Unfreeze { id } => format!("unfreeze {}", id),
+                OperationKind::Close {
+                    id, sweep_to: None, ..
+                } => format!("close {}", id),
+                OperationKind::Close {
+                    id,
+                    sweep_to: Some(sweep_to),
+                    amount,
+                    rate,
+                } => format!("close {} {} {} {}", id, sweep_to, amount, rate),
+                OperationKind::ScheduleTransfer {
+                    id,
+                    sender_id,
+                    receiver_id,
+                    amount,
+                    start,
+                    interval,
+                } => format!(
+                    "schedule_transfer {} {} {} {} {} {}",
+                    id,
+                    sender_id,
+                    receiver_id,
+                    amount,
+                    start,
+                    encode_optional(interval)
+                ),
+                OperationKind::CancelSchedule { id } => format!("cancel_schedule {}", id),
+                OperationKind::SkipSchedule { id } => format!("skip_schedule {}", id),
+                OperationKind::SetFee { operation, fee } => {
+                    format!("set_fee {} {}", operation, encode_optional(fee))
+                }
+                OperationKind::Hold {
+                    id,
+                    account_id,
+                    amount,
+                } => format!("hold {} {} {}", id, account_id, amount),
+                OperationKind::Capture {
+                    id,
+                    account_id,
+                    amount,
+                } => format!("capture {} {} {}", id, account_id, amount),
+                OperationKind::Release {
+                    id,
+                    account_id,
+                    amount,
+                } => format!("release {} {} {}", id, account_id, amount),
+                OperationKind::SetLimit { id, kind, limit } => format!(
+                    "set_limit {} {} {}",
+                    encode_optional(id),
+                    kind,
+                    encode_optional(limit)
+                ),
+                OperationKind::InterbankSend {
+                    sender_id,
+                    bank_id,
+                    receiver_id,
+                    amount,
+                    rate,
+                } => format!(
+                    "interbank_send {} {} {} {} {}",
+                    sender_id, bank_id, receiver_id, amount, rate
+                ),
+                OperationKind::InterbankReceive {
+                    operation_id,
+                    bank_id,
+                    sender_id,
+                    receiver_id,
+                    amount,
+                } => format!(
+                    "interbank_receive {} {} {} {} {}",
+                    operation_id, bank_id, sender_id, receiver_id, amount
+                ),
+                OperationKind::UpdateProfile { id, field } => format!(
+                    "update_profile {} {} {}",
+                    id,
+                    field.name(),
+                    encode_optional(field.value().map(|value| encode_text(&value)))
+                ),
+            };
+
+            format!(
+                "operation {} {} {} {} {} {} {}",
+                bank_id,
+                operation.id,
+                operation.timestamp,
+                encode_optional(operation.key),
+                encode_optional(operation.schedule),
+                encode_side(operation.fee),
+                kind
+            )
+        }
+    }
+}
+
+fn decode_record(payload: &str) -> Option<Record> {
+    let parts: Vec<&str> = payload.split(' ').collect();
+
+    match parts[..] {
+        ["new_bank"] => Some(Record::NewBank),
+        ["restore_bank", id] => Some(Record::RestoreBank {
+            id: id.parse().ok()?,
+        }),
+        ["batch", size] => match size.parse().ok()? {
+            0 => None,
+            size => Some(Record::Batch { size }),
+        },
+        ["operation", bank_id, operation_id, timestamp, key, schedule, fee_account, fee, ref kind @ ..] =>
+        {
+            let kind = match *kind {
+                ["register", id, balance, currency] => OperationKind::Register {
+                    id: AccountID::parse_str(id).ok()?,
+                    balance: balance.parse().ok()?,
+                    currency: currency.parse().ok()?,
+                },
+                ["deposit", id, amount] => OperationKind::Deposit {
+                    id: AccountID::parse_str(id).ok()?,
+                    amount: amount.parse().ok()?,
+                },
+                ["withdraw", id, amount] => OperationKind::Withdraw {
+                    id: AccountID::parse_str(id).ok()?,
+                    amount: amount.parse().ok()?,
+                },
+                ["transfer", sender_id, receiver_id, amount, rate] => OperationKind::Transfer {
+                    sender_id: AccountID::parse_str(sender_id).ok()?,
+                    receiver_id: AccountID::parse_str(receiver_id).ok()?,
+                    amount: amount.parse().ok()?,
+                    rate: rate.parse().ok()?,
+                },
+                ["set_exchange_rate", from, to, rate] => OperationKind::SetExchangeRate {
+                    from: from.parse().ok()?,
+                    to: to.parse().ok()?,
+                    rate: rate.parse().ok()?,
+                },
+                ["set_overdraft_limit", id, limit] => OperationKind::SetOverdraftLimit {
+                    id: AccountID::parse_str(id).ok()?,
+                    limit: limit.parse().ok()?,
+                },
+                ["set_interest_rate", id, rate] => OperationKind::SetInterestRate {
+                    id: AccountID::parse_str(id).ok()?,
+                    rate: rate.parse().ok()?,
+                },
+                ["interest", id, amount] => OperationKind::Interest {
+                    id: AccountID::parse_str(id).ok()?,
+                    amount: amount.parse().ok()?,
+                },
+                ["reverse", reversed_id, debit_id, debit_amount, credit_id, credit_amount] => {
+                    OperationKind::Reverse {
+                        operation_id: OperationID::parse_str(reversed_id).ok()?,
+                        debit: decode_side(debit_id, debit_amount)?,
+                        credit: decode_side(credit_id, credit_amount)?,
+                    }
+                }
+                ["freeze", id] => OperationKind::Freeze {
+                    id: AccountID::parse_str(id).ok()?,
+                },
+                ["unfreeze", id] => OperationKind::Unfreeze {
+                    id: AccountID::parse_str(id).ok()?,
+                },
+                ["close", id] => OperationKind::Close {
+                    id: AccountID::parse_str(id).ok()?,
+                    sweep_to: None,
+                    amount: Money::ZERO,
+                    rate: Rate::ONE,
+                },
+                ["close", id, sweep_to, amount, rate] => OperationKind::Close {
+                    id: AccountID::parse_str(id).ok()?,
+                    sweep_to: Some(AccountID::parse_str(sweep_to).ok()?),
+                    amount: amount.parse().ok()?,
+                    rate: rate.parse().ok()?,
+                },
+                ["schedule_transfer", id, sender_id, receiver_id, amount, start, interval] => {
+                    OperationKind::ScheduleTransfer {
+                        id: ScheduleID::parse_str(id).ok()?,
+                        sender_id: AccountID::parse_str(sender_id).ok()?,
+                        receiver_id: AccountID::parse_str(receiver_id).ok()?,
+                        amount: amount.parse().ok()?,
+                        start: start.parse().ok()?,
+                        interval: match interval {
+                            "-" => None,
+                            interval => Some(interval.parse().ok()?),
+                        },
+                    }
+                }
+                ["cancel_schedule", id] => OperationKind::CancelSchedule {
+                    id: ScheduleID::parse_str(id).ok()?,
+                },
+                ["skip_schedule", id] => OperationKind::SkipSchedule {
+                    id: ScheduleID::parse_str(id).ok()?,
+                },
+                ["set_fee", operation, fee] => OperationKind::SetFee {
+                    operation: operation.parse().ok()?,
+                    fee: match fee {
+                        "-" => None,
+                        fee => Some(fee.parse().ok()?),
+                    },
+                },
+                ["hold", id, account_id, amount] => OperationKind::Hold {
+                    id: HoldID::parse_str(id).ok()?,
+                    account_id: AccountID::parse_str(account_id).ok()?,
+                    amount: amount.parse().ok()?,
+                },
+                ["capture", id, account_id, amount] => OperationKind::Capture {
+                    id: HoldID::parse_str(id).ok()?,
+                    account_id: AccountID::parse_str(account_id).ok()?,
+                    amount: amount.parse().ok()?,
+                },
+                ["release", id, account_id, amount] => OperationKind::Release {
+                    id: HoldID::parse_str(id).ok()?,
+                    account_id: AccountID::parse_str(account_id).ok()?,
+                    amount: amount.parse().ok()?,
+                },
+                ["set_limit", id, kind, limit] => OperationKind::SetLimit {
+                    id: match id {
+                        "-" => None,
+                        id => Some(AccountID::parse_str(id).ok()?),
+                    },
+                    kind: kind.parse().ok()?,
+                    limit: match limit {
+                        "-" => None,
+                        limit => Some(limit.parse().ok()?),
+                    },
+                },
+                ["interbank_send", sender_id, bank_id, receiver_id, amount, rate] => {
+                    OperationKind::InterbankSend {
+                        sender_id: AccountID::parse_str(sender_id).ok()?,
+                        bank_id: bank_id.parse().ok()?,
+                        receiver_id: AccountID::parse_str(receiver_id).ok()?,
+                        amount: amount.parse().ok()?,
+                        rate: rate.parse().ok()?,
+                    }
+                }
+                ["interbank_receive", sent_id, bank_id, sender_id, receiver_id, amount] => {
+                    OperationKind::InterbankReceive {
+                        operation_id: OperationID::parse_str(sent_id).ok()?,
+                        bank_id: bank_id.parse().ok()?,
+                        sender_id: AccountID::parse_str(sender_id).ok()?,
+                        receiver_id: AccountID::parse_str(receiver_id).ok()?,
+                        amount: amount.parse().ok()?,
+                    }
+                }
+                ["update_profile", id, field, value] => OperationKind::UpdateProfile {
+                    id: AccountID::parse_str(id).ok()?,
+                    field: match value {
+                        "-" => ProfileField::parse(field, "none").ok()?,
+                        value => ProfileField::parse(field, &decode_text(value)?).ok()?,
+                    },
+                },
+                _ => return None,
+            };
+
+            Some(Record::Operation {
+                bank_id: bank_id.parse().ok()?,
+                operation: Operation {
+                    id: OperationID::parse_str(operation_id).ok()?,
+                    kind,
+                    key: match key {
+                        "-" => None,
+                        key => Some(key.parse().ok()?),
+                    },
+                    schedule: match schedule {
+                        "-" => None,
+                        schedule => Some(ScheduleID::parse_str(schedule).ok()?),
+                    },
+                    fee: decode_side(fee_account, fee)?,
+                    timestamp: timestamp.parse().ok()?,
+                },
+            })
+        }
+        _ => None,
+    }
+}
+
+pub(crate) fn encode_optional<T: std::fmt::Display>(value: Option<T>) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => String::from("-"),
+    }
+}
+
+// Text may contain spaces, so `%` and spaces are stored as `%25` and `%20`.
+pub(crate) fn encode_text(text: &str) -> String {
+    text.replace('%', "%25").replace(' ', "%20")
+}
+
+pub(crate) fn decode_text(text: &str) -> Option<String> {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(index) = rest.find('%') {
+        decoded.push_str(&rest[..index]);
+        decoded.push(match rest.get(index + 1..index + 3)? {
+            "20" => ' ',
+            "25" => '%',
+            _ => return None,
+        });
+        rest = &rest[index + 3..];
+    }
+    decoded.push_str(rest);
+
+    Some(decoded)
+}
+
+// Missing account and amount pair, like a side of a reversal, is stored as `- -`.
+fn encode_side(side: Option<(AccountID, Money)>) -> String {
+    match side {
+        Some((id, amount)) => format!("{} {}", id, amount),
+        None => String::from("- -"),
+    }
+}
+
+fn decode_side(id: &str, amount: &str) -> Option<Option<(AccountID, Money)>> {
+    match (id, amount) {
+        ("-", "-") => Some(None),
+        _ => Some(Some((AccountID::parse_str(id).ok()?, amount.parse().ok()?))),
+    }
+}
+
+// CRC-32 (IEEE 802.3), bitwise version is fast enough for short records
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bank::currency::Currency;
+    use crate::bank::fee::FeeOperation;
+    use crate::bank::limit::LimitKind;
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("bank-wal-{}", OperationID::new()))
+    }
+
+    fn records() -> Vec<Record> {
+        let sender_id = AccountID::new();
+        let receiver_id = AccountID::new();
+
+        vec![
+            Record::NewBank,
+            Record::Operation {
+                bank_id: 1,
+                operation: Operation {
+                    id: OperationID::new(),
+                    key: None,
+                    kind: OperationKind::Register {
+                        id: sender_id,
+                        balance: Money::from_minor(100),
+                        currency: Currency::default(),
+                    },
+                    schedule: None,
+                    fee: None,
+                    timestamp: 1709296200,
+                },
+            },
+            Record::Operation {
+                bank_id: 1,
+                operation: Operation {
+                    id: OperationID::new(),
+                    key: None,
+                    kind: OperationKind::Register {
+                        id: receiver_id,
+                        balance: Money::from_minor(0),
+                        currency: Currency::default(),
+                    },
+                    schedule: None,
+                    fee: None,
+                    timestamp: 0,
+                },
+            },
+            Record::Operation {
+                bank_id: 1,
+                operation: Operation {
+                    id: OperationID::new(),
+                    key: Some("retry-1".parse().unwrap()),
+                    kind: OperationKind::Deposit {
+                        id: sender_id,
+                        amount: Money::from_minor(50),
+                    },
+                    schedule: None,
+                    fee: None,
+                    timestamp: 0,
+                },
+            },
+            Record::Operation {
+                bank_id: 1,
+                operation: Operation {
+                    id: OperationID::new(),
+                    key: None,
+                    kind: OperationKind::Withdraw {
+                        id: sender_id,
+                        amount: Money::from_minor(10),
+                    },
+                    schedule: None,
+                    fee: Some((
+                        AccountID::fee_account(Currency::default()),
+                        Money::from_minor(1),
+                    )),
+                    timestamp: 0,
+                },
+            },
+            Record::Operation {
+                bank_id: 1,
+                operation: Operation {
+                    id: OperationID::new(),
+                    key: None,
+                    kind: OperationKind::Transfer {
+                        sender_id,
+                        receiver_id,
+                        amount: Money::from_minor(20),
+                        rate: Rate::ONE,
+                    },
+                    schedule: Some(ScheduleID::new()),
+                    fee: None,
+                    timestamp: 0,
+                },
+            },
+            Record::Operation {
+                bank_id: 1,
+                operation: Operation {
+                    id: OperationID::new(),
+                    key: None,
+                    kind: OperationKind::SetExchangeRate {
+                        from: Currency::default(),
+                        to: "EUR".parse().unwrap(),
+                        rate: "0.92".parse().unwrap(),
+                    },
+                    schedule: None,
+                    fee: None,
+                    timestamp: 0,
+                },
+            },
+            Record::Operation {
+                bank_id: 1,
+                operation: Operation {
+                    id: OperationID::new(),
+                    key: None,
+                    kind: OperationKind::SetOverdraftLimit {
+                        id: sender_id,
+                        limit: Money::from_minor(5000),
+                    },
+                    schedule: None,
+                    fee: None,
+                    timestamp: 0,
+                },
+            },
+            Record::Operation {
+                bank_id: 1,
+                operation: Operation {
+                    id: OperationID::new(),
+                    key: None,
+                    kind: OperationKind::Reverse {
+                        operation_id: OperationID::new(),
+                        debit: Some((receiver_id, Money::from_minor(20))),
+                        credit: Some((sender_id, Money::from_minor(20))),
+                    },
+                    schedule: None,
+                    fee: None,
+                    timestamp: 0,
+                },
+            },
+            Record::Operation {
+                bank_id: 1,
+                operation: Operation {
+                    id: OperationID::new(),
+                    key: None,
+                    kind: OperationKind::Reverse {
+                        operation_id: OperationID::new(),
+                        debit: None,
+                        credit: Some((sender_id, Money::from_minor(7))),
+                    },
+                    schedule: None,
+                    fee: None,
+                    timestamp: 0,
+                },
+            },
+            Record::Operation {
+                bank_id: 1,
+                operation: Operation {
+                    id: OperationID::new(),
+                    key: None,
+                    kind: OperationKind::Freeze { id: sender_id },
+                    schedule: None,
+                    fee: None,
+                    timestamp: 0,
+                },
+            },
+            Record::Operation {
+                bank_id: 1,
+                operation: Operation {
+                    id: OperationID::new(),
+                    key: None,
+                    kind: OperationKind::Unfreeze { id: sender_id },
+                    schedule: None,
+                    fee: None,
+                    timestamp: 0,
+                },
+            },
+            Record::Operation {
+                bank_id: 1,
+                operation: Operation {
+                    id: OperationID::new(),
+                    key: None,
+                    kind: OperationKind::Close {
+                        id: AccountID::new(),
+                        sweep_to: None,
+                        amount: Money::ZERO,
+                        rate: Rate::ONE,
+                    },
+                    schedule: None,
+                    fee: None,
+                    timestamp: 0,
+                },
+            },
+            Record::Operation {
+                bank_id: 1,
+                operation: Operation {
+                    id: OperationID::new(),
+                    key: None,
+                    kind: OperationKind::Close {
+                        id: AccountID::new(),
+                        sweep_to: Some(AccountID::new()),
+                        amount: Money::from_minor(12),
+                        rate: "0.5".parse().unwrap(),
+                    },
+                    schedule: None,
+                    fee: None,
+                    timestamp: 0,
+                },
+            },
+            Record::Operation {
+                bank_id: 1,
+                operation: Operation {
+                    id: OperationID::new(),
+                    key: None,
+                    kind: OperationKind::ScheduleTransfer {
+                        id: ScheduleID::new(),
+                        sender_id,
+                        receiver_id,
+                        amount: Money::from_minor(30),
+                        start: 1_700_000_000,
+                        interval: Some(86400),
+                    },
+                    schedule: None,
+                    fee: None,
+                    timestamp: 0,
+                },
+            },
+            Record::Operation {
+                bank_id: 1,
+                operation: Operation {
+                    id: OperationID::new(),
+                    key: None,
+                    kind: OperationKind::CancelSchedule {
+                        id: ScheduleID::new(),
+                    },
+                    schedule: None,
+                    fee: None,
+                    timestamp: 0,
+                },
+            },
+            Record::Operation {
+                bank_id: 1,
+                operation: Operation {
+                    id: OperationID::new(),
+                    key: None,
+                    kind: OperationKind::SkipSchedule {
+                        id: ScheduleID::new(),
+                    },
+                    schedule: None,
+                    fee: None,
+                    timestamp: 0,
+                },
+            },
+            Record::Operation {
+                bank_id: 1,
+                operation: Operation {
+                    id: OperationID::new(),
+                    key: None,
+                    kind: OperationKind::SetInterestRate {
+                        id: sender_id,
+                        rate: "0.035".parse().unwrap(),
+                    },
+                    schedule: None,
+                    fee: None,
+                    timestamp: 0,
+                },
+            },
+            Record::Operation {
+                bank_id: 1,
+                operation: Operation {
+                    id: OperationID::new(),
+                    key: None,
+                    kind: OperationKind::Interest {
+                        id: sender_id,
+                        amount: Money::from_minor(3),
+                    },
+                    schedule: None,
+                    fee: None,
+                    timestamp: 0,
+                },
+            },
+            Record::Operation {
+                bank_id: 1,
+                operation: Operation {
+                    id: OperationID::new(),
+                    key: None,
+                    kind: OperationKind::SetFee {
+                        operation: FeeOperation::Transfer,
+                        fee: Some("capped:0.01:5".parse().unwrap()),
+                    },
+                    schedule: None,
+                    fee: None,
+                    timestamp: 0,
+                },
+            },
+            Record::Operation {
+                bank_id: 1,
+                operation: Operation {
+                    id: OperationID::new(),
+                    key: None,
+                    kind: OperationKind::SetFee {
+                        operation: FeeOperation::Withdraw,
+                        fee: None,
+                    },
+                    schedule: None,
+                    fee: None,
+                    timestamp: 0,
+                },
+            },
+            Record::Operation {
+                bank_id: 1,
+                operation: Operation {
+                    id: OperationID::new(),
+                    key: None,
+                    kind: OperationKind::UpdateProfile {
+                        id: sender_id,
+                        field: ProfileField::parse("email", "jane@example.com").unwrap(),
+                    },
+                    schedule: None,
+                    fee: None,
+                    timestamp: 0,
+                },
+            },
+            Record::Operation {
+                bank_id: 1,
+                operation: Operation {
+                    id: OperationID::new(),
+                    key: None,
+                    kind: OperationKind::UpdateProfile {
+                        id: sender_id,
+                        field: ProfileField::parse("name", "Jane 100% Doe").unwrap(),
+                    },
+                    schedule: None,
+                    fee: None,
+                    timestamp: 0,
+                },
+            },
+            Record::Operation {
+                bank_id: 1,
+                operation: Operation {
+                    id: OperationID::new(),
+                    key: None,
+                    kind: OperationKind::UpdateProfile {
+                        id: sender_id,
+                        field: ProfileField::Alias(None),
+                    },
+                    schedule: None,
+                    fee: None,
+                    timestamp: 0,
+                },
+            },
+            Record::Operation {
+                bank_id: 1,
+                operation: Operation {
+                    id: OperationID::new(),
+                    key: None,
+                    kind: OperationKind::SetLimit {
+                        id: Some(sender_id),
+                        kind: LimitKind::Daily,
+                        limit: Some(Money::from_minor(50000)),
+                    },
+                    schedule: None,
+                    fee: None,
+                    timestamp: 0,
+                },
+            },
+            Record::Operation {
+                bank_id: 1,
+                operation: Operation {
+                    id: OperationID::new(),
+                    key: None,
+                    kind: OperationKind::SetLimit {
+                        id: None,
+                        kind: LimitKind::PerTransaction,
+                        limit: None,
+                    },
+                    schedule: None,
+                    fee: None,
+                    timestamp: 0,
+                },
+            },
+            Record::Operation {
+                bank_id: 1,
+                operation: Operation {
+                    id: OperationID::new(),
+                    key: None,
+                    kind: OperationKind::Capture {
+                        id: HoldID::new(),
+                        account_id: sender_id,
+                        amount: Money::from_minor(750),
+                    },
+                    schedule: None,
+                    fee: None,
+                    timestamp: 0,
+                },
+            },
+            Record::Operation {
+                bank_id: 1,
+                operation: Operation {
+                    id: OperationID::new(),
+                    key: None,
+                    kind: OperationKind::InterbankSend {
+                        sender_id,
+                        bank_id: 2,
+                        receiver_id,
+                        amount: Money::from_minor(300),
+                        rate: "0.5".parse().unwrap(),
+                    },
+                    schedule: None,
+                    fee: None,
+                    timestamp: 0,
+                },
+            },
+            Record::Operation {
+                bank_id: 2,
+                operation: Operation {
+                    id: OperationID::new(),
+                    key: None,
+                    kind: OperationKind::InterbankReceive {
+                        operation_id: OperationID::new(),
+                        bank_id: 1,
+                        sender_id,
+                        receiver_id,
+                        amount: Money::from_minor(150),
+                    },
+                    schedule: None,
+                    fee: None,
+                    timestamp: 0,
+                },
+            },
+            Record::RestoreBank { id: 1 },
+        ]
+    }
+
+    #[test]
+    fn crc32_works() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn encode_text_works() {
+        for text in ["Jane Doe", "100%", "%20", "plain"] {
+            assert_eq!(decode_text(&encode_text(text)).as_deref(), Some(text));
+        }
+        assert_eq!(encode_text("Jane 100%"), "Jane%20100%25");
+        assert!(!encode_text("Jane Doe").contains(' '));
+        assert_eq!(decode_text("Jane%2"), None);
+        assert_eq!(decode_text("Jane%41"), None);
+    }
+
+    #[test]
+    fn append_and_open_works() {
+        let dir = temp_dir();
+        let expected = records();
+
+        let (mut wal, stored) = Wal::open(&dir, 1).unwrap();
+        assert!(stored.is_empty());
+        for record in expected.iter() {
+            wal.append(record).unwrap();
+        }
+        drop(wal);
+
+        let (_, stored) = Wal::open(&dir, 1).unwrap();
+        assert_eq!(stored, expected);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn append_after_failed_write_works() {
+        let dir = temp_dir();
+        let expected = records();
+
+        let (mut wal, _) = Wal::open(&dir, 1).unwrap();
+        wal.append(&expected[0]).unwrap();
+
+        // part of a record got to the file before writing failed
+        let path = segment_path(&dir, 1);
+        OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"0badc0de new_b")
+            .unwrap();
+        wal.file = File::open(&path).unwrap();
+        assert!(matches!(wal.append(&expected[1]), Err(WalError::Io(_))));
+
+        for record in expected[1..].iter() {
+            wal.append(record).unwrap();
+        }
+        drop(wal);
+
+        let (_, stored) = Wal::open(&dir, 1).unwrap();
+        assert_eq!(stored, expected);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn open_cuts_torn_tail_works() {
+        let dir = temp_dir();
+        let expected = records();
+
+        let (mut wal, _) = Wal::open(&dir, 1).unwrap();
+        for record in expected.iter() {
+            wal.append(record).unwrap();
+        }
+        drop(wal);
+
+        let path = segment_path(&dir, 1);
+        let full_len = std::fs::metadata(&path).unwrap().len();
+        let file = OpenOptions::new().write(true).open(&path).unwrap();
+        file.set_len(full_len - 5).unwrap();
+        drop(file);
+
+        let (mut wal, stored) = Wal::open(&dir, 1).unwrap();
+        assert_eq!(stored, expected[..expected.len() - 1]);
+
+        // log stays appendable after the torn record was cut off
+        wal.append(&Record::NewBank).unwrap();
+        drop(wal);
+
+        let (_, stored) = Wal::open(&dir, 1).unwrap();
+        assert_eq!(stored.len(), expected.len());
+        assert_eq!(stored.last(), Some(&Record::NewBank));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn open_cuts_torn_batch_works() {
+        let dir = temp_dir();
+        let expected = records();
+        let (head, batch) = expected.split_at(2);
+
+        let (mut wal, _) = Wal::open(&dir, 1).unwrap();
+        for record in head.iter() {
+            wal.append(record).unwrap();
+        }
+        wal.append_batch(batch).unwrap();
+        drop(wal);
+
+        let (_, stored) = Wal::open(&dir, 1).unwrap();
+        assert_eq!(stored, expected);
+
+        // only the last record of the batch is torn, but the whole batch is dropped
+        let path = segment_path(&dir, 1);
+        let full_len = std::fs::metadata(&path).unwrap().len();
+        let file = OpenOptions::new().write(true).open(&path).unwrap();
+        file.set_len(full_len - 5).unwrap();
+        drop(file);
+
+        let (mut wal, stored) = Wal::open(&dir, 1).unwrap();
+        assert_eq!(stored, head);
+
+        wal.append_batch(&[Record::NewBank, Record::NewBank])
+            .unwrap();
+        drop(wal);
+
+        let (_, stored) = Wal::open(&dir, 1).unwrap();
+        assert_eq!(stored.len(), head.len() + 2);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn open_detects_corrupted_record_works() {
+        let dir = temp_dir();
+
+        let (mut wal, _) = Wal::open(&dir, 1).unwrap();
+        for record in records().iter() {
+            wal.append(record).unwrap();
+        }
+        drop(wal);
+
+        let path = segment_path(&dir, 1);
+        let data = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, data.replacen("deposit", "dEposit", 1)).unwrap();
+
+        assert!(matches!(
+            Wal::open(&dir, 1),
+            Err(WalError::Corrupted {
+                segment: 1,
+                line: 4
+            })
+        ));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn open_detects_torn_tail_in_old_segment_works() {
+        let dir = temp_dir();
+
+        let (mut wal, _) = Wal::open(&dir, 1).unwrap();
+        wal.append(&Record::NewBank).unwrap();
+        wal.rotate().unwrap();
+        wal.append(&Record::NewBank).unwrap();
+        drop(wal);
+
+        let path = segment_path(&dir, 1);
+        let full_len = std::fs::metadata(&path).unwrap().len();
+        let file = OpenOptions::new().write(true).open(&path).unwrap();
+        file.set_len(full_len - 1).unwrap();
+        drop(file);
+
+        assert!(matches!(
+            Wal::open(&dir, 1),
+            Err(WalError::Corrupted {
+                segment: 1,
+                line: 1
+            })
+        ));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rotate_and_compact_works() {
+        let dir = temp_dir();
+        let expected = records();
+
+        let (mut wal, _) = Wal::open(&dir, 1).unwrap();
+        wal.append(&expected[0]).unwrap();
+        assert_eq!(wal.rotate().unwrap(), 2);
+        wal.append(&expected[1]).unwrap();
+        assert_eq!(wal.rotate().unwrap(), 3);
+        wal.append(&expected[2]).unwrap();
+        drop(wal);
+
+        let (mut wal, stored) = Wal::open(&dir, 1).unwrap();
+        assert_eq!(wal.segment(), 3);
+        assert_eq!(stored, expected[..3]);
+
+        wal.compact(3).unwrap();
+        assert_eq!(list_segments(&dir).unwrap(), vec![3]);
+        drop(wal);
+
+        let (_, stored) = Wal::open(&dir, 3).unwrap();
+        assert_eq!(stored, expected[2..3]);
+
+        // segments skipped on open are removed as well
+        let (mut wal, _) = Wal::open(&dir, 3).unwrap();
+        wal.rotate().unwrap();
+        drop(wal);
+        let (wal, stored) = Wal::open(&dir, 4).unwrap();
+        assert!(stored.is_empty());
+        assert_eq!(wal.segment(), 4);
+        assert_eq!(list_segments(&dir).unwrap(), vec![4]);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
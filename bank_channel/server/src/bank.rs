@@ -1,8 +1,10 @@
 pub mod account;
 pub mod log;
+pub mod snapshot;
 
 use account::*;
 use log::*;
+use snapshot::*;
 use std::collections::HashMap;
 
 #[derive(Debug, PartialEq)]
@@ -12,6 +14,7 @@ pub enum BankError {
     ZeroAmount,
     InsufficientFunds,
     TransferToItself,
+    Overflow,
 }
 
 impl std::fmt::Display for BankError {
@@ -22,6 +25,7 @@ impl std::fmt::Display for BankError {
             BankError::ZeroAmount => write!(f, "Zero amount"),
             BankError::InsufficientFunds => write!(f, "Insufficient funds"),
             BankError::TransferToItself => write!(f, "Transfer to itself"),
+            BankError::Overflow => write!(f, "Balance overflow"),
         }
     }
 }
@@ -32,14 +36,28 @@ impl std::error::Error for BankError {}
 pub struct Bank {
     accounts: HashMap<AccountID, Account>,
     operations_log: OperationsLog,
+    base_snapshot: Snapshot,
 }
 
 pub type Result<T> = std::result::Result<T, BankError>;
 
 impl Bank {
     pub fn restore<'a, I: Iterator<Item = &'a Operation>>(operations: I) -> Result<Bank> {
+        Self::restore_from_snapshot(Snapshot::default(), operations)
+    }
+
+    /// Restores bank from `snapshot` replaying only operations made after it.
+    pub fn restore_from_snapshot<'a, I: Iterator<Item = &'a Operation>>(
+        snapshot: Snapshot,
+        operations: I,
+    ) -> Result<Bank> {
         let mut bank = Self::default();
 
+        for account in snapshot.accounts.iter() {
+            bank.do_register_account(*account)?;
+        }
+        bank.base_snapshot = snapshot;
+
         for operation in operations {
             match operation.kind {
                 OperationKind::Register { id, balance } => {
@@ -89,6 +107,29 @@ impl Bank {
         Ok(operation_id)
     }
 
+    /// Current state of accounts, enough to restore bank without replaying its log.
+    pub fn snapshot(&self) -> Snapshot {
+        let last_operation_id = self
+            .operations_log
+            .get_all_operations()
+            .last()
+            .map(|operation| operation.id)
+            .or(self.base_snapshot.last_operation_id);
+
+        let mut accounts: Vec<Account> = self.accounts.values().copied().collect();
+        accounts.sort_by_key(|account| account.id);
+
+        Snapshot {
+            accounts,
+            last_operation_id,
+        }
+    }
+
+    /// Snapshot the bank was restored from, operations log contains only what happened after it.
+    pub fn base_snapshot(&self) -> &Snapshot {
+        &self.base_snapshot
+    }
+
     pub fn get_operation(&self, operation_id: OperationID) -> Option<&Operation> {
         self.operations_log.get(operation_id)
     }
@@ -100,24 +141,36 @@ impl Bank {
         }
     }
 
-    fn update_account_balance_by_amount(&mut self, id: AccountID, amount: i64) -> Result<()> {
+    fn increase_account_balance(&mut self, id: AccountID, amount: u64) -> Result<()> {
         if amount == 0 {
             return Err(BankError::ZeroAmount);
         }
 
         let account = self.accounts.get_mut(&id).ok_or(BankError::NotFound)?;
+        account.balance = account
+            .balance
+            .checked_add(amount)
+            .ok_or(BankError::Overflow)?;
 
-        let result_balance = account.balance as i64 + amount;
-        if result_balance < 0 {
-            return Err(BankError::InsufficientFunds);
+        Ok(())
+    }
+
+    fn decrease_account_balance(&mut self, id: AccountID, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Err(BankError::ZeroAmount);
         }
 
-        account.balance = result_balance as u64;
+        let account = self.accounts.get_mut(&id).ok_or(BankError::NotFound)?;
+        account.balance = account
+            .balance
+            .checked_sub(amount)
+            .ok_or(BankError::InsufficientFunds)?;
+
         Ok(())
     }
 
     fn do_deposit(&mut self, id: AccountID, amount: u64) -> Result<()> {
-        self.update_account_balance_by_amount(id, amount as i64)
+        self.increase_account_balance(id, amount)
     }
 
     pub fn deposit(&mut self, id: AccountID, amount: u64) -> Result<OperationID> {
@@ -130,7 +183,7 @@ impl Bank {
     }
 
     fn do_withdraw(&mut self, id: AccountID, amount: u64) -> Result<()> {
-        self.update_account_balance_by_amount(id, -(amount as i64))
+        self.decrease_account_balance(id, amount)
     }
 
     pub fn withdraw(&mut self, id: AccountID, amount: u64) -> Result<OperationID> {
@@ -152,8 +205,25 @@ impl Bank {
             return Err(BankError::TransferToItself);
         }
 
-        self.update_account_balance_by_amount(sender_id, -(amount as i64))?;
-        self.update_account_balance_by_amount(receiver_id, amount as i64)?;
+        if amount == 0 {
+            return Err(BankError::ZeroAmount);
+        }
+
+        // validate both sides before touching any balance,
+        // so a failed transfer never leaves a half-applied debit
+        let sender_balance = self.get_balance(sender_id)?;
+        let receiver_balance = self.get_balance(receiver_id)?;
+
+        if sender_balance < amount {
+            return Err(BankError::InsufficientFunds);
+        }
+
+        if receiver_balance.checked_add(amount).is_none() {
+            return Err(BankError::Overflow);
+        }
+
+        self.decrease_account_balance(sender_id, amount)?;
+        self.increase_account_balance(receiver_id, amount)?;
 
         Ok(())
     }
@@ -350,6 +420,64 @@ mod tests {
         assert_eq!(bank.get_balance(receiver_id).unwrap(), 250);
     }
 
+    #[test]
+    fn failed_transfer_keeps_balances_works() {
+        let mut bank = Bank::default();
+        let sender = Account::new(100);
+        let receiver = Account::new(200);
+        let sender_id = sender.id;
+        let receiver_id = receiver.id;
+        let account_undefined_id = AccountID::new();
+
+        bank.register_account(sender).unwrap();
+        bank.register_account(receiver).unwrap();
+
+        let failed_transfers = vec![
+            (sender_id, receiver_id, 0, BankError::ZeroAmount),
+            (sender_id, sender_id, 50, BankError::TransferToItself),
+            (sender_id, receiver_id, 1000, BankError::InsufficientFunds),
+            (sender_id, account_undefined_id, 50, BankError::NotFound),
+            (account_undefined_id, receiver_id, 50, BankError::NotFound),
+        ];
+
+        for (from_id, to_id, amount, err) in failed_transfers {
+            assert_eq!(bank.transfer(from_id, to_id, amount), Err(err));
+            assert_eq!(bank.get_balance(sender_id).unwrap(), 100);
+            assert_eq!(bank.get_balance(receiver_id).unwrap(), 200);
+            assert_eq!(bank.get_all_operations().count(), 2);
+        }
+    }
+
+    #[test]
+    fn overflow_works() {
+        let mut bank = Bank::default();
+        let rich = Account::new(u64::MAX);
+        let poor = Account::new(10);
+        let rich_id = rich.id;
+        let poor_id = poor.id;
+
+        bank.register_account(rich).unwrap();
+        bank.register_account(poor).unwrap();
+
+        assert_eq!(bank.deposit(rich_id, 1), Err(BankError::Overflow));
+        assert_eq!(bank.transfer(poor_id, rich_id, 5), Err(BankError::Overflow));
+        assert_eq!(
+            bank.withdraw(poor_id, u64::MAX),
+            Err(BankError::InsufficientFunds)
+        );
+        assert_eq!(
+            bank.transfer(rich_id, poor_id, u64::MAX),
+            Err(BankError::Overflow)
+        );
+
+        assert_eq!(bank.get_balance(rich_id).unwrap(), u64::MAX);
+        assert_eq!(bank.get_balance(poor_id).unwrap(), 10);
+        assert_eq!(bank.get_all_operations().count(), 2);
+
+        bank.withdraw(rich_id, u64::MAX - 1).unwrap();
+        assert_eq!(bank.get_balance(rich_id).unwrap(), 1);
+    }
+
     #[test]
     fn get_all_operations_works() {
         let mut bank = Bank::default();
@@ -535,4 +663,44 @@ mod tests {
 
         assert_eq!(bank1, bank2)
     }
+
+    #[test]
+    fn restore_from_snapshot_works() {
+        let mut bank1 = Bank::default();
+
+        let account1 = Account::new(100);
+        let account2 = Account::new(200);
+
+        let account1_id = account1.id;
+        let account2_id = account2.id;
+
+        bank1.register_account(account1).unwrap();
+        bank1.register_account(account2).unwrap();
+        let operation_id = bank1.transfer(account1_id, account2_id, 30).unwrap();
+
+        let snapshot = bank1.snapshot();
+        assert_eq!(snapshot.last_operation_id, Some(operation_id));
+        assert_eq!(snapshot.accounts.len(), 2);
+        assert!(snapshot.accounts[0].id < snapshot.accounts[1].id);
+
+        let operations_count = bank1.get_all_operations().count();
+        bank1.deposit(account1_id, 50).unwrap();
+        bank1.withdraw(account2_id, 20).unwrap();
+
+        let bank2 = Bank::restore_from_snapshot(
+            snapshot.clone(),
+            bank1.get_all_operations().skip(operations_count),
+        )
+        .unwrap();
+
+        assert_eq!(bank2.base_snapshot(), &snapshot);
+        assert_eq!(bank2.get_all_operations().count(), 2);
+        assert_eq!(bank2.get_balance(account1_id), Ok(120));
+        assert_eq!(bank2.get_balance(account2_id), Ok(210));
+        assert_eq!(bank2.snapshot(), bank1.snapshot());
+        assert_eq!(
+            bank2.snapshot().last_operation_id,
+            bank1.snapshot().last_operation_id
+        );
+    }
 }
@@ -1,7 +1,7 @@
 use std::fmt;
 use uuid::Uuid;
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
 pub struct AccountID(Uuid);
 pub type Error = uuid::Error;
 
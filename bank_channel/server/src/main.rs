@@ -1,24 +1,31 @@
-use server::server::actor::repository_actor;
-use server::server::command::Command;
+use server::server::actor::{repository_actor, Request};
 use server::server::handler::handle;
 use server::server::repository::Repository;
 use std::io::{BufReader, Write};
 use std::net::TcpListener;
-use std::sync::mpsc::{channel, Sender};
+use std::sync::mpsc::channel;
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
 const ADDR: &str = "127.0.0.1:1337";
+const DATA_DIR: &str = "bank_data";
 
 fn main() -> Result<()> {
     let listener = TcpListener::bind(ADDR)?;
 
     println!("Listening on {}", listener.local_addr()?);
 
-    let (sender, receiver) = channel::<(Command, Sender<String>)>();
+    let mut repository = Repository::open(DATA_DIR)?;
+
+    println!(
+        "Restored {} bank(s) from {}",
+        repository.banks.len(),
+        DATA_DIR
+    );
+
+    let (sender, receiver) = channel::<Request>();
 
     let actor_handle = std::thread::spawn(move || {
-        let mut repository = Repository::default();
         repository_actor(&mut repository, receiver);
     });
 
@@ -66,7 +73,7 @@ mod tests {
         let mut writer = Vec::new();
         let mut terminal = Vec::new();
 
-        let (sender, _) = channel::<(Command, Sender<String>)>();
+        let (sender, _) = channel::<Request>();
 
         handle(&sender, &mut reader, &mut writer, &mut terminal).unwrap();
 
@@ -83,7 +90,7 @@ mod tests {
         let mut writer = Vec::new();
         let mut terminal = Vec::new();
 
-        let (sender, _) = channel::<(Command, Sender<String>)>();
+        let (sender, _) = channel::<Request>();
         handle(&sender, &mut reader, &mut writer, &mut terminal).unwrap();
 
         assert_eq!(
@@ -98,7 +105,7 @@ mod tests {
         let mut writer = Vec::new();
         let mut terminal = Vec::new();
 
-        let (sender, _) = channel::<(Command, Sender<String>)>();
+        let (sender, _) = channel::<Request>();
         handle(&sender, &mut reader, &mut writer, &mut terminal).unwrap();
 
         assert_eq!(
@@ -117,7 +124,7 @@ mod tests {
         let mut writer = Vec::new();
         let mut terminal = Vec::new();
 
-        let (sender, receiver) = channel::<(Command, Sender<String>)>();
+        let (sender, receiver) = channel::<Request>();
 
         std::thread::spawn(move || {
             let mut repository = Repository::default();
@@ -134,13 +141,13 @@ mod tests {
 
     #[test]
     fn handle_which_bank_command() {
-        let input = vec!["which_bank", "new_bank", "which_bank"].join("\n");
+        let input = ["which_bank", "new_bank", "which_bank"].join("\n");
         let mut reader = input.as_bytes();
 
         let mut writer = Vec::new();
         let mut terminal = Vec::new();
 
-        let (sender, receiver) = channel::<(Command, Sender<String>)>();
+        let (sender, receiver) = channel::<Request>();
 
         std::thread::spawn(move || {
             let mut repository = Repository::default();
@@ -149,7 +156,7 @@ mod tests {
 
         handle(&sender, &mut reader, &mut writer, &mut terminal).unwrap();
 
-        let expected = vec![
+        let expected = [
             "Bank: 1\nStatus: ok\nResult: 1\n\n".to_owned(),
             "Bank: 1\nStatus: ok\nResult: 2\n\n".to_owned(),
             "Bank: 2\nStatus: ok\nResult: 2\n\n".to_owned(),
@@ -161,7 +168,7 @@ mod tests {
 
     #[test]
     fn handle_change_bank_command() {
-        let input = vec![
+        let input = [
             "new_bank",
             "new_bank",
             "new_bank",
@@ -177,7 +184,7 @@ mod tests {
         let mut writer = Vec::new();
         let mut terminal = Vec::new();
 
-        let (sender, receiver) = channel::<(Command, Sender<String>)>();
+        let (sender, receiver) = channel::<Request>();
 
         std::thread::spawn(move || {
             let mut repository = Repository::default();
@@ -185,7 +192,7 @@ mod tests {
         });
 
         handle(&sender, &mut reader, &mut writer, &mut terminal).unwrap();
-        let expected = vec![
+        let expected = [
             "Bank: 0\nStatus: ok\nResult: 1\n\n".to_owned(),
             "Bank: 1\nStatus: ok\nResult: 2\n\n".to_owned(),
             "Bank: 2\nStatus: ok\nResult: 3\n\n".to_owned(),
@@ -203,14 +210,14 @@ mod tests {
 
     #[test]
     fn handle_register_account_works() {
-        let input = vec!["register_account", "register_account 100"].join("\n");
+        let input = ["register_account", "register_account 100"].join("\n");
 
         let mut reader = input.as_bytes();
 
         let mut writer = Vec::new();
         let mut terminal = Vec::new();
 
-        let (sender, receiver) = channel::<(Command, Sender<String>)>();
+        let (sender, receiver) = channel::<Request>();
 
         std::thread::spawn(move || {
             let mut repository = Repository::default();
@@ -221,7 +228,6 @@ mod tests {
 
         let result = from_utf8(writer.as_slice())
             .unwrap()
-            .to_owned()
             .split("\n\n")
             .map(|s| s.to_owned())
             .collect::<Vec<_>>();
@@ -254,7 +260,7 @@ mod tests {
         let mut writer = Vec::new();
         let mut terminal = Vec::new();
 
-        let (sender, receiver) = channel::<(Command, Sender<String>)>();
+        let (sender, receiver) = channel::<Request>();
 
         std::thread::spawn(move || {
             let mut repository = Repository::default();
@@ -265,7 +271,6 @@ mod tests {
 
         let result = from_utf8(writer.as_slice())
             .unwrap()
-            .to_owned()
             .split("\n\n")
             .map(|s| s.to_owned())
             .collect::<Vec<_>>();
@@ -280,10 +285,10 @@ mod tests {
         assert!(OperationID::parse_str(operation_id).is_ok());
         assert!(AccountID::parse_str(account_id).is_ok());
 
-        let input = vec![
+        let input = [
             "get_balance".to_owned(),
             "get_balance test".to_owned(),
-            format!("get_balance {}", account_id.to_string()),
+            format!("get_balance {}", account_id),
         ]
         .join("\n");
 
@@ -293,7 +298,6 @@ mod tests {
 
         let result = from_utf8(writer.as_slice())
             .unwrap()
-            .to_owned()
             .split("\n\n")
             .map(|s| s.to_owned())
             .collect::<Vec<_>>();
@@ -329,7 +333,7 @@ mod tests {
         let mut writer = Vec::new();
         let mut terminal = Vec::new();
 
-        let (sender, receiver) = channel::<(Command, Sender<String>)>();
+        let (sender, receiver) = channel::<Request>();
 
         std::thread::spawn(move || {
             let mut repository = Repository::default();
@@ -340,7 +344,6 @@ mod tests {
 
         let result = from_utf8(writer.as_slice())
             .unwrap()
-            .to_owned()
             .split("\n\n")
             .map(|s| s.to_owned())
             .collect::<Vec<_>>();
@@ -355,11 +358,11 @@ mod tests {
         assert!(OperationID::parse_str(operation_id).is_ok());
         assert!(AccountID::parse_str(account_id).is_ok());
 
-        let input = vec![
+        let input = [
             "deposit".to_owned(),
             "deposit test 10".to_owned(),
-            format!("deposit {} test", account_id.to_string()),
-            format!("deposit {} 100", account_id.to_string()),
+            format!("deposit {} test", account_id),
+            format!("deposit {} 100", account_id),
         ]
         .join("\n");
 
@@ -369,7 +372,6 @@ mod tests {
 
         let result = from_utf8(writer.as_slice())
             .unwrap()
-            .to_owned()
             .split("\n\n")
             .map(|s| s.to_owned())
             .collect::<Vec<_>>();
@@ -398,7 +400,7 @@ mod tests {
         assert_eq!(
             format!(
                 "Command: deposit {} test\nStatus: error\nType: parse\nError: {}",
-                account_id.to_string(),
+                account_id,
                 ParseError::InvalidArgumentUint {
                     name: "amount".to_owned(),
                     e: "test".parse::<u64>().unwrap_err(),
@@ -424,7 +426,7 @@ mod tests {
         let mut writer = Vec::new();
         let mut terminal = Vec::new();
 
-        let (sender, receiver) = channel::<(Command, Sender<String>)>();
+        let (sender, receiver) = channel::<Request>();
 
         std::thread::spawn(move || {
             let mut repository = Repository::default();
@@ -435,7 +437,6 @@ mod tests {
 
         let result = from_utf8(writer.as_slice())
             .unwrap()
-            .to_owned()
             .split("\n\n")
             .map(|s| s.to_owned())
             .collect::<Vec<_>>();
@@ -450,12 +451,12 @@ mod tests {
         assert!(OperationID::parse_str(operation_id).is_ok());
         assert!(AccountID::parse_str(account_id).is_ok());
 
-        let input = vec![
+        let input = [
             "withdraw".to_owned(),
             "withdraw test 10".to_owned(),
-            format!("withdraw {} test", account_id.to_string()),
-            format!("withdraw {} 100", account_id.to_string()),
-            format!("withdraw {} 100", account_id.to_string()),
+            format!("withdraw {} test", account_id),
+            format!("withdraw {} 100", account_id),
+            format!("withdraw {} 100", account_id),
         ]
         .join("\n");
 
@@ -465,7 +466,6 @@ mod tests {
 
         let result = from_utf8(writer.as_slice())
             .unwrap()
-            .to_owned()
             .split("\n\n")
             .map(|s| s.to_owned())
             .collect::<Vec<_>>();
@@ -494,7 +494,7 @@ mod tests {
         assert_eq!(
             format!(
                 "Command: withdraw {} test\nStatus: error\nType: parse\nError: {}",
-                account_id.to_string(),
+                account_id,
                 ParseError::InvalidArgumentUint {
                     name: "amount".to_owned(),
                     e: "test".parse::<u64>().unwrap_err(),
@@ -520,7 +520,7 @@ mod tests {
 
     #[test]
     fn handle_transfer_works() {
-        let input = vec![
+        let input = [
             "register_account 100".to_owned(),
             "register_account 50".to_owned(),
         ]
@@ -531,7 +531,7 @@ mod tests {
         let mut writer = Vec::new();
         let mut terminal = Vec::new();
 
-        let (sender, receiver) = channel::<(Command, Sender<String>)>();
+        let (sender, receiver) = channel::<Request>();
 
         std::thread::spawn(move || {
             let mut repository = Repository::default();
@@ -542,7 +542,6 @@ mod tests {
 
         let result = from_utf8(writer.as_slice())
             .unwrap()
-            .to_owned()
             .split("\n\n")
             .map(|s| s.to_owned())
             .collect::<Vec<_>>();
@@ -562,26 +561,14 @@ mod tests {
         assert!(OperationID::parse_str(operation2_id).is_ok());
         assert!(AccountID::parse_str(account2_id).is_ok());
 
-        let input = vec![
+        let input = [
             "transfer".to_owned(),
             "transfer test1 test2 50".to_owned(),
-            format!("transfer {} test2 50", account1_id.to_string()),
-            format!("transfer test1 {} 50", account2_id.to_string()),
-            format!(
-                "transfer {} {} test",
-                account1_id.to_string(),
-                account2_id.to_string()
-            ),
-            format!(
-                "transfer {} {} 50",
-                account1_id.to_string(),
-                account2_id.to_string()
-            ),
-            format!(
-                "transfer {} {} 500",
-                account2_id.to_string(),
-                account1_id.to_string()
-            ),
+            format!("transfer {} test2 50", account1_id),
+            format!("transfer test1 {} 50", account2_id),
+            format!("transfer {} {} test", account1_id, account2_id),
+            format!("transfer {} {} 50", account1_id, account2_id),
+            format!("transfer {} {} 500", account2_id, account1_id),
         ]
         .join("\n");
 
@@ -591,7 +578,6 @@ mod tests {
 
         let result = from_utf8(writer.as_slice())
             .unwrap()
-            .to_owned()
             .split("\n\n")
             .map(|s| s.to_owned())
             .collect::<Vec<_>>();
@@ -624,7 +610,7 @@ mod tests {
         assert_eq!(
             format!(
                 "Command: transfer {} test2 50\nStatus: error\nType: parse\nError: {}",
-                account1_id.to_string(),
+                account1_id,
                 ParseError::InvalidArgumentAccountID {
                     name: "receiver_account_id".to_owned(),
                     e: AccountID::parse_str("test2").unwrap_err()
@@ -636,7 +622,7 @@ mod tests {
         assert_eq!(
             format!(
                 "Command: transfer test1 {} 50\nStatus: error\nType: parse\nError: {}",
-                account2_id.to_string(),
+                account2_id,
                 ParseError::InvalidArgumentAccountID {
                     name: "sender_account_id".to_owned(),
                     e: AccountID::parse_str("test1").unwrap_err()
@@ -648,8 +634,8 @@ mod tests {
         assert_eq!(
             format!(
                 "Command: transfer {} {} test\nStatus: error\nType: parse\nError: invalid argument amount: {}",
-                account1_id.to_string(),
-                account2_id.to_string(),
+                account1_id,
+                account2_id,
                 "test".parse::<u64>().unwrap_err(),
             ),
             result[6]
@@ -672,7 +658,7 @@ mod tests {
 
     #[test]
     fn handle_list_operations_works() {
-        let input = vec![
+        let input = [
             "register_account 100".to_owned(),
             "register_account 50".to_owned(),
         ]
@@ -683,7 +669,7 @@ mod tests {
         let mut writer = Vec::new();
         let mut terminal = Vec::new();
 
-        let (sender, receiver) = channel::<(Command, Sender<String>)>();
+        let (sender, receiver) = channel::<Request>();
 
         std::thread::spawn(move || {
             let mut repository = Repository::default();
@@ -694,7 +680,6 @@ mod tests {
 
         let result = from_utf8(writer.as_slice())
             .unwrap()
-            .to_owned()
             .split("\n\n")
             .map(|s| s.to_owned())
             .collect::<Vec<_>>();
@@ -714,18 +699,14 @@ mod tests {
         assert!(OperationID::parse_str(operation2_id).is_ok());
         assert!(AccountID::parse_str(account2_id).is_ok());
 
-        let input = vec![
-            format!("deposit {} 100", account1_id.to_string()),
-            format!("deposit {} 250", account2_id.to_string()),
-            format!(
-                "transfer {} {} 50",
-                account1_id.to_string(),
-                account2_id.to_string()
-            ),
-            format!("withdraw {} 50", account2_id.to_string()),
+        let input = [
+            format!("deposit {} 100", account1_id),
+            format!("deposit {} 250", account2_id),
+            format!("transfer {} {} 50", account1_id, account2_id),
+            format!("withdraw {} 50", account2_id),
             "list_account_operations".to_owned(),
             "list_account_operations test".to_owned(),
-            format!("list_account_operations {}", account1_id.to_string()),
+            format!("list_account_operations {}", account1_id),
             "list_all_operations".to_owned(),
         ]
         .join("\n");
@@ -736,7 +717,6 @@ mod tests {
 
         let result = from_utf8(writer.as_slice())
             .unwrap()
-            .to_owned()
             .split("\n\n")
             .map(|s| s.to_owned())
             .collect::<Vec<_>>();
@@ -778,28 +758,28 @@ mod tests {
         let (_, [operation1, operation2, operation3]) = re.captures(&result[8]).unwrap().extract();
 
         let re = Regex::new(r"([a-f0-9-]+): \(Register ([a-f0-9-]+) 100\)").unwrap();
-        assert!(re.is_match(&operation1));
+        assert!(re.is_match(operation1));
 
-        let (_, [operation_id, account_id]) = re.captures(&operation1).unwrap().extract();
+        let (_, [operation_id, account_id]) = re.captures(operation1).unwrap().extract();
         assert!(OperationID::parse_str(operation_id).is_ok());
         assert!(AccountID::parse_str(account_id).is_ok());
 
         assert_eq!(account1_id, account_id);
 
         let re = Regex::new(r"([a-f0-9-]+): \(Deposit ([a-f0-9-]+) 100\)").unwrap();
-        assert!(re.is_match(&operation2));
+        assert!(re.is_match(operation2));
 
-        let (_, [operation_id, account_id]) = re.captures(&operation2).unwrap().extract();
+        let (_, [operation_id, account_id]) = re.captures(operation2).unwrap().extract();
         assert!(OperationID::parse_str(operation_id).is_ok());
         assert!(AccountID::parse_str(account_id).is_ok());
 
         assert_eq!(account1_id, account_id);
 
         let re = Regex::new(r"([a-f0-9-]+): \(Transfer ([a-f0-9-]+) ([a-f0-9-]+) 50\)").unwrap();
-        assert!(re.is_match(&operation3));
+        assert!(re.is_match(operation3));
 
         let (_, [operation_id, sender_account_id, receiver_account_id]) =
-            re.captures(&operation3).unwrap().extract();
+            re.captures(operation3).unwrap().extract();
         assert!(OperationID::parse_str(operation_id).is_ok());
         assert!(AccountID::parse_str(sender_account_id).is_ok());
         assert!(AccountID::parse_str(receiver_account_id).is_ok());
@@ -816,46 +796,46 @@ mod tests {
             re.captures(&result[9]).unwrap().extract();
 
         let re = Regex::new(r"([a-f0-9-]+): \(Register ([a-f0-9-]+) 100\)").unwrap();
-        assert!(re.is_match(&operation1));
+        assert!(re.is_match(operation1));
 
-        let (_, [operation_id, account_id]) = re.captures(&operation1).unwrap().extract();
+        let (_, [operation_id, account_id]) = re.captures(operation1).unwrap().extract();
         assert!(OperationID::parse_str(operation_id).is_ok());
         assert!(AccountID::parse_str(account_id).is_ok());
 
         assert_eq!(account1_id, account_id);
 
         let re = Regex::new(r"([a-f0-9-]+): \(Register ([a-f0-9-]+) 50\)").unwrap();
-        assert!(re.is_match(&operation2));
+        assert!(re.is_match(operation2));
 
-        let (_, [operation_id, account_id]) = re.captures(&operation2).unwrap().extract();
+        let (_, [operation_id, account_id]) = re.captures(operation2).unwrap().extract();
         assert!(OperationID::parse_str(operation_id).is_ok());
         assert!(AccountID::parse_str(account_id).is_ok());
 
         assert_eq!(account2_id, account_id);
 
         let re = Regex::new(r"([a-f0-9-]+): \(Deposit ([a-f0-9-]+) 100\)").unwrap();
-        assert!(re.is_match(&operation3));
+        assert!(re.is_match(operation3));
 
-        let (_, [operation_id, account_id]) = re.captures(&operation3).unwrap().extract();
+        let (_, [operation_id, account_id]) = re.captures(operation3).unwrap().extract();
         assert!(OperationID::parse_str(operation_id).is_ok());
         assert!(AccountID::parse_str(account_id).is_ok());
 
         assert_eq!(account1_id, account_id);
 
         let re = Regex::new(r"([a-f0-9-]+): \(Deposit ([a-f0-9-]+) 250\)").unwrap();
-        assert!(re.is_match(&operation4));
+        assert!(re.is_match(operation4));
 
-        let (_, [operation_id, account_id]) = re.captures(&operation4).unwrap().extract();
+        let (_, [operation_id, account_id]) = re.captures(operation4).unwrap().extract();
         assert!(OperationID::parse_str(operation_id).is_ok());
         assert!(AccountID::parse_str(account_id).is_ok());
 
         assert_eq!(account2_id, account_id);
 
         let re = Regex::new(r"([a-f0-9-]+): \(Transfer ([a-f0-9-]+) ([a-f0-9-]+) 50\)").unwrap();
-        assert!(re.is_match(&operation5));
+        assert!(re.is_match(operation5));
 
         let (_, [operation_id, sender_account_id, receiver_account_id]) =
-            re.captures(&operation5).unwrap().extract();
+            re.captures(operation5).unwrap().extract();
         assert!(OperationID::parse_str(operation_id).is_ok());
         assert!(AccountID::parse_str(sender_account_id).is_ok());
         assert!(AccountID::parse_str(receiver_account_id).is_ok());
@@ -864,9 +844,9 @@ mod tests {
         assert_eq!(account2_id, receiver_account_id);
 
         let re = Regex::new(r"([a-f0-9-]+): \(Withdraw ([a-f0-9-]+) 50\)").unwrap();
-        assert!(re.is_match(&operation6));
+        assert!(re.is_match(operation6));
 
-        let (_, [operation_id, account_id]) = re.captures(&operation6).unwrap().extract();
+        let (_, [operation_id, account_id]) = re.captures(operation6).unwrap().extract();
         assert!(OperationID::parse_str(operation_id).is_ok());
         assert!(AccountID::parse_str(account_id).is_ok());
 
@@ -875,7 +855,7 @@ mod tests {
 
     #[test]
     fn handle_restore_bank_works() {
-        let input = vec![
+        let input = [
             "register_account 100".to_owned(),
             "register_account 50".to_owned(),
         ]
@@ -886,7 +866,7 @@ mod tests {
         let mut writer = Vec::new();
         let mut terminal = Vec::new();
 
-        let (sender, receiver) = channel::<(Command, Sender<String>)>();
+        let (sender, receiver) = channel::<Request>();
 
         std::thread::spawn(move || {
             let mut repository = Repository::default();
@@ -897,7 +877,6 @@ mod tests {
 
         let result = from_utf8(writer.as_slice())
             .unwrap()
-            .to_owned()
             .split("\n\n")
             .map(|s| s.to_owned())
             .collect::<Vec<_>>();
@@ -917,15 +896,11 @@ mod tests {
         assert!(OperationID::parse_str(operation2_id).is_ok());
         assert!(AccountID::parse_str(account2_id).is_ok());
 
-        let input = vec![
-            format!("deposit {} 100", account1_id.to_string()),
-            format!("deposit {} 250", account2_id.to_string()),
-            format!(
-                "transfer {} {} 50",
-                account1_id.to_string(),
-                account2_id.to_string()
-            ),
-            format!("withdraw {} 50", account2_id.to_string()),
+        let input = [
+            format!("deposit {} 100", account1_id),
+            format!("deposit {} 250", account2_id),
+            format!("transfer {} {} 50", account1_id, account2_id),
+            format!("withdraw {} 50", account2_id),
             "restore_bank".to_owned(),
             "restore_bank test".to_owned(),
             "restore_bank 100".to_owned(),
@@ -940,7 +915,6 @@ mod tests {
 
         let result = from_utf8(writer.as_slice())
             .unwrap()
-            .to_owned()
             .split("\n\n")
             .map(|s| s.to_owned())
             .collect::<Vec<_>>();
@@ -992,46 +966,46 @@ mod tests {
             re.captures(&result[10]).unwrap().extract();
 
         let re = Regex::new(r"([a-f0-9-]+): \(Register ([a-f0-9-]+) 100\)").unwrap();
-        assert!(re.is_match(&operation1));
+        assert!(re.is_match(operation1));
 
-        let (_, [operation_id, account_id]) = re.captures(&operation1).unwrap().extract();
+        let (_, [operation_id, account_id]) = re.captures(operation1).unwrap().extract();
         assert!(OperationID::parse_str(operation_id).is_ok());
         assert!(AccountID::parse_str(account_id).is_ok());
 
         assert_eq!(account1_id, account_id);
 
         let re = Regex::new(r"([a-f0-9-]+): \(Register ([a-f0-9-]+) 50\)").unwrap();
-        assert!(re.is_match(&operation2));
+        assert!(re.is_match(operation2));
 
-        let (_, [operation_id, account_id]) = re.captures(&operation2).unwrap().extract();
+        let (_, [operation_id, account_id]) = re.captures(operation2).unwrap().extract();
         assert!(OperationID::parse_str(operation_id).is_ok());
         assert!(AccountID::parse_str(account_id).is_ok());
 
         assert_eq!(account2_id, account_id);
 
         let re = Regex::new(r"([a-f0-9-]+): \(Deposit ([a-f0-9-]+) 100\)").unwrap();
-        assert!(re.is_match(&operation3));
+        assert!(re.is_match(operation3));
 
-        let (_, [operation_id, account_id]) = re.captures(&operation3).unwrap().extract();
+        let (_, [operation_id, account_id]) = re.captures(operation3).unwrap().extract();
         assert!(OperationID::parse_str(operation_id).is_ok());
         assert!(AccountID::parse_str(account_id).is_ok());
 
         assert_eq!(account1_id, account_id);
 
         let re = Regex::new(r"([a-f0-9-]+): \(Deposit ([a-f0-9-]+) 250\)").unwrap();
-        assert!(re.is_match(&operation4));
+        assert!(re.is_match(operation4));
 
-        let (_, [operation_id, account_id]) = re.captures(&operation4).unwrap().extract();
+        let (_, [operation_id, account_id]) = re.captures(operation4).unwrap().extract();
         assert!(OperationID::parse_str(operation_id).is_ok());
         assert!(AccountID::parse_str(account_id).is_ok());
 
         assert_eq!(account2_id, account_id);
 
         let re = Regex::new(r"([a-f0-9-]+): \(Transfer ([a-f0-9-]+) ([a-f0-9-]+) 50\)").unwrap();
-        assert!(re.is_match(&operation5));
+        assert!(re.is_match(operation5));
 
         let (_, [operation_id, sender_account_id, receiver_account_id]) =
-            re.captures(&operation5).unwrap().extract();
+            re.captures(operation5).unwrap().extract();
         assert!(OperationID::parse_str(operation_id).is_ok());
         assert!(AccountID::parse_str(sender_account_id).is_ok());
         assert!(AccountID::parse_str(receiver_account_id).is_ok());
@@ -1040,9 +1014,9 @@ mod tests {
         assert_eq!(account2_id, receiver_account_id);
 
         let re = Regex::new(r"([a-f0-9-]+): \(Withdraw ([a-f0-9-]+) 50\)").unwrap();
-        assert!(re.is_match(&operation6));
+        assert!(re.is_match(operation6));
 
-        let (_, [operation_id, account_id]) = re.captures(&operation6).unwrap().extract();
+        let (_, [operation_id, account_id]) = re.captures(operation6).unwrap().extract();
         assert!(OperationID::parse_str(operation_id).is_ok());
         assert!(AccountID::parse_str(account_id).is_ok());
 
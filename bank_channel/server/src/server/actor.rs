@@ -1,31 +1,46 @@
 use crate::bank::account::AccountID;
 use crate::bank::log::Operation;
 use crate::server::command::Command;
-use crate::server::repository::{Repository, RepositoryError};
+use crate::server::repository::{Repository, RepositoryError, Session};
 use std::sync::mpsc::{Receiver, Sender};
 
-pub fn repository_actor(
-    repository: &mut Repository,
-    command_receiver: Receiver<(Command, Sender<String>)>,
-) {
+/// Connection's session travels with every command and comes back updated with the response.
+pub type Response = (Session, String);
+pub type Request = (Command, Session, Sender<Response>);
+
+pub fn repository_actor(repository: &mut Repository, command_receiver: Receiver<Request>) {
     command_receiver
         .iter()
-        .for_each(|(command, response_sender)| {
-            let response = handle_command(repository, &command);
-            if let Err(err) = response_sender.send(response) {
+        .for_each(|(command, mut session, response_sender)| {
+            let response = handle_command(repository, &mut session, &command);
+            if let Err(err) = response_sender.send((session, response)) {
                 eprintln!("Error sending response: {}", err);
             }
         })
 }
 
-fn handle_new_bank(repository: &mut Repository) -> String {
-    let bank_id = repository.new_bank();
-    format!("Bank: {}\nStatus: ok\nResult: {}\n\n", bank_id - 1, bank_id)
+fn handle_repository_error(
+    repository: &Repository,
+    session: &Session,
+    e: RepositoryError,
+) -> String {
+    format!(
+        "Bank: {}\nStatus: error\nType: repository\nError: {}\n\n",
+        repository.current_bank_id(session),
+        e,
+    )
 }
 
-fn handle_change_bank(repository: &mut Repository, id: u64) -> String {
-    let current_bank_id = repository.current_bank_id();
-    match repository.change_bank(id) {
+fn handle_new_bank(repository: &mut Repository, session: &mut Session) -> String {
+    match repository.new_bank(session) {
+        Ok(bank_id) => format!("Bank: {}\nStatus: ok\nResult: {}\n\n", bank_id - 1, bank_id),
+        Err(e) => handle_repository_error(repository, session, e),
+    }
+}
+
+fn handle_change_bank(repository: &mut Repository, session: &mut Session, id: u64) -> String {
+    let current_bank_id = repository.current_bank_id(session);
+    match repository.change_bank(session, id) {
         Ok(_) => format!("Bank: {}\nStatus: ok\nResult: {}\n\n", current_bank_id, id),
         Err(_) => format!(
             "Bank: {}\nStatus: error\nType: bank\nError: invalid bank id\n\n",
@@ -34,26 +49,28 @@ fn handle_change_bank(repository: &mut Repository, id: u64) -> String {
     }
 }
 
-fn handle_which_bank(repository: &mut Repository) -> String {
-    if repository.current_bank_id() == 0 {
-        repository.new_bank();
+fn handle_which_bank(repository: &mut Repository, session: &mut Session) -> String {
+    if repository.current_bank_id(session) == 0 {
+        if let Err(e) = repository.new_bank(session) {
+            return handle_repository_error(repository, session, e);
+        }
     }
 
-    let current_bank = repository.current_bank_id();
+    let current_bank = repository.current_bank_id(session);
     format!(
         "Bank: {}\nStatus: ok\nResult: {}\n\n",
         current_bank, current_bank
     )
 }
 
-fn handle_restore_bank(repository: &mut Repository, id: u64) -> String {
-    let current_bank = repository.current_bank_id();
+fn handle_restore_bank(repository: &mut Repository, session: &mut Session, id: u64) -> String {
+    let current_bank = repository.current_bank_id(session);
 
-    match repository.restore_bank(id) {
+    match repository.restore_bank(session, id) {
         Ok(_) => format!(
             "Bank: {}\nStatus: ok\nResult: {}\n\n",
             current_bank,
-            repository.current_bank_id(),
+            repository.current_bank_id(session),
         ),
         Err(repository_err) => match repository_err {
             RepositoryError::InvalidBankId => format!(
@@ -64,16 +81,21 @@ fn handle_restore_bank(repository: &mut Repository, id: u64) -> String {
                 "Bank: {}\nStatus: error\nType: bank\nError: {}\n\n",
                 current_bank, e,
             ),
+            e => handle_repository_error(repository, session, e),
         },
     }
 }
 
-fn handle_register_account(repository: &mut Repository, balance: u64) -> String {
-    match repository.register_account(balance) {
+fn handle_register_account(
+    repository: &mut Repository,
+    session: &mut Session,
+    balance: u64,
+) -> String {
+    match repository.register_account(session, balance) {
         Ok((account_id, opperation_id)) => {
             format!(
                 "Bank: {}\nOpID: {}\nStatus: ok\nResult: {}\n\n",
-                repository.current_bank_id(),
+                repository.current_bank_id(session),
                 opperation_id,
                 account_id
             )
@@ -81,64 +103,74 @@ fn handle_register_account(repository: &mut Repository, balance: u64) -> String
         Err(e) => {
             format!(
                 "Bank: {}\nStatus: error\nType: bank\nError: {}\n\n",
-                repository.current_bank_id(),
+                repository.current_bank_id(session),
                 e
             )
         }
     }
 }
 
-fn handle_get_balance(repository: &mut Repository, id: AccountID) -> String {
-    match repository.get_balance(id) {
+fn handle_get_balance(repository: &mut Repository, session: &mut Session, id: AccountID) -> String {
+    match repository.get_balance(session, id) {
         Ok(balance) => {
             format!(
                 "Bank: {}\nStatus: ok\nResult: {}\n\n",
-                repository.current_bank_id(),
+                repository.current_bank_id(session),
                 balance
             )
         }
         Err(e) => {
             format!(
                 "Bank: {}\nStatus: fail\nResult: {}\n\n",
-                repository.current_bank_id(),
+                repository.current_bank_id(session),
                 e
             )
         }
     }
 }
 
-fn handle_deposit(repository: &mut Repository, id: AccountID, amount: u64) -> String {
-    match repository.deposit(id, amount) {
+fn handle_deposit(
+    repository: &mut Repository,
+    session: &mut Session,
+    id: AccountID,
+    amount: u64,
+) -> String {
+    match repository.deposit(session, id, amount) {
         Ok(opperation_id) => {
             format!(
                 "Bank: {}\nOpID: {}\nStatus: ok\n\n",
-                repository.current_bank_id(),
+                repository.current_bank_id(session),
                 opperation_id,
             )
         }
         Err(e) => {
             format!(
                 "Bank: {}\nStatus: fail\nResult: {}\n\n",
-                repository.current_bank_id(),
+                repository.current_bank_id(session),
                 e
             )
         }
     }
 }
 
-fn handle_withdraw(repository: &mut Repository, id: AccountID, amount: u64) -> String {
-    match repository.withdraw(id, amount) {
+fn handle_withdraw(
+    repository: &mut Repository,
+    session: &mut Session,
+    id: AccountID,
+    amount: u64,
+) -> String {
+    match repository.withdraw(session, id, amount) {
         Ok(opperation_id) => {
             format!(
                 "Bank: {}\nOpID: {}\nStatus: ok\n\n",
-                repository.current_bank_id(),
+                repository.current_bank_id(session),
                 opperation_id,
             )
         }
         Err(e) => {
             format!(
                 "Bank: {}\nStatus: error\nType: bank\nError: {}\n\n",
-                repository.current_bank_id(),
+                repository.current_bank_id(session),
                 e
             )
         }
@@ -147,22 +179,23 @@ fn handle_withdraw(repository: &mut Repository, id: AccountID, amount: u64) -> S
 
 fn handle_transfer(
     repository: &mut Repository,
+    session: &mut Session,
     sender: AccountID,
     receiver: AccountID,
     amount: u64,
 ) -> String {
-    match repository.transfer(sender, receiver, amount) {
+    match repository.transfer(session, sender, receiver, amount) {
         Ok(opperation_id) => {
             format!(
                 "Bank: {}\nOpID: {}\nStatus: ok\n\n",
-                repository.current_bank_id(),
+                repository.current_bank_id(session),
                 opperation_id,
             )
         }
         Err(e) => {
             format!(
                 "Bank: {}\nStatus: error\nType: bank\nError: {}\n\n",
-                repository.current_bank_id(),
+                repository.current_bank_id(session),
                 e
             )
         }
@@ -174,45 +207,53 @@ fn operations_as_string<'a, I: Iterator<Item = &'a Operation>>(operations: I) ->
     operations.join("\n")
 }
 
-fn handle_list_account_operations(repository: &mut Repository, id: AccountID) -> String {
-    let operations = repository.get_account_operations(id);
+fn handle_list_account_operations(
+    repository: &mut Repository,
+    session: &mut Session,
+    id: AccountID,
+) -> String {
+    let operations = repository.get_account_operations(session, id);
     format!(
         "Bank: {}\nStatus: ok\nResult: \n{}\n\n",
-        repository.current_bank_id(),
+        repository.current_bank_id(session),
         operations_as_string(operations),
     )
 }
 
-fn handle_list_all_operations(repository: &mut Repository) -> String {
-    let operations = repository.get_all_operations();
+fn handle_list_all_operations(repository: &mut Repository, session: &mut Session) -> String {
+    let operations = repository.get_all_operations(session);
     format!(
         "Bank: {}\nStatus: ok\nResult: \n{}\n\n",
-        repository.current_bank_id(),
+        repository.current_bank_id(session),
         operations_as_string(operations),
     )
 }
 
-fn handle_command(repository: &mut Repository, command: &Command) -> String {
+fn handle_command(repository: &mut Repository, session: &mut Session, command: &Command) -> String {
     match *command {
-        Command::NewBank => handle_new_bank(repository),
-        Command::ChangeBank { id } => handle_change_bank(repository, id),
-        Command::RestoreBank { id } => handle_restore_bank(repository, id),
-        Command::WhichBank => handle_which_bank(repository),
-        Command::RegisterAccount { balance } => handle_register_account(repository, balance),
-        Command::GetBalance { id } => handle_get_balance(repository, id),
-        Command::Deposit { id, balance } => handle_deposit(repository, id, balance),
-        Command::Withdraw { id, balance } => handle_withdraw(repository, id, balance),
+        Command::NewBank => handle_new_bank(repository, session),
+        Command::ChangeBank { id } => handle_change_bank(repository, session, id),
+        Command::RestoreBank { id } => handle_restore_bank(repository, session, id),
+        Command::WhichBank => handle_which_bank(repository, session),
+        Command::RegisterAccount { balance } => {
+            handle_register_account(repository, session, balance)
+        }
+        Command::GetBalance { id } => handle_get_balance(repository, session, id),
+        Command::Deposit { id, balance } => handle_deposit(repository, session, id, balance),
+        Command::Withdraw { id, balance } => handle_withdraw(repository, session, id, balance),
         Command::Transfer {
             sender,
             receiver,
             amount,
-        } => handle_transfer(repository, sender, receiver, amount),
+        } => handle_transfer(repository, session, sender, receiver, amount),
 
-        Command::ListAccountOperations { id } => handle_list_account_operations(repository, id),
-        Command::ListAllOperations => handle_list_all_operations(repository),
+        Command::ListAccountOperations { id } => {
+            handle_list_account_operations(repository, session, id)
+        }
+        Command::ListAllOperations => handle_list_all_operations(repository, session),
         _ => format!(
             "Bank: {}\nStatus: error\nType: repository\nError: unknown command\n\n",
-            repository.current_bank_id(),
+            repository.current_bank_id(session),
         ),
     }
 }
@@ -1,5 +1,7 @@
 use crate::bank::Bank;
+use crate::server::actor::{Request, Response};
 use crate::server::command::{parse_command, Command, ParseError};
+use crate::server::repository::Session;
 use std::io::{BufRead, Write};
 use std::sync::mpsc::{channel, Sender};
 
@@ -43,7 +45,8 @@ fn handle_help<W: Write>(writer: &mut W) -> Result<()> {
 }
 
 fn handle_command(
-    sender: &Sender<(Command, Sender<String>)>,
+    sender: &Sender<Request>,
+    session: &mut Session,
     command: &Command,
     writer: &mut impl Write,
 ) -> Result<()> {
@@ -51,9 +54,10 @@ fn handle_command(
         Command::Quit => handle_quit(writer)?,
         Command::Help => handle_help(writer)?,
         _ => {
-            let (response_sender, response_receiver) = channel::<String>();
-            sender.send((*command, response_sender))?;
-            let response = response_receiver.recv()?;
+            let (response_sender, response_receiver) = channel::<Response>();
+            sender.send((*command, *session, response_sender))?;
+            let (updated_session, response) = response_receiver.recv()?;
+            *session = updated_session;
             writer.write_all(response.as_bytes())?;
         }
     };
@@ -75,11 +79,13 @@ fn handle_parse_error(e: ParseError, command: &str, writer: &mut impl Write) ->
 }
 
 pub fn handle<R: BufRead, W: Write, T: Write>(
-    sender: &Sender<(Command, Sender<String>)>,
+    sender: &Sender<Request>,
     reader: &mut R,
     writer: &mut W,
     terminal: &mut T,
 ) -> Result<()> {
+    let mut session = Session::default();
+
     loop {
         let mut line = String::new();
         match reader.read_line(&mut line) {
@@ -91,7 +97,7 @@ pub fn handle<R: BufRead, W: Write, T: Write>(
 
                 match parse_command(&line) {
                     Ok(command) => {
-                        handle_command(sender, &command, writer)?;
+                        handle_command(sender, &mut session, &command, writer)?;
                         if command == Command::Quit {
                             terminal.write_all("Client quited\n".as_bytes())?;
                             break;
@@ -122,7 +128,7 @@ mod tests {
         let mut writer = Vec::new();
         let mut terminal = Vec::new();
 
-        let (sender, _) = channel::<(Command, Sender<String>)>();
+        let (sender, _) = channel::<Request>();
 
         handle(&sender, &mut reader, &mut writer, &mut terminal).unwrap();
 
@@ -139,7 +145,7 @@ mod tests {
         let mut writer = Vec::new();
         let mut terminal = Vec::new();
 
-        let (sender, _) = channel::<(Command, Sender<String>)>();
+        let (sender, _) = channel::<Request>();
         handle(&sender, &mut reader, &mut writer, &mut terminal).unwrap();
 
         assert_eq!(
@@ -154,7 +160,7 @@ mod tests {
         let mut writer = Vec::new();
         let mut terminal = Vec::new();
 
-        let (sender, _) = channel::<(Command, Sender<String>)>();
+        let (sender, _) = channel::<Request>();
         handle(&sender, &mut reader, &mut writer, &mut terminal).unwrap();
 
         assert_eq!(
@@ -173,13 +179,13 @@ mod tests {
         let mut writer = Vec::new();
         let mut terminal = Vec::new();
 
-        let (sender, receiver) = channel::<(Command, Sender<String>)>();
+        let (sender, receiver) = channel::<Request>();
 
         std::thread::spawn(move || {
-            let (command, response_sender) = receiver.recv().unwrap();
+            let (command, session, response_sender) = receiver.recv().unwrap();
             assert_eq!(command, Command::NewBank);
             response_sender
-                .send("Response from command actor\n\n".to_owned())
+                .send((session, "Response from command actor\n\n".to_owned()))
                 .unwrap();
         });
 
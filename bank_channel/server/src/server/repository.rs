@@ -1,11 +1,19 @@
 use crate::bank::account::{Account, AccountID};
 use crate::bank::log::{Operation, OperationID};
+use crate::bank::snapshot::Snapshot;
 use crate::bank::{Bank, BankError};
+use crate::server::checkpoint::Checkpoint;
+use crate::server::wal::{Record, Wal, WalError};
+use std::path::Path;
 
-#[derive(Debug, PartialEq)]
+/// Number of log records after which repository takes a new checkpoint.
+pub const SNAPSHOT_INTERVAL: usize = 1000;
+
+#[derive(Debug)]
 pub enum RepositoryError {
     InvalidBankId,
     BankError(BankError),
+    StorageError(WalError),
 }
 
 impl std::fmt::Display for RepositoryError {
@@ -13,6 +21,7 @@ impl std::fmt::Display for RepositoryError {
         match self {
             RepositoryError::InvalidBankId => write!(f, "Invalid bank id"),
             RepositoryError::BankError(e) => write!(f, "Bank error: {}", e),
+            RepositoryError::StorageError(e) => write!(f, "Storage error: {}", e),
         }
     }
 }
@@ -21,105 +30,288 @@ impl std::error::Error for RepositoryError {}
 
 pub type Result<T> = std::result::Result<T, RepositoryError>;
 
-#[derive(Default, Clone)]
+/// Per-connection state: every client works with its own current bank.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Session {
+    pub current_bank: usize,
+}
+
+#[derive(Default)]
 pub struct Repository {
     pub banks: Vec<Bank>,
-    pub current_bank: usize,
+    pub snapshot_interval: usize,
+    wal: Option<Wal>,
+    records_since_snapshot: usize,
 }
 
 impl Repository {
-    pub fn current_bank_id(&self) -> usize {
+    /// Opens repository stored in `dir`: banks are loaded from the latest
+    /// checkpoint and only the log written after it is replayed.
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<Repository> {
+        let checkpoint = Checkpoint::load(&dir)
+            .map_err(RepositoryError::StorageError)?
+            .unwrap_or_default();
+
+        let (wal, records) =
+            Wal::open(&dir, checkpoint.segment).map_err(RepositoryError::StorageError)?;
+
+        let mut banks_state: Vec<(Snapshot, Vec<Operation>)> = checkpoint
+            .banks
+            .into_iter()
+            .map(|snapshot| (snapshot, Vec::new()))
+            .collect();
+
+        let records_since_snapshot = records.len();
+        for record in records {
+            match record {
+                Record::NewBank => banks_state.push((Snapshot::default(), Vec::new())),
+                Record::RestoreBank { id } => {
+                    let state = Self::bank_index(banks_state.len(), id)
+                        .map(|idx| banks_state[idx].clone())?;
+                    banks_state.push(state);
+                }
+                Record::Operation { bank_id, operation } => {
+                    let idx = Self::bank_index(banks_state.len(), bank_id)?;
+                    banks_state[idx].1.push(operation);
+                }
+            }
+        }
+
+        let banks = banks_state
+            .into_iter()
+            .map(|(snapshot, operations)| Bank::restore_from_snapshot(snapshot, operations.iter()))
+            .collect::<std::result::Result<Vec<Bank>, BankError>>()
+            .map_err(RepositoryError::BankError)?;
+
+        Ok(Repository {
+            banks,
+            snapshot_interval: SNAPSHOT_INTERVAL,
+            wal: Some(wal),
+            records_since_snapshot,
+        })
+    }
+
+    /// Saves snapshots of all banks and drops log segments they cover.
+    pub fn snapshot(&mut self) -> Result<()> {
+        let wal = match self.wal.as_mut() {
+            Some(wal) => wal,
+            None => return Ok(()),
+        };
+
+        // new records go to the fresh segment, so everything before it
+        // is covered by the checkpoint once it is saved
+        let segment = wal.rotate().map_err(RepositoryError::StorageError)?;
+
+        let checkpoint = Checkpoint {
+            segment,
+            banks: self.banks.iter().map(Bank::snapshot).collect(),
+        };
+
+        checkpoint
+            .save(wal.dir())
+            .map_err(RepositoryError::StorageError)?;
+        wal.compact(segment)
+            .map_err(RepositoryError::StorageError)?;
+
+        self.records_since_snapshot = 0;
+        Ok(())
+    }
+
+    fn maybe_snapshot(&mut self) {
+        if self.snapshot_interval == 0 || self.records_since_snapshot < self.snapshot_interval {
+            return;
+        }
+
+        // log already holds everything, failed snapshot only delays compaction
+        if let Err(e) = self.snapshot() {
+            eprintln!("Error taking snapshot: {}", e);
+        }
+    }
+
+    fn bank_index(banks_count: usize, id: u64) -> Result<usize> {
+        if id < 1 || id > banks_count as u64 {
+            return Err(RepositoryError::InvalidBankId);
+        }
+
+        Ok((id - 1) as usize)
+    }
+
+    fn append(&mut self, record: Record) -> Result<()> {
+        match self.wal.as_mut() {
+            Some(wal) => wal.append(&record).map_err(RepositoryError::StorageError)?,
+            None => return Ok(()),
+        }
+
+        self.records_since_snapshot += 1;
+        Ok(())
+    }
+
+    // Persists operation just applied to the current bank.
+    // If it could not be written, it is dropped from memory too,
+    // so the client never sees a change that would be lost on restart.
+    fn commit(&mut self, current_bank: usize, operation_id: OperationID) -> Result<OperationID> {
+        let bank = &self.banks[current_bank];
+        let operation = *bank.get_operation(operation_id).unwrap();
+
+        let record = Record::Operation {
+            bank_id: current_bank as u64 + 1,
+            operation,
+        };
+
+        if let Err(e) = self.append(record) {
+            let bank = &self.banks[current_bank];
+            let count = bank.get_all_operations().count();
+            let bank = Bank::restore_from_snapshot(
+                bank.base_snapshot().clone(),
+                bank.get_all_operations().take(count - 1),
+            )
+            .map_err(RepositoryError::BankError)?;
+            self.banks[current_bank] = bank;
+
+            return Err(e);
+        }
+
+        self.maybe_snapshot();
+        Ok(operation_id)
+    }
+
+    pub fn current_bank_id(&self, session: &Session) -> usize {
         if self.banks.is_empty() {
             0
         } else {
-            self.current_bank + 1
+            session.current_bank + 1
         }
     }
 
-    pub fn new_bank(&mut self) -> usize {
+    pub fn new_bank(&mut self, session: &mut Session) -> Result<usize> {
+        self.append(Record::NewBank)?;
+
         self.banks.push(Bank::default());
-        self.current_bank = self.banks.len() - 1;
-        self.current_bank + 1
+        session.current_bank = self.banks.len() - 1;
+
+        self.maybe_snapshot();
+        Ok(session.current_bank + 1)
     }
 
-    pub fn change_bank(&mut self, id: u64) -> Result<()> {
+    pub fn change_bank(&self, session: &mut Session, id: u64) -> Result<()> {
         if id < 1 || id > self.banks.len() as u64 {
             return Err(RepositoryError::InvalidBankId);
         }
 
         let new_current_bank = (id - 1) as usize;
-        self.current_bank = new_current_bank;
+        session.current_bank = new_current_bank;
 
         Ok(())
     }
 
-    pub fn restore_bank(&mut self, id: u64) -> Result<()> {
+    pub fn restore_bank(&mut self, session: &mut Session, id: u64) -> Result<()> {
         if id < 1 || id > self.banks.len() as u64 {
             return Err(RepositoryError::InvalidBankId);
         }
 
-        let current_bank = self.current_bank;
+        let current_bank = session.current_bank;
         let src_bank = &self.banks[current_bank];
 
-        match Bank::restore(src_bank.get_all_operations()) {
-            Ok(new_bank) => {
-                self.banks.push(new_bank);
-                self.current_bank = self.banks.len() - 1;
-                Ok(())
-            }
-            Err(e) => Err(RepositoryError::BankError(e)),
-        }
+        let new_bank = Bank::restore_from_snapshot(
+            src_bank.base_snapshot().clone(),
+            src_bank.get_all_operations(),
+        )
+        .map_err(RepositoryError::BankError)?;
+
+        self.append(Record::RestoreBank {
+            id: current_bank as u64 + 1,
+        })?;
+
+        self.banks.push(new_bank);
+        session.current_bank = self.banks.len() - 1;
+
+        self.maybe_snapshot();
+        Ok(())
     }
 
-    pub fn register_account(&mut self, balance: u64) -> Result<(AccountID, OperationID)> {
+    pub fn register_account(
+        &mut self,
+        session: &mut Session,
+        balance: u64,
+    ) -> Result<(AccountID, OperationID)> {
         if self.banks.is_empty() {
-            self.new_bank();
+            self.new_bank(session)?;
         }
 
-        let current_bank = self.current_bank;
+        let current_bank = session.current_bank;
         let bank = &mut self.banks[current_bank];
         let account = Account::new(balance);
 
-        match bank.register_account(account) {
-            Ok(operation_id) => Ok((account.id, operation_id)),
-            Err(e) => Err(RepositoryError::BankError(e)),
-        }
+        let operation_id = bank
+            .register_account(account)
+            .map_err(RepositoryError::BankError)?;
+
+        self.commit(current_bank, operation_id)
+            .map(|operation_id| (account.id, operation_id))
     }
 
-    pub fn get_balance(&self, id: AccountID) -> Result<u64> {
-        let bank = &self.banks[self.current_bank];
+    pub fn get_balance(&self, session: &Session, id: AccountID) -> Result<u64> {
+        let bank = &self.banks[session.current_bank];
         bank.get_balance(id).map_err(RepositoryError::BankError)
     }
 
-    pub fn deposit(&mut self, id: AccountID, amount: u64) -> Result<OperationID> {
-        let bank = &mut self.banks[self.current_bank];
-        bank.deposit(id, amount).map_err(RepositoryError::BankError)
+    pub fn deposit(
+        &mut self,
+        session: &Session,
+        id: AccountID,
+        amount: u64,
+    ) -> Result<OperationID> {
+        let current_bank = session.current_bank;
+        let bank = &mut self.banks[current_bank];
+        let operation_id = bank
+            .deposit(id, amount)
+            .map_err(RepositoryError::BankError)?;
+
+        self.commit(current_bank, operation_id)
     }
 
-    pub fn withdraw(&mut self, id: AccountID, amount: u64) -> Result<OperationID> {
-        let bank = &mut self.banks[self.current_bank];
-        bank.withdraw(id, amount)
-            .map_err(RepositoryError::BankError)
+    pub fn withdraw(
+        &mut self,
+        session: &Session,
+        id: AccountID,
+        amount: u64,
+    ) -> Result<OperationID> {
+        let current_bank = session.current_bank;
+        let bank = &mut self.banks[current_bank];
+        let operation_id = bank
+            .withdraw(id, amount)
+            .map_err(RepositoryError::BankError)?;
+
+        self.commit(current_bank, operation_id)
     }
 
     pub fn transfer(
         &mut self,
+        session: &Session,
         sender_id: AccountID,
         receiver_id: AccountID,
         amount: u64,
     ) -> Result<OperationID> {
-        let bank = &mut self.banks[self.current_bank];
-        bank.transfer(sender_id, receiver_id, amount)
-            .map_err(RepositoryError::BankError)
+        let current_bank = session.current_bank;
+        let bank = &mut self.banks[current_bank];
+        let operation_id = bank
+            .transfer(sender_id, receiver_id, amount)
+            .map_err(RepositoryError::BankError)?;
+
+        self.commit(current_bank, operation_id)
     }
 
-    pub fn get_account_operations(&self, id: AccountID) -> impl Iterator<Item = &Operation> {
-        let bank = &self.banks[self.current_bank];
+    pub fn get_account_operations(
+        &self,
+        session: &Session,
+        id: AccountID,
+    ) -> impl Iterator<Item = &Operation> {
+        let bank = &self.banks[session.current_bank];
         bank.get_account_operations(id)
     }
 
-    pub fn get_all_operations(&self) -> impl Iterator<Item = &Operation> {
-        let bank = &self.banks[self.current_bank];
+    pub fn get_all_operations(&self, session: &Session) -> impl Iterator<Item = &Operation> {
+        let bank = &self.banks[session.current_bank];
         bank.get_all_operations()
     }
 }
@@ -132,106 +324,120 @@ mod tests {
 
     #[test]
     fn new_bank_works() {
-        let bank_id = Repository::default().new_bank();
+        let bank_id = Repository::default()
+            .new_bank(&mut Session::default())
+            .unwrap();
         assert_eq!(bank_id, 1);
     }
 
     #[test]
     fn current_bank_id_works() {
         let mut repository = Repository::default();
-        assert_eq!(repository.current_bank_id(), 0);
+        let mut session = Session::default();
+        assert_eq!(repository.current_bank_id(&session), 0);
 
-        repository.new_bank();
-        assert_eq!(repository.current_bank_id(), 1);
+        repository.new_bank(&mut session).unwrap();
+        assert_eq!(repository.current_bank_id(&session), 1);
     }
 
     #[test]
     fn change_bank_works() {
         let mut repository = Repository::default();
-        repository.new_bank();
-        repository.new_bank();
-        repository.new_bank();
-
-        assert_eq!(repository.current_bank_id(), 3);
-
-        assert!(repository.change_bank(1).is_ok());
-        assert_eq!(repository.current_bank_id(), 1);
-        assert!(repository.change_bank(2).is_ok());
-        assert_eq!(repository.current_bank_id(), 2);
-        assert!(repository.change_bank(3).is_ok());
-        assert_eq!(repository.current_bank_id(), 3);
-
-        assert_eq!(
-            Err(RepositoryError::InvalidBankId),
-            repository.change_bank(0)
-        );
-        assert_eq!(
-            Err(RepositoryError::InvalidBankId),
-            repository.change_bank(4)
-        );
-        assert_eq!(
-            Err(RepositoryError::InvalidBankId),
-            repository.change_bank(100)
-        );
-
-        assert_eq!(repository.current_bank_id(), 3);
+        let mut session = Session::default();
+        repository.new_bank(&mut session).unwrap();
+        repository.new_bank(&mut session).unwrap();
+        repository.new_bank(&mut session).unwrap();
+
+        assert_eq!(repository.current_bank_id(&session), 3);
+
+        assert!(repository.change_bank(&mut session, 1).is_ok());
+        assert_eq!(repository.current_bank_id(&session), 1);
+        assert!(repository.change_bank(&mut session, 2).is_ok());
+        assert_eq!(repository.current_bank_id(&session), 2);
+        assert!(repository.change_bank(&mut session, 3).is_ok());
+        assert_eq!(repository.current_bank_id(&session), 3);
+
+        assert!(matches!(
+            repository.change_bank(&mut session, 0),
+            Err(RepositoryError::InvalidBankId)
+        ));
+        assert!(matches!(
+            repository.change_bank(&mut session, 4),
+            Err(RepositoryError::InvalidBankId)
+        ));
+        assert!(matches!(
+            repository.change_bank(&mut session, 100),
+            Err(RepositoryError::InvalidBankId)
+        ));
+
+        assert_eq!(repository.current_bank_id(&session), 3);
     }
 
     #[test]
     fn register_account_works() {
         let mut repository = Repository::default();
-        assert!(repository.register_account(100).is_ok());
-        assert!(repository.register_account(0).is_ok());
+        let mut session = Session::default();
+        assert!(repository.register_account(&mut session, 100).is_ok());
+        assert!(repository.register_account(&mut session, 0).is_ok());
     }
 
     #[test]
     fn get_balance_works() {
         let mut repository = Repository::default();
-        let (account_id, _) = repository.register_account(100).unwrap();
-        assert_eq!(100, repository.get_balance(account_id).unwrap());
+        let mut session = Session::default();
+        let (account_id, _) = repository.register_account(&mut session, 100).unwrap();
+        assert_eq!(100, repository.get_balance(&session, account_id).unwrap());
 
         let fake_account = Account::new(10);
-        assert!(repository.get_balance(fake_account.id).is_err());
+        assert!(repository.get_balance(&session, fake_account.id).is_err());
     }
 
     #[test]
     fn deposit_works() {
         let mut repository = Repository::default();
-        let (account_id, _) = repository.register_account(100).unwrap();
-        assert!(repository.deposit(account_id, 10).is_ok());
-        assert_eq!(110, repository.get_balance(account_id).unwrap());
+        let mut session = Session::default();
+        let (account_id, _) = repository.register_account(&mut session, 100).unwrap();
+        assert!(repository.deposit(&session, account_id, 10).is_ok());
+        assert_eq!(110, repository.get_balance(&session, account_id).unwrap());
     }
 
     #[test]
     fn withdraw_works() {
         let mut repository = Repository::default();
-        let (account_id, _) = repository.register_account(100).unwrap();
-        assert!(repository.withdraw(account_id, 10).is_ok());
-        assert_eq!(90, repository.get_balance(account_id).unwrap());
+        let mut session = Session::default();
+        let (account_id, _) = repository.register_account(&mut session, 100).unwrap();
+        assert!(repository.withdraw(&session, account_id, 10).is_ok());
+        assert_eq!(90, repository.get_balance(&session, account_id).unwrap());
     }
 
     #[test]
     fn transfer_works() {
         let mut repository = Repository::default();
-        let (sender_id, _) = repository.register_account(100).unwrap();
-        let (receiver_id, _) = repository.register_account(100).unwrap();
-        assert!(repository.transfer(sender_id, receiver_id, 10).is_ok());
-        assert_eq!(90, repository.get_balance(sender_id).unwrap());
-        assert_eq!(110, repository.get_balance(receiver_id).unwrap());
+        let mut session = Session::default();
+        let (sender_id, _) = repository.register_account(&mut session, 100).unwrap();
+        let (receiver_id, _) = repository.register_account(&mut session, 100).unwrap();
+        assert!(repository
+            .transfer(&session, sender_id, receiver_id, 10)
+            .is_ok());
+        assert_eq!(90, repository.get_balance(&session, sender_id).unwrap());
+        assert_eq!(110, repository.get_balance(&session, receiver_id).unwrap());
     }
 
     #[test]
     fn get_account_operations_works() {
         let mut repository = Repository::default();
-        let (account1_id, _) = repository.register_account(100).unwrap();
-        repository.deposit(account1_id, 10).unwrap();
-        repository.withdraw(account1_id, 10).unwrap();
+        let mut session = Session::default();
+        let (account1_id, _) = repository.register_account(&mut session, 100).unwrap();
+        repository.deposit(&session, account1_id, 10).unwrap();
+        repository.withdraw(&session, account1_id, 10).unwrap();
 
-        let (account2_id, _) = repository.register_account(50).unwrap();
-        repository.transfer(account1_id, account2_id, 10).unwrap();
+        let (account2_id, _) = repository.register_account(&mut session, 50).unwrap();
+        repository
+            .transfer(&session, account1_id, account2_id, 10)
+            .unwrap();
 
         let operations: Vec<OperationKind> = repository
-            .get_account_operations(account1_id)
+            .get_account_operations(&session, account1_id)
             .map(|op| op.kind)
             .collect();
 
@@ -260,15 +466,20 @@ mod tests {
     #[test]
     fn get_all_operations_works() {
         let mut repository = Repository::default();
-        let (account1_id, _) = repository.register_account(100).unwrap();
-        repository.deposit(account1_id, 10).unwrap();
-        repository.withdraw(account1_id, 10).unwrap();
+        let mut session = Session::default();
+        let (account1_id, _) = repository.register_account(&mut session, 100).unwrap();
+        repository.deposit(&session, account1_id, 10).unwrap();
+        repository.withdraw(&session, account1_id, 10).unwrap();
 
-        let (account2_id, _) = repository.register_account(50).unwrap();
-        repository.transfer(account1_id, account2_id, 10).unwrap();
+        let (account2_id, _) = repository.register_account(&mut session, 50).unwrap();
+        repository
+            .transfer(&session, account1_id, account2_id, 10)
+            .unwrap();
 
-        let operations: Vec<OperationKind> =
-            repository.get_all_operations().map(|op| op.kind).collect();
+        let operations: Vec<OperationKind> = repository
+            .get_all_operations(&session)
+            .map(|op| op.kind)
+            .collect();
 
         let expected: Vec<OperationKind> = vec![
             OperationKind::Register {
@@ -299,29 +510,149 @@ mod tests {
     #[test]
     fn restore_bank_works() {
         let mut repository = Repository::default();
-        let (account1_id, _) = repository.register_account(100).unwrap();
-        let (account2_id, _) = repository.register_account(50).unwrap();
-
-        repository.deposit(account1_id, 100).unwrap();
-        repository.deposit(account2_id, 250).unwrap();
-        repository.transfer(account1_id, account2_id, 50).unwrap();
-        repository.withdraw(account2_id, 50).unwrap();
-        repository.restore_bank(1).unwrap();
+        let mut session = Session::default();
+        let (account1_id, _) = repository.register_account(&mut session, 100).unwrap();
+        let (account2_id, _) = repository.register_account(&mut session, 50).unwrap();
+
+        repository.deposit(&session, account1_id, 100).unwrap();
+        repository.deposit(&session, account2_id, 250).unwrap();
+        repository
+            .transfer(&session, account1_id, account2_id, 50)
+            .unwrap();
+        repository.withdraw(&session, account2_id, 50).unwrap();
+        repository.restore_bank(&mut session, 1).unwrap();
 
         let bank1_operations = repository
-            .get_all_operations()
+            .get_all_operations(&session)
             .map(|op| op.to_string())
             .collect::<Vec<_>>();
 
-        assert_eq!(2, repository.current_bank_id());
+        assert_eq!(2, repository.current_bank_id(&session));
 
-        repository.change_bank(1).unwrap();
+        repository.change_bank(&mut session, 1).unwrap();
 
         let bank2_operations = repository
-            .get_all_operations()
+            .get_all_operations(&session)
             .map(|op| op.to_string())
             .collect::<Vec<_>>();
 
         assert_eq!(bank1_operations, bank2_operations);
     }
+
+    #[test]
+    fn sessions_are_independent_works() {
+        let mut repository = Repository::default();
+        let mut first_session = Session::default();
+        let mut second_session = Session::default();
+
+        let (account_id, _) = repository
+            .register_account(&mut first_session, 100)
+            .unwrap();
+        assert_eq!(repository.new_bank(&mut second_session).unwrap(), 2);
+
+        assert_eq!(repository.current_bank_id(&first_session), 1);
+        assert_eq!(repository.current_bank_id(&second_session), 2);
+
+        repository.deposit(&first_session, account_id, 50).unwrap();
+        assert!(repository.deposit(&second_session, account_id, 50).is_err());
+
+        repository.change_bank(&mut second_session, 1).unwrap();
+        assert_eq!(
+            repository.get_balance(&second_session, account_id).unwrap(),
+            150
+        );
+        assert_eq!(repository.current_bank_id(&first_session), 1);
+    }
+
+    #[test]
+    fn open_restores_repository_works() {
+        let path = std::env::temp_dir().join(format!("bank-repository-{}", OperationID::new()));
+
+        let mut repository = Repository::open(&path).unwrap();
+        let mut session = Session::default();
+        let (account1_id, _) = repository.register_account(&mut session, 100).unwrap();
+        let (account2_id, _) = repository.register_account(&mut session, 50).unwrap();
+        repository.deposit(&session, account1_id, 100).unwrap();
+        repository
+            .transfer(&session, account1_id, account2_id, 50)
+            .unwrap();
+        repository.withdraw(&session, account2_id, 50).unwrap();
+        assert!(repository.withdraw(&session, account2_id, 500).is_err());
+
+        repository.restore_bank(&mut session, 1).unwrap();
+        repository.deposit(&session, account1_id, 10).unwrap();
+        repository.new_bank(&mut session).unwrap();
+
+        let expected_banks = repository.banks.clone();
+        drop(repository);
+
+        let repository = Repository::open(&path).unwrap();
+        let mut session = Session::default();
+        assert_eq!(repository.banks, expected_banks);
+        assert_eq!(repository.current_bank_id(&session), 1);
+
+        repository.change_bank(&mut session, 1).unwrap();
+        assert_eq!(repository.get_balance(&session, account1_id).unwrap(), 150);
+        assert_eq!(repository.get_balance(&session, account2_id).unwrap(), 50);
+
+        repository.change_bank(&mut session, 2).unwrap();
+        assert_eq!(repository.get_balance(&session, account1_id).unwrap(), 160);
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn snapshot_compacts_log_works() {
+        let dir = std::env::temp_dir().join(format!("bank-repository-{}", OperationID::new()));
+
+        let mut repository = Repository::open(&dir).unwrap();
+        let mut session = Session::default();
+        repository.snapshot_interval = 4;
+
+        let (account1_id, _) = repository.register_account(&mut session, 100).unwrap();
+        let (account2_id, _) = repository.register_account(&mut session, 50).unwrap();
+        for _ in 0..10 {
+            repository
+                .transfer(&session, account1_id, account2_id, 5)
+                .unwrap();
+        }
+        repository.restore_bank(&mut session, 1).unwrap();
+        repository.deposit(&session, account1_id, 10).unwrap();
+
+        let segments = std::fs::read_dir(&dir)
+            .unwrap()
+            .filter(|entry| {
+                let name = entry.as_ref().unwrap().file_name();
+                name.to_str().unwrap().starts_with("wal.")
+            })
+            .count();
+        assert_eq!(segments, 1);
+
+        // the last snapshot was taken after the 12th record, before bank 2 appeared
+        let checkpoint = Checkpoint::load(&dir).unwrap().unwrap();
+        assert_eq!(checkpoint.banks.len(), 1);
+        let last_operation_id = checkpoint.banks[0].last_operation_id.unwrap();
+        assert!(repository.banks[0]
+            .get_operation(last_operation_id)
+            .is_some());
+        drop(repository);
+
+        let mut repository = Repository::open(&dir).unwrap();
+        let mut session = Session::default();
+        assert!(repository.banks[0].get_all_operations().count() < 12);
+
+        repository.change_bank(&mut session, 1).unwrap();
+        assert_eq!(repository.get_balance(&session, account1_id).unwrap(), 50);
+        assert_eq!(repository.get_balance(&session, account2_id).unwrap(), 100);
+
+        repository.change_bank(&mut session, 2).unwrap();
+        assert_eq!(repository.get_balance(&session, account1_id).unwrap(), 60);
+        assert_eq!(repository.get_balance(&session, account2_id).unwrap(), 100);
+
+        // bank restored from the snapshot still could be copied
+        repository.restore_bank(&mut session, 2).unwrap();
+        assert_eq!(repository.get_balance(&session, account1_id).unwrap(), 60);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
 }
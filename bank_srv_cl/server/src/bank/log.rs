@@ -46,6 +46,8 @@ impl std::fmt::Display for OperationKind {
     }
 }
 
+pub type Error = uuid::Error;
+
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Default)]
 pub struct OperationID(Uuid);
 
@@ -53,6 +55,10 @@ impl OperationID {
     pub fn new() -> OperationID {
         OperationID(Uuid::new_v4())
     }
+
+    pub fn parse_str(s: &str) -> Result<OperationID, Error> {
+        Uuid::parse_str(s).map(OperationID)
+    }
 }
 
 impl std::fmt::Display for OperationID {
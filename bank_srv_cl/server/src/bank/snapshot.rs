@@ -0,0 +1,9 @@
+use crate::bank::account::Account;
+use crate::bank::log::OperationID;
+
+/// State of all bank accounts right after the operation `last_operation_id`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Snapshot {
+    pub accounts: Vec<Account>,
+    pub last_operation_id: Option<OperationID>,
+}
@@ -6,13 +6,18 @@ use std::sync::{Arc, RwLock};
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
 const ADDR: &str = "127.0.0.1:1337";
+const DATA_DIR: &str = "bank_data";
 
 fn main() -> Result<()> {
     let listener = TcpListener::bind(ADDR)?;
 
     println!("Listening on {}", listener.local_addr()?);
 
-    let original_lock_context = Arc::new(RwLock::new(Context::default()));
+    let context = Context::open(DATA_DIR)?;
+
+    println!("Restored {} bank(s) from {}", context.banks.len(), DATA_DIR);
+
+    let original_lock_context = Arc::new(RwLock::new(context));
 
     for stream in listener.incoming() {
         let stream = stream?;
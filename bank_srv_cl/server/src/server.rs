@@ -1,2 +1,4 @@
+pub mod checkpoint;
 pub mod command;
 pub mod handler;
+pub mod wal;
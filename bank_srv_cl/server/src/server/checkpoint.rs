@@ -0,0 +1,181 @@
+use crate::bank::account::{Account, AccountID};
+use crate::bank::log::OperationID;
+use crate::bank::snapshot::Snapshot;
+use crate::server::wal::{crc32, Result, WalError};
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+const CHECKPOINT_FILE: &str = "checkpoint";
+const CHECKPOINT_TMP_FILE: &str = "checkpoint.tmp";
+
+/// Snapshots of all repository banks, together with the first log segment
+/// that has to be replayed on top of them.
+///
+/// Stored as text, one item per line, the last line is the checksum of all
+/// previous ones:
+///
+/// ```text
+/// segment <first_segment>
+/// bank <last_operation_id|->
+/// account <account_id> <balance>
+/// crc32 <checksum>
+/// ```
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Checkpoint {
+    pub segment: u64,
+    pub banks: Vec<Snapshot>,
+}
+
+impl Checkpoint {
+    /// Loads checkpoint from `dir`, returns `None` if no checkpoint was saved yet.
+    pub fn load<P: AsRef<Path>>(dir: P) -> Result<Option<Checkpoint>> {
+        let path = dir.as_ref().join(CHECKPOINT_FILE);
+        let data = match std::fs::read_to_string(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        decode(&data).map(Some).ok_or(WalError::CorruptedCheckpoint)
+    }
+
+    /// Saves checkpoint to `dir`, the previous one is replaced atomically.
+    pub fn save<P: AsRef<Path>>(&self, dir: P) -> Result<()> {
+        let dir = dir.as_ref();
+        let tmp_path = dir.join(CHECKPOINT_TMP_FILE);
+
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(encode(self).as_bytes())?;
+        file.sync_all()?;
+
+        std::fs::rename(tmp_path, dir.join(CHECKPOINT_FILE))?;
+        File::open(dir)?.sync_all()?;
+
+        Ok(())
+    }
+}
+
+fn encode(checkpoint: &Checkpoint) -> String {
+    let mut data = format!("segment {}\n", checkpoint.segment);
+
+    for snapshot in checkpoint.banks.iter() {
+        match snapshot.last_operation_id {
+            Some(operation_id) => data.push_str(&format!("bank {}\n", operation_id)),
+            None => data.push_str("bank -\n"),
+        }
+
+        for account in snapshot.accounts.iter() {
+            data.push_str(&format!("account {} {}\n", account.id, account.balance));
+        }
+    }
+
+    let checksum = crc32(data.as_bytes());
+    data.push_str(&format!("crc32 {:08x}\n", checksum));
+    data
+}
+
+fn decode(data: &str) -> Option<Checkpoint> {
+    let body = data.strip_suffix('\n')?;
+    let (body, checksum_line) = match body.rsplit_once('\n') {
+        Some((body, checksum_line)) => (format!("{}\n", body), checksum_line),
+        None => return None,
+    };
+
+    let checksum = checksum_line.strip_prefix("crc32 ")?;
+    if u32::from_str_radix(checksum, 16).ok()? != crc32(body.as_bytes()) {
+        return None;
+    }
+
+    let mut lines = body.lines();
+    let segment = lines.next()?.strip_prefix("segment ")?.parse().ok()?;
+
+    let mut checkpoint = Checkpoint {
+        segment,
+        banks: Vec::new(),
+    };
+
+    for line in lines {
+        let parts: Vec<&str> = line.split(' ').collect();
+        match parts[..] {
+            ["bank", "-"] => checkpoint.banks.push(Snapshot::default()),
+            ["bank", operation_id] => checkpoint.banks.push(Snapshot {
+                accounts: Vec::new(),
+                last_operation_id: Some(OperationID::parse_str(operation_id).ok()?),
+            }),
+            ["account", id, balance] => checkpoint.banks.last_mut()?.accounts.push(Account {
+                id: AccountID::parse_str(id).ok()?,
+                balance: balance.parse().ok()?,
+            }),
+            _ => return None,
+        }
+    }
+
+    Some(checkpoint)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("bank-checkpoint-{}", OperationID::new()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn save_and_load_works() {
+        let dir = temp_dir();
+        assert!(Checkpoint::load(&dir).unwrap().is_none());
+
+        let checkpoint = Checkpoint {
+            segment: 3,
+            banks: vec![
+                Snapshot {
+                    accounts: vec![Account::new(100), Account::new(0)],
+                    last_operation_id: Some(OperationID::new()),
+                },
+                Snapshot::default(),
+            ],
+        };
+
+        checkpoint.save(&dir).unwrap();
+        assert_eq!(Checkpoint::load(&dir).unwrap(), Some(checkpoint.clone()));
+
+        let checkpoint = Checkpoint {
+            segment: 4,
+            banks: Vec::new(),
+        };
+
+        checkpoint.save(&dir).unwrap();
+        assert_eq!(Checkpoint::load(&dir).unwrap(), Some(checkpoint));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn load_detects_corrupted_checkpoint_works() {
+        let dir = temp_dir();
+
+        let checkpoint = Checkpoint {
+            segment: 3,
+            banks: vec![Snapshot {
+                accounts: vec![Account::new(100)],
+                last_operation_id: None,
+            }],
+        };
+        checkpoint.save(&dir).unwrap();
+
+        let path = dir.join(CHECKPOINT_FILE);
+        let data = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, data.replacen(" 100\n", " 900\n", 1)).unwrap();
+
+        assert!(matches!(
+            Checkpoint::load(&dir),
+            Err(WalError::CorruptedCheckpoint)
+        ));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,13 +1,28 @@
 use crate::bank::account::{Account, AccountID};
-use crate::bank::log::Operation;
+use crate::bank::log::{Operation, OperationID};
+use crate::bank::snapshot::Snapshot;
 use crate::bank::Bank;
+use crate::server::checkpoint::Checkpoint;
 use crate::server::command::{parse_command, Command, ParseError};
+use crate::server::wal::{self, Record, Wal, WalError};
 use std::io::{BufRead, Write};
+use std::path::Path;
 use std::sync::{Arc, RwLock};
 
-#[derive(Default, Clone, Debug)]
+/// Number of log records after which context takes a new checkpoint.
+pub const SNAPSHOT_INTERVAL: usize = 1000;
+
+#[derive(Default, Debug)]
 pub struct Context {
     pub banks: Vec<Bank>,
+    pub snapshot_interval: usize,
+    wal: Option<Wal>,
+    records_since_snapshot: usize,
+}
+
+/// Per-connection state: every client works with its own current bank.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Session {
     pub current_bank: usize,
 }
 
@@ -15,21 +30,169 @@ type ARWLockContext = Arc<RwLock<Context>>;
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
-fn handle_new_bank<W: Write>(lock_context: ARWLockContext, writer: &mut W) -> Result<()> {
+impl Context {
+    /// Opens context stored in `dir`: banks are loaded from the latest
+    /// checkpoint and only the log written after it is replayed.
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<Context> {
+        let checkpoint = Checkpoint::load(&dir)?.unwrap_or_default();
+        let (wal, records) = Wal::open(&dir, checkpoint.segment)?;
+
+        let mut banks_state: Vec<(Snapshot, Vec<Operation>)> = checkpoint
+            .banks
+            .into_iter()
+            .map(|snapshot| (snapshot, Vec::new()))
+            .collect();
+
+        let records_since_snapshot = records.len();
+        for record in records {
+            match record {
+                Record::NewBank => banks_state.push((Snapshot::default(), Vec::new())),
+                Record::RestoreBank { id } => {
+                    let state = banks_state
+                        .get((id as usize).wrapping_sub(1))
+                        .ok_or("invalid bank id in write-ahead log")?
+                        .clone();
+                    banks_state.push(state);
+                }
+                Record::Operation { bank_id, operation } => banks_state
+                    .get_mut((bank_id as usize).wrapping_sub(1))
+                    .ok_or("invalid bank id in write-ahead log")?
+                    .1
+                    .push(operation),
+            }
+        }
+
+        let mut banks = Vec::new();
+        for (snapshot, operations) in banks_state {
+            banks.push(Bank::restore_from_snapshot(snapshot, operations.iter())?);
+        }
+
+        Ok(Context {
+            banks,
+            snapshot_interval: SNAPSHOT_INTERVAL,
+            wal: Some(wal),
+            records_since_snapshot,
+        })
+    }
+
+    /// Saves snapshots of all banks and drops log segments they cover.
+    pub fn snapshot(&mut self) -> wal::Result<()> {
+        let wal = match self.wal.as_mut() {
+            Some(wal) => wal,
+            None => return Ok(()),
+        };
+
+        // new records go to the fresh segment, so everything before it
+        // is covered by the checkpoint once it is saved
+        let segment = wal.rotate()?;
+
+        let checkpoint = Checkpoint {
+            segment,
+            banks: self.banks.iter().map(Bank::snapshot).collect(),
+        };
+
+        checkpoint.save(wal.dir())?;
+        wal.compact(segment)?;
+
+        self.records_since_snapshot = 0;
+        Ok(())
+    }
+
+    fn maybe_snapshot(&mut self) {
+        if self.snapshot_interval == 0 || self.records_since_snapshot < self.snapshot_interval {
+            return;
+        }
+
+        // log already holds everything, failed snapshot only delays compaction
+        if let Err(e) = self.snapshot() {
+            eprintln!("Error taking snapshot: {}", e);
+        }
+    }
+
+    fn append(&mut self, record: Record) -> wal::Result<()> {
+        match self.wal.as_mut() {
+            Some(wal) => wal.append(&record)?,
+            None => return Ok(()),
+        }
+
+        self.records_since_snapshot += 1;
+        Ok(())
+    }
+
+    fn new_bank(&mut self) -> wal::Result<()> {
+        self.append(Record::NewBank)?;
+        self.banks.push(Bank::default());
+
+        self.maybe_snapshot();
+        Ok(())
+    }
+
+    // Persists operation just applied to the current bank.
+    // If it could not be written, it is dropped from memory too,
+    // so the client never sees a change that would be lost on restart.
+    fn commit(
+        &mut self,
+        current_bank: usize,
+        operation_id: OperationID,
+    ) -> wal::Result<OperationID> {
+        let bank = &self.banks[current_bank];
+        let operation = *bank.get_operation(operation_id).unwrap();
+
+        let record = Record::Operation {
+            bank_id: current_bank as u64 + 1,
+            operation,
+        };
+
+        if let Err(e) = self.append(record) {
+            let bank = &self.banks[current_bank];
+            let count = bank.get_all_operations().count();
+            self.banks[current_bank] = Bank::restore_from_snapshot(
+                bank.base_snapshot().clone(),
+                bank.get_all_operations().take(count - 1),
+            )
+            .expect("prefix of valid operations log must be restorable");
+
+            return Err(e);
+        }
+
+        self.maybe_snapshot();
+        Ok(operation_id)
+    }
+}
+
+fn write_storage_error<W: Write>(bank_id: usize, e: WalError, writer: &mut W) -> Result<()> {
+    writer.write_all(
+        format!(
+            "Bank: {}\nStatus: error\nType: storage\nError: {}\n\n",
+            bank_id, e
+        )
+        .as_bytes(),
+    )?;
+
+    Ok(())
+}
+
+fn handle_new_bank<W: Write>(
+    lock_context: ARWLockContext,
+    session: &mut Session,
+    writer: &mut W,
+) -> Result<()> {
     let context = lock_context.read().unwrap();
     let prev_bank_id = if context.banks.is_empty() {
         0
     } else {
-        context.current_bank + 1
+        session.current_bank + 1
     };
 
     drop(context);
 
     let mut context = lock_context.write().unwrap();
-    context.banks.push(Bank::default());
-    context.current_bank = context.banks.len() - 1;
+    if let Err(e) = context.new_bank() {
+        return write_storage_error(prev_bank_id, e, writer);
+    }
+    session.current_bank = context.banks.len() - 1;
 
-    let current_bank_id = context.current_bank + 1;
+    let current_bank_id = session.current_bank + 1;
 
     writer.write_all(
         format!(
@@ -45,6 +208,7 @@ fn handle_new_bank<W: Write>(lock_context: ARWLockContext, writer: &mut W) -> Re
 fn handle_change_bank<W: Write>(
     id: u64,
     lock_context: ARWLockContext,
+    session: &mut Session,
     writer: &mut W,
 ) -> Result<()> {
     let context = lock_context.read().unwrap();
@@ -52,7 +216,7 @@ fn handle_change_bank<W: Write>(
         writer.write_all(
             format!(
                 "Bank: {}\nStatus: error\nType: bank\nError: invalid bank id\n\n",
-                context.current_bank + 1,
+                session.current_bank + 1,
             )
             .as_bytes(),
         )?;
@@ -62,30 +226,35 @@ fn handle_change_bank<W: Write>(
 
     drop(context);
 
-    let mut context = lock_context.write().unwrap();
     let new_current_bank = (id - 1) as usize;
 
     writer.write_all(
         format!(
             "Bank: {}\nStatus: ok\nResult: {}\n\n",
-            context.current_bank + 1,
+            session.current_bank + 1,
             new_current_bank + 1,
         )
         .as_bytes(),
     )?;
 
-    context.current_bank = new_current_bank;
+    session.current_bank = new_current_bank;
 
     Ok(())
 }
 
-fn handle_which_bank<W: Write>(lock_context: ARWLockContext, writer: &mut W) -> Result<()> {
+fn handle_which_bank<W: Write>(
+    lock_context: ARWLockContext,
+    session: &mut Session,
+    writer: &mut W,
+) -> Result<()> {
     let mut context = lock_context.write().unwrap();
     if context.banks.is_empty() {
-        context.banks.push(Bank::default());
+        if let Err(e) = context.new_bank() {
+            return write_storage_error(0, e, writer);
+        }
     }
 
-    let current_bank = context.current_bank + 1;
+    let current_bank = session.current_bank + 1;
     writer.write_all(
         format!(
             "Bank: {}\nStatus: ok\nResult: {}\n\n",
@@ -100,6 +269,7 @@ fn handle_which_bank<W: Write>(lock_context: ARWLockContext, writer: &mut W) ->
 fn handle_restore_bank<W: Write>(
     id: u64,
     lock_context: ARWLockContext,
+    session: &mut Session,
     writer: &mut W,
 ) -> Result<()> {
     let context = lock_context.read().unwrap();
@@ -108,7 +278,7 @@ fn handle_restore_bank<W: Write>(
         writer.write_all(
             format!(
                 "Bank: {}\nStatus: error\nType: bank\nError: invalid bank id\n\n",
-                context.current_bank + 1,
+                session.current_bank + 1,
             )
             .as_bytes(),
         )?;
@@ -119,11 +289,21 @@ fn handle_restore_bank<W: Write>(
     drop(context);
 
     let mut context = lock_context.write().unwrap();
-    let current_bank = context.current_bank;
+    let current_bank = session.current_bank;
 
     let src_bank = &mut context.banks[current_bank];
-    match Bank::restore(src_bank.get_all_operations()) {
+    match Bank::restore_from_snapshot(
+        src_bank.base_snapshot().clone(),
+        src_bank.get_all_operations(),
+    ) {
         Ok(new_bank) => {
+            let record = Record::RestoreBank {
+                id: current_bank as u64 + 1,
+            };
+            if let Err(e) = context.append(record) {
+                return write_storage_error(current_bank + 1, e, writer);
+            }
+
             writer.write_all(
                 format!(
                     "Bank: {}\nStatus: ok\nResult: {}\n\n",
@@ -133,7 +313,8 @@ fn handle_restore_bank<W: Write>(
                 .as_bytes(),
             )?;
             context.banks.push(new_bank);
-            context.current_bank = context.banks.len() - 1;
+            session.current_bank = context.banks.len() - 1;
+            context.maybe_snapshot();
         }
         Err(e) => {
             writer.write_all(
@@ -153,23 +334,30 @@ fn handle_restore_bank<W: Write>(
 fn handle_register_account<W: Write>(
     balance: u64,
     lock_context: ARWLockContext,
+    session: &mut Session,
     writer: &mut W,
 ) -> Result<()> {
     let mut context = lock_context.write().unwrap();
     if context.banks.is_empty() {
-        context.banks.push(Bank::default());
+        if let Err(e) = context.new_bank() {
+            return write_storage_error(0, e, writer);
+        }
     }
 
-    let current_bank = context.current_bank;
+    let current_bank = session.current_bank;
     let bank = &mut context.banks[current_bank];
     let account = Account::new(balance);
 
     match bank.register_account(account) {
         Ok(opperation_id) => {
+            if let Err(e) = context.commit(current_bank, opperation_id) {
+                return write_storage_error(current_bank + 1, e, writer);
+            }
+
             writer.write_all(
                 format!(
                     "Bank: {}\nOpID: {}\nStatus: ok\nResult: {}\n\n",
-                    context.current_bank + 1,
+                    session.current_bank + 1,
                     opperation_id,
                     account.id
                 )
@@ -180,7 +368,7 @@ fn handle_register_account<W: Write>(
             writer.write_all(
                 format!(
                     "Bank: {}\nStatus: error\nType: bank\nError: {}\n\n",
-                    context.current_bank, e
+                    session.current_bank, e
                 )
                 .as_bytes(),
             )?;
@@ -193,16 +381,17 @@ fn handle_register_account<W: Write>(
 fn handle_get_balance<W: Write>(
     id: AccountID,
     lock_context: ARWLockContext,
+    session: &mut Session,
     writer: &mut W,
 ) -> Result<()> {
     let context = lock_context.read().unwrap();
-    let bank = &context.banks[context.current_bank];
+    let bank = &context.banks[session.current_bank];
     match bank.get_balance(id) {
         Ok(balance) => {
             writer.write_all(
                 format!(
                     "Bank: {}\nStatus: ok\nResult: {}\n\n",
-                    context.current_bank + 1,
+                    session.current_bank + 1,
                     balance
                 )
                 .as_bytes(),
@@ -212,7 +401,7 @@ fn handle_get_balance<W: Write>(
             writer.write_all(
                 format!(
                     "Bank: {}\nStatus: fail\nResult: {}\n\n",
-                    context.current_bank + 1,
+                    session.current_bank + 1,
                     e
                 )
                 .as_bytes(),
@@ -227,13 +416,18 @@ fn handle_deposit<W: Write>(
     id: AccountID,
     amount: u64,
     lock_context: ARWLockContext,
+    session: &mut Session,
     writer: &mut W,
 ) -> Result<()> {
     let mut context = lock_context.write().unwrap();
-    let current_bank = context.current_bank;
+    let current_bank = session.current_bank;
     let bank = &mut context.banks[current_bank];
     match bank.deposit(id, amount) {
         Ok(opperation_id) => {
+            if let Err(e) = context.commit(current_bank, opperation_id) {
+                return write_storage_error(current_bank + 1, e, writer);
+            }
+
             writer.write_all(
                 format!(
                     "Bank: {}\nOpID: {}\nStatus: ok\n\n",
@@ -262,13 +456,18 @@ fn handle_withdraw<W: Write>(
     id: AccountID,
     amount: u64,
     lock_context: ARWLockContext,
+    session: &mut Session,
     writer: &mut W,
 ) -> Result<()> {
     let mut context = lock_context.write().unwrap();
-    let current_bank = context.current_bank;
+    let current_bank = session.current_bank;
     let bank = &mut context.banks[current_bank];
     match bank.withdraw(id, amount) {
         Ok(opperation_id) => {
+            if let Err(e) = context.commit(current_bank, opperation_id) {
+                return write_storage_error(current_bank + 1, e, writer);
+            }
+
             writer.write_all(
                 format!(
                     "Bank: {}\nOpID: {}\nStatus: ok\n\n",
@@ -298,17 +497,22 @@ fn handle_transfer<W: Write>(
     reciever: AccountID,
     amount: u64,
     lock_context: ARWLockContext,
+    session: &mut Session,
     writer: &mut W,
 ) -> Result<()> {
     let mut context = lock_context.write().unwrap();
-    let current_bank = context.current_bank;
+    let current_bank = session.current_bank;
     let bank = &mut context.banks[current_bank];
     match bank.transfer(sender, reciever, amount) {
         Ok(opperation_id) => {
+            if let Err(e) = context.commit(current_bank, opperation_id) {
+                return write_storage_error(current_bank + 1, e, writer);
+            }
+
             writer.write_all(
                 format!(
                     "Bank: {}\nOpID: {}\nStatus: ok\n\n",
-                    context.current_bank + 1,
+                    session.current_bank + 1,
                     opperation_id,
                 )
                 .as_bytes(),
@@ -318,7 +522,7 @@ fn handle_transfer<W: Write>(
             writer.write_all(
                 format!(
                     "Bank: {}\nStatus: error\nType: bank\nError: {}\n\n",
-                    context.current_bank + 1,
+                    session.current_bank + 1,
                     e
                 )
                 .as_bytes(),
@@ -337,16 +541,17 @@ fn operations_as_string<'a, I: Iterator<Item = &'a Operation>>(operations: I) ->
 fn handle_list_account_operations<W: Write>(
     id: AccountID,
     lock_context: ARWLockContext,
+    session: &mut Session,
     writer: &mut W,
 ) -> Result<()> {
     let context = lock_context.read().unwrap();
-    let bank = &context.banks[context.current_bank];
+    let bank = &context.banks[session.current_bank];
     let operations = bank.get_account_operations(id);
 
     writer.write_all(
         format!(
             "Bank: {}\nStatus: ok\nResult: \n{}\n\n",
-            context.current_bank + 1,
+            session.current_bank + 1,
             operations_as_string(operations),
         )
         .as_bytes(),
@@ -357,16 +562,17 @@ fn handle_list_account_operations<W: Write>(
 
 fn handle_list_all_operations<W: Write>(
     lock_context: ARWLockContext,
+    session: &mut Session,
     writer: &mut W,
 ) -> Result<()> {
     let context = lock_context.read().unwrap();
-    let bank = &context.banks[context.current_bank];
+    let bank = &context.banks[session.current_bank];
     let operations = bank.get_all_operations();
 
     writer.write_all(
         format!(
             "Bank: {}\nStatus: ok\nResult: \n{}\n\n",
-            context.current_bank + 1,
+            session.current_bank + 1,
             operations_as_string(operations),
         )
         .as_bytes(),
@@ -409,29 +615,34 @@ fn handle_help<W: Write>(writer: &mut W) -> Result<()> {
 fn handle_command(
     command: &Command,
     lock_context: ARWLockContext,
+    session: &mut Session,
     writer: &mut impl Write,
 ) -> Result<()> {
     match *command {
-        Command::NewBank => handle_new_bank(lock_context, writer)?,
-        Command::ChangeBank { id } => handle_change_bank(id, lock_context, writer)?,
-        Command::RestoreBank { id } => handle_restore_bank(id, lock_context, writer)?,
-        Command::WhichBank => handle_which_bank(lock_context, writer)?,
+        Command::NewBank => handle_new_bank(lock_context, session, writer)?,
+        Command::ChangeBank { id } => handle_change_bank(id, lock_context, session, writer)?,
+        Command::RestoreBank { id } => handle_restore_bank(id, lock_context, session, writer)?,
+        Command::WhichBank => handle_which_bank(lock_context, session, writer)?,
         Command::RegisterAccount { balance } => {
-            handle_register_account(balance, lock_context, writer)?
+            handle_register_account(balance, lock_context, session, writer)?
+        }
+        Command::GetBalance { id } => handle_get_balance(id, lock_context, session, writer)?,
+        Command::Deposit { id, balance } => {
+            handle_deposit(id, balance, lock_context, session, writer)?
+        }
+        Command::Withdraw { id, balance } => {
+            handle_withdraw(id, balance, lock_context, session, writer)?
         }
-        Command::GetBalance { id } => handle_get_balance(id, lock_context, writer)?,
-        Command::Deposit { id, balance } => handle_deposit(id, balance, lock_context, writer)?,
-        Command::Withdraw { id, balance } => handle_withdraw(id, balance, lock_context, writer)?,
         Command::Transfer {
             sender,
             reciever,
             amount,
-        } => handle_transfer(sender, reciever, amount, lock_context, writer)?,
+        } => handle_transfer(sender, reciever, amount, lock_context, session, writer)?,
 
         Command::ListAccountOperations { id } => {
-            handle_list_account_operations(id, lock_context, writer)?
+            handle_list_account_operations(id, lock_context, session, writer)?
         }
-        Command::ListAllOperations => handle_list_all_operations(lock_context, writer)?,
+        Command::ListAllOperations => handle_list_all_operations(lock_context, session, writer)?,
         Command::Quit => handle_quit(writer)?,
         Command::Help => handle_help(writer)?,
     };
@@ -459,6 +670,8 @@ pub fn handle<R: BufRead, W: Write, T: Write>(
     terminal: &mut T,
 ) -> Result<()> {
     let original_lock_context = lock_context;
+    let mut session = Session::default();
+
     loop {
         let mut line = String::new();
         match reader.read_line(&mut line) {
@@ -471,7 +684,7 @@ pub fn handle<R: BufRead, W: Write, T: Write>(
                 match parse_command(&line) {
                     Ok(command) => {
                         let lock_context = Arc::clone(&original_lock_context);
-                        handle_command(&command, lock_context, writer)?;
+                        handle_command(&command, lock_context, &mut session, writer)?;
                         if command == Command::Quit {
                             terminal.write_all("Client quited\n".as_bytes())?;
                             break;
@@ -581,7 +794,7 @@ mod tests {
     fn handle_which_bank_command() {
         let original_lock_context = Arc::new(RwLock::new(Context::default()));
 
-        let input = vec!["which_bank", "new_bank", "which_bank"].join("\n");
+        let input = ["which_bank", "new_bank", "which_bank"].join("\n");
         let mut reader = input.as_bytes();
 
         let mut writer = Vec::new();
@@ -590,7 +803,7 @@ mod tests {
         let lock_context = Arc::clone(&original_lock_context);
         handle(lock_context, &mut reader, &mut writer, &mut terminal).unwrap();
 
-        let expected = vec![
+        let expected = [
             "Bank: 1\nStatus: ok\nResult: 1\n\n".to_owned(),
             "Bank: 1\nStatus: ok\nResult: 2\n\n".to_owned(),
             "Bank: 2\nStatus: ok\nResult: 2\n\n".to_owned(),
@@ -604,7 +817,7 @@ mod tests {
     fn handle_change_bank_command() {
         let original_lock_context = Arc::new(RwLock::new(Context::default()));
 
-        let input = vec![
+        let input = [
             "new_bank",
             "new_bank",
             "new_bank",
@@ -622,7 +835,7 @@ mod tests {
 
         let lock_context = Arc::clone(&original_lock_context);
         handle(lock_context, &mut reader, &mut writer, &mut terminal).unwrap();
-        let expected = vec![
+        let expected = [
             "Bank: 0\nStatus: ok\nResult: 1\n\n".to_owned(),
             "Bank: 1\nStatus: ok\nResult: 2\n\n".to_owned(),
             "Bank: 2\nStatus: ok\nResult: 3\n\n".to_owned(),
@@ -638,11 +851,42 @@ mod tests {
         assert_eq!(from_utf8(writer.as_slice()).unwrap(), expected);
     }
 
+    #[test]
+    fn handle_keeps_session_per_connection_works() {
+        let original_lock_context = Arc::new(RwLock::new(Context::default()));
+        let mut terminal = Vec::new();
+
+        let mut reader = "new_bank\nnew_bank\nwhich_bank".as_bytes();
+        let mut writer = Vec::new();
+        let lock_context = Arc::clone(&original_lock_context);
+        handle(lock_context, &mut reader, &mut writer, &mut terminal).unwrap();
+
+        assert_eq!(
+            from_utf8(writer.as_slice()).unwrap(),
+            [
+                "Bank: 0\nStatus: ok\nResult: 1\n\n",
+                "Bank: 1\nStatus: ok\nResult: 2\n\n",
+                "Bank: 2\nStatus: ok\nResult: 2\n\n",
+            ]
+            .join("")
+        );
+
+        let mut reader = "which_bank".as_bytes();
+        let mut writer = Vec::new();
+        let lock_context = Arc::clone(&original_lock_context);
+        handle(lock_context, &mut reader, &mut writer, &mut terminal).unwrap();
+
+        assert_eq!(
+            from_utf8(writer.as_slice()).unwrap(),
+            "Bank: 1\nStatus: ok\nResult: 1\n\n"
+        );
+    }
+
     #[test]
     fn handle_register_account_works() {
         let original_lock_context = Arc::new(RwLock::new(Context::default()));
 
-        let input = vec!["register_account", "register_account 100"].join("\n");
+        let input = ["register_account", "register_account 100"].join("\n");
 
         let mut reader = input.as_bytes();
 
@@ -655,9 +899,7 @@ mod tests {
 
         let lock_context = Arc::clone(&original_lock_context);
         let context = lock_context.read().unwrap();
-        let operations: Vec<&Operation> = context.banks[context.current_bank]
-            .get_all_operations()
-            .collect();
+        let operations: Vec<&Operation> = context.banks[0].get_all_operations().collect();
 
         let operation = operations[0];
         let operation_id = operation.id;
@@ -668,7 +910,7 @@ mod tests {
             AccountID::new()
         };
 
-        let expected = vec![
+        let expected = [
             format!(
                 "Command: register_account\nStatus: error\nType: parse\nError: {}\n\n",
                 ParseError::RequireArguments {
@@ -702,9 +944,7 @@ mod tests {
         let lock_context = Arc::clone(&original_lock_context);
         let context = lock_context.read().unwrap();
 
-        let operations: Vec<&Operation> = context.banks[context.current_bank]
-            .get_all_operations()
-            .collect();
+        let operations: Vec<&Operation> = context.banks[0].get_all_operations().collect();
 
         let account_id = if let OperationKind::Register { id, .. } = operations[0].kind {
             id
@@ -712,10 +952,10 @@ mod tests {
             AccountID::new()
         };
 
-        let input = vec![
+        let input = [
             "get_balance".to_owned(),
             "get_balance test".to_owned(),
-            format!("get_balance {}", account_id.to_string()),
+            format!("get_balance {}", account_id),
         ]
         .join("\n");
 
@@ -724,7 +964,7 @@ mod tests {
         let lock_context = Arc::clone(&original_lock_context);
         handle(lock_context, &mut reader, &mut writer, &mut terminal).unwrap();
 
-        let expected = vec![
+        let expected = [
             format!(
                 "Bank: 1\nOpID: {}\nStatus: ok\nResult: {}\n\n",
                 operations[0].id, account_id,
@@ -764,9 +1004,7 @@ mod tests {
         let account_id = {
             let lock_context = Arc::clone(&original_lock_context);
             let context = lock_context.read().unwrap();
-            let current_bank = context.current_bank;
-            let operations: Vec<&Operation> =
-                context.banks[current_bank].get_all_operations().collect();
+            let operations: Vec<&Operation> = context.banks[0].get_all_operations().collect();
 
             if let OperationKind::Register { id, .. } = operations[0].kind {
                 id
@@ -775,11 +1013,11 @@ mod tests {
             }
         };
 
-        let input = vec![
+        let input = [
             "deposit".to_owned(),
             "deposit test 10".to_owned(),
-            format!("deposit {} test", account_id.to_string()),
-            format!("deposit {} 100", account_id.to_string()),
+            format!("deposit {} test", account_id),
+            format!("deposit {} 100", account_id),
         ]
         .join("\n");
 
@@ -791,11 +1029,9 @@ mod tests {
         let expected = {
             let lock_context = Arc::clone(&original_lock_context);
             let context = lock_context.read().unwrap();
-            let operations: Vec<&Operation> = context.banks[context.current_bank]
-                .get_all_operations()
-                .collect();
+            let operations: Vec<&Operation> = context.banks[0].get_all_operations().collect();
 
-            vec![
+            [
                 format!(
                     "Bank: 1\nOpID: {}\nStatus: ok\nResult: {}\n\n",
                     operations[0].id, account_id,
@@ -815,7 +1051,7 @@ mod tests {
                 ),
                 format!(
                     "Command: deposit {} test\nStatus: error\nType: parse\nError: {}\n\n",
-                    account_id.to_string(),
+                    account_id,
                     ParseError::InvalidArgumentUint {
                         name: "amount".to_owned(),
                         e: "test".parse::<u64>().unwrap_err(),
@@ -845,9 +1081,7 @@ mod tests {
             let lock_context = Arc::clone(&original_lock_context);
             let context = lock_context.read().unwrap();
 
-            let operations: Vec<&Operation> = context.banks[context.current_bank]
-                .get_all_operations()
-                .collect();
+            let operations: Vec<&Operation> = context.banks[0].get_all_operations().collect();
 
             if let OperationKind::Register { id, .. } = operations[0].kind {
                 id
@@ -856,12 +1090,12 @@ mod tests {
             }
         };
 
-        let input = vec![
+        let input = [
             "withdraw".to_owned(),
             "withdraw test 10".to_owned(),
-            format!("withdraw {} test", account_id.to_string()),
-            format!("withdraw {} 100", account_id.to_string()),
-            format!("withdraw {} 100", account_id.to_string()),
+            format!("withdraw {} test", account_id),
+            format!("withdraw {} 100", account_id),
+            format!("withdraw {} 100", account_id),
         ]
         .join("\n");
 
@@ -873,11 +1107,9 @@ mod tests {
         let expected = {
             let lock_context = Arc::clone(&original_lock_context);
             let context = lock_context.read().unwrap();
-            let operations: Vec<&Operation> = context.banks[context.current_bank]
-                .get_all_operations()
-                .collect();
+            let operations: Vec<&Operation> = context.banks[0].get_all_operations().collect();
 
-            vec![
+            [
                 format!(
                     "Bank: 1\nOpID: {}\nStatus: ok\nResult: {}\n\n",
                     operations[0].id, account_id,
@@ -897,7 +1129,7 @@ mod tests {
                 ),
                 format!(
                     "Command: withdraw {} test\nStatus: error\nType: parse\nError: {}\n\n",
-                    account_id.to_string(),
+                    account_id,
                     ParseError::InvalidArgumentUint {
                         name: "amount".to_owned(),
                         e: "test".parse::<u64>().unwrap_err(),
@@ -914,7 +1146,7 @@ mod tests {
 
     #[test]
     fn handle_transfer_works() {
-        let input = vec![
+        let input = [
             "register_account 100".to_owned(),
             "register_account 50".to_owned(),
         ]
@@ -934,9 +1166,7 @@ mod tests {
             let lock_context = Arc::clone(&original_lock_context);
             let context = lock_context.read().unwrap();
 
-            let operations: Vec<&Operation> = context.banks[context.current_bank]
-                .get_all_operations()
-                .collect();
+            let operations: Vec<&Operation> = context.banks[0].get_all_operations().collect();
 
             (
                 if let OperationKind::Register { id, .. } = operations[0].kind {
@@ -952,26 +1182,14 @@ mod tests {
             )
         };
 
-        let input = vec![
+        let input = [
             "transfer".to_owned(),
             "transfer test1 test2 50".to_owned(),
-            format!("transfer {} test2 50", account1_id.to_string()),
-            format!("transfer test1 {} 50", account2_id.to_string()),
-            format!(
-                "transfer {} {} test",
-                account1_id.to_string(),
-                account2_id.to_string()
-            ),
-            format!(
-                "transfer {} {} 50",
-                account1_id.to_string(),
-                account2_id.to_string()
-            ),
-            format!(
-                "transfer {} {} 500",
-                account2_id.to_string(),
-                account1_id.to_string()
-            ),
+            format!("transfer {} test2 50", account1_id),
+            format!("transfer test1 {} 50", account2_id),
+            format!("transfer {} {} test", account1_id, account2_id),
+            format!("transfer {} {} 50", account1_id, account2_id),
+            format!("transfer {} {} 500", account2_id, account1_id),
         ]
         .join("\n");
 
@@ -983,12 +1201,9 @@ mod tests {
         let expected = {
             let lock_context = Arc::clone(&original_lock_context);
             let context = lock_context.read().unwrap();
-            let operations: Vec<&Operation> = context.banks[context.current_bank]
-                .get_all_operations()
-                .collect();
+            let operations: Vec<&Operation> = context.banks[0].get_all_operations().collect();
 
-            vec![
-                format!(
+            [format!(
                     "Bank: 1\nOpID: {}\nStatus: ok\nResult: {}\n\n",
                     operations[0].id, account1_id,
                 ),
@@ -1015,7 +1230,7 @@ mod tests {
                 ),
                 format!(
                     "Command: transfer {} test2 50\nStatus: error\nType: parse\nError: {}\n\n",
-                    account1_id.to_string(),
+                    account1_id,
                     ParseError::InvalidArgumentAccountID{
                         name: "reciever_account_id".to_owned(),
                         e: AccountID::parse_str("test2").unwrap_err()
@@ -1023,7 +1238,7 @@ mod tests {
                 ),
                 format!(
                     "Command: transfer test1 {} 50\nStatus: error\nType: parse\nError: {}\n\n",
-                    account2_id.to_string(),
+                    account2_id,
                     ParseError::InvalidArgumentAccountID{
                         name: "sender_account_id".to_owned(),
                         e: AccountID::parse_str("test1").unwrap_err()
@@ -1031,13 +1246,12 @@ mod tests {
                 ),
                 format!(
                     "Command: transfer {} {} test\nStatus: error\nType: parse\nError: invalid argument amount: {}\n\n",
-                    account1_id.to_string(),
-                    account2_id.to_string(),
+                    account1_id,
+                    account2_id,
                     "test".parse::<u64>().unwrap_err(),
                 ),
                 format!("Bank: 1\nOpID: {}\nStatus: ok\n\n", operations[2].id),
-                "Bank: 1\nStatus: error\nType: bank\nError: Insufficient funds\n\n".to_owned(),
-            ]
+                "Bank: 1\nStatus: error\nType: bank\nError: Insufficient funds\n\n".to_owned()]
             .join("")
         };
 
@@ -1046,7 +1260,7 @@ mod tests {
 
     #[test]
     fn handle_list_operations_works() {
-        let input = vec![
+        let input = [
             "register_account 100".to_owned(),
             "register_account 50".to_owned(),
         ]
@@ -1065,9 +1279,7 @@ mod tests {
         let (account1_id, account2_id) = {
             let lock_context = Arc::clone(&original_lock_context);
             let context = lock_context.read().unwrap();
-            let operations: Vec<&Operation> = context.banks[context.current_bank]
-                .get_all_operations()
-                .collect();
+            let operations: Vec<&Operation> = context.banks[0].get_all_operations().collect();
 
             (
                 if let OperationKind::Register { id, .. } = operations[0].kind {
@@ -1083,18 +1295,14 @@ mod tests {
             )
         };
 
-        let input = vec![
-            format!("deposit {} 100", account1_id.to_string()),
-            format!("deposit {} 250", account2_id.to_string()),
-            format!(
-                "transfer {} {} 50",
-                account1_id.to_string(),
-                account2_id.to_string()
-            ),
-            format!("withdraw {} 50", account2_id.to_string()),
+        let input = [
+            format!("deposit {} 100", account1_id),
+            format!("deposit {} 250", account2_id),
+            format!("transfer {} {} 50", account1_id, account2_id),
+            format!("withdraw {} 50", account2_id),
             "list_account_operations".to_owned(),
             "list_account_operations test".to_owned(),
-            format!("list_account_operations {}", account1_id.to_string()),
+            format!("list_account_operations {}", account1_id),
             "list_all_operations".to_owned(),
         ]
         .join("\n");
@@ -1107,15 +1315,11 @@ mod tests {
         let expected = {
             let lock_context = Arc::clone(&original_lock_context);
             let context = lock_context.read().unwrap();
-            let operations: Vec<&Operation> = context.banks[context.current_bank]
-                .get_all_operations()
-                .collect();
+            let operations: Vec<&Operation> = context.banks[0].get_all_operations().collect();
 
-            let account1_operations =
-                context.banks[context.current_bank].get_account_operations(account1_id);
+            let account1_operations = context.banks[0].get_account_operations(account1_id);
 
-            vec![
-                format!(
+            [format!(
                     "Bank: 1\nOpID: {}\nStatus: ok\nResult: {}\n\n",
                     operations[0].id, account1_id,
                 ),
@@ -1147,8 +1351,7 @@ mod tests {
                 format!(
                     "Bank: 1\nStatus: ok\nResult: \n{}\n\n",
                     operations_as_string(operations.into_iter())
-                ),
-            ]
+                )]
             .join("")
         };
 
@@ -1157,7 +1360,7 @@ mod tests {
 
     #[test]
     fn handle_restore_bank_works() {
-        let input = vec![
+        let input = [
             "register_account 100".to_owned(),
             "register_account 50".to_owned(),
         ]
@@ -1176,9 +1379,7 @@ mod tests {
         let (account1_id, account2_id) = {
             let lock_context = Arc::clone(&original_lock_context);
             let context = lock_context.read().unwrap();
-            let operations: Vec<&Operation> = context.banks[context.current_bank]
-                .get_all_operations()
-                .collect();
+            let operations: Vec<&Operation> = context.banks[0].get_all_operations().collect();
 
             (
                 if let OperationKind::Register { id, .. } = operations[0].kind {
@@ -1194,15 +1395,11 @@ mod tests {
             )
         };
 
-        let input = vec![
-            format!("deposit {} 100", account1_id.to_string()),
-            format!("deposit {} 250", account2_id.to_string()),
-            format!(
-                "transfer {} {} 50",
-                account1_id.to_string(),
-                account2_id.to_string()
-            ),
-            format!("withdraw {} 50", account2_id.to_string()),
+        let input = [
+            format!("deposit {} 100", account1_id),
+            format!("deposit {} 250", account2_id),
+            format!("transfer {} {} 50", account1_id, account2_id),
+            format!("withdraw {} 50", account2_id),
             "restore_bank".to_owned(),
             "restore_bank test".to_owned(),
             "restore_bank 100".to_owned(),
@@ -1220,11 +1417,9 @@ mod tests {
             let lock_context = Arc::clone(&original_lock_context);
             let context = lock_context.read().unwrap();
 
-            let operations: Vec<&Operation> = context.banks[context.current_bank - 1]
-                .get_all_operations()
-                .collect();
+            let operations: Vec<&Operation> = context.banks[0].get_all_operations().collect();
 
-            vec![
+            [
                 format!(
                     "Bank: 1\nOpID: {}\nStatus: ok\nResult: {}\n\n",
                     operations[0].id, account1_id,
@@ -1262,4 +1457,114 @@ mod tests {
 
         assert_eq!(from_utf8(writer.as_slice()).unwrap(), expected);
     }
+
+    #[test]
+    fn context_open_restores_banks_works() {
+        let path = std::env::temp_dir().join(format!("bank-context-{}", OperationID::new()));
+
+        let lock_context = Arc::new(RwLock::new(Context::open(&path).unwrap()));
+
+        let mut reader = "register_account 100\nregister_account 50\nnew_bank\n".as_bytes();
+        let mut writer = Vec::new();
+        let mut terminal = Vec::new();
+        handle(
+            Arc::clone(&lock_context),
+            &mut reader,
+            &mut writer,
+            &mut terminal,
+        )
+        .unwrap();
+
+        let output = from_utf8(writer.as_slice()).unwrap().to_owned();
+        let account_ids: Vec<&str> = output
+            .split("\n\n")
+            .filter_map(|response| response.split("Result: ").nth(1))
+            .take(2)
+            .collect();
+
+        let input = format!(
+            "change_bank 1\ndeposit {0} 10\ntransfer {0} {1} 20\nwithdraw {1} 5\nrestore_bank 1\n",
+            account_ids[0], account_ids[1]
+        );
+        let mut reader = input.as_bytes();
+        handle(
+            Arc::clone(&lock_context),
+            &mut reader,
+            &mut writer,
+            &mut terminal,
+        )
+        .unwrap();
+
+        let expected_banks = lock_context.read().unwrap().banks.clone();
+        drop(lock_context);
+
+        let context = Context::open(&path).unwrap();
+        assert_eq!(context.banks.len(), 3);
+        assert_eq!(context.banks, expected_banks);
+
+        let account_id = AccountID::parse_str(account_ids[0]).unwrap();
+        assert_eq!(context.banks[0].get_balance(account_id), Ok(90));
+        assert_eq!(context.banks[2].get_balance(account_id), Ok(90));
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn context_snapshot_compacts_log_works() {
+        let dir = std::env::temp_dir().join(format!("bank-context-{}", OperationID::new()));
+
+        let mut context = Context::open(&dir).unwrap();
+        context.snapshot_interval = 3;
+        let lock_context = Arc::new(RwLock::new(context));
+
+        let mut reader = "register_account 100\nregister_account 50\n".as_bytes();
+        let mut writer = Vec::new();
+        let mut terminal = Vec::new();
+        handle(
+            Arc::clone(&lock_context),
+            &mut reader,
+            &mut writer,
+            &mut terminal,
+        )
+        .unwrap();
+
+        let output = from_utf8(writer.as_slice()).unwrap().to_owned();
+        let account_ids: Vec<&str> = output
+            .split("\n\n")
+            .filter_map(|response| response.split("Result: ").nth(1))
+            .collect();
+
+        let input = format!("transfer {0} {1} 10\n", account_ids[0], account_ids[1]).repeat(5);
+        let mut reader = input.as_bytes();
+        handle(
+            Arc::clone(&lock_context),
+            &mut reader,
+            &mut writer,
+            &mut terminal,
+        )
+        .unwrap();
+        drop(lock_context);
+
+        let checkpoint = Checkpoint::load(&dir).unwrap().unwrap();
+        assert_eq!(checkpoint.banks.len(), 1);
+
+        let segments = std::fs::read_dir(&dir)
+            .unwrap()
+            .filter(|entry| {
+                let name = entry.as_ref().unwrap().file_name();
+                name.to_str().unwrap().starts_with("wal.")
+            })
+            .count();
+        assert_eq!(segments, 1);
+
+        let context = Context::open(&dir).unwrap();
+        assert!(context.banks[0].get_all_operations().count() < 7);
+
+        let sender_id = AccountID::parse_str(account_ids[0]).unwrap();
+        let receiver_id = AccountID::parse_str(account_ids[1]).unwrap();
+        assert_eq!(context.banks[0].get_balance(sender_id), Ok(50));
+        assert_eq!(context.banks[0].get_balance(receiver_id), Ok(100));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
 }
@@ -0,0 +1,584 @@
+use crate::bank::account::AccountID;
+use crate::bank::log::{Operation, OperationID, OperationKind};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Record {
+    NewBank,
+    RestoreBank { id: u64 },
+    Operation { bank_id: u64, operation: Operation },
+}
+
+#[derive(Debug)]
+pub enum WalError {
+    Io(std::io::Error),
+    Corrupted {
+        segment: u64,
+        line: usize,
+    },
+    CorruptedCheckpoint,
+    /// Failed write could not be cut off the log, nothing more is appended to it.
+    Broken,
+}
+
+impl std::fmt::Display for WalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            WalError::Io(e) => write!(f, "io error: {}", e),
+            WalError::Corrupted { segment, line } => {
+                write!(
+                    f,
+                    "corrupted record in segment {} at line {}",
+                    segment, line
+                )
+            }
+            WalError::CorruptedCheckpoint => write!(f, "corrupted checkpoint"),
+            WalError::Broken => write!(f, "log is broken by a failed write"),
+        }
+    }
+}
+
+impl std::error::Error for WalError {}
+
+impl From<std::io::Error> for WalError {
+    fn from(e: std::io::Error) -> WalError {
+        WalError::Io(e)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, WalError>;
+
+/// Append-only log of repository changes split into numbered segments
+/// (`wal.1`, `wal.2`, ...) inside of a directory.
+///
+/// Every record is stored on its own line as `<crc32> <payload>`, so a record
+/// that was only partially written before a crash (torn tail) is detected on
+/// open and cut off.
+#[derive(Debug)]
+pub struct Wal {
+    dir: PathBuf,
+    segment: u64,
+    file: File,
+    /// Length of the segment up to its last fully written record.
+    len: u64,
+    broken: bool,
+}
+
+impl Wal {
+    /// Opens (or creates) log in `dir` and returns records of all segments
+    /// starting from `first_segment`, older segments are removed.
+    pub fn open<P: AsRef<Path>>(dir: P, first_segment: u64) -> Result<(Wal, Vec<Record>)> {
+        let dir = dir.as_ref().to_path_buf();
+        std::fs::create_dir_all(&dir)?;
+
+        let mut segments = Vec::new();
+        for segment in list_segments(&dir)? {
+            if segment < first_segment {
+                std::fs::remove_file(segment_path(&dir, segment))?;
+            } else {
+                segments.push(segment);
+            }
+        }
+
+        let last_segment = segments.last().copied().unwrap_or(first_segment);
+
+        let mut records = Vec::new();
+        let mut last_segment_len = 0;
+        for segment in segments.iter().copied() {
+            let data = std::fs::read(segment_path(&dir, segment))?;
+            let (segment_records, valid_len) =
+                decode(&data).map_err(|line| WalError::Corrupted { segment, line })?;
+
+            // only the newest segment is written to, so only it could have torn tail
+            if valid_len < data.len() && segment != last_segment {
+                return Err(WalError::Corrupted {
+                    segment,
+                    line: segment_records.len() + 1,
+                });
+            }
+
+            records.extend(segment_records);
+            last_segment_len = valid_len;
+        }
+
+        let file = open_segment(&dir, last_segment)?;
+        if file.metadata()?.len() > last_segment_len as u64 {
+            file.set_len(last_segment_len as u64)?;
+            file.sync_all()?;
+        }
+
+        let wal = Wal {
+            dir,
+            segment: last_segment,
+            file,
+            len: last_segment_len as u64,
+            broken: false,
+        };
+
+        Ok((wal, records))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Number of the segment new records are appended to.
+    pub fn segment(&self) -> u64 {
+        self.segment
+    }
+
+    /// Starts new segment, all following records are appended to it.
+    pub fn rotate(&mut self) -> Result<u64> {
+        let segment = self.segment + 1;
+        self.file = open_segment(&self.dir, segment)?;
+        self.len = self.file.metadata()?.len();
+        self.segment = segment;
+
+        Ok(segment)
+    }
+
+    /// Removes all segments older than `first_segment`.
+    pub fn compact(&mut self, first_segment: u64) -> Result<()> {
+        for segment in list_segments(&self.dir)? {
+            if segment < first_segment {
+                std::fs::remove_file(segment_path(&self.dir, segment))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Appends record and waits until it reaches the disk.
+    pub fn append(&mut self, record: &Record) -> Result<()> {
+        let payload = encode_record(record);
+        let line = format!("{:08x} {}\n", crc32(payload.as_bytes()), payload);
+
+        self.write(line.as_bytes())
+    }
+
+    // Writes `data` and waits until it reaches the disk. Failed write is cut off,
+    // so the next record doesn't follow a partial line and the failed one isn't
+    // read back after a restart, though its caller got an error.
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        if self.broken {
+            return Err(WalError::Broken);
+        }
+
+        if let Err(e) = self
+            .file
+            .write_all(data)
+            .and_then(|()| self.file.sync_data())
+        {
+            if self.truncate().is_err() {
+                self.broken = true;
+            }
+            return Err(WalError::Io(e));
+        }
+
+        self.len += data.len() as u64;
+        Ok(())
+    }
+
+    // Cuts the segment back to its last fully written record, through a new handle
+    // as the old one may be unusable after a failed write.
+    fn truncate(&mut self) -> Result<()> {
+        let file = open_segment(&self.dir, self.segment)?;
+        file.set_len(self.len)?;
+        file.sync_all()?;
+        self.file = file;
+
+        Ok(())
+    }
+}
+
+fn segment_path(dir: &Path, segment: u64) -> PathBuf {
+    dir.join(format!("wal.{}", segment))
+}
+
+fn list_segments(dir: &Path) -> Result<Vec<u64>> {
+    let mut segments = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let name = entry?.file_name();
+        let segment = name
+            .to_str()
+            .and_then(|name| name.strip_prefix("wal."))
+            .and_then(|segment| segment.parse().ok());
+
+        if let Some(segment) = segment {
+            segments.push(segment);
+        }
+    }
+
+    segments.sort();
+    Ok(segments)
+}
+
+fn open_segment(dir: &Path, segment: u64) -> Result<File> {
+    let file = OpenOptions::new()
+        .read(true)
+        .append(true)
+        .create(true)
+        .open(segment_path(dir, segment))?;
+
+    // make sure the new segment itself survives a crash, not only its content
+    File::open(dir)?.sync_all()?;
+
+    Ok(file)
+}
+
+// Returns decoded records and length of the valid prefix of data,
+// or number of the broken line if it is not the last one.
+fn decode(data: &[u8]) -> std::result::Result<(Vec<Record>, usize), usize> {
+    let mut records = Vec::new();
+    let mut valid_len = 0;
+
+    let lines: Vec<&[u8]> = data.split_inclusive(|b| *b == b'\n').collect();
+    for (idx, line) in lines.iter().enumerate() {
+        let is_last = idx == lines.len() - 1;
+
+        match line.strip_suffix(b"\n").and_then(decode_line) {
+            Some(record) => {
+                records.push(record);
+                valid_len += line.len();
+            }
+            // only the last record could be torn by a crash in the middle of write,
+            // broken record before it means that log is damaged
+            None if is_last => break,
+            None => return Err(idx + 1),
+        }
+    }
+
+    Ok((records, valid_len))
+}
+
+fn decode_line(line: &[u8]) -> Option<Record> {
+    let line = std::str::from_utf8(line).ok()?;
+    let (checksum, payload) = line.split_once(' ')?;
+
+    if u32::from_str_radix(checksum, 16).ok()? != crc32(payload.as_bytes()) {
+        return None;
+    }
+
+    decode_record(payload)
+}
+
+fn encode_record(record: &Record) -> String {
+    match record {
+        Record::NewBank => String::from("new_bank"),
+        Record::RestoreBank { id } => format!("restore_bank {}", id),
+        Record::Operation { bank_id, operation } => {
+            let kind = match operation.kind {
+                OperationKind::Register { id, balance } => format!("register {} {}", id, balance),
+                OperationKind::Deposit { id, amount } => format!("deposit {} {}", id, amount),
+                OperationKind::Withdraw { id, amount } => format!("withdraw {} {}", id, amount),
+                OperationKind::Transfer {
+                    sender_id,
+                    reciever_id,
+                    amount,
+                } => format!("transfer {} {} {}", sender_id, reciever_id, amount),
+            };
+
+            format!("operation {} {} {}", bank_id, operation.id, kind)
+        }
+    }
+}
+
+fn decode_record(payload: &str) -> Option<Record> {
+    let parts: Vec<&str> = payload.split(' ').collect();
+
+    match parts[..] {
+        ["new_bank"] => Some(Record::NewBank),
+        ["restore_bank", id] => Some(Record::RestoreBank {
+            id: id.parse().ok()?,
+        }),
+        ["operation", bank_id, operation_id, ref kind @ ..] => {
+            let kind = match *kind {
+                ["register", id, balance] => OperationKind::Register {
+                    id: AccountID::parse_str(id).ok()?,
+                    balance: balance.parse().ok()?,
+                },
+                ["deposit", id, amount] => OperationKind::Deposit {
+                    id: AccountID::parse_str(id).ok()?,
+                    amount: amount.parse().ok()?,
+                },
+                ["withdraw", id, amount] => OperationKind::Withdraw {
+                    id: AccountID::parse_str(id).ok()?,
+                    amount: amount.parse().ok()?,
+                },
+                ["transfer", sender_id, reciever_id, amount] => OperationKind::Transfer {
+                    sender_id: AccountID::parse_str(sender_id).ok()?,
+                    reciever_id: AccountID::parse_str(reciever_id).ok()?,
+                    amount: amount.parse().ok()?,
+                },
+                _ => return None,
+            };
+
+            Some(Record::Operation {
+                bank_id: bank_id.parse().ok()?,
+                operation: Operation {
+                    id: OperationID::parse_str(operation_id).ok()?,
+                    kind,
+                },
+            })
+        }
+        _ => None,
+    }
+}
+
+// CRC-32 (IEEE 802.3), bitwise version is fast enough for short records
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("bank-wal-{}", OperationID::new()))
+    }
+
+    fn records() -> Vec<Record> {
+        let sender_id = AccountID::new();
+        let reciever_id = AccountID::new();
+
+        vec![
+            Record::NewBank,
+            Record::Operation {
+                bank_id: 1,
+                operation: Operation {
+                    id: OperationID::new(),
+                    kind: OperationKind::Register {
+                        id: sender_id,
+                        balance: 100,
+                    },
+                },
+            },
+            Record::Operation {
+                bank_id: 1,
+                operation: Operation {
+                    id: OperationID::new(),
+                    kind: OperationKind::Register {
+                        id: reciever_id,
+                        balance: 0,
+                    },
+                },
+            },
+            Record::Operation {
+                bank_id: 1,
+                operation: Operation {
+                    id: OperationID::new(),
+                    kind: OperationKind::Deposit {
+                        id: sender_id,
+                        amount: 50,
+                    },
+                },
+            },
+            Record::Operation {
+                bank_id: 1,
+                operation: Operation {
+                    id: OperationID::new(),
+                    kind: OperationKind::Withdraw {
+                        id: sender_id,
+                        amount: 10,
+                    },
+                },
+            },
+            Record::Operation {
+                bank_id: 1,
+                operation: Operation {
+                    id: OperationID::new(),
+                    kind: OperationKind::Transfer {
+                        sender_id,
+                        reciever_id,
+                        amount: 20,
+                    },
+                },
+            },
+            Record::RestoreBank { id: 1 },
+        ]
+    }
+
+    #[test]
+    fn crc32_works() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn append_and_open_works() {
+        let dir = temp_dir();
+        let expected = records();
+
+        let (mut wal, stored) = Wal::open(&dir, 1).unwrap();
+        assert!(stored.is_empty());
+        for record in expected.iter() {
+            wal.append(record).unwrap();
+        }
+        drop(wal);
+
+        let (_, stored) = Wal::open(&dir, 1).unwrap();
+        assert_eq!(stored, expected);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn append_after_failed_write_works() {
+        let dir = temp_dir();
+        let expected = records();
+
+        let (mut wal, _) = Wal::open(&dir, 1).unwrap();
+        wal.append(&expected[0]).unwrap();
+
+        // part of a record got to the file before writing failed
+        let path = segment_path(&dir, 1);
+        OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"0badc0de new_b")
+            .unwrap();
+        wal.file = File::open(&path).unwrap();
+        assert!(matches!(wal.append(&expected[1]), Err(WalError::Io(_))));
+
+        for record in expected[1..].iter() {
+            wal.append(record).unwrap();
+        }
+        drop(wal);
+
+        let (_, stored) = Wal::open(&dir, 1).unwrap();
+        assert_eq!(stored, expected);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn open_cuts_torn_tail_works() {
+        let dir = temp_dir();
+        let expected = records();
+
+        let (mut wal, _) = Wal::open(&dir, 1).unwrap();
+        for record in expected.iter() {
+            wal.append(record).unwrap();
+        }
+        drop(wal);
+
+        let path = segment_path(&dir, 1);
+        let full_len = std::fs::metadata(&path).unwrap().len();
+        let file = OpenOptions::new().write(true).open(&path).unwrap();
+        file.set_len(full_len - 5).unwrap();
+        drop(file);
+
+        let (mut wal, stored) = Wal::open(&dir, 1).unwrap();
+        assert_eq!(stored, expected[..expected.len() - 1]);
+
+        // log stays appendable after the torn record was cut off
+        wal.append(&Record::NewBank).unwrap();
+        drop(wal);
+
+        let (_, stored) = Wal::open(&dir, 1).unwrap();
+        assert_eq!(stored.len(), expected.len());
+        assert_eq!(stored.last(), Some(&Record::NewBank));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn open_detects_corrupted_record_works() {
+        let dir = temp_dir();
+
+        let (mut wal, _) = Wal::open(&dir, 1).unwrap();
+        for record in records().iter() {
+            wal.append(record).unwrap();
+        }
+        drop(wal);
+
+        let path = segment_path(&dir, 1);
+        let data = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, data.replacen("deposit", "dEposit", 1)).unwrap();
+
+        assert!(matches!(
+            Wal::open(&dir, 1),
+            Err(WalError::Corrupted {
+                segment: 1,
+                line: 4
+            })
+        ));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn open_detects_torn_tail_in_old_segment_works() {
+        let dir = temp_dir();
+
+        let (mut wal, _) = Wal::open(&dir, 1).unwrap();
+        wal.append(&Record::NewBank).unwrap();
+        wal.rotate().unwrap();
+        wal.append(&Record::NewBank).unwrap();
+        drop(wal);
+
+        let path = segment_path(&dir, 1);
+        let full_len = std::fs::metadata(&path).unwrap().len();
+        let file = OpenOptions::new().write(true).open(&path).unwrap();
+        file.set_len(full_len - 1).unwrap();
+        drop(file);
+
+        assert!(matches!(
+            Wal::open(&dir, 1),
+            Err(WalError::Corrupted {
+                segment: 1,
+                line: 1
+            })
+        ));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rotate_and_compact_works() {
+        let dir = temp_dir();
+        let expected = records();
+
+        let (mut wal, _) = Wal::open(&dir, 1).unwrap();
+        wal.append(&expected[0]).unwrap();
+        assert_eq!(wal.rotate().unwrap(), 2);
+        wal.append(&expected[1]).unwrap();
+        assert_eq!(wal.rotate().unwrap(), 3);
+        wal.append(&expected[2]).unwrap();
+        drop(wal);
+
+        let (mut wal, stored) = Wal::open(&dir, 1).unwrap();
+        assert_eq!(wal.segment(), 3);
+        assert_eq!(stored, expected[..3]);
+
+        wal.compact(3).unwrap();
+        assert_eq!(list_segments(&dir).unwrap(), vec![3]);
+        drop(wal);
+
+        let (_, stored) = Wal::open(&dir, 3).unwrap();
+        assert_eq!(stored, expected[2..3]);
+
+        // segments skipped on open are removed as well
+        let (mut wal, _) = Wal::open(&dir, 3).unwrap();
+        wal.rotate().unwrap();
+        drop(wal);
+        let (wal, stored) = Wal::open(&dir, 4).unwrap();
+        assert!(stored.is_empty());
+        assert_eq!(wal.segment(), 4);
+        assert_eq!(list_segments(&dir).unwrap(), vec![4]);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}